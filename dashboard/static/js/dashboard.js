@@ -3,13 +3,24 @@ const DEBUG = true;
 
 // Override fetch to log all API requests in debug mode
 const originalFetch = window.fetch;
-window.fetch = function(url, options) {
+window.fetch = function(url, options = {}) {
+    // State-changing requests on a session must echo its CSRF token
+    const method = (options.method || 'GET').toUpperCase();
+    if (csrfToken && method !== 'GET' && method !== 'HEAD') {
+        options.headers = { ...(options.headers || {}), 'X-CSRF-Token': csrfToken };
+    }
+    
     if (DEBUG) {
         console.log(`Fetch request to: ${url}`, options);
     }
     
     return originalFetch(url, options)
         .then(response => {
+            // Expired or missing session: ask for credentials again
+            if (response.status === 401 && String(url).startsWith('/api/') && !String(url).startsWith('/api/auth/')) {
+                showLoginModal();
+            }
+            
             if (DEBUG) {
                 // Clone the response so we can both log it and return it
                 const clone = response.clone();
@@ -53,6 +64,8 @@ let selectedPosition = '';
 let charts = {};
 let lastAccountValue = null; // Store the last known account value
 let currentSettings = {}; // Store current settings
+let csrfToken = null; // CSRF token of the current session, if logged in
+let dashboardStarted = false;
 
 // DOM elements
 const refreshDataBtn = document.getElementById('refreshData');
@@ -99,6 +112,16 @@ document.addEventListener('DOMContentLoaded', function() {
         saveSettings();
     });
     
+    // Set up login form and logout button
+    document.getElementById('loginForm').addEventListener('submit', function(e) {
+        e.preventDefault();
+        login();
+    });
+    document.getElementById('logoutBtn').addEventListener('click', function(e) {
+        e.preventDefault();
+        logout();
+    });
+    
     // Set up dark mode toggle
     themeToggle.addEventListener('change', function() {
         if (this.checked) {
@@ -127,15 +150,143 @@ document.addEventListener('DOMContentLoaded', function() {
         themeIcon.className = 'fas fa-sun';
     }
     
+    // Check credentials before loading data; a successful login starts the dashboard instead
+    checkSession().then(ready => {
+        if (ready) {
+            startDashboard();
+        }
+    });
+});
+
+// Load data and start updates once the API is reachable
+function startDashboard() {
+    if (dashboardStarted) {
+        return;
+    }
+    dashboardStarted = true;
+    
     // Initial data load
     refreshAllData();
     
     // Load account value immediately
     loadMetricHistory('account_value', updateAccountValue);
     
-    // Set up auto-refresh (every 60 seconds)
+    // Set up auto-refresh (every 60 seconds) for history charts; live data arrives over the stream
     refreshInterval = setInterval(refreshAllData, 60000);
-});
+    
+    connectLiveUpdates();
+}
+
+// Find out whether authentication is required and who we are; returns true when the API can be used
+async function checkSession() {
+    try {
+        const response = await fetch('/api/auth/session');
+        const session = (await response.json()).data;
+        updateSessionInfo(session);
+        
+        if (session.auth_enabled && !session.authenticated) {
+            showLoginModal();
+            return false;
+        }
+        return true;
+    } catch (error) {
+        console.error('Error checking session:', error);
+        return true;
+    }
+}
+
+function updateSessionInfo(session) {
+    csrfToken = session.csrf_token || null;
+    
+    const logoutItem = document.getElementById('logoutItem');
+    if (session.authenticated) {
+        document.getElementById('sessionUser').textContent = `${session.username} (${session.role})`;
+        logoutItem.classList.remove('d-none');
+    } else {
+        logoutItem.classList.add('d-none');
+    }
+    
+    // Read-only users can look at settings but not save them
+    const saveButton = document.querySelector('#settingsModal button[type="submit"]');
+    if (saveButton) {
+        saveButton.disabled = session.auth_enabled && session.role !== 'admin';
+    }
+}
+
+function showLoginModal() {
+    document.getElementById('loginError').classList.add('d-none');
+    $('#loginModal').modal('show');
+}
+
+async function login() {
+    const response = await fetch('/api/auth/login', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json'
+        },
+        body: JSON.stringify({
+            username: document.getElementById('loginUsername').value,
+            password: document.getElementById('loginPassword').value
+        })
+    });
+    
+    if (!response.ok) {
+        const loginError = document.getElementById('loginError');
+        loginError.textContent = response.status === 429
+            ? 'Too many attempts, please wait a moment.'
+            : 'Invalid username or password.';
+        loginError.classList.remove('d-none');
+        return;
+    }
+    
+    updateSessionInfo((await response.json()).data);
+    document.getElementById('loginPassword').value = '';
+    $('#loginModal').modal('hide');
+    startDashboard();
+}
+
+async function logout() {
+    await fetch('/api/auth/logout', { method: 'POST' });
+    csrfToken = null;
+    window.location.reload();
+}
+
+// Subscribe to pushed analyses, price ticks and risk events from the server
+function connectLiveUpdates() {
+    if (!window.EventSource) {
+        console.log('EventSource not supported, relying on polling');
+        return;
+    }
+    
+    // EventSource reconnects on its own after a dropped connection
+    const stream = new EventSource('/api/stream');
+    
+    stream.addEventListener('analysis', event => {
+        const data = JSON.parse(event.data);
+        updateSummaryCards(data.summary);
+        updatePositionsTable(data.analysis.positions, data.analysis.position_metrics);
+        updateWarnings(data.analysis.warnings);
+    });
+    
+    stream.addEventListener('prices', event => {
+        const data = JSON.parse(event.data);
+        Object.entries(data.mids).forEach(([coin, mid]) => {
+            document.querySelectorAll(`.mark-price[data-coin="${coin}"]`).forEach(element => {
+                element.textContent = '$' + formatNumber(parseFloat(mid));
+            });
+        });
+    });
+    
+    stream.addEventListener('risk_event', event => {
+        if (DEBUG) {
+            console.log('Risk event:', JSON.parse(event.data));
+        }
+    });
+    
+    stream.onerror = () => {
+        console.error('Live update stream interrupted, reconnecting...');
+    };
+}
 
 // Redraw all charts to apply theme changes
 function redrawAllCharts() {
@@ -219,6 +370,10 @@ async function refreshAllData() {
             loadMetricHistory('account_value', updateAccountValue).catch(err => {
                 console.error('Error loading account value history:', err);
                 return null;
+            }),
+            loadFeeAnalytics().catch(err => {
+                console.error('Error loading fee analytics:', err);
+                return null;
             })
         ]);
         
@@ -274,6 +429,34 @@ async function loadRiskAnalysis() {
     }
 }
 
+// Load fee tier and trading cost analytics
+async function loadFeeAnalytics() {
+    const response = await fetch('/api/fees');
+    if (!response.ok) {
+        throw new Error(`HTTP error! status: ${response.status}`);
+    }
+    const result = await response.json();
+    if (!result.success) {
+        throw new Error(result.error);
+    }
+    updateFeeAnalytics(result.data);
+}
+
+// Update the trading costs card
+function updateFeeAnalytics(fees) {
+    const formatRate = rate => rate === null || rate === undefined ? '--' : (rate * 10000).toFixed(2) + ' bps';
+    
+    document.getElementById('feeTier').textContent = fees.tier === 0 ? 'Base' : `VIP ${fees.tier}`;
+    document.getElementById('feeVolume').textContent = '$' + formatNumber(fees.fourteen_day_volume);
+    document.getElementById('feeNextTier').textContent = fees.volume_to_next_tier !== null
+        ? '$' + formatNumber(fees.volume_to_next_tier)
+        : 'Top tier';
+    document.getElementById('feeRealizedRates').textContent =
+        `${formatRate(fees.realized_maker_rate)} / ${formatRate(fees.realized_taker_rate)}`;
+    document.getElementById('feeBuilder').textContent = '$' + formatNumber(fees.builder_fees_paid);
+    document.getElementById('feeProjected').textContent = '$' + formatNumber(fees.projected_monthly_fees);
+}
+
 // Load positions list
 async function loadPositions() {
     try {
@@ -370,6 +553,13 @@ function updateSummaryCards(data) {
         
         portfolioHeat.textContent = data.portfolio_heat.toFixed(1);
         portfolioHeatBar.style.width = `${data.portfolio_heat}%`;
+        if (data.heat_breakdown) {
+            const b = data.heat_breakdown;
+            portfolioHeat.title = `Leverage: ${b.leverage.toFixed(1)}\n` +
+                `Margin utilization: ${b.margin_utilization.toFixed(1)}\n` +
+                `Concentration: ${b.concentration.toFixed(1)}\n` +
+                `Liquidation: ${b.liquidation.toFixed(1)}`;
+        }
         
         // Set color based on risk level
         if (data.portfolio_heat < 30) {
@@ -508,18 +698,31 @@ function updatePositionsTable(positions, positionMetrics) {
         const leverage = parseFloat(position.leverage).toFixed(1) + 'x';
         const marginType = position.is_cross ? 'cross' : 'isolated';
         const riskScore = metrics.risk_score ? metrics.risk_score.toFixed(1) : '--';
-        const liqDistance = metrics.distance_to_liquidation ? metrics.distance_to_liquidation.toFixed(2) + '%' : '--';
+        const scoreBreakdown = metrics.risk_score_breakdown
+            ? `Leverage: ${metrics.risk_score_breakdown.leverage.toFixed(1)}, ` +
+              `Liquidation: ${metrics.risk_score_breakdown.liquidation.toFixed(1)}, ` +
+              `Size: ${metrics.risk_score_breakdown.size.toFixed(1)}`
+            : '';
+        let liqDistance = metrics.distance_to_liquidation ? metrics.distance_to_liquidation.toFixed(2) + '%' : '--';
+        if (metrics.liquidation_distance_sigma !== undefined && metrics.liquidation_distance_sigma !== null) {
+            liqDistance += ` (${metrics.liquidation_distance_sigma.toFixed(1)}σ)`;
+        }
+        const liqProbability = metrics.liquidation_forecast
+            ? `1h ${(metrics.liquidation_forecast.one_hour * 100).toFixed(1)}%, ` +
+              `8h ${(metrics.liquidation_forecast.eight_hours * 100).toFixed(1)}%, ` +
+              `24h ${(metrics.liquidation_forecast.twenty_four_hours * 100).toFixed(1)}%`
+            : '--';
         const marginUsed = position.margin_used ? '$' + parseFloat(position.margin_used).toFixed(2) : '--';
         const pnl = position.unrealized_pnl ? '$' + parseFloat(position.unrealized_pnl).toFixed(2) : '--';
         
         // Create the row HTML
         row.innerHTML = `
-            <td><strong>${position.coin}</strong></td>
+            <td><strong>${position.coin}</strong> <small class="text-muted mark-price" data-coin="${position.coin}"></small></td>
             <td>${size}</td>
             <td>${leverage}</td>
             <td>${marginType}</td>
-            <td>${riskScore}</td>
-            <td>${liqDistance}</td>
+            <td title="${scoreBreakdown}">${riskScore}</td>
+            <td title="Liquidation probability: ${liqProbability}">${liqDistance}</td>
             <td>${marginUsed}</td>
             <td>${pnl}</td>
             <td>
@@ -1196,6 +1399,8 @@ async function loadSettings() {
         document.getElementById('minDistanceToLiq').value = settings.risk_limits.min_distance_to_liq || 10;
         document.getElementById('maxCorrelation').value = settings.risk_limits.max_correlation || 0.7;
         document.getElementById('maxMarginUtilization').value = settings.risk_limits.max_margin_utilization || 80;
+        document.getElementById('maxNetBetaExposureUsd').value = settings.risk_limits.max_net_beta_exposure_usd || 250000;
+        document.getElementById('maxLiquidationProbability').value = settings.risk_limits.max_liquidation_probability || 0.05;
         
     } catch (error) {
         console.error('Error loading settings:', error);
@@ -1210,13 +1415,17 @@ async function saveSettings() {
         const settings = {
             wallet_address: document.getElementById('walletAddress').value,
             risk_limits: {
+                // Keep limits that are not editable in the form
+                ...(currentSettings ? currentSettings.risk_limits : {}),
                 max_position_size_usd: parseFloat(document.getElementById('maxPositionSizeUsd').value),
                 max_leverage: parseFloat(document.getElementById('maxLeverage').value),
                 max_drawdown_pct: parseFloat(document.getElementById('maxDrawdownPct').value),
                 max_position_pct: parseFloat(document.getElementById('maxPositionPct').value),
                 min_distance_to_liq: parseFloat(document.getElementById('minDistanceToLiq').value),
                 max_correlation: parseFloat(document.getElementById('maxCorrelation').value),
-                max_margin_utilization: parseFloat(document.getElementById('maxMarginUtilization').value)
+                max_margin_utilization: parseFloat(document.getElementById('maxMarginUtilization').value),
+                max_net_beta_exposure_usd: parseFloat(document.getElementById('maxNetBetaExposureUsd').value),
+                max_liquidation_probability: parseFloat(document.getElementById('maxLiquidationProbability').value)
             }
         };
         
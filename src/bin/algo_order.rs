@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use ethers::signers::LocalWallet;
+use log::info;
+
+use hyperliquid_rust_sdk::{
+    AlgoOrder, AlgoOrderParams, AlgoStrategy, BaseUrl, ExchangeClient, IcebergParams, InfoClient,
+};
+use tokio::time::{sleep, Duration};
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Key was randomly generated for testing and shouldn't be used with any real funds
+    let wallet: LocalWallet = "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+        .parse()
+        .unwrap();
+
+    let exchange_client = Arc::new(
+        ExchangeClient::new(None, wallet, Some(BaseUrl::Testnet), None, None)
+            .await
+            .unwrap(),
+    );
+    let info_client = InfoClient::new(None, Some(BaseUrl::Testnet)).await.unwrap();
+
+    // Buy 0.1 ETH showing 0.02 at a time, never paying more than 4000
+    let algo = AlgoOrder::start(
+        exchange_client,
+        info_client,
+        AlgoOrderParams {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            sz: 0.1,
+            limit_px: Some(4000.0),
+            reduce_only: false,
+            strategy: AlgoStrategy::Iceberg(IcebergParams { visible_sz: 0.02 }),
+            poll_interval: Duration::from_secs(1),
+        },
+    )
+    .await
+    .unwrap();
+
+    for _ in 0..6 {
+        sleep(Duration::from_secs(5)).await;
+        info!("Iceberg progress: {:?}", algo.progress());
+    }
+
+    algo.pause();
+    sleep(Duration::from_secs(5)).await;
+    algo.resume();
+    sleep(Duration::from_secs(10)).await;
+
+    // Pull whatever is left
+    algo.cancel();
+    info!("Iceberg finished: {:?}", algo.wait().await);
+}
@@ -0,0 +1,60 @@
+use ethers::signers::LocalWallet;
+use log::info;
+
+use hyperliquid_rust_sdk::{
+    BaseUrl, BracketOrderParams, ClientTpsl, ExchangeClient, PositionTpslParams,
+};
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Key was randomly generated for testing and shouldn't be used with any real funds
+    let wallet: LocalWallet = "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+        .parse()
+        .unwrap();
+
+    let exchange_client = ExchangeClient::new(None, wallet, Some(BaseUrl::Testnet), None, None)
+        .await
+        .unwrap();
+
+    // Entry with a market take profit and a limit stop loss; the legs activate once the entry fills
+    let response = exchange_client
+        .bracket_order(BracketOrderParams {
+            asset: "ETH",
+            is_buy: true,
+            sz: 0.01,
+            limit_px: 1800.0,
+            tif: "Gtc".to_string(),
+            cloid: None,
+            take_profit: Some(ClientTpsl {
+                trigger_px: 2000.0,
+                limit_px: None,
+            }),
+            stop_loss: Some(ClientTpsl {
+                trigger_px: 1700.0,
+                limit_px: Some(1690.0),
+            }),
+            wallet: None,
+        })
+        .await
+        .unwrap();
+    info!("Bracket order placed: {response:?}");
+
+    // Take profit and stop loss for the whole open ETH position, checked against the current mid
+    let response = exchange_client
+        .position_tpsl(PositionTpslParams {
+            asset: "ETH",
+            sz: None,
+            take_profit: Some(ClientTpsl {
+                trigger_px: 2200.0,
+                limit_px: None,
+            }),
+            stop_loss: Some(ClientTpsl {
+                trigger_px: 1500.0,
+                limit_px: None,
+            }),
+            wallet: None,
+        })
+        .await;
+    info!("Position TP/SL placed: {response:?}");
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethers::signers::LocalWallet;
+use log::info;
+
+use hyperliquid_rust_sdk::{BaseUrl, DeadMansSwitch, DeadMansSwitchConfig, ExchangeClient};
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Key was randomly generated for testing and shouldn't be used with any real funds
+    let wallet: LocalWallet = "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+        .parse()
+        .unwrap();
+
+    let exchange_client = Arc::new(
+        ExchangeClient::new(None, wallet, Some(BaseUrl::Testnet), None, None)
+            .await
+            .unwrap(),
+    );
+
+    // Open orders are cancelled a minute after the trading loop stops calling `heartbeat`
+    let switch = DeadMansSwitch::start(
+        exchange_client,
+        DeadMansSwitchConfig {
+            heartbeat_timeout: Some(Duration::from_secs(30)),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    info!("Orders cancel at {:?} unless refreshed", switch.deadline());
+
+    for _ in 0..10 {
+        // Trading loop work goes here
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        switch.heartbeat();
+        info!("Orders now cancel at {:?}", switch.deadline());
+    }
+
+    // Shut down without cancelling the open orders
+    switch.disarm().await.unwrap();
+}
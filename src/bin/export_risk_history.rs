@@ -0,0 +1,68 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use hyperliquid_rust_sdk::risk_management::{DataLogger, ExportDataset, ExportFormat, HistoryExporter, RiskConfig};
+use hyperliquid_rust_sdk::Error;
+
+const USAGE: &str = "Usage: export_risk_history <snapshots|positions|warnings> [--format csv|ndjson|parquet] \
+[--start <unix seconds>] [--end <unix seconds>] [--columns <a,b,...>] [--output <path>]";
+
+// Exports the logged risk history (hyperliquid_risk_log.jsonl) to stdout or a file, streaming it
+// entry by entry so large logs never have to fit in memory
+fn main() -> std::result::Result<(), Error> {
+    env_logger::init();
+
+    let mut args = env::args().skip(1);
+    let mut dataset = None;
+    let mut format = ExportFormat::Csv;
+    let mut start = None;
+    let mut end = None;
+    let mut columns = None;
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| Error::Custom(format!("Missing value for {}\n{}", arg, USAGE)));
+        match arg.as_str() {
+            "--format" => format = ExportFormat::from_str(&value()?)?,
+            "--start" => start = Some(parse_timestamp(&value()?)?),
+            "--end" => end = Some(parse_timestamp(&value()?)?),
+            "--columns" => columns = Some(value()?.split(',').map(|c| c.trim().to_string()).collect::<Vec<_>>()),
+            "--output" => output = Some(value()?),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if dataset.is_none() && !arg.starts_with("--") => dataset = Some(ExportDataset::from_str(&arg)?),
+            _ => return Err(Error::Custom(format!("Unexpected argument: {}\n{}", arg, USAGE))),
+        }
+    }
+    let dataset = dataset.ok_or_else(|| Error::Custom(USAGE.to_string()))?;
+    let mut exporter = HistoryExporter::new(dataset, format, columns.as_deref())?;
+
+    // Read the log file whatever the current logging settings are
+    let mut config = RiskConfig::from_env()?;
+    config.enable_logging = true;
+    config.log_to_console = true;
+    let data_logger = DataLogger::new(config);
+
+    let mut writer: Box<dyn Write> = match &output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    let exported = data_logger.export_history(&mut exporter, start, end, |chunk| {
+        writer.write_all(&chunk)?;
+        Ok(())
+    })?;
+    writer.flush()?;
+
+    eprintln!("Exported {} log entries as {} {}", exported, dataset.name(), format.extension());
+    Ok(())
+}
+
+fn parse_timestamp(value: &str) -> std::result::Result<u64, Error> {
+    value
+        .parse::<u64>()
+        .map_err(|_| Error::Custom(format!("Invalid timestamp: {}, must be unix seconds", value)))
+}
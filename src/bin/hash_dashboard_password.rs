@@ -0,0 +1,17 @@
+use std::io::{self, BufRead};
+
+use hyperliquid_rust_sdk::risk_management::hash_password;
+
+// Reads a password from stdin (so it stays out of shell history) and prints its hash for DASHBOARD_USERS
+fn main() {
+    eprintln!("Password:");
+    let mut password = String::new();
+    io::stdin().lock().read_line(&mut password).unwrap();
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        eprintln!("Password must not be empty");
+        std::process::exit(1);
+    }
+
+    println!("{}", hash_password(password));
+}
@@ -0,0 +1,24 @@
+use std::env;
+
+use hyperliquid_rust_sdk::mock_server::{MockFixtures, MockServer};
+use log::info;
+
+// Serves on the port `BaseUrl::Localhost` points at unless MOCK_SERVER_ADDR says otherwise
+const DEFAULT_ADDR: &str = "127.0.0.1:3001";
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+
+    let addr = env::var("MOCK_SERVER_ADDR").unwrap_or_else(|_| DEFAULT_ADDR.to_string());
+    let fixtures = match env::var("MOCK_FIXTURES") {
+        Ok(path) => MockFixtures::from_file(&path).unwrap(),
+        Err(_) => MockFixtures::default(),
+    };
+
+    let server = MockServer::start(&addr, fixtures).await.unwrap();
+    info!("Mock Hyperliquid API listening on {}", server.url());
+    println!("Mock Hyperliquid API listening on {} (websocket {})", server.url(), server.ws_url());
+
+    tokio::signal::ctrl_c().await.unwrap();
+}
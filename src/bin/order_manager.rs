@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use ethers::signers::LocalWallet;
+use log::info;
+
+use hyperliquid_rust_sdk::{
+    BaseUrl, ClientLimit, ClientOrder, ClientOrderRequest, ExchangeClient, InfoClient,
+    OrderManager, OrderManagerConfig,
+};
+use tokio::time::{sleep, Duration};
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Key was randomly generated for testing and shouldn't be used with any real funds
+    let wallet: LocalWallet = "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+        .parse()
+        .unwrap();
+
+    let exchange_client = Arc::new(
+        ExchangeClient::new(None, wallet, Some(BaseUrl::Testnet), None, None)
+            .await
+            .unwrap(),
+    );
+    let info_client = InfoClient::new(None, Some(BaseUrl::Testnet)).await.unwrap();
+    let oms = OrderManager::start(exchange_client, info_client, OrderManagerConfig::default())
+        .await
+        .unwrap();
+
+    let placed = oms
+        .place(vec![ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px: 1800.0,
+            sz: 0.01,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit {
+                tif: "Gtc".to_string(),
+            }),
+        }])
+        .await
+        .unwrap();
+    info!("Placed: {placed:?}");
+
+    sleep(Duration::from_secs(10)).await;
+    info!("Open orders: {:?}", oms.open_orders());
+    info!("Drift: {:?}", oms.reconcile().await.unwrap());
+
+    if let Some(oid) = placed[0].oid {
+        oms.cancel(oid).await.unwrap();
+        info!("After cancel: {:?}", oms.get(oid));
+    }
+}
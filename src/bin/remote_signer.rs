@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use log::info;
+
+use hyperliquid_rust_sdk::{
+    BaseUrl, ClientLimit, ClientOrder, ClientOrderRequest, ExchangeClient, HyperliquidSigner,
+    KeystoreSigner, RemoteSigner,
+};
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Either sign with a key kept in an encrypted keystore...
+    let signer: Arc<dyn HyperliquidSigner> = match std::env::var("HL_KEYSTORE") {
+        Ok(path) => Arc::new(
+            KeystoreSigner::from_file(path, std::env::var("HL_KEYSTORE_PASSWORD").unwrap())
+                .unwrap(),
+        ),
+        // ...or ask a local signing daemon, which never hands out the key
+        Err(_) => Arc::new(
+            RemoteSigner::connect("http://127.0.0.1:8547")
+                .await
+                .unwrap(),
+        ),
+    };
+    info!("Signing as {:?}", signer.address());
+
+    let exchange_client = ExchangeClient::new(None, signer, Some(BaseUrl::Testnet), None, None)
+        .await
+        .unwrap();
+
+    let order = ClientOrderRequest {
+        asset: "ETH".to_string(),
+        is_buy: true,
+        reduce_only: false,
+        limit_px: 1800.0,
+        sz: 0.01,
+        cloid: None,
+        order_type: ClientOrder::Limit(ClientLimit {
+            tif: "Gtc".to_string(),
+        }),
+    };
+    let response = exchange_client.order(order, None).await.unwrap();
+    info!("Order placed: {response:?}");
+}
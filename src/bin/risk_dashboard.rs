@@ -1,26 +1,277 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
 use std::env;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use actix_cors::Cors;
 use actix_files as fs;
-use actix_web::{web, App, HttpResponse, HttpServer, Responder, Result, middleware};
-use log::{info, error};
+use actix_web::{web, App, HttpMessage, HttpRequest, HttpResponse, HttpServer, Responder, Result, middleware};
+use actix_web::body::MessageBody;
+use actix_web::cookie::{time::Duration as CookieDuration, Cookie, SameSite};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use log::{info, error, warn};
+use serde::Deserialize;
 use serde_json::{json, Value};
 use tokio::time;
 use actix_web::http::header;
 use actix_governor::{Governor, GovernorConfigBuilder};
-use tokio::sync::Semaphore;
+use tokio::sync::{broadcast, mpsc, Semaphore};
 
 use hyperliquid_rust_sdk::risk_management::{
-    RiskManagementSystem, RiskConfig, DataLogger
+    RiskManagementSystem, RiskConfig, DataLogger, RiskAnalysisResult, RiskEvent, RiskEventFilter,
+    RiskEventKind, RiskService, RiskSnapshot, RiskSummary, DashboardAuth, DashboardAuthConfig, DashboardIdentity, DashboardRole,
+    ExportDataset, ExportFormat, HistoryExporter, CSRF_HEADER_NAME, SESSION_COOKIE_NAME
 };
+use hyperliquid_rust_sdk::{InfoClient, Message, Subscription};
+
+// Number of recent risk events kept for the events endpoint
+const RECENT_EVENTS_CAPACITY: usize = 500;
+
+// Frames buffered per live stream client before a slow client starts skipping them
+const LIVE_UPDATES_CAPACITY: usize = 64;
+
+// Minimum interval between pushed price ticks
+const PRICE_TICK_INTERVAL: Duration = Duration::from_millis(500);
+
+// Interval between keep-alive comments on the live stream, so idle proxies don't close it
+const STREAM_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+// Encoded chunks buffered per export download before reading the log waits for the client
+const EXPORT_CHANNEL_CAPACITY: usize = 4;
+
+// API endpoints reachable without credentials, so the dashboard can show its login form
+const PUBLIC_API_PATHS: [&str; 2] = ["/api/auth/login", "/api/auth/session"];
 
 // Shared state between threads
 struct AppState {
-    risk_system: Mutex<RiskManagementSystem>,
+    // Owns the risk system and analyzes once per tick; endpoints read its latest snapshot
+    risk_service: RiskService,
     data_logger: DataLogger,
-    // Add a semaphore to limit concurrent intensive operations
+    // Limits concurrent CPU-intensive work such as password hashing
     intensive_ops_semaphore: Semaphore,
+    // Most recent risk events with the time (unix seconds) they were received, oldest first
+    recent_events: Mutex<VecDeque<(u64, RiskEvent)>>,
+    // Server-sent event frames pushed to every /api/stream client
+    live_updates: broadcast::Sender<web::Bytes>,
+    // API credentials; everything is open when none are configured
+    auth: DashboardAuth,
+}
+
+fn sse_frame(event: &str, data: &Value) -> web::Bytes {
+    web::Bytes::from(format!("event: {}\ndata: {}\n\n", event, data))
+}
+
+fn summary_json(summary: &RiskSummary) -> Value {
+    json!({
+        "portfolio_heat": summary.portfolio_heat,
+        "highest_risk_position": summary.highest_risk_position,
+        "warning_count": summary.warning_count,
+        "margin_utilization": summary.margin_utilization,
+        "account_value": summary.account_value,
+        "heat_breakdown": summary.heat_breakdown
+    })
+}
+
+fn analysis_json(analysis: &RiskAnalysisResult) -> Value {
+    json!({
+        "positions": analysis.positions,
+        "portfolio_metrics": analysis.portfolio_metrics,
+        "position_metrics": analysis.position_metrics,
+        "warnings": analysis.warnings
+    })
+}
+
+// When the snapshot was taken, so clients can tell how fresh the data is
+fn snapshot_json(snapshot: &RiskSnapshot) -> Value {
+    json!({
+        "updated_at": snapshot.updated_at,
+        "age_ms": snapshot.age_ms(),
+        "sequence": snapshot.sequence,
+        "last_error": snapshot.last_error
+    })
+}
+
+// Pushed to live stream clients after every tick; None until the first analysis succeeds
+fn analysis_frame(snapshot: &RiskSnapshot) -> Option<web::Bytes> {
+    let (analysis, summary) = (snapshot.analysis.as_ref()?, snapshot.summary.as_ref()?);
+    Some(sse_frame("analysis", &json!({
+        "analysis": analysis_json(analysis),
+        "summary": summary_json(summary),
+        "snapshot": snapshot_json(snapshot)
+    })))
+}
+
+// Response for endpoints that need an analysis before the first one has succeeded
+fn no_analysis_yet(snapshot: &RiskSnapshot) -> HttpResponse {
+    HttpResponse::ServiceUnavailable()
+        .insert_header((header::RETRY_AFTER, "5"))
+        .json(json!({
+            "success": false,
+            "error": snapshot.last_error.clone().unwrap_or_else(|| "No risk analysis yet".to_string()),
+            "snapshot": snapshot_json(snapshot)
+        }))
+}
+
+// Coins with open positions in the latest analysis
+fn held_coins(state: &AppState) -> HashSet<String> {
+    state.risk_service.snapshot()
+        .analysis
+        .iter()
+        .flat_map(|analysis| analysis.positions.iter().map(|position| position.coin.clone()))
+        .collect()
+}
+
+// Authenticates a request from its Authorization header, falling back to the session cookie
+async fn identify(state: &web::Data<Arc<AppState>>, req: &HttpRequest) -> Option<DashboardIdentity> {
+    let authorization = req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_string);
+    let session = req.cookie(SESSION_COOKIE_NAME).map(|c| c.value().to_string());
+    let state = state.clone();
+    
+    // Basic auth hashes the password, so keep it off the async workers
+    web::block(move || match authorization {
+        Some(authorization) => state.auth.authenticate_header(&authorization),
+        None => session.and_then(|session| state.auth.authenticate_session(&session)),
+    }).await.ok().flatten()
+}
+
+// Reads are open to every role; anything that changes state needs admin, except logging out
+fn required_role(req: &ServiceRequest) -> DashboardRole {
+    match *req.method() {
+        Method::GET | Method::HEAD => DashboardRole::ReadOnly,
+        _ if req.path() == "/api/auth/logout" => DashboardRole::ReadOnly,
+        _ => DashboardRole::Admin,
+    }
+}
+
+// Requires credentials, the right role and a CSRF token for /api when authentication is configured.
+// Static files stay public so the dashboard can load and prompt for a login.
+async fn authorize(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>> {
+    let state = req.app_data::<web::Data<Arc<AppState>>>()
+        .filter(|state| state.auth.is_enabled())
+        .cloned();
+    let Some(state) = state else {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    };
+    if !req.path().starts_with("/api") || PUBLIC_API_PATHS.contains(&req.path()) {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    }
+    
+    let Some(identity) = identify(&state, req.request()).await else {
+        let response = HttpResponse::Unauthorized().json(json!({ "error": "Authentication required" }));
+        return Ok(req.into_response(response).map_into_right_body());
+    };
+    
+    if !identity.role.allows(required_role(&req)) {
+        let response = HttpResponse::Forbidden().json(json!({ "error": "This action requires the admin role" }));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+    
+    let changes_state = !matches!(*req.method(), Method::GET | Method::HEAD);
+    let csrf_token = req.headers().get(CSRF_HEADER_NAME).and_then(|h| h.to_str().ok());
+    if changes_state && !state.auth.verify_csrf(&identity, csrf_token) {
+        let response = HttpResponse::Forbidden().json(json!({ "error": "Missing or invalid CSRF token" }));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+    
+    req.extensions_mut().insert(identity);
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+fn session_cookie(auth: &DashboardAuth, value: String) -> Cookie<'static> {
+    Cookie::build(SESSION_COOKIE_NAME, value)
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .secure(auth.config().secure_cookies)
+        .max_age(CookieDuration::seconds(auth.config().session_ttl_seconds as i64))
+        .finish()
+}
+
+fn identity_json(auth: &DashboardAuth, identity: Option<&DashboardIdentity>) -> Value {
+    json!({
+        "auth_enabled": auth.is_enabled(),
+        "authenticated": identity.is_some(),
+        "username": identity.map(|i| i.name.clone()),
+        "role": identity.map(|i| i.role),
+        "method": identity.map(|i| i.method),
+        "csrf_token": identity.and_then(|i| i.csrf_token.clone())
+    })
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+// API endpoint to log in with a username and password, setting a signed session cookie
+async fn login(
+    data: web::Data<Arc<AppState>>,
+    credentials: web::Json<LoginRequest>,
+) -> Result<impl Responder> {
+    if !data.auth.is_enabled() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Authentication is not configured"
+        })));
+    }
+    
+    let data_clone = data.clone();
+    let credentials = credentials.into_inner();
+    let session = run_intensive_task(&data.intensive_ops_semaphore, move || {
+        data_clone.auth.login(&credentials.username, &credentials.password)
+    }).await;
+    
+    match session {
+        Some(session) => {
+            info!("Dashboard login by {}", session.identity.name);
+            Ok(HttpResponse::Ok()
+                .cookie(session_cookie(&data.auth, session.cookie_value))
+                .json(json!({
+                    "success": true,
+                    "data": identity_json(&data.auth, Some(&session.identity))
+                })))
+        },
+        None => {
+            warn!("Failed dashboard login");
+            Ok(HttpResponse::Unauthorized().json(json!({
+                "error": "Invalid username or password"
+            })))
+        }
+    }
+}
+
+// API endpoint to end the current session
+async fn logout(data: web::Data<Arc<AppState>>, req: HttpRequest) -> Result<impl Responder> {
+    if let Some(identity) = req.extensions().get::<DashboardIdentity>() {
+        data.auth.logout(identity);
+    }
+    
+    let mut removal = session_cookie(&data.auth, String::new());
+    removal.make_removal();
+    Ok(HttpResponse::Ok().cookie(removal).json(json!({ "success": true })))
+}
+
+// API endpoint describing who the caller is, including the CSRF token for session requests
+async fn get_session(data: web::Data<Arc<AppState>>, req: HttpRequest) -> Result<impl Responder> {
+    let identity = if data.auth.is_enabled() {
+        identify(&data, &req).await
+    } else {
+        None
+    };
+    
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": identity_json(&data.auth, identity.as_ref())
+    })))
 }
 
 // Helper function to run CPU-intensive operations in a blocking task
@@ -36,56 +287,21 @@ where
     let _permit = semaphore.acquire().await.unwrap();
     
     // Run the CPU-intensive task in a blocking thread
-    let result = web::block(move || task()).await.unwrap();
-    
-    result
+    web::block(task).await.unwrap()
 }
 
 // API endpoint to get the latest risk analysis
 async fn get_risk_analysis(data: web::Data<Arc<AppState>>) -> Result<impl Responder> {
-    // Clone the Arc to avoid borrowing issues
-    let data_clone = data.clone();
+    let snapshot = data.risk_service.snapshot();
+    let Some(analysis) = &snapshot.analysis else {
+        return Ok(no_analysis_yet(&snapshot));
+    };
     
-    match run_intensive_task(&data.intensive_ops_semaphore, move || {
-        let mut risk_system = data_clone.risk_system.lock().unwrap();
-        
-        match futures::executor::block_on(risk_system.analyze_risk_profile()) {
-            Ok(analysis) => {
-                // Convert to JSON response
-                let json_response = json!({
-                    "success": true,
-                    "data": {
-                        "positions": analysis.positions,
-                        "portfolio_metrics": analysis.portfolio_metrics,
-                        "position_metrics": analysis.position_metrics,
-                        "warnings": analysis.warnings
-                    }
-                });
-                Ok::<Value, String>(json_response)
-            },
-            Err(e) => {
-                let error_message = format!("Failed to analyze risk profile: {}", e);
-                error!("{}", error_message);
-                
-                let json_response = json!({
-                    "success": false,
-                    "error": error_message
-                });
-                Ok::<Value, String>(json_response)
-            }
-        }
-    }).await {
-        Ok(json_data) => Ok(HttpResponse::Ok().json(json_data)),
-        Err(e) => {
-            let error_message = format!("Error processing request: {}", e);
-            error!("{}", error_message);
-            
-            Ok(HttpResponse::InternalServerError().json(json!({
-                "success": false,
-                "error": error_message
-            })))
-        }
-    }
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": analysis_json(analysis),
+        "snapshot": snapshot_json(&snapshot)
+    })))
 }
 
 // API endpoint to get historical data for a specific metric
@@ -100,7 +316,8 @@ async fn get_metric_history(
     let valid_metrics = [
         "portfolio_heat", "concentration_score", "risk_adjusted_return", 
         "margin_utilization", "total_unrealized_pnl", "account_value", 
-        "total_position_value", "average_leverage"
+        "total_position_value", "average_leverage", "net_dollar_delta",
+        "gross_dollar_delta", "btc_equivalent_exposure"
     ];
     
     if !valid_metrics.contains(&metric_name.as_str()) {
@@ -218,47 +435,243 @@ async fn get_positions(data: web::Data<Arc<AppState>>) -> Result<impl Responder>
 
 // API endpoint to get a summary of the current risk status
 async fn get_risk_summary(data: web::Data<Arc<AppState>>) -> Result<impl Responder> {
-    // Clone the Arc to avoid borrowing issues
-    let data_clone = data.clone();
+    let snapshot = data.risk_service.snapshot();
+    let Some(summary) = &snapshot.summary else {
+        return Ok(no_analysis_yet(&snapshot));
+    };
     
-    match run_intensive_task(&data.intensive_ops_semaphore, move || {
-        let mut risk_system = data_clone.risk_system.lock().unwrap();
-        
-        match futures::executor::block_on(risk_system.get_risk_summary()) {
-            Ok(summary) => {
-                // Convert to JSON response
-                let json_response = json!({
-                    "success": true,
-                    "data": {
-                        "portfolio_heat": summary.portfolio_heat,
-                        "highest_risk_position": summary.highest_risk_position,
-                        "warning_count": summary.warning_count,
-                        "margin_utilization": summary.margin_utilization,
-                        "account_value": summary.account_value
-                    }
-                });
-                Ok::<Value, String>(json_response)
-            },
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": summary_json(summary),
+        "snapshot": snapshot_json(&snapshot)
+    })))
+}
+
+// API endpoint to attribute PnL over an interval (`start`/`end` in ms, defaults to the last 24 hours)
+async fn get_pnl_attribution(
+    data: web::Data<Arc<AppState>>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl Responder> {
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let parse_time = |key: &str| query.get(key).map(|v| v.parse::<u64>());
+    let end_time = match parse_time("end") {
+        Some(Ok(end)) => end,
+        Some(Err(_)) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "error": "Invalid end parameter, must be a timestamp in milliseconds"
+            })));
+        },
+        None => now,
+    };
+    let start_time = match parse_time("start") {
+        Some(Ok(start)) => start,
+        Some(Err(_)) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "error": "Invalid start parameter, must be a timestamp in milliseconds"
+            })));
+        },
+        None => end_time.saturating_sub(24 * 60 * 60 * 1000),
+    };
+    
+    // Keep requests bounded: at most 30 days per attribution
+    if start_time >= end_time || end_time - start_time > 30 * 24 * 60 * 60 * 1000 {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "start must be before end and the interval at most 30 days"
+        })));
+    }
+    
+    let attribution = data.risk_service.attribute_pnl(start_time, end_time).await;
+    
+    match attribution {
+        Ok(attribution) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": attribution
+        }))),
+        Err(e) => {
+            error!("Failed to attribute PnL: {}", e);
+            Ok(HttpResponse::InternalServerError().json(json!({
+                "success": false,
+                "error": format!("Failed to attribute PnL: {}", e)
+            })))
+        }
+    }
+}
+
+// API endpoint to get stored PnL attributions, newest first
+async fn get_pnl_attribution_history(
+    data: web::Data<Arc<AppState>>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl Responder> {
+    let limit = query.get("limit")
+        .and_then(|l| l.parse::<usize>().ok())
+        .unwrap_or(100)
+        .min(1000);
+    
+    match data.data_logger.get_pnl_attribution_history(limit) {
+        Ok(history) => Ok(HttpResponse::Ok().json(json!({
+            "data": history
+        }))),
+        Err(e) => {
+            error!("Failed to get PnL attribution history: {}", e);
+            Ok(HttpResponse::InternalServerError().json(json!({
+                "error": format!("Failed to get PnL attribution history: {}", e)
+            })))
+        }
+    }
+}
+
+// API endpoint to get recent risk events, newest first.
+// Optional filters: `types` (comma-separated event types), `coin` and `limit` (default 100)
+async fn get_risk_events(
+    data: web::Data<Arc<AppState>>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl Responder> {
+    let limit = query.get("limit")
+        .and_then(|l| l.parse::<usize>().ok())
+        .unwrap_or(100)
+        .min(RECENT_EVENTS_CAPACITY);
+    
+    let mut filter = RiskEventFilter::all();
+    if let Some(types) = query.get("types") {
+        match types.split(',').map(|t| RiskEventKind::from_str(t.trim())).collect::<std::result::Result<Vec<_>, _>>() {
+            Ok(kinds) => filter = filter.kinds(kinds),
             Err(e) => {
-                let error_message = format!("Failed to get risk summary: {}", e);
-                error!("{}", error_message);
-                
-                let json_response = json!({
-                    "success": false,
-                    "error": error_message
-                });
-                Ok::<Value, String>(json_response)
+                return Ok(HttpResponse::BadRequest().json(json!({
+                    "error": e.to_string()
+                })));
+            }
+        }
+    }
+    if let Some(coin) = query.get("coin") {
+        filter = filter.coins([coin.clone()]);
+    }
+    
+    let events: Vec<Value> = data.recent_events.lock().unwrap()
+        .iter()
+        .rev()
+        .filter(|(_, event)| filter.matches(event))
+        .take(limit)
+        .map(|(timestamp, event)| json!({ "timestamp": timestamp, "event": event }))
+        .collect();
+    
+    Ok(HttpResponse::Ok().json(json!({
+        "data": events
+    })))
+}
+
+// Server-sent events stream: `analysis` after every tick (and the latest one on connect), `risk_event`
+// for each risk event and `prices` with the mids of held coins, at most every PRICE_TICK_INTERVAL
+async fn stream_updates(data: web::Data<Arc<AppState>>) -> HttpResponse {
+    let receiver = data.live_updates.subscribe();
+    let initial: Vec<std::result::Result<web::Bytes, Infallible>> = analysis_frame(&data.risk_service.snapshot())
+        .map(Ok)
+        .into_iter()
+        .collect();
+    
+    let updates = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(frame) => return Some((Ok(frame), receiver)),
+                // A slow client skips frames rather than holding up everyone else
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
             }
         }
-    }).await {
-        Ok(json_data) => Ok(HttpResponse::Ok().json(json_data)),
+    });
+    
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(futures::StreamExt::chain(futures::stream::iter(initial), updates))
+}
+
+// API endpoint to export logged history (`snapshots`, `positions` or `warnings`) as a download.
+// Optional: `format` (csv, ndjson or, with the export-parquet feature, parquet; default csv), `start`/`end` (unix seconds) and `columns`
+// (comma-separated, in output order). The log is read on a blocking thread and streamed as it is encoded.
+async fn export_history(
+    data: web::Data<Arc<AppState>>,
+    path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<HttpResponse> {
+    let bad_request = |message: String| Ok(HttpResponse::BadRequest().json(json!({ "error": message })));
+    
+    let dataset = match ExportDataset::from_str(&path) {
+        Ok(dataset) => dataset,
+        Err(e) => return bad_request(e.to_string()),
+    };
+    let format = match query.get("format").map(|f| ExportFormat::from_str(f)).unwrap_or(Ok(ExportFormat::Csv)) {
+        Ok(format) => format,
+        Err(e) => return bad_request(e.to_string()),
+    };
+    let mut bounds = [None, None];
+    for (bound, key) in bounds.iter_mut().zip(["start", "end"]) {
+        match query.get(key).map(|v| v.parse::<u64>()) {
+            Some(Ok(value)) => *bound = Some(value),
+            Some(Err(_)) => return bad_request(format!("Invalid {} parameter, must be a timestamp in seconds", key)),
+            None => {}
+        }
+    }
+    let [start, end] = bounds;
+    let columns: Option<Vec<String>> = query.get("columns")
+        .map(|c| c.split(',').map(|column| column.trim().to_string()).filter(|column| !column.is_empty()).collect());
+    let mut exporter = match HistoryExporter::new(dataset, format, columns.as_deref()) {
+        Ok(exporter) => exporter,
+        Err(e) => return bad_request(e.to_string()),
+    };
+    
+    // The bounded channel keeps the reader at most a few chunks ahead of a slow client
+    let (sender, receiver) = mpsc::channel::<web::Bytes>(EXPORT_CHANNEL_CAPACITY);
+    let state = data.get_ref().clone();
+    tokio::task::spawn_blocking(move || {
+        let result = state.data_logger.export_history(&mut exporter, start, end, |chunk| {
+            sender.blocking_send(web::Bytes::from(chunk))
+                .map_err(|_| hyperliquid_rust_sdk::Error::Custom("Export download was closed".to_string()))
+        });
+        if let Err(e) = result {
+            error!("Failed to export {} history: {}", dataset.name(), e);
+        }
+    });
+    
+    let chunks = futures::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (Ok::<_, Infallible>(chunk), receiver))
+    });
+    
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"risk_{}.{}\"", dataset.name(), format.extension()),
+        ))
+        .streaming(chunks))
+}
+
+// API endpoint to get fee tier and trading cost analytics (`days` lookback for fills, default 14)
+async fn get_fee_analytics(
+    data: web::Data<Arc<AppState>>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl Responder> {
+    let lookback_days = match query.get("days").map(|d| d.parse::<u64>()) {
+        Some(Ok(days)) if (1..=90).contains(&days) => days,
+        Some(_) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "error": "days must be a number between 1 and 90"
+            })));
+        },
+        None => 14,
+    };
+    
+    let analytics = data.risk_service.get_fee_analytics(lookback_days).await;
+    
+    match analytics {
+        Ok(analytics) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": analytics
+        }))),
         Err(e) => {
-            let error_message = format!("Error processing request: {}", e);
-            error!("{}", error_message);
-            
+            error!("Failed to get fee analytics: {}", e);
             Ok(HttpResponse::InternalServerError().json(json!({
                 "success": false,
-                "error": error_message
+                "error": format!("Failed to get fee analytics: {}", e)
             })))
         }
     }
@@ -266,21 +679,24 @@ async fn get_risk_summary(data: web::Data<Arc<AppState>>) -> Result<impl Respond
 
 // API endpoint to get the current settings
 async fn get_settings(data: web::Data<Arc<AppState>>) -> Result<impl Responder> {
-    let risk_system = data.risk_system.lock().unwrap();
-    let settings = risk_system.get_config().get_user_settings();
-    
-    Ok(HttpResponse::Ok().json(settings))
+    Ok(HttpResponse::Ok().json(&data.risk_service.snapshot().settings))
 }
 
 // API endpoint to update settings
 async fn update_settings(
     data: web::Data<Arc<AppState>>,
+    req: HttpRequest,
     settings: web::Json<hyperliquid_rust_sdk::risk_management::UserSettings>,
 ) -> Result<impl Responder> {
-    let mut risk_system = data.risk_system.lock().unwrap();
+    let updated_by = req.extensions().get::<DashboardIdentity>().map(|identity| identity.name.clone());
     
-    match risk_system.update_settings(settings.into_inner()) {
+    // Returns once the risk service has analyzed with the new settings
+    match data.risk_service.update_settings(settings.into_inner()).await {
         Ok(_) => {
+            if let Some(updated_by) = updated_by {
+                info!("Settings updated by {}", updated_by);
+            }
+            
             Ok(HttpResponse::Ok().json(json!({
                 "status": "success",
                 "message": "Settings updated successfully"
@@ -297,38 +713,30 @@ async fn update_settings(
 
 // Debug endpoint to check what's happening with the risk summary
 async fn debug_risk_summary(data: web::Data<Arc<AppState>>) -> Result<impl Responder> {
-    let mut risk_system = data.risk_system.lock().unwrap();
+    let snapshot = data.risk_service.snapshot();
+    let Some(summary) = &snapshot.summary else {
+        return Ok(no_analysis_yet(&snapshot));
+    };
     
-    match risk_system.get_risk_summary().await {
-        Ok(summary) => {
-            // Log the summary for debugging
-            info!("Debug risk summary: {:?}", &summary);
-            
-            let highest_risk = summary.highest_risk_position.clone().map(|(pos, score)| {
-                json!({
-                    "coin": pos.coin,
-                    "risk_score": score
-                })
-            });
-            
-            let response = json!({
-                "portfolio_heat": summary.portfolio_heat,
-                "highest_risk_position": highest_risk,
-                "warning_count": summary.warning_count,
-                "margin_utilization": summary.margin_utilization,
-                "account_value": summary.account_value,
-                "debug_info": format!("{:?}", summary)
-            });
-            
-            Ok(HttpResponse::Ok().json(response))
-        },
-        Err(e) => {
-            error!("Failed to get debug risk summary: {}", e);
-            Ok(HttpResponse::InternalServerError().json(json!({
-                "error": format!("Failed to get debug risk summary: {}", e)
-            })))
-        }
-    }
+    // Log the summary for debugging
+    info!("Debug risk summary: {:?}", summary);
+    
+    let highest_risk = summary.highest_risk_position.clone().map(|(pos, score)| {
+        json!({
+            "coin": pos.coin,
+            "risk_score": score
+        })
+    });
+    
+    Ok(HttpResponse::Ok().json(json!({
+        "portfolio_heat": summary.portfolio_heat,
+        "highest_risk_position": highest_risk,
+        "warning_count": summary.warning_count,
+        "margin_utilization": summary.margin_utilization,
+        "account_value": summary.account_value,
+        "snapshot": snapshot_json(&snapshot),
+        "debug_info": format!("{:?}", summary)
+    })))
 }
 
 // Health check endpoint for Render
@@ -417,6 +825,20 @@ async fn main() -> std::io::Result<()> {
         }
     };
     
+    // Dashboard API credentials
+    let auth_config = match DashboardAuthConfig::from_env() {
+        Ok(auth_config) => auth_config,
+        Err(e) => {
+            error!("Failed to load dashboard authentication: {}", e);
+            println!("\nConfiguration Error: {}", e);
+            return Ok(());
+        }
+    };
+    if !auth_config.is_enabled() {
+        warn!("Dashboard authentication is disabled; set DASHBOARD_API_TOKENS or DASHBOARD_USERS to require credentials");
+        println!("Warning: dashboard authentication is disabled, anyone who can reach the server can change settings.");
+    }
+    
     // Create risk management system
     let risk_system = match RiskManagementSystem::new(config.clone()).await {
         Ok(system) => system,
@@ -430,55 +852,118 @@ async fn main() -> std::io::Result<()> {
     // Create data logger
     let data_logger = DataLogger::new(config.clone());
     
+    // Subscribe before the first analysis so no events are missed
+    let mut event_subscription = risk_system.subscribe(RiskEventFilter::all());
+    
     // Create shared state with a semaphore to limit concurrent intensive operations
     // Allow up to 3 concurrent intensive operations
+    let update_interval = Duration::from_secs(config.log_interval_seconds.max(1));
     let app_state = Arc::new(AppState {
-        risk_system: Mutex::new(risk_system),
+        risk_service: RiskService::spawn(risk_system, update_interval),
         data_logger,
         intensive_ops_semaphore: Semaphore::new(3),
+        recent_events: Mutex::new(VecDeque::with_capacity(RECENT_EVENTS_CAPACITY)),
+        live_updates: broadcast::channel(LIVE_UPDATES_CAPACITY).0,
+        auth: DashboardAuth::new(auth_config),
     });
     
-    // Start background task to update risk analysis
-    let app_state_clone = app_state.clone();
-    let update_interval = config.log_interval_seconds;
+    // Keep a rolling buffer of risk events for the events endpoint
+    let app_state_events = app_state.clone();
+    tokio::spawn(async move {
+        while let Ok(event) = event_subscription.recv().await {
+            let timestamp = chrono::Utc::now().timestamp() as u64;
+            let _ = app_state_events.live_updates.send(sse_frame("risk_event", &json!({
+                "timestamp": timestamp,
+                "event": event
+            })));
+            let mut recent_events = app_state_events.recent_events.lock().unwrap();
+            if recent_events.len() == RECENT_EVENTS_CAPACITY {
+                recent_events.pop_front();
+            }
+            recent_events.push_back((timestamp, event));
+        }
+    });
     
-    // Use a separate thread for the background task
+    // Push price ticks for held coins to live stream clients
+    let app_state_prices = app_state.clone();
+    let price_base_url = config.base_url;
     tokio::spawn(async move {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let mut info_client = match InfoClient::with_reconnect(None, Some(price_base_url)).await {
+            Ok(client) => client,
+            Err(e) => {
+                error!("Price ticks unavailable: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = info_client.subscribe(Subscription::AllMids, sender).await {
+            error!("Price ticks unavailable: {}", e);
+            return;
+        }
+        
+        let mut latest_mids: HashMap<String, String> = HashMap::new();
+        let mut changed = false;
+        let mut ticker = time::interval(PRICE_TICK_INTERVAL);
         loop {
-            // Sleep for the configured interval
-            time::sleep(Duration::from_secs(update_interval)).await;
-            
-            // Clone the Arc for the semaphore
-            let app_state_semaphore = app_state_clone.clone();
-            
-            // Create a separate clone for the closure to avoid borrowing issues
-            let app_state_for_closure = app_state_clone.clone();
-            
-            // Use the cloned state for the semaphore as well
-            match run_intensive_task(&app_state_semaphore.intensive_ops_semaphore, move || {
-                // Get a lock on the risk system
-                let mut risk_system = app_state_for_closure.risk_system.lock().unwrap();
-                
-                // Perform risk analysis
-                match futures::executor::block_on(risk_system.analyze_risk_profile()) {
-                    Ok(analysis) => {
-                        info!("Updated risk analysis");
-                        
-                        // Log the analysis if configured to do so
-                        if let Err(e) = futures::executor::block_on(app_state_for_closure.data_logger.log_risk_data(&analysis)) {
-                            error!("Failed to log risk data: {}", e);
-                        }
-                        
-                        Ok::<(), String>(())
-                    },
-                    Err(e) => {
-                        error!("Failed to update risk analysis: {}", e);
-                        Err::<(), String>(format!("Analysis error: {}", e))
+            tokio::select! {
+                message = receiver.recv() => match message {
+                    Some(Message::AllMids(all_mids)) => {
+                        latest_mids = all_mids.data.mids;
+                        changed = true;
+                    }
+                    Some(_) => {}
+                    None => break,
+                },
+                _ = ticker.tick() => {
+                    if !changed || app_state_prices.live_updates.receiver_count() == 0 {
+                        continue;
+                    }
+                    changed = false;
+                    let held = held_coins(&app_state_prices);
+                    let mids: HashMap<&String, &String> = latest_mids.iter()
+                        .filter(|(coin, _)| held.contains(*coin))
+                        .collect();
+                    if !mids.is_empty() {
+                        let _ = app_state_prices.live_updates.send(sse_frame("prices", &json!({
+                            "timestamp": chrono::Utc::now().timestamp_millis(),
+                            "mids": mids
+                        })));
                     }
                 }
-            }).await {
-                Ok(_) => info!("Background risk analysis completed successfully"),
-                Err(e) => error!("Background risk analysis failed: {}", e)
+            }
+        }
+    });
+    
+    // Keep idle live streams open through proxies
+    let app_state_keep_alive = app_state.clone();
+    tokio::spawn(async move {
+        let mut ticker = time::interval(STREAM_KEEP_ALIVE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let _ = app_state_keep_alive.live_updates.send(web::Bytes::from_static(b": keep-alive\n\n"));
+        }
+    });
+    
+    // Log each new analysis and push it to live stream clients
+    let app_state_snapshots = app_state.clone();
+    let mut snapshots = app_state.risk_service.subscribe();
+    tokio::spawn(async move {
+        let mut last_logged = None;
+        while snapshots.changed().await.is_ok() {
+            let snapshot = snapshots.borrow_and_update().clone();
+            // No receivers just means nobody is streaming
+            if let Some(frame) = analysis_frame(&snapshot) {
+                let _ = app_state_snapshots.live_updates.send(frame);
+            }
+            
+            // Failed ticks keep the previous analysis, which is already logged
+            let Some(analysis) = snapshot.analysis.as_ref().filter(|_| snapshot.updated_at != last_logged) else {
+                continue;
+            };
+            last_logged = snapshot.updated_at;
+            info!("Updated risk analysis");
+            if let Err(e) = app_state_snapshots.data_logger.log_risk_data(analysis).await {
+                error!("Failed to log risk data: {}", e);
             }
         }
     });
@@ -551,6 +1036,7 @@ async fn main() -> std::io::Result<()> {
         
         // Base app with security headers
         let base_app = App::new()
+            .wrap(middleware::from_fn(authorize))
             .wrap(cors)
             // Add security headers middleware
             .wrap(middleware::DefaultHeaders::new()
@@ -578,6 +1064,20 @@ async fn main() -> std::io::Result<()> {
                     .route("/positions", web::get().to(get_positions))
                     .route("/metrics/{metric}", web::get().to(get_metric_history))
                     .route("/positions/{coin}/{metric}", web::get().to(get_position_history))
+                    .route("/pnl_attribution", web::get().to(get_pnl_attribution))
+                    .route("/pnl_attribution/history", web::get().to(get_pnl_attribution_history))
+                    .route("/fees", web::get().to(get_fee_analytics))
+                    .route("/events", web::get().to(get_risk_events))
+                    .route("/stream", web::get().to(stream_updates))
+                    .route("/export/{dataset}", web::get().to(export_history))
+                    // Auth endpoints share the stricter rate limit to slow down password guessing
+                    .service(
+                        web::scope("/auth")
+                            .wrap(Governor::new(&settings_governor_conf))
+                            .route("/login", web::post().to(login))
+                            .route("/logout", web::post().to(logout))
+                            .route("/session", web::get().to(get_session))
+                    )
                     // Settings endpoints with stricter rate limit
                     .service(
                         web::scope("/settings")
@@ -1,8 +1,10 @@
 use std::env;
 use std::time::Duration;
 use tokio::time;
-use log::{info, error};
-use hyperliquid_rust_sdk::risk_management::{RiskManagementSystem, RiskConfig};
+use log::{info, error, warn};
+use hyperliquid_rust_sdk::risk_management::{
+    RiskManagementSystem, RiskConfig, RiskEvent, RiskEventFilter, RiskEventKind, RiskSeverity
+};
 use hyperliquid_rust_sdk::Error;
 
 #[tokio::main]
@@ -90,6 +92,41 @@ async fn main() -> std::result::Result<(), Error> {
         }
     };
     
+    // Alert on high severity warnings, position changes and stale data as they happen
+    let mut alerts = risk_system.subscribe(
+        RiskEventFilter::all()
+            .kinds([
+                RiskEventKind::PositionOpened,
+                RiskEventKind::PositionClosed,
+                RiskEventKind::WarningRaised,
+                RiskEventKind::WarningCleared,
+                RiskEventKind::DataStale,
+            ])
+            .min_severity(RiskSeverity::High),
+    );
+    tokio::spawn(async move {
+        while let Ok(event) = alerts.recv().await {
+            match event {
+                RiskEvent::PositionOpened { position } => {
+                    println!("[ALERT] Opened {} position of {}", position.coin, position.size);
+                }
+                RiskEvent::PositionClosed { position } => {
+                    println!("[ALERT] Closed {} position", position.coin);
+                }
+                RiskEvent::WarningRaised { warning } => {
+                    println!("[ALERT] {:?}: {} - {}", warning.severity, warning.message, warning.suggested_action);
+                }
+                RiskEvent::WarningCleared { warning } => {
+                    println!("[ALERT] Cleared {:?} warning: {}", warning.warning_type, warning.message);
+                }
+                RiskEvent::DataStale { source, error, .. } => {
+                    warn!("Risk data is stale, failed to refresh {}: {}", source, error);
+                }
+                _ => {}
+            }
+        }
+    });
+    
     // Run initial risk analysis
     println!("\nPerforming initial risk analysis...\n");
     match risk_system.analyze_risk_profile().await {
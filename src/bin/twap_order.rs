@@ -0,0 +1,74 @@
+use ethers::signers::{LocalWallet, Signer};
+use log::info;
+
+use hyperliquid_rust_sdk::{
+    BaseUrl, ClientTwapRequest, ExchangeClient, InfoClient, Subscription, TwapOrderStatus,
+    TwapResponseStatus, TwapTracker,
+};
+use tokio::{sync::mpsc::unbounded_channel, time::Duration};
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Key was randomly generated for testing and shouldn't be used with any real funds
+    let wallet: LocalWallet = "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+        .parse()
+        .unwrap();
+    let user = wallet.address();
+
+    let exchange_client = ExchangeClient::new(None, wallet, Some(BaseUrl::Testnet), None, None)
+        .await
+        .unwrap();
+    let mut info_client = InfoClient::new(None, Some(BaseUrl::Testnet)).await.unwrap();
+
+    let (sender, mut receiver) = unbounded_channel();
+    info_client
+        .subscribe(Subscription::UserTwapSliceFills { user }, sender.clone())
+        .await
+        .unwrap();
+    info_client
+        .subscribe(Subscription::UserTwapHistory { user }, sender)
+        .await
+        .unwrap();
+
+    let response = exchange_client
+        .twap_order(
+            ClientTwapRequest {
+                asset: "ETH".to_string(),
+                is_buy: true,
+                sz: 0.1,
+                reduce_only: false,
+                minutes: 5,
+                randomize: false,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    info!("TWAP order placed: {response:?}");
+
+    let twap_id = match response {
+        TwapResponseStatus::Ok(response) => match response.data.status {
+            TwapOrderStatus::Running(running) => running.twap_id,
+            TwapOrderStatus::Error(e) => panic!("Error with TWAP order: {e}"),
+        },
+        TwapResponseStatus::Err(e) => panic!("Error with TWAP order: {e}"),
+    };
+
+    // Follow the slices for a minute, then cancel whatever is left
+    let mut tracker = TwapTracker::new();
+    let _ = tokio::time::timeout(Duration::from_secs(60), async {
+        while let Some(message) = receiver.recv().await {
+            if tracker.apply(&message).contains(&twap_id) {
+                info!("TWAP progress: {:?}", tracker.get(twap_id));
+            }
+        }
+    })
+    .await;
+
+    let response = exchange_client
+        .twap_cancel("ETH", twap_id, None)
+        .await
+        .unwrap();
+    info!("TWAP cancel result: {response:?}");
+}
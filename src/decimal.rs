@@ -0,0 +1,64 @@
+use rust_decimal::prelude::*;
+
+use crate::{helpers::WIRE_DECIMALS, prelude::*, Error};
+
+pub use rust_decimal::Decimal;
+
+/// Parses an API string into a decimal, naming the field in the error on failure
+pub fn parse_decimal(field: &str, value: &str) -> Result<Decimal> {
+    Decimal::from_str_exact(value.trim())
+        .or_else(|_| Decimal::from_scientific(value.trim()))
+        .map_err(|_| Error::DecimalParse {
+            field: field.to_string(),
+            value: value.to_string(),
+        })
+}
+
+/// Parses an optional API string into a decimal
+pub fn parse_optional_decimal(field: &str, value: Option<&String>) -> Result<Option<Decimal>> {
+    value.map(|v| parse_decimal(field, v)).transpose()
+}
+
+/// Converts a decimal to `f64` for statistical risk math
+pub fn decimal_to_f64(value: Decimal) -> f64 {
+    value.to_f64().unwrap_or(0.0)
+}
+
+/// Wire representation of a decimal; errors instead of silently rounding past 8 decimal places
+pub(crate) fn decimal_to_string_for_hashing(x: Decimal) -> Result<String> {
+    let normalized = x.normalize();
+    if normalized.scale() > WIRE_DECIMALS as u32 {
+        return Err(Error::DecimalPrecision(x.to_string()));
+    }
+    if normalized.is_zero() {
+        Ok("0".to_string())
+    } else {
+        Ok(normalized.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_decimal_surfaces_errors() {
+        assert_eq!(parse_decimal("px", "0.1").unwrap().to_string(), "0.1");
+        assert_eq!(parse_decimal("px", "1e-3").unwrap().to_string(), "0.001");
+        let err = parse_decimal("szi", "abc").unwrap_err();
+        assert!(matches!(err, Error::DecimalParse { field, .. } if field == "szi"));
+    }
+
+    #[test]
+    fn decimal_to_string_for_hashing_test() -> Result<()> {
+        let d = |s: &str| Decimal::from_str(s).unwrap();
+        assert_eq!(decimal_to_string_for_hashing(d("0"))?, "0");
+        assert_eq!(decimal_to_string_for_hashing(d("-0.0000"))?, "0");
+        assert_eq!(decimal_to_string_for_hashing(d("0.00076000"))?, "0.00076");
+        assert_eq!(decimal_to_string_for_hashing(d("87654321.12345678"))?, "87654321.12345678");
+        assert_eq!(decimal_to_string_for_hashing(d("987654321.00000000"))?, "987654321");
+        assert!(decimal_to_string_for_hashing(d("0.000000001")).is_err());
+        Ok(())
+    }
+
+}
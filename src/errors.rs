@@ -1,5 +1,9 @@
+use std::time::Duration;
+
 use thiserror::Error;
 
+use crate::{ExchangeRejection, OrderValidationError, Retryability};
+
 #[derive(Error, Debug, Clone)]
 pub enum Error {
     // TODO: turn some embedded types into errors instead of strings
@@ -55,6 +59,12 @@ pub enum Error {
     RmpParse(String),
     #[error("Invalid input number")]
     FloatStringParse,
+    #[error("Failed to parse {field} as a decimal: {value:?}")]
+    DecimalParse { field: String, value: String },
+    #[error("Decimal has more than 8 decimal places: {0}")]
+    DecimalPrecision(String),
+    #[error("Invalid TP/SL: {0}")]
+    InvalidTpsl(String),
     #[error("No cloid found in order request when expected")]
     NoCloid,
     #[error("ECDSA signature failed: {0:?}")]
@@ -65,6 +75,47 @@ pub enum Error {
     Custom(String),
     #[error("IO error: {0}")]
     Io(String),
+    #[error("Order validation failed: {0}")]
+    OrderValidation(OrderValidationError),
+    #[error("Exchange rejected request: {0}")]
+    Rejected(ExchangeRejection),
+    #[error("Rate limit budget exhausted, retry after {0:?}")]
+    RateLimited(Duration),
+    #[error("API marked down by the circuit breaker, retry after {0:?}")]
+    CircuitOpen(Duration),
+    /// An exchange request failed after it may have reached the exchange, e.g. on a timeout
+    #[error("Exchange request may or may not have been applied: {0}")]
+    OutcomeUnknown(Box<Error>),
+}
+
+impl Error {
+    /// Whether the failed call can succeed if made again
+    pub fn retryability(&self) -> Retryability {
+        match self {
+            Error::Rejected(rejection) => rejection.retryability(),
+            Error::ClientRequest {
+                status_code: 429, ..
+            }
+            | Error::ServerRequest { .. }
+            | Error::GenericRequest(_)
+            | Error::RateLimited(_)
+            | Error::CircuitOpen(_) => Retryability::RetryAfterBackoff,
+            Error::OutcomeUnknown(_) => Retryability::OutcomeUnknown,
+            _ => Retryability::NotRetryable,
+        }
+    }
+}
+
+impl From<ExchangeRejection> for Error {
+    fn from(error: ExchangeRejection) -> Self {
+        Error::Rejected(error)
+    }
+}
+
+impl From<OrderValidationError> for Error {
+    fn from(error: OrderValidationError) -> Self {
+        Error::OrderValidation(error)
+    }
 }
 
 impl From<std::io::Error> for Error {
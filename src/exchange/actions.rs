@@ -12,7 +12,7 @@ pub(crate) use ethers::{
 };
 use serde::{Deserialize, Serialize};
 
-use super::{cancel::CancelRequestCloid, BuilderInfo};
+use super::{cancel::CancelRequestCloid, twap::TwapRequest, BuilderInfo};
 
 pub(crate) const HYPERLIQUID_EIP_PREFIX: &str = "HyperliquidTransaction:";
 
@@ -121,6 +121,29 @@ pub struct BulkCancelCloid {
     pub cancels: Vec<CancelRequestCloid>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TwapOrder {
+    pub twap: TwapRequest,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TwapCancel {
+    #[serde(rename = "a", alias = "asset")]
+    pub asset: u32,
+    #[serde(rename = "t", alias = "twapId")]
+    pub twap_id: u64,
+}
+
+/// Cancels every open order at `time` (unix ms); `None` removes the scheduled cancel
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleCancel {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<u64>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ApproveAgent {
@@ -0,0 +1,1278 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethers::types::H160;
+use log::{info, warn};
+use rand::{thread_rng, Rng};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant, MissedTickBehavior};
+use uuid::Uuid;
+
+use crate::{
+    prelude::*, ClientCancelRequest, ClientLimit, ClientModifyRequest, ClientOrder,
+    ClientOrderRequest, Error, ExchangeClient, ExchangeDataStatus, ExchangeResponseStatus,
+    InfoClient, Message, Subscription, Trade, TradeInfo, EPSILON, MIN_ORDER_NOTIONAL,
+};
+
+/// Rejected child orders in a row before the algo gives up
+const MAX_CONSECUTIVE_REJECTS: u32 = 5;
+/// How long fills for orders the algo hasn't heard back about yet are kept
+const PENDING_FILL_TTL_MS: u64 = 60_000;
+/// Largest shift of a randomized TWAP slice time, as a fraction of the slice interval
+const TWAP_TIME_JITTER: f64 = 0.3;
+/// Largest change of a randomized TWAP slice size, as a fraction of the average slice
+const TWAP_SIZE_JITTER: f64 = 0.25;
+
+/// Releases the order in `slices` over `duration`. Each slice rests at the touch until the next
+/// one is released; anything still behind schedule then crosses the spread.
+#[derive(Debug, Clone)]
+pub struct TwapSchedule {
+    pub duration: Duration,
+    pub slices: u32,
+    /// Jitters slice times and sizes so the slices are harder to spot
+    pub randomize: bool,
+}
+
+/// Shows at most `visible_sz` at the touch and replenishes it as it fills
+#[derive(Debug, Clone)]
+pub struct IcebergParams {
+    pub visible_sz: f64,
+}
+
+/// Trades `participation_rate` (above 0, at most 1) of the volume printed since the start. Its
+/// share of each print is taken by crossing the spread, so it never runs ahead of the market.
+#[derive(Debug, Clone)]
+pub struct PovParams {
+    pub participation_rate: f64,
+    /// Largest single child order
+    pub max_child_sz: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub enum AlgoStrategy {
+    Twap(TwapSchedule),
+    Iceberg(IcebergParams),
+    Pov(PovParams),
+}
+
+#[derive(Debug, Clone)]
+pub struct AlgoOrderParams {
+    /// Perp to trade
+    pub asset: String,
+    pub is_buy: bool,
+    pub sz: f64,
+    /// Children never buy above or sell below this price
+    pub limit_px: Option<f64>,
+    pub reduce_only: bool,
+    pub strategy: AlgoStrategy,
+    /// How often the working child order is re-priced and re-sized
+    pub poll_interval: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlgoStatus {
+    Running,
+    Paused,
+    Completed,
+    Cancelled,
+    Failed(String),
+}
+
+impl AlgoStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            AlgoStatus::Completed | AlgoStatus::Cancelled | AlgoStatus::Failed(_)
+        )
+    }
+}
+
+/// The child order currently on the book
+#[derive(Debug, Clone)]
+pub struct WorkingOrder {
+    pub oid: u64,
+    pub px: f64,
+    pub sz: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct AlgoProgress {
+    pub status: AlgoStatus,
+    pub target_sz: f64,
+    pub filled_sz: f64,
+    pub filled_notional: f64,
+    pub fees: f64,
+    /// Size the TWAP schedule or the POV share of volume calls for by now
+    pub scheduled_sz: Option<f64>,
+    /// Child orders placed, counting each modify as a new order
+    pub child_orders: usize,
+    pub working_order: Option<WorkingOrder>,
+    pub last_error: Option<String>,
+}
+
+impl AlgoProgress {
+    pub fn average_px(&self) -> Option<f64> {
+        (self.filled_sz > 0.0).then(|| self.filled_notional / self.filled_sz)
+    }
+
+    pub fn remaining_sz(&self) -> f64 {
+        (self.target_sz - self.filled_sz).max(0.0)
+    }
+}
+
+enum AlgoCommand {
+    Pause,
+    Resume,
+    Cancel,
+}
+
+/// Client-side execution algorithm working a parent order through child limit orders from a
+/// background task.
+///
+/// The task keeps at most one child on the book, placing it with `bulk_order` and re-pricing it
+/// with `bulk_modify`, priced off the `l2Book` feed. Fills are counted from the `userFills`
+/// channel. Dropping the handle cancels the algo along with its working order.
+pub struct AlgoOrder {
+    commands: mpsc::UnboundedSender<AlgoCommand>,
+    progress: watch::Receiver<AlgoProgress>,
+    task: JoinHandle<()>,
+}
+
+impl AlgoOrder {
+    /// Subscribes to the feeds the strategy needs and starts working the order. `info_client` is
+    /// moved into the task so its subscriptions live as long as the algo.
+    pub async fn start(
+        exchange_client: Arc<ExchangeClient>,
+        mut info_client: InfoClient,
+        params: AlgoOrderParams,
+    ) -> Result<Self> {
+        validate_params(&params)?;
+        let sz_decimals = exchange_client
+            .instruments
+            .get(&params.asset)
+            .map(|instrument| instrument.sz_decimals)
+            .ok_or(Error::AssetNotFound)?;
+        let user = exchange_client
+            .vault_address
+            .unwrap_or_else(|| exchange_client.wallet.address());
+
+        let (sender, messages) = mpsc::unbounded_channel();
+        info_client
+            .subscribe(
+                Subscription::L2Book {
+                    coin: params.asset.clone(),
+                },
+                sender.clone(),
+            )
+            .await?;
+        info_client
+            .subscribe(Subscription::UserFills { user }, sender.clone())
+            .await?;
+        if let AlgoStrategy::Pov(_) = params.strategy {
+            info_client
+                .subscribe(
+                    Subscription::Trades {
+                        coin: params.asset.clone(),
+                    },
+                    sender,
+                )
+                .await?;
+        }
+
+        let engine = AlgoEngine::new(exchange_client, params, sz_decimals);
+        info!(
+            "Starting {} for {} {}",
+            engine.strategy.name(),
+            engine.params.sz,
+            engine.params.asset
+        );
+        let (progress_sender, progress) = watch::channel(engine.progress());
+        let (commands, command_receiver) = mpsc::unbounded_channel();
+        let task = tokio::spawn(run(
+            engine,
+            info_client,
+            messages,
+            command_receiver,
+            progress_sender,
+        ));
+
+        Ok(Self {
+            commands,
+            progress,
+            task,
+        })
+    }
+
+    /// Pulls the working order and stops placing children until `resume`. A paused TWAP's
+    /// schedule is pushed back by the time spent paused.
+    pub fn pause(&self) {
+        let _ = self.commands.send(AlgoCommand::Pause);
+    }
+
+    pub fn resume(&self) {
+        let _ = self.commands.send(AlgoCommand::Resume);
+    }
+
+    /// Cancels the working order and stops the algo; `wait` returns once that is done
+    pub fn cancel(&self) {
+        let _ = self.commands.send(AlgoCommand::Cancel);
+    }
+
+    pub fn progress(&self) -> AlgoProgress {
+        self.progress.borrow().clone()
+    }
+
+    /// Waits for the algo to complete, fail or be cancelled and returns its final progress
+    pub async fn wait(self) -> AlgoProgress {
+        let Self {
+            commands,
+            progress,
+            task,
+        } = self;
+        let _ = task.await;
+        drop(commands);
+        let progress = progress.borrow().clone();
+        progress
+    }
+}
+
+fn validate_params(params: &AlgoOrderParams) -> Result<()> {
+    let positive = |value: f64| value.is_finite() && value > 0.0;
+    let invalid = |message: &str| Err(Error::Custom(format!("Invalid algo order: {message}")));
+
+    if !positive(params.sz) {
+        return invalid("size must be positive");
+    }
+    if params.limit_px.is_some_and(|px| !positive(px)) {
+        return invalid("limit price must be positive");
+    }
+    if params.poll_interval.is_zero() {
+        return invalid("poll interval must be positive");
+    }
+    match &params.strategy {
+        AlgoStrategy::Twap(schedule) if schedule.slices == 0 || schedule.duration.is_zero() => {
+            invalid("TWAP needs at least one slice and a positive duration")
+        }
+        AlgoStrategy::Iceberg(iceberg) if !positive(iceberg.visible_sz) => {
+            invalid("iceberg visible size must be positive")
+        }
+        AlgoStrategy::Pov(pov)
+            if !(positive(pov.participation_rate) && pov.participation_rate <= 1.0) =>
+        {
+            invalid("participation rate must be above 0 and at most 1")
+        }
+        AlgoStrategy::Pov(pov) if pov.max_child_sz.is_some_and(|sz| !positive(sz)) => {
+            invalid("max child size must be positive")
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn run(
+    mut engine: AlgoEngine,
+    // Held so the subscriptions stay open
+    info_client: InfoClient,
+    mut messages: mpsc::UnboundedReceiver<Message>,
+    mut commands: mpsc::UnboundedReceiver<AlgoCommand>,
+    progress: watch::Sender<AlgoProgress>,
+) {
+    let mut ticker = time::interval(engine.params.poll_interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    while !engine.status.is_finished() {
+        tokio::select! {
+            command = commands.recv() => match command {
+                Some(AlgoCommand::Pause) => engine.pause().await,
+                Some(AlgoCommand::Resume) => engine.resume(),
+                Some(AlgoCommand::Cancel) | None => engine.finish(AlgoStatus::Cancelled).await,
+            },
+            message = messages.recv() => match message {
+                Some(message) => engine.on_message(&message),
+                None => {
+                    let reason = "Websocket subscriptions closed".to_string();
+                    engine.finish(AlgoStatus::Failed(reason)).await;
+                }
+            },
+            _ = ticker.tick() => engine.step(&info_client).await,
+        }
+        progress.send_replace(engine.progress());
+    }
+    info!(
+        "{} for {} {} finished: {:?}",
+        engine.strategy.name(),
+        engine.params.sz,
+        engine.params.asset,
+        engine.status
+    );
+}
+
+/// Size the strategy wants working now and whether it should cross the spread for it
+#[derive(Debug, PartialEq)]
+struct ChildTarget {
+    sz: f64,
+    aggressive: bool,
+}
+
+enum StrategyState {
+    Twap {
+        duration: Duration,
+        /// Release time and cumulative size released at that time, per slice
+        releases: Vec<(Duration, f64)>,
+    },
+    Iceberg {
+        visible_sz: f64,
+    },
+    Pov {
+        participation_rate: f64,
+        max_child_sz: Option<f64>,
+        started_ms: u64,
+        /// Market volume since the start, leaving out our own fills
+        volume: f64,
+        seen_trades: HashSet<u64>,
+        own_trades: HashSet<u64>,
+    },
+}
+
+impl StrategyState {
+    fn new(strategy: &AlgoStrategy, total_sz: f64, started_ms: u64) -> Self {
+        match strategy {
+            AlgoStrategy::Twap(schedule) => StrategyState::Twap {
+                duration: schedule.duration,
+                releases: twap_releases(schedule, total_sz),
+            },
+            AlgoStrategy::Iceberg(iceberg) => StrategyState::Iceberg {
+                visible_sz: iceberg.visible_sz,
+            },
+            AlgoStrategy::Pov(pov) => StrategyState::Pov {
+                participation_rate: pov.participation_rate,
+                max_child_sz: pov.max_child_sz,
+                started_ms,
+                volume: 0.0,
+                seen_trades: HashSet::new(),
+                own_trades: HashSet::new(),
+            },
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            StrategyState::Twap { .. } => "TWAP",
+            StrategyState::Iceberg { .. } => "Iceberg",
+            StrategyState::Pov { .. } => "POV",
+        }
+    }
+
+    fn on_trades(&mut self, trades: &[Trade]) {
+        if let StrategyState::Pov {
+            started_ms,
+            volume,
+            seen_trades,
+            own_trades,
+            ..
+        } = self
+        {
+            for trade in trades {
+                if trade.time < *started_ms
+                    || own_trades.contains(&trade.tid)
+                    || !seen_trades.insert(trade.tid)
+                {
+                    continue;
+                }
+                *volume += trade.sz.parse::<f64>().unwrap_or_default();
+            }
+        }
+    }
+
+    // Our children's fills print on the trades feed too, and counting them would have POV chase
+    // its own volume. The fill can land before or after its trade print.
+    fn on_own_fill(&mut self, tid: u64, sz: f64) {
+        if let StrategyState::Pov {
+            volume,
+            seen_trades,
+            own_trades,
+            ..
+        } = self
+        {
+            if own_trades.insert(tid) && seen_trades.contains(&tid) {
+                *volume = (*volume - sz).max(0.0);
+            }
+        }
+    }
+
+    fn scheduled(&self, total_sz: f64, elapsed: Duration) -> Option<f64> {
+        match self {
+            StrategyState::Twap { releases, .. } => Some(
+                releases
+                    .iter()
+                    .take_while(|(at, _)| *at <= elapsed)
+                    .last()
+                    .map_or(0.0, |(_, released)| *released),
+            ),
+            StrategyState::Iceberg { .. } => None,
+            StrategyState::Pov {
+                participation_rate,
+                volume,
+                ..
+            } => Some((participation_rate * volume).min(total_sz)),
+        }
+    }
+
+    fn child(&self, total_sz: f64, filled_sz: f64, elapsed: Duration) -> Option<ChildTarget> {
+        let remaining = total_sz - filled_sz;
+        let target = match self {
+            StrategyState::Twap { duration, releases } => {
+                let released = releases.iter().filter(|(at, _)| *at <= elapsed).count();
+                let current_slice = match released {
+                    0 => 0.0,
+                    1 => releases[0].1,
+                    n => releases[n - 1].1 - releases[n - 2].1,
+                };
+                let behind = self.scheduled(total_sz, elapsed)? - filled_sz;
+                ChildTarget {
+                    sz: behind,
+                    aggressive: elapsed >= *duration || behind > current_slice + EPSILON,
+                }
+            }
+            StrategyState::Iceberg { visible_sz } => ChildTarget {
+                sz: visible_sz.min(remaining),
+                aggressive: false,
+            },
+            StrategyState::Pov { max_child_sz, .. } => {
+                let behind = self.scheduled(total_sz, elapsed)? - filled_sz;
+                ChildTarget {
+                    sz: max_child_sz.map_or(behind, |max| behind.min(max)),
+                    aggressive: true,
+                }
+            }
+        };
+        (target.sz > EPSILON).then_some(ChildTarget {
+            sz: target.sz.min(remaining),
+            ..target
+        })
+    }
+}
+
+// Release time and cumulative size for each TWAP slice. The first slice is released right away
+// and the last before `duration`, even when randomized.
+fn twap_releases(schedule: &TwapSchedule, total_sz: f64) -> Vec<(Duration, f64)> {
+    let slices = schedule.slices as usize;
+    let interval = schedule.duration.as_secs_f64() / slices as f64;
+    let mut rng = thread_rng();
+    let mut jitter = |max: f64| {
+        if schedule.randomize {
+            rng.gen_range(-max..=max)
+        } else {
+            0.0
+        }
+    };
+
+    let times: Vec<f64> = (0..slices)
+        .map(|i| match i {
+            0 => 0.0,
+            i => (i as f64 + jitter(TWAP_TIME_JITTER)) * interval,
+        })
+        .collect();
+    let weights: Vec<f64> = (0..slices)
+        .map(|_| 1.0 + jitter(TWAP_SIZE_JITTER))
+        .collect();
+    let total_weight: f64 = weights.iter().sum();
+
+    let mut released = 0.0;
+    times
+        .into_iter()
+        .zip(weights)
+        .enumerate()
+        .map(|(i, (at, weight))| {
+            released = if i + 1 == slices {
+                total_sz
+            } else {
+                released + total_sz * weight / total_weight
+            };
+            (Duration::from_secs_f64(at), released)
+        })
+        .collect()
+}
+
+// Joins the touch on our side, or crosses to the other side when aggressive, without going
+// through the limit price
+fn child_px(
+    is_buy: bool,
+    aggressive: bool,
+    best_bid: Option<f64>,
+    best_ask: Option<f64>,
+    limit_px: Option<f64>,
+) -> Option<f64> {
+    let px = if is_buy == aggressive {
+        best_ask?
+    } else {
+        best_bid?
+    };
+    Some(match limit_px {
+        Some(limit) if is_buy => px.min(limit),
+        Some(limit) => px.max(limit),
+        None => px,
+    })
+}
+
+#[derive(Debug, Default)]
+struct ChildFills {
+    /// From the order response, for children that filled on placement
+    reported_sz: f64,
+    reported_px: f64,
+    /// From the `userFills` channel
+    streamed_sz: f64,
+    streamed_notional: f64,
+    fees: f64,
+}
+
+impl ChildFills {
+    // The order response and the fills feed can arrive in either order, so go with whichever has
+    // seen more
+    fn filled(&self) -> (f64, f64) {
+        if self.streamed_sz + EPSILON >= self.reported_sz {
+            (self.streamed_sz, self.streamed_notional)
+        } else {
+            (self.reported_sz, self.reported_sz * self.reported_px)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SentChild {
+    cloid: Uuid,
+    px: f64,
+    sz: f64,
+}
+
+struct ActiveClock {
+    started: Instant,
+    paused_total: Duration,
+    paused_at: Option<Instant>,
+}
+
+impl ActiveClock {
+    fn elapsed(&self) -> Duration {
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        now.saturating_duration_since(self.started)
+            .saturating_sub(self.paused_total)
+    }
+}
+
+struct AlgoEngine {
+    client: Arc<ExchangeClient>,
+    user: H160,
+    params: AlgoOrderParams,
+    sz_decimals: u32,
+    strategy: StrategyState,
+    status: AlgoStatus,
+    clock: ActiveClock,
+    started_ms: u64,
+    best_bid: Option<f64>,
+    best_ask: Option<f64>,
+    children: HashMap<u64, ChildFills>,
+    /// Fills for oids not known yet, in case the feed beats the order response
+    pending_fills: Vec<TradeInfo>,
+    seen_fills: HashSet<u64>,
+    working: Option<WorkingOrder>,
+    /// Child sent in a request whose outcome is unknown, looked up before sending another
+    unconfirmed: Option<SentChild>,
+    child_orders: usize,
+    rejects: u32,
+    last_error: Option<String>,
+}
+
+impl AlgoEngine {
+    fn new(client: Arc<ExchangeClient>, params: AlgoOrderParams, sz_decimals: u32) -> Self {
+        let started_ms = chrono::Utc::now().timestamp_millis() as u64;
+        let user = client
+            .vault_address
+            .unwrap_or_else(|| client.wallet.address());
+        Self {
+            client,
+            user,
+            strategy: StrategyState::new(&params.strategy, params.sz, started_ms),
+            params,
+            sz_decimals,
+            status: AlgoStatus::Running,
+            clock: ActiveClock {
+                started: Instant::now(),
+                paused_total: Duration::ZERO,
+                paused_at: None,
+            },
+            started_ms,
+            best_bid: None,
+            best_ask: None,
+            children: HashMap::new(),
+            pending_fills: Vec::new(),
+            seen_fills: HashSet::new(),
+            working: None,
+            unconfirmed: None,
+            child_orders: 0,
+            rejects: 0,
+            last_error: None,
+        }
+    }
+
+    fn progress(&self) -> AlgoProgress {
+        let (filled_sz, filled_notional, fees) = self.filled();
+        AlgoProgress {
+            status: self.status.clone(),
+            target_sz: self.params.sz,
+            filled_sz,
+            filled_notional,
+            fees,
+            scheduled_sz: self
+                .strategy
+                .scheduled(self.params.sz, self.clock.elapsed()),
+            child_orders: self.child_orders,
+            working_order: self.working.clone(),
+            last_error: self.last_error.clone(),
+        }
+    }
+
+    fn filled(&self) -> (f64, f64, f64) {
+        self.children.values().fold((0.0, 0.0, 0.0), |acc, child| {
+            let (sz, notional) = child.filled();
+            (acc.0 + sz, acc.1 + notional, acc.2 + child.fees)
+        })
+    }
+
+    fn round_sz(&self, sz: f64) -> f64 {
+        let pow10 = 10f64.powi(self.sz_decimals as i32);
+        // Nudged so sizes like 0.3 that are a hair under in binary don't lose a lot
+        ((sz * pow10) + 1e-9).floor().max(0.0) / pow10
+    }
+
+    fn on_message(&mut self, message: &Message) {
+        match message {
+            Message::L2Book(book) if book.data.coin == self.params.asset => {
+                let best = |side: usize| {
+                    book.data
+                        .levels
+                        .get(side)
+                        .and_then(|levels| levels.first())
+                        .and_then(|level| level.px.parse::<f64>().ok())
+                };
+                self.best_bid = best(0);
+                self.best_ask = best(1);
+            }
+            Message::Trades(trades) => self.strategy.on_trades(&trades.data),
+            Message::UserFills(fills) => {
+                for fill in &fills.data.fills {
+                    self.on_fill(fill);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_fill(&mut self, fill: &TradeInfo) {
+        let Some(child) = self.children.get_mut(&fill.oid) else {
+            if fill.coin == self.params.asset && fill.time >= self.started_ms {
+                self.pending_fills.push(fill.clone());
+            }
+            return;
+        };
+        if !self.seen_fills.insert(fill.tid) {
+            return;
+        }
+        let (Ok(px), Ok(sz)) = (fill.px.parse::<f64>(), fill.sz.parse::<f64>()) else {
+            return;
+        };
+        child.streamed_sz += sz;
+        child.streamed_notional += px * sz;
+        child.fees += fill.fee.parse::<f64>().unwrap_or_default();
+        self.strategy.on_own_fill(fill.tid, sz);
+    }
+
+    fn register_child(&mut self, oid: u64) {
+        self.children.entry(oid).or_default();
+        self.child_orders += 1;
+        let (matched, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending_fills)
+            .into_iter()
+            .partition(|fill| fill.oid == oid);
+        self.pending_fills = pending;
+        for fill in &matched {
+            self.on_fill(fill);
+        }
+    }
+
+    async fn step(&mut self, info_client: &InfoClient) {
+        let cutoff = chrono::Utc::now().timestamp_millis() as u64 - PENDING_FILL_TTL_MS;
+        self.pending_fills.retain(|fill| fill.time >= cutoff);
+        if self.status != AlgoStatus::Running {
+            return;
+        }
+        if self.unconfirmed.is_some() && !self.check_unconfirmed(info_client).await {
+            return;
+        }
+
+        let (filled_sz, _, _) = self.filled();
+        let remaining = self.round_sz(self.params.sz - filled_sz);
+        if remaining <= 0.0 {
+            self.finish(AlgoStatus::Completed).await;
+            return;
+        }
+        if self.rejects >= MAX_CONSECUTIVE_REJECTS {
+            let reason = self.last_error.clone().unwrap_or_default();
+            self.finish(AlgoStatus::Failed(reason)).await;
+            return;
+        }
+
+        let target = self
+            .strategy
+            .child(self.params.sz, filled_sz, self.clock.elapsed())
+            .and_then(|target| {
+                let px = child_px(
+                    self.params.is_buy,
+                    target.aggressive,
+                    self.best_bid,
+                    self.best_ask,
+                    self.params.limit_px,
+                )?;
+                let sz = self.round_sz(target.sz.min(remaining));
+                let finishes = sz + EPSILON >= remaining;
+                (sz > 0.0 && (finishes || sz * px >= MIN_ORDER_NOTIONAL)).then_some((px, sz))
+            });
+        self.reconcile(target).await;
+    }
+
+    async fn reconcile(&mut self, target: Option<(f64, f64)>) {
+        let Some((px, sz)) = target else {
+            self.cancel_working().await;
+            return;
+        };
+        let sent = SentChild {
+            cloid: Uuid::new_v4(),
+            px,
+            sz,
+        };
+        let Some(working) = self.working.clone() else {
+            let response = self
+                .client
+                .bulk_order(vec![self.child_request(sent)], None)
+                .await;
+            self.on_child_response(response, sent);
+            return;
+        };
+
+        let working_filled = self
+            .children
+            .get(&working.oid)
+            .map_or(0.0, |child| child.filled().0);
+        let lot = 10f64.powi(-(self.sz_decimals as i32));
+        let resting_sz = working.sz - working_filled;
+        if (working.px - px).abs() > EPSILON || (resting_sz - sz).abs() >= lot / 2.0 {
+            let modify = ClientModifyRequest {
+                oid: working.oid,
+                order: self.child_request(sent),
+            };
+            let response = self.client.bulk_modify(vec![modify], None).await;
+            self.on_child_response(response, sent);
+        }
+    }
+
+    // Looks up a child whose request may or may not have landed, by its cloid and then, if it
+    // didn't, the working order it was to replace. Returns false if a lookup failed too.
+    async fn check_unconfirmed(&mut self, info_client: &InfoClient) -> bool {
+        let Some(sent) = self.unconfirmed else {
+            return true;
+        };
+        let child = match info_client
+            .query_order_by_cloid(self.user, sent.cloid)
+            .await
+        {
+            Ok(response) => response.order,
+            Err(e) => {
+                warn!("Failed to look up child order {}: {e}", sent.cloid);
+                self.last_error = Some(e.to_string());
+                return false;
+            }
+        };
+        if let Some(child) = child {
+            // A modify that landed replaced the working order with this one
+            self.register_child(child.order.oid);
+            self.working = (child.status == "open").then_some(WorkingOrder {
+                oid: child.order.oid,
+                px: sent.px,
+                sz: sent.sz,
+            });
+        } else if let Some(working) = self.working.clone() {
+            match info_client.query_order_by_oid(self.user, working.oid).await {
+                Ok(response) => {
+                    if response.order.is_none_or(|order| order.status != "open") {
+                        self.working = None;
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to look up child order {}: {e}", working.oid);
+                    self.last_error = Some(e.to_string());
+                    return false;
+                }
+            }
+        }
+        self.unconfirmed = None;
+        true
+    }
+
+    fn child_request(&self, sent: SentChild) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: self.params.asset.clone(),
+            is_buy: self.params.is_buy,
+            reduce_only: self.params.reduce_only,
+            limit_px: sent.px,
+            sz: sent.sz,
+            cloid: Some(sent.cloid),
+            order_type: ClientOrder::Limit(ClientLimit {
+                tif: "Gtc".to_string(),
+            }),
+        }
+    }
+
+    fn on_child_response(&mut self, response: Result<ExchangeResponseStatus>, sent: SentChild) {
+        if let Err(e) = &response {
+            // The request may have landed, so it is looked up before anything else is sent;
+            // otherwise it never left and the working order is as it was
+            if matches!(e, Error::OutcomeUnknown(_)) {
+                self.unconfirmed = Some(sent);
+            }
+            self.reject(e.to_string());
+            return;
+        }
+        // A rejected modify means the old order already filled or was canceled
+        self.working = None;
+        match first_status(response) {
+            Ok(ExchangeDataStatus::Resting(resting)) => {
+                self.register_child(resting.oid);
+                self.working = Some(WorkingOrder {
+                    oid: resting.oid,
+                    px: sent.px,
+                    sz: sent.sz,
+                });
+                self.rejects = 0;
+            }
+            Ok(ExchangeDataStatus::Filled(filled)) => {
+                self.register_child(filled.oid);
+                if let Some(child) = self.children.get_mut(&filled.oid) {
+                    child.reported_sz = filled.total_sz.parse().unwrap_or_default();
+                    child.reported_px = filled.avg_px.parse().unwrap_or_default();
+                }
+                self.rejects = 0;
+            }
+            Ok(status) => self.reject(format!("Unexpected child order status: {status:?}")),
+            Err(e) => self.reject(e),
+        }
+    }
+
+    fn reject(&mut self, error: String) {
+        warn!(
+            "{} child order for {} failed: {error}",
+            self.strategy.name(),
+            self.params.asset
+        );
+        self.rejects += 1;
+        self.last_error = Some(error);
+    }
+
+    async fn cancel_working(&mut self) {
+        let Some(working) = self.working.take() else {
+            return;
+        };
+        let cancel = ClientCancelRequest {
+            asset: self.params.asset.clone(),
+            oid: working.oid,
+        };
+        match self.client.bulk_cancel(vec![cancel], None).await {
+            Ok(response) => {
+                // An error status means the order is already gone
+                if let Err(e) = first_status(Ok(response)) {
+                    warn!("Child order {} was not canceled: {e}", working.oid);
+                }
+            }
+            Err(e) => {
+                warn!("Failed to cancel child order {}: {e}", working.oid);
+                self.last_error = Some(e.to_string());
+                self.working = Some(working);
+            }
+        }
+    }
+
+    async fn pause(&mut self) {
+        if self.status == AlgoStatus::Running {
+            self.status = AlgoStatus::Paused;
+            self.clock.paused_at = Some(Instant::now());
+            self.cancel_working().await;
+        }
+    }
+
+    fn resume(&mut self) {
+        if self.status == AlgoStatus::Paused {
+            if let Some(paused_at) = self.clock.paused_at.take() {
+                self.clock.paused_total += paused_at.elapsed();
+            }
+            self.status = AlgoStatus::Running;
+        }
+    }
+
+    async fn finish(&mut self, status: AlgoStatus) {
+        self.cancel_working().await;
+        self.status = status;
+    }
+}
+
+fn first_status(
+    response: Result<ExchangeResponseStatus>,
+) -> std::result::Result<ExchangeDataStatus, String> {
+    match response {
+        Ok(ExchangeResponseStatus::Ok(response)) => match response
+            .data
+            .and_then(|data| data.statuses.into_iter().next())
+        {
+            Some(ExchangeDataStatus::Error(e)) => Err(e),
+            Some(status) => Ok(status),
+            None => Err("Exchange response has no statuses".to_string()),
+        },
+        Ok(ExchangeResponseStatus::Err(e)) => Err(e),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::test_utils::*;
+    use crate::mock_server::{MockEndpoint, MockFault, MockFixtures, MockServer};
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn twap_rests_each_slice_then_catches_up() {
+        let schedule = AlgoStrategy::Twap(TwapSchedule {
+            duration: secs(40),
+            slices: 4,
+            randomize: false,
+        });
+        let twap = StrategyState::new(&schedule, 1.0, 0);
+
+        let passive = |sz| {
+            Some(ChildTarget {
+                sz,
+                aggressive: false,
+            })
+        };
+        let aggressive = |sz| {
+            Some(ChildTarget {
+                sz,
+                aggressive: true,
+            })
+        };
+        assert_eq!(twap.child(1.0, 0.0, secs(0)), passive(0.25));
+        assert_eq!(twap.child(1.0, 0.25, secs(9)), None);
+        // The first slice never filled, so both are crossed once the second is released
+        assert_eq!(twap.child(1.0, 0.0, secs(10)), aggressive(0.5));
+        assert_eq!(twap.child(1.0, 0.25, secs(10)), passive(0.25));
+        assert_eq!(twap.child(1.0, 0.5, secs(40)), aggressive(0.5));
+        assert_eq!(twap.scheduled(1.0, secs(25)), Some(0.75));
+
+        let randomized = twap_releases(
+            &TwapSchedule {
+                duration: secs(40),
+                slices: 8,
+                randomize: true,
+            },
+            3.0,
+        );
+        assert_eq!(randomized.len(), 8);
+        assert_eq!(randomized[0].0, Duration::ZERO);
+        assert_eq!(randomized[7].1, 3.0);
+        assert!(randomized
+            .windows(2)
+            .all(|pair| pair[0].0 < pair[1].0 && pair[0].1 < pair[1].1));
+        assert!(randomized[7].0 < secs(40));
+    }
+
+    #[test]
+    fn pov_follows_volume_since_start() {
+        let pov = AlgoStrategy::Pov(PovParams {
+            participation_rate: 0.1,
+            max_child_sz: Some(0.5),
+        });
+        let mut pov = StrategyState::new(&pov, 2.0, 1_000);
+        let trade = |tid, time, sz: &str| Trade {
+            coin: "BTC".to_string(),
+            side: "B".to_string(),
+            px: "50000".to_string(),
+            sz: sz.to_string(),
+            time,
+            hash: "0x0".to_string(),
+            tid,
+        };
+
+        assert_eq!(pov.child(2.0, 0.0, secs(1)), None);
+        pov.on_trades(&[
+            trade(1, 999, "100"),
+            trade(2, 1_000, "3"),
+            trade(3, 1_500, "2"),
+        ]);
+        // Resent trades are only counted once
+        pov.on_trades(&[trade(3, 1_500, "2")]);
+        assert_eq!(pov.scheduled(2.0, secs(1)), Some(0.5));
+        assert_eq!(
+            pov.child(2.0, 0.2, secs(1)),
+            Some(ChildTarget {
+                sz: 0.3,
+                aggressive: true
+            })
+        );
+
+        pov.on_trades(&[trade(4, 2_000, "40")]);
+        assert_eq!(pov.scheduled(2.0, secs(1)), Some(2.0));
+        assert_eq!(
+            pov.child(2.0, 0.5, secs(1)).map(|child| child.sz),
+            Some(0.5)
+        );
+    }
+
+    #[test]
+    fn pov_leaves_out_its_own_fills() {
+        let pov = AlgoStrategy::Pov(PovParams {
+            participation_rate: 0.5,
+            max_child_sz: None,
+        });
+        let mut pov = StrategyState::new(&pov, 10.0, 0);
+        let trade = |tid, sz: &str| Trade {
+            coin: "BTC".to_string(),
+            side: "B".to_string(),
+            px: "50000".to_string(),
+            sz: sz.to_string(),
+            time: 1,
+            hash: "0x0".to_string(),
+            tid,
+        };
+
+        pov.on_trades(&[trade(1, "4"), trade(2, "1")]);
+        // Fill after its print
+        pov.on_own_fill(2, 1.0);
+        assert_eq!(pov.scheduled(10.0, secs(1)), Some(2.0));
+        // Fill before its print
+        pov.on_own_fill(3, 2.0);
+        pov.on_trades(&[trade(3, "2")]);
+        assert_eq!(pov.scheduled(10.0, secs(1)), Some(2.0));
+        // Repeated fills are only taken out once
+        pov.on_own_fill(2, 1.0);
+        assert_eq!(pov.scheduled(10.0, secs(1)), Some(2.0));
+    }
+
+    #[test]
+    fn children_are_capped_at_the_limit_price() {
+        let (bid, ask) = (Some(99.0), Some(101.0));
+        assert_eq!(child_px(true, false, bid, ask, None), Some(99.0));
+        assert_eq!(child_px(true, true, bid, ask, Some(100.0)), Some(100.0));
+        assert_eq!(child_px(false, true, bid, ask, Some(100.0)), Some(100.0));
+        assert_eq!(child_px(false, false, bid, ask, Some(100.0)), Some(101.0));
+        assert_eq!(child_px(true, true, bid, None, None), None);
+    }
+
+    async fn wait_for_progress(
+        algo: &AlgoOrder,
+        done: impl Fn(&AlgoProgress) -> bool,
+    ) -> AlgoProgress {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        loop {
+            let progress = algo.progress();
+            if done(&progress) {
+                return progress;
+            }
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "algo stuck at {progress:?}"
+            );
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn twap_algo_crosses_slices_left_behind() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let client = Arc::new(exchange_client(&server, MAKER_KEY).await);
+        let book = push_btc_book(&server);
+
+        // Slices resting at the 49,990 bid never fill, so each one crosses when the next is released
+        let params = AlgoOrderParams {
+            asset: "BTC".to_string(),
+            is_buy: true,
+            sz: 0.003,
+            limit_px: Some(50_005.0),
+            reduce_only: false,
+            strategy: AlgoStrategy::Twap(TwapSchedule {
+                duration: Duration::from_millis(900),
+                slices: 3,
+                randomize: false,
+            }),
+            poll_interval: Duration::from_millis(50),
+        };
+        let info = InfoClient::new(None, Some(server.base_url())).await?;
+        let algo = AlgoOrder::start(Arc::clone(&client), info, params).await?;
+        let resting = wait_for_progress(&algo, |p| p.working_order.is_some()).await;
+        assert_eq!(resting.working_order.unwrap().px, 49_990.0);
+
+        let progress = tokio::time::timeout(Duration::from_secs(5), algo.wait())
+            .await
+            .expect("TWAP did not finish");
+        book.abort();
+        assert_eq!(progress.status, AlgoStatus::Completed);
+        assert!((progress.filled_sz - 0.003).abs() < EPSILON);
+        // Crossing children are capped at the limit, which still crosses the mid
+        assert_eq!(progress.average_px(), Some(50_000.0));
+        assert!(progress.child_orders >= 3);
+        assert!(InfoClient::new(None, Some(server.base_url()))
+            .await?
+            .open_orders(client.wallet.address())
+            .await?
+            .is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn children_are_looked_up_after_a_transport_error() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let client = Arc::new(exchange_client(&server, MAKER_KEY).await);
+        let info = InfoClient::new(None, Some(server.base_url())).await?;
+        let book = push_btc_book(&server);
+        let params = AlgoOrderParams {
+            asset: "BTC".to_string(),
+            is_buy: true,
+            sz: 0.003,
+            limit_px: None,
+            reduce_only: false,
+            strategy: AlgoStrategy::Iceberg(IcebergParams { visible_sz: 0.001 }),
+            poll_interval: Duration::from_millis(50),
+        };
+
+        // The first child lands but its response is lost; it is found by cloid, not sent again
+        server.inject_fault(MockEndpoint::Exchange, MockFault::DropResponse, Some(1));
+        let algo = AlgoOrder::start(
+            Arc::clone(&client),
+            InfoClient::new(None, Some(server.base_url())).await?,
+            params.clone(),
+        )
+        .await?;
+        let progress = wait_for_progress(&algo, |p| p.working_order.is_some()).await;
+        assert_eq!(progress.child_orders, 1);
+        assert!(progress.last_error.is_some());
+        let open_orders = info.open_orders(client.wallet.address()).await?;
+        assert_eq!(open_orders.len(), 1);
+        assert_eq!(progress.working_order.unwrap().oid, open_orders[0].oid);
+        algo.cancel();
+        algo.wait().await;
+        book.abort();
+
+        // A modify that never landed leaves the working order as it was, unless it is gone
+        let mut engine = AlgoEngine::new(Arc::clone(&client), params, 5);
+        let ExchangeDataStatus::Resting(resting) = status(
+            client
+                .order(limit(true, 49_000.0, 0.001, "Gtc"), None)
+                .await?,
+        ) else {
+            panic!("order did not rest");
+        };
+        let timed_out = || {
+            let timeout = Error::GenericRequest("operation timed out".to_string());
+            Err(Error::OutcomeUnknown(Box::new(timeout)))
+        };
+        let modify = || SentChild {
+            cloid: Uuid::new_v4(),
+            px: 49_990.0,
+            sz: 0.001,
+        };
+        engine.working = Some(WorkingOrder {
+            oid: resting.oid,
+            px: 49_000.0,
+            sz: 0.001,
+        });
+        engine.on_child_response(timed_out(), modify());
+        assert!(engine.check_unconfirmed(&info).await);
+        assert_eq!(engine.working.as_ref().map(|o| o.oid), Some(resting.oid));
+        assert!(engine.unconfirmed.is_none());
+
+        client
+            .cancel(
+                ClientCancelRequest {
+                    asset: "BTC".to_string(),
+                    oid: resting.oid,
+                },
+                None,
+            )
+            .await?;
+        engine.on_child_response(timed_out(), modify());
+        assert!(engine.check_unconfirmed(&info).await);
+        assert!(engine.working.is_none());
+        assert_eq!(engine.rejects, 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn iceberg_algo_replenishes_pauses_and_cancels() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let client = Arc::new(exchange_client(&server, MAKER_KEY).await);
+        let seller = exchange_client(&server, TAKER_KEY).await;
+        let info = InfoClient::new(None, Some(server.base_url())).await?;
+        let book = push_btc_book(&server);
+
+        let params = AlgoOrderParams {
+            asset: "BTC".to_string(),
+            is_buy: true,
+            sz: 0.003,
+            limit_px: None,
+            reduce_only: false,
+            strategy: AlgoStrategy::Iceberg(IcebergParams { visible_sz: 0.001 }),
+            poll_interval: Duration::from_millis(50),
+        };
+        let algo = AlgoOrder::start(
+            Arc::clone(&client),
+            InfoClient::new(None, Some(server.base_url())).await?,
+            params,
+        )
+        .await?;
+        let first = wait_for_progress(&algo, |p| p.working_order.is_some())
+            .await
+            .working_order
+            .unwrap();
+        assert_eq!((first.px, first.sz), (49_990.0, 0.001));
+
+        // Only the visible size trades against the seller; the next clip replaces it
+        status(
+            seller
+                .order(limit(false, 49_990.0, 0.002, "Ioc"), None)
+                .await?,
+        );
+        let refilled = wait_for_progress(&algo, |p| {
+            p.working_order.as_ref().is_some_and(|o| o.oid != first.oid)
+        })
+        .await;
+        assert!((refilled.filled_sz - 0.001).abs() < EPSILON);
+        assert_eq!(refilled.average_px(), Some(49_990.0));
+
+        algo.pause();
+        wait_for_progress(&algo, |p| {
+            p.status == AlgoStatus::Paused && p.working_order.is_none()
+        })
+        .await;
+        assert!(info.open_orders(client.wallet.address()).await?.is_empty());
+        algo.resume();
+        wait_for_progress(&algo, |p| p.working_order.is_some()).await;
+        assert_eq!(info.open_orders(client.wallet.address()).await?.len(), 1);
+
+        algo.cancel();
+        let progress = algo.wait().await;
+        book.abort();
+        assert_eq!(progress.status, AlgoStatus::Cancelled);
+        assert!((progress.remaining_sz() - 0.002).abs() < EPSILON);
+        assert!(info.open_orders(client.wallet.address()).await?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{info, warn};
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant, MissedTickBehavior};
+
+use crate::{prelude::*, Error, ExchangeClient, ExchangeRejection, ExchangeResponseStatus};
+
+/// The exchange rejects scheduled cancels less than this far in the future
+pub const MIN_SCHEDULE_CANCEL_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct DeadMansSwitchConfig {
+    /// How far ahead of now the cancel is scheduled on every refresh. Must exceed
+    /// `MIN_SCHEDULE_CANCEL_DELAY` by more than the request latency.
+    pub window: Duration,
+    /// How often the cancel is pushed forward. Keep it a fraction of `window` so a failed refresh
+    /// or two doesn't let the deadline pass.
+    pub refresh_interval: Duration,
+    /// When set, the cancel is only pushed forward if `DeadMansSwitch::heartbeat` was called
+    /// within this long, so a stuck trading loop gets its orders cancelled even though the process
+    /// is still running
+    pub heartbeat_timeout: Option<Duration>,
+}
+
+impl Default for DeadMansSwitchConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            refresh_interval: Duration::from_secs(15),
+            heartbeat_timeout: None,
+        }
+    }
+}
+
+/// Keeps a `scheduleCancel` deadline a fixed window ahead of now from a background task, so every
+/// open order is cancelled by the exchange if the process dies or stops refreshing.
+///
+/// Dropping the switch stops the refreshes and leaves the last deadline in place, which cancels
+/// the orders once it passes; use `disarm` to shut down without cancelling.
+pub struct DeadMansSwitch {
+    client: Arc<ExchangeClient>,
+    heartbeat: watch::Sender<Instant>,
+    deadline: watch::Receiver<Option<u64>>,
+    stop: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl DeadMansSwitch {
+    /// Schedules the first cancel and starts refreshing it. Fails if the configuration is invalid
+    /// or the exchange rejects the first schedule.
+    pub async fn start(client: Arc<ExchangeClient>, config: DeadMansSwitchConfig) -> Result<Self> {
+        if config.window < MIN_SCHEDULE_CANCEL_DELAY {
+            return Err(Error::Custom(format!(
+                "Dead man's switch window must be at least {:?}",
+                MIN_SCHEDULE_CANCEL_DELAY
+            )));
+        }
+        if config.refresh_interval.is_zero() || config.refresh_interval >= config.window {
+            return Err(Error::Custom(
+                "Dead man's switch refresh interval must be shorter than its window".to_string(),
+            ));
+        }
+
+        let first_deadline = schedule(&client, config.window).await?;
+        info!("Dead man's switch armed, orders cancel at {first_deadline} unless refreshed");
+
+        let (heartbeat, heartbeats) = watch::channel(Instant::now());
+        let (deadline_sender, deadline) = watch::channel(Some(first_deadline));
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(refresh(
+            Arc::clone(&client),
+            config,
+            heartbeats,
+            deadline_sender,
+            stopped,
+        ));
+
+        Ok(Self {
+            client,
+            heartbeat,
+            deadline,
+            stop: Some(stop),
+            task: Some(task),
+        })
+    }
+
+    /// Marks the process as healthy; only needed with `heartbeat_timeout`
+    pub fn heartbeat(&self) {
+        self.heartbeat.send_replace(Instant::now());
+    }
+
+    /// When open orders will be cancelled (unix ms), as of the last successful refresh
+    pub fn deadline(&self) -> Option<u64> {
+        *self.deadline.borrow()
+    }
+
+    /// Stops refreshing and removes the scheduled cancel, leaving open orders on the book
+    pub async fn disarm(mut self) -> Result<()> {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        // Wait for an in-flight refresh so it can't re-arm the switch after it is cleared
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+
+        match self.client.schedule_cancel(None, None).await? {
+            ExchangeResponseStatus::Ok(_) => {
+                info!("Dead man's switch disarmed");
+                Ok(())
+            }
+            ExchangeResponseStatus::Err(e) => Err(ExchangeRejection::parse(e).into()),
+        }
+    }
+}
+
+impl Drop for DeadMansSwitch {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+async fn refresh(
+    client: Arc<ExchangeClient>,
+    config: DeadMansSwitchConfig,
+    heartbeats: watch::Receiver<Instant>,
+    deadline: watch::Sender<Option<u64>>,
+    mut stopped: oneshot::Receiver<()>,
+) {
+    let mut ticker = time::interval_at(
+        Instant::now() + config.refresh_interval,
+        config.refresh_interval,
+    );
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = &mut stopped => break,
+            _ = ticker.tick() => {}
+        }
+
+        if let Some(timeout) = config.heartbeat_timeout {
+            let since_heartbeat = heartbeats.borrow().elapsed();
+            if since_heartbeat > timeout {
+                warn!(
+                    "No heartbeat for {since_heartbeat:?}, letting the scheduled cancel at {:?} fire",
+                    *deadline.borrow()
+                );
+                continue;
+            }
+        }
+
+        match schedule(&client, config.window).await {
+            Ok(next) => {
+                deadline.send_replace(Some(next));
+            }
+            Err(e) => warn!(
+                "Failed to refresh the scheduled cancel, orders still cancel at {:?}: {e}",
+                *deadline.borrow()
+            ),
+        }
+    }
+}
+
+// Schedules the cancel `window` from now and returns its time (unix ms)
+async fn schedule(client: &ExchangeClient, window: Duration) -> Result<u64> {
+    let time = chrono::Utc::now().timestamp_millis() as u64 + window.as_millis() as u64;
+    match client.schedule_cancel(Some(time), None).await? {
+        ExchangeResponseStatus::Ok(_) => Ok(time),
+        ExchangeResponseStatus::Err(e) => Err(ExchangeRejection::parse(e).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::test_utils::*;
+    use crate::mock_server::{MockFixtures, MockServer};
+    use crate::InfoClient;
+
+    #[tokio::test]
+    async fn dead_mans_switch_cancels_orders_without_heartbeats() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let client = Arc::new(exchange_client(&server, MAKER_KEY).await);
+        let info = InfoClient::new(None, Some(server.base_url())).await?;
+        let now = || chrono::Utc::now().timestamp_millis() as u64;
+
+        let too_early = client.schedule_cancel(Some(now() + 1_000), None).await?;
+        assert!(
+            matches!(too_early, ExchangeResponseStatus::Err(message) if message.contains("too early"))
+        );
+
+        // Disarming clears the schedule, so orders outlive the window
+        let config = DeadMansSwitchConfig {
+            window: Duration::from_secs(6),
+            refresh_interval: Duration::from_millis(500),
+            heartbeat_timeout: Some(Duration::from_millis(700)),
+        };
+        let switch = DeadMansSwitch::start(Arc::clone(&client), config.clone()).await?;
+        let first_deadline = switch.deadline().unwrap();
+        assert!(first_deadline >= now() + 5_000);
+        status(
+            client
+                .order(limit(true, 49_000.0, 0.1, "Gtc"), None)
+                .await?,
+        );
+        switch.disarm().await?;
+
+        // Heartbeats keep pushing the deadline forward; once they stop the orders are cancelled
+        let switch = DeadMansSwitch::start(Arc::clone(&client), config).await?;
+        for _ in 0..4 {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            switch.heartbeat();
+        }
+        assert!(switch.deadline().unwrap() > first_deadline + 1_000);
+        assert_eq!(info.open_orders(client.wallet.address()).await?.len(), 1);
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+        while !info.open_orders(client.wallet.address()).await?.is_empty() {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "orders were not cancelled"
+            );
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+        Ok(())
+    }
+}
@@ -1,21 +1,23 @@
-use crate::signature::sign_typed_data;
 use crate::{
     exchange::{
         actions::{
-            ApproveAgent, ApproveBuilderFee, BulkCancel, BulkModify, BulkOrder, SetReferrer,
-            UpdateIsolatedMargin, UpdateLeverage, UsdSend,
+            ApproveAgent, ApproveBuilderFee, BulkCancel, BulkModify, BulkOrder, ScheduleCancel,
+            SetReferrer, TwapCancel, TwapOrder, UpdateIsolatedMargin, UpdateLeverage, UsdSend,
         },
         cancel::{CancelRequest, CancelRequestCloid},
         modify::{ClientModifyRequest, ModifyRequest},
-        ClientCancelRequest, ClientOrderRequest,
+        order::OrderRequest,
+        ClientCancelRequest, ClientOrderRequest, DecimalClientOrderRequest, InstrumentRegistry,
+        OrderValidation, OrderValidationError,
     },
-    helpers::{generate_random_key, next_nonce, uuid_to_hex_string},
+    helpers::{float_to_string_for_hashing, generate_random_key, next_nonce, uuid_to_hex_string},
     info::info_client::InfoClient,
     meta::Meta,
     prelude::*,
     req::HttpClient,
-    signature::sign_l1_action,
-    BaseUrl, BulkCancelCloid, Error, ExchangeResponseStatus,
+    signature::{l1_action_digest, typed_data_digest},
+    BaseUrl, BulkCancelCloid, CircuitBreaker, Error, ExchangeResponseStatus, HyperliquidSigner,
+    RateLimiter, RetryPolicy, SigningKind, SigningRequest, TwapCancelResponse, TwapOrderResponse,
 };
 use crate::{ClassTransfer, SpotSend, SpotUser, VaultTransfer, Withdraw3};
 use ethers::{
@@ -25,20 +27,28 @@ use ethers::{
 };
 use log::debug;
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use super::cancel::ClientCancelRequestCloid;
-use super::order::{MarketCloseParams, MarketOrderParams};
-use super::{BuilderInfo, ClientLimit, ClientOrder};
+use super::order::{validate_tpsl, MarketCloseParams, MarketOrderParams};
+use super::{
+    BracketOrderParams, BuilderInfo, ClientLimit, ClientOrder, ClientTwapRequest, Instrument,
+    PositionTpslParams,
+};
 
 #[derive(Debug)]
 pub struct ExchangeClient {
     pub http_client: HttpClient,
-    pub wallet: LocalWallet,
+    /// Signs every action this client sends unless a method is given another signer
+    pub wallet: Arc<dyn HyperliquidSigner>,
     pub meta: Meta,
     pub vault_address: Option<H160>,
     pub coin_to_asset: HashMap<String, u32>,
+    /// Tick and lot sizes for every perp and spot pair, as of construction
+    pub instruments: InstrumentRegistry,
+    pub order_validation: OrderValidation,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -68,10 +78,13 @@ pub enum Actions {
     SpotSend(SpotSend),
     SetReferrer(SetReferrer),
     ApproveBuilderFee(ApproveBuilderFee),
+    ScheduleCancel(ScheduleCancel),
+    TwapOrder(TwapOrder),
+    TwapCancel(TwapCancel),
 }
 
 impl Actions {
-    fn hash(&self, timestamp: u64, vault_address: Option<H160>) -> Result<H256> {
+    pub(crate) fn hash(&self, timestamp: u64, vault_address: Option<H160>) -> Result<H256> {
         let mut bytes =
             rmp_serde::to_vec_named(self).map_err(|e| Error::RmpParse(e.to_string()))?;
         bytes.extend(timestamp.to_be_bytes());
@@ -88,7 +101,7 @@ impl Actions {
 impl ExchangeClient {
     pub async fn new(
         client: Option<Client>,
-        wallet: LocalWallet,
+        wallet: impl HyperliquidSigner + 'static,
         base_url: Option<BaseUrl>,
         meta: Option<Meta>,
         vault_address: Option<H160>,
@@ -108,29 +121,120 @@ impl ExchangeClient {
             coin_to_asset.insert(asset.name.clone(), asset_ind as u32);
         }
 
-        coin_to_asset = info
-            .spot_meta()
-            .await?
-            .add_pair_and_name_to_index_map(coin_to_asset);
+        let spot_meta = info.spot_meta().await?;
+        let instruments = InstrumentRegistry::new(&meta, &spot_meta);
+        coin_to_asset = spot_meta.add_pair_and_name_to_index_map(coin_to_asset);
 
         Ok(ExchangeClient {
-            wallet,
+            wallet: Arc::new(wallet),
             meta,
             vault_address,
             http_client: HttpClient {
                 client,
-                base_url: base_url.get_url(),
+                base_url,
+                rate_limiter: None,
+                retry_policy: None,
+                circuit_breaker: None,
             },
             coin_to_asset,
+            instruments,
+            order_validation: OrderValidation::default(),
         })
     }
 
-    async fn post(
+    /// Sets what happens to order prices and sizes that don't fit the instrument's tick and lot
+    /// size; by default orders are sent as given
+    pub fn with_order_validation(mut self, order_validation: OrderValidation) -> Self {
+        self.order_validation = order_validation;
+        self
+    }
+
+    /// Throttles this client's requests, and the info requests it makes internally, with
+    /// `rate_limiter`; share one limiter between every client using the same IP
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.http_client.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Retries requests that fail for transient reasons according to `retry_policy`; exchange
+    /// actions are only retried when resending them can't apply them twice
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.http_client.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Fails requests fast while `circuit_breaker` considers the API down
+    pub fn with_circuit_breaker(mut self, circuit_breaker: Arc<CircuitBreaker>) -> Self {
+        self.http_client.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    // Shares this client's HTTP settings, so internal info requests are limited and retried too
+    async fn info_client(&self) -> Result<InfoClient> {
+        let mut info_client = InfoClient::new(None, Some(self.http_client.base_url)).await?;
+        info_client.http_client = self.http_client.clone();
+        Ok(info_client)
+    }
+
+    /// Rounds and/or validates orders according to `order_validation`, then converts them
+    fn prepare_orders(&self, orders: Vec<ClientOrderRequest>) -> Result<Vec<OrderRequest>> {
+        let mut transformed_orders = Vec::with_capacity(orders.len());
+        for mut order in orders {
+            if self.order_validation == OrderValidation::Round {
+                self.instruments.round(&mut order)?;
+            }
+            let order = order.convert(&self.coin_to_asset)?;
+            if self.order_validation != OrderValidation::Disabled {
+                self.instruments.validate(&order)?;
+            }
+            transformed_orders.push(order);
+        }
+        Ok(transformed_orders)
+    }
+
+    async fn sign_l1_action(
+        &self,
+        wallet: &dyn HyperliquidSigner,
+        action: &serde_json::Value,
+        connection_id: H256,
+        nonce: u64,
+    ) -> Result<Signature> {
+        let is_mainnet = self.http_client.is_mainnet();
+        let request = SigningRequest {
+            digest: l1_action_digest(connection_id, is_mainnet)?,
+            kind: SigningKind::L1Action,
+            action: action.clone(),
+            nonce,
+            vault_address: self.vault_address,
+            is_mainnet,
+        };
+        wallet.sign(&request).await
+    }
+
+    async fn sign_user_action(
+        &self,
+        wallet: &dyn HyperliquidSigner,
+        digest: H256,
+        action: &serde_json::Value,
+        nonce: u64,
+    ) -> Result<Signature> {
+        let request = SigningRequest {
+            digest,
+            kind: SigningKind::UserAction,
+            action: action.clone(),
+            nonce,
+            vault_address: self.vault_address,
+            is_mainnet: self.http_client.is_mainnet(),
+        };
+        wallet.sign(&request).await
+    }
+
+    async fn post<T: DeserializeOwned>(
         &self,
         action: serde_json::Value,
         signature: Signature,
         nonce: u64,
-    ) -> Result<ExchangeResponseStatus> {
+    ) -> Result<T> {
         let exchange_payload = ExchangePayload {
             action,
             signature,
@@ -141,11 +245,7 @@ impl ExchangeClient {
             .map_err(|e| Error::JsonParse(e.to_string()))?;
         debug!("Sending request {res:?}");
 
-        let output = &self
-            .http_client
-            .post("/exchange", res)
-            .await
-            .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let output = &self.http_client.post("/exchange", res).await?;
         serde_json::from_str(output).map_err(|e| Error::JsonParse(e.to_string()))
     }
 
@@ -153,9 +253,9 @@ impl ExchangeClient {
         &self,
         amount: &str,
         destination: &str,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
         let hyperliquid_chain = if self.http_client.is_mainnet() {
             "Mainnet".to_string()
         } else {
@@ -170,9 +270,12 @@ impl ExchangeClient {
             amount: amount.to_string(),
             time: timestamp,
         };
-        let signature = sign_typed_data(&usd_send, wallet)?;
+        let digest = typed_data_digest(&usd_send)?;
         let action = serde_json::to_value(Actions::UsdSend(usd_send))
             .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let signature = self
+            .sign_user_action(wallet, digest, &action, timestamp)
+            .await?;
 
         self.post(action, signature, timestamp).await
     }
@@ -181,11 +284,11 @@ impl ExchangeClient {
         &self,
         usdc: f64,
         to_perp: bool,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
         // payload expects usdc without decimals
         let usdc = (usdc * 1e6).round() as u64;
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
 
         let timestamp = next_nonce();
 
@@ -194,8 +297,9 @@ impl ExchangeClient {
         });
         let connection_id = action.hash(timestamp, self.vault_address)?;
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = self
+            .sign_l1_action(wallet, &action, connection_id, timestamp)
+            .await?;
 
         self.post(action, signature, timestamp).await
     }
@@ -205,13 +309,13 @@ impl ExchangeClient {
         is_deposit: bool,
         usd: String,
         vault_address: Option<H160>,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
         let vault_address = self
             .vault_address
             .or(vault_address)
             .ok_or(Error::VaultAddressNotFound)?;
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
 
         let timestamp = next_nonce();
 
@@ -222,8 +326,9 @@ impl ExchangeClient {
         });
         let connection_id = action.hash(timestamp, self.vault_address)?;
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = self
+            .sign_l1_action(wallet, &action, connection_id, timestamp)
+            .await?;
 
         self.post(action, signature, timestamp).await
     }
@@ -233,7 +338,7 @@ impl ExchangeClient {
         params: MarketOrderParams<'_>,
     ) -> Result<ExchangeResponseStatus> {
         let slippage = params.slippage.unwrap_or(0.05); // Default 5% slippage
-        let (px, sz_decimals) = self
+        let (px, instrument) = self
             .calculate_slippage_price(params.asset, params.is_buy, slippage, params.px)
             .await?;
 
@@ -242,7 +347,7 @@ impl ExchangeClient {
             is_buy: params.is_buy,
             reduce_only: false,
             limit_px: px,
-            sz: round_to_decimals(params.sz, sz_decimals),
+            sz: instrument.round_sz(params.sz),
             cloid: params.cloid,
             order_type: ClientOrder::Limit(ClientLimit {
                 tif: "Ioc".to_string(),
@@ -258,7 +363,7 @@ impl ExchangeClient {
         builder: BuilderInfo,
     ) -> Result<ExchangeResponseStatus> {
         let slippage = params.slippage.unwrap_or(0.05); // Default 5% slippage
-        let (px, sz_decimals) = self
+        let (px, instrument) = self
             .calculate_slippage_price(params.asset, params.is_buy, slippage, params.px)
             .await?;
 
@@ -267,7 +372,7 @@ impl ExchangeClient {
             is_buy: params.is_buy,
             reduce_only: false,
             limit_px: px,
-            sz: round_to_decimals(params.sz, sz_decimals),
+            sz: instrument.round_sz(params.sz),
             cloid: params.cloid,
             order_type: ClientOrder::Limit(ClientLimit {
                 tif: "Ioc".to_string(),
@@ -282,14 +387,9 @@ impl ExchangeClient {
         params: MarketCloseParams<'_>,
     ) -> Result<ExchangeResponseStatus> {
         let slippage = params.slippage.unwrap_or(0.05); // Default 5% slippage
-        let wallet = params.wallet.unwrap_or(&self.wallet);
+        let wallet = params.wallet.unwrap_or(self.wallet.as_ref());
 
-        let base_url = match self.http_client.base_url.as_str() {
-            "https://api.hyperliquid.xyz" => BaseUrl::Mainnet,
-            "https://api.hyperliquid-testnet.xyz" => BaseUrl::Testnet,
-            _ => return Err(Error::GenericRequest("Invalid base URL".to_string())),
-        };
-        let info_client = InfoClient::new(None, Some(base_url)).await?;
+        let info_client = self.info_client().await?;
         let user_state = info_client.user_state(wallet.address()).await?;
 
         let position = user_state
@@ -304,11 +404,11 @@ impl ExchangeClient {
             .parse::<f64>()
             .map_err(|_| Error::FloatStringParse)?;
 
-        let (px, sz_decimals) = self
+        let (px, instrument) = self
             .calculate_slippage_price(params.asset, szi < 0.0, slippage, params.px)
             .await?;
 
-        let sz = round_to_decimals(params.sz.unwrap_or_else(|| szi.abs()), sz_decimals);
+        let sz = instrument.round_sz(params.sz.unwrap_or_else(|| szi.abs()));
 
         let order = ClientOrderRequest {
             asset: params.asset.to_string(),
@@ -325,38 +425,111 @@ impl ExchangeClient {
         self.order(order, Some(wallet)).await
     }
 
+    /// Places an entry order with take-profit and stop-loss legs as one `normalTpsl` group. The
+    /// triggers must sit on the profitable and losing sides of the entry price respectively.
+    pub async fn bracket_order(
+        &self,
+        params: BracketOrderParams<'_>,
+    ) -> Result<ExchangeResponseStatus> {
+        validate_tpsl(
+            params.is_buy,
+            params.limit_px,
+            params.take_profit.as_ref(),
+            params.stop_loss.as_ref(),
+        )?;
+
+        let mut orders = vec![ClientOrderRequest {
+            asset: params.asset.to_string(),
+            is_buy: params.is_buy,
+            reduce_only: false,
+            limit_px: params.limit_px,
+            sz: params.sz,
+            cloid: params.cloid,
+            order_type: ClientOrder::Limit(ClientLimit { tif: params.tif }),
+        }];
+        if let Some(take_profit) = &params.take_profit {
+            orders.push(take_profit.leg(params.asset, !params.is_buy, params.sz, "tp"));
+        }
+        if let Some(stop_loss) = &params.stop_loss {
+            orders.push(stop_loss.leg(params.asset, !params.is_buy, params.sz, "sl"));
+        }
+
+        let transformed_orders = self.prepare_orders(orders)?;
+        self.post_bulk_order(transformed_orders, params.wallet, None, "normalTpsl")
+            .await
+    }
+
+    /// Attaches take-profit and stop-loss legs to an open position as one `positionTpsl` group.
+    /// The triggers must sit on the profitable and losing sides of the current mid respectively.
+    pub async fn position_tpsl(
+        &self,
+        params: PositionTpslParams<'_>,
+    ) -> Result<ExchangeResponseStatus> {
+        let wallet = params.wallet.unwrap_or(self.wallet.as_ref());
+
+        let info_client = self.info_client().await?;
+        let user_state = info_client.user_state(wallet.address()).await?;
+
+        let position = user_state
+            .asset_positions
+            .iter()
+            .find(|p| p.position.coin == params.asset)
+            .ok_or(Error::AssetNotFound)?;
+
+        let szi = position
+            .position
+            .szi
+            .parse::<f64>()
+            .map_err(|_| Error::FloatStringParse)?;
+
+        let mid = info_client
+            .all_mids()
+            .await?
+            .get(params.asset)
+            .ok_or(Error::AssetNotFound)?
+            .parse::<f64>()
+            .map_err(|_| Error::FloatStringParse)?;
+
+        validate_tpsl(
+            szi > 0.0,
+            mid,
+            params.take_profit.as_ref(),
+            params.stop_loss.as_ref(),
+        )?;
+
+        let sz = params.sz.unwrap_or_else(|| szi.abs());
+        let mut orders = Vec::new();
+        for (leg, tpsl) in [(&params.take_profit, "tp"), (&params.stop_loss, "sl")] {
+            if let Some(leg) = leg {
+                orders.push(leg.leg(params.asset, szi < 0.0, sz, tpsl));
+            }
+        }
+        let mut transformed_orders = self.prepare_orders(orders)?;
+        if params.sz.is_none() {
+            // Validated at the current size, but sent as size 0 so the legs close the whole
+            // position when they trigger, however it has changed since
+            for order in &mut transformed_orders {
+                order.sz = "0".to_string();
+            }
+        }
+
+        self.post_bulk_order(transformed_orders, Some(wallet), None, "positionTpsl")
+            .await
+    }
+
     async fn calculate_slippage_price(
         &self,
         asset: &str,
         is_buy: bool,
         slippage: f64,
         px: Option<f64>,
-    ) -> Result<(f64, u32)> {
-        let base_url = match self.http_client.base_url.as_str() {
-            "https://api.hyperliquid.xyz" => BaseUrl::Mainnet,
-            "https://api.hyperliquid-testnet.xyz" => BaseUrl::Testnet,
-            _ => return Err(Error::GenericRequest("Invalid base URL".to_string())),
-        };
-        let info_client = InfoClient::new(None, Some(base_url)).await?;
-        let meta = info_client.meta().await?;
-
-        let asset_meta = meta
-            .universe
-            .iter()
-            .find(|a| a.name == asset)
-            .ok_or(Error::AssetNotFound)?;
-
-        let sz_decimals = asset_meta.sz_decimals;
-        let max_decimals: u32 = if self.coin_to_asset[asset] < 10000 {
-            6
-        } else {
-            8
-        };
-        let price_decimals = max_decimals.saturating_sub(sz_decimals);
+    ) -> Result<(f64, &Instrument)> {
+        let instrument = self.instruments.get(asset).ok_or(Error::AssetNotFound)?;
 
         let px = if let Some(px) = px {
             px
         } else {
+            let info_client = self.info_client().await?;
             let all_mids = info_client.all_mids().await?;
             all_mids
                 .get(asset)
@@ -374,16 +547,16 @@ impl ExchangeClient {
         let px = px * slippage_factor;
 
         // Round to the correct number of decimal places and significant figures
-        let px = round_to_significant_and_decimal(px, 5, price_decimals);
+        let px = instrument.round_px(px);
 
         debug!("px after slippage: {px:?}");
-        Ok((px, sz_decimals))
+        Ok((px, instrument))
     }
 
     pub async fn order(
         &self,
         order: ClientOrderRequest,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
         self.bulk_order(vec![order], wallet).await
     }
@@ -391,7 +564,7 @@ impl ExchangeClient {
     pub async fn order_with_builder(
         &self,
         order: ClientOrderRequest,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
         builder: BuilderInfo,
     ) -> Result<ExchangeResponseStatus> {
         self.bulk_order_with_builder(vec![order], wallet, builder)
@@ -401,64 +574,82 @@ impl ExchangeClient {
     pub async fn bulk_order(
         &self,
         orders: Vec<ClientOrderRequest>,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
-        let timestamp = next_nonce();
-
-        let mut transformed_orders = Vec::new();
-
-        for order in orders {
-            transformed_orders.push(order.convert(&self.coin_to_asset)?);
-        }
-
-        let action = Actions::Order(BulkOrder {
-            orders: transformed_orders,
-            grouping: "na".to_string(),
-            builder: None,
-        });
-        let connection_id = action.hash(timestamp, self.vault_address)?;
-        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-        self.post(action, signature, timestamp).await
+        let transformed_orders = self.prepare_orders(orders)?;
+        self.post_bulk_order(transformed_orders, wallet, None, "na")
+            .await
     }
 
     pub async fn bulk_order_with_builder(
         &self,
         orders: Vec<ClientOrderRequest>,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
         mut builder: BuilderInfo,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
-        let timestamp = next_nonce();
-
         builder.builder = builder.builder.to_lowercase();
 
+        let transformed_orders = self.prepare_orders(orders)?;
+
+        self.post_bulk_order(transformed_orders, wallet, Some(builder), "na")
+            .await
+    }
+
+    pub async fn order_decimal(
+        &self,
+        order: DecimalClientOrderRequest,
+        wallet: Option<&dyn HyperliquidSigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        self.bulk_order_decimal(vec![order], wallet).await
+    }
+
+    pub async fn bulk_order_decimal(
+        &self,
+        orders: Vec<DecimalClientOrderRequest>,
+        wallet: Option<&dyn HyperliquidSigner>,
+    ) -> Result<ExchangeResponseStatus> {
         let mut transformed_orders = Vec::new();
 
         for order in orders {
-            transformed_orders.push(order.convert(&self.coin_to_asset)?);
+            let order = order.convert(&self.coin_to_asset)?;
+            if self.order_validation != OrderValidation::Disabled {
+                self.instruments.validate(&order)?;
+            }
+            transformed_orders.push(order);
         }
 
+        self.post_bulk_order(transformed_orders, wallet, None, "na")
+            .await
+    }
+
+    async fn post_bulk_order(
+        &self,
+        orders: Vec<OrderRequest>,
+        wallet: Option<&dyn HyperliquidSigner>,
+        builder: Option<BuilderInfo>,
+        grouping: &str,
+    ) -> Result<ExchangeResponseStatus> {
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
+        let timestamp = next_nonce();
+
         let action = Actions::Order(BulkOrder {
-            orders: transformed_orders,
-            grouping: "na".to_string(),
-            builder: Some(builder),
+            orders,
+            grouping: grouping.to_string(),
+            builder,
         });
         let connection_id = action.hash(timestamp, self.vault_address)?;
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
 
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = self
+            .sign_l1_action(wallet, &action, connection_id, timestamp)
+            .await?;
         self.post(action, signature, timestamp).await
     }
 
     pub async fn cancel(
         &self,
         cancel: ClientCancelRequest,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
         self.bulk_cancel(vec![cancel], wallet).await
     }
@@ -466,9 +657,9 @@ impl ExchangeClient {
     pub async fn bulk_cancel(
         &self,
         cancels: Vec<ClientCancelRequest>,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
         let timestamp = next_nonce();
 
         let mut transformed_cancels = Vec::new();
@@ -489,8 +680,9 @@ impl ExchangeClient {
         let connection_id = action.hash(timestamp, self.vault_address)?;
 
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = self
+            .sign_l1_action(wallet, &action, connection_id, timestamp)
+            .await?;
 
         self.post(action, signature, timestamp).await
     }
@@ -498,7 +690,7 @@ impl ExchangeClient {
     pub async fn modify(
         &self,
         modify: ClientModifyRequest,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
         self.bulk_modify(vec![modify], wallet).await
     }
@@ -506,18 +698,20 @@ impl ExchangeClient {
     pub async fn bulk_modify(
         &self,
         modifies: Vec<ClientModifyRequest>,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
         let timestamp = next_nonce();
 
-        let mut transformed_modifies = Vec::new();
-        for modify in modifies.into_iter() {
-            transformed_modifies.push(ModifyRequest {
-                oid: modify.oid,
-                order: modify.order.convert(&self.coin_to_asset)?,
-            });
-        }
+        let (oids, orders): (Vec<_>, Vec<_>) = modifies
+            .into_iter()
+            .map(|modify| (modify.oid, modify.order))
+            .unzip();
+        let transformed_modifies = oids
+            .into_iter()
+            .zip(self.prepare_orders(orders)?)
+            .map(|(oid, order)| ModifyRequest { oid, order })
+            .collect();
 
         let action = Actions::BatchModify(BulkModify {
             modifies: transformed_modifies,
@@ -525,8 +719,9 @@ impl ExchangeClient {
         let connection_id = action.hash(timestamp, self.vault_address)?;
 
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = self
+            .sign_l1_action(wallet, &action, connection_id, timestamp)
+            .await?;
 
         self.post(action, signature, timestamp).await
     }
@@ -534,7 +729,7 @@ impl ExchangeClient {
     pub async fn cancel_by_cloid(
         &self,
         cancel: ClientCancelRequestCloid,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
         self.bulk_cancel_by_cloid(vec![cancel], wallet).await
     }
@@ -542,9 +737,9 @@ impl ExchangeClient {
     pub async fn bulk_cancel_by_cloid(
         &self,
         cancels: Vec<ClientCancelRequestCloid>,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
         let timestamp = next_nonce();
 
         let mut transformed_cancels: Vec<CancelRequestCloid> = Vec::new();
@@ -565,8 +760,82 @@ impl ExchangeClient {
 
         let connection_id = action.hash(timestamp, self.vault_address)?;
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = self
+            .sign_l1_action(wallet, &action, connection_id, timestamp)
+            .await?;
+
+        self.post(action, signature, timestamp).await
+    }
+
+    /// Schedules every open order to be cancelled at `time` (unix ms, at least 5 seconds from now),
+    /// replacing any earlier schedule. `None` removes the scheduled cancel.
+    pub async fn schedule_cancel(
+        &self,
+        time: Option<u64>,
+        wallet: Option<&dyn HyperliquidSigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
+        let timestamp = next_nonce();
+
+        let action = Actions::ScheduleCancel(ScheduleCancel { time });
+        let connection_id = action.hash(timestamp, self.vault_address)?;
+        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
+        let signature = self
+            .sign_l1_action(wallet, &action, connection_id, timestamp)
+            .await?;
+
+        self.post(action, signature, timestamp).await
+    }
+
+    /// Starts an exchange-side TWAP; the response carries the `twap_id` needed to cancel it and to
+    /// follow its slices on the `userTwapSliceFills` channel
+    pub async fn twap_order(
+        &self,
+        twap: ClientTwapRequest,
+        wallet: Option<&dyn HyperliquidSigner>,
+    ) -> Result<TwapOrderResponse> {
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
+        let timestamp = next_nonce();
+
+        let mut twap = twap.convert(&self.coin_to_asset)?;
+        if self.order_validation != OrderValidation::Disabled {
+            let instrument = self
+                .instruments
+                .get_by_asset(twap.asset)
+                .ok_or_else(|| OrderValidationError::UnknownAsset(twap.asset.to_string()))?;
+            if self.order_validation == OrderValidation::Round {
+                let sz = twap.sz.parse().map_err(|_| Error::FloatStringParse)?;
+                twap.sz = float_to_string_for_hashing(instrument.round_sz(sz));
+            }
+            instrument.validate_sz(&twap.sz)?;
+        }
+
+        let action = Actions::TwapOrder(TwapOrder { twap });
+        let connection_id = action.hash(timestamp, self.vault_address)?;
+        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
+        let signature = self
+            .sign_l1_action(wallet, &action, connection_id, timestamp)
+            .await?;
+
+        self.post(action, signature, timestamp).await
+    }
+
+    pub async fn twap_cancel(
+        &self,
+        asset: &str,
+        twap_id: u64,
+        wallet: Option<&dyn HyperliquidSigner>,
+    ) -> Result<TwapCancelResponse> {
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
+        let timestamp = next_nonce();
+
+        let &asset = self.coin_to_asset.get(asset).ok_or(Error::AssetNotFound)?;
+        let action = Actions::TwapCancel(TwapCancel { asset, twap_id });
+        let connection_id = action.hash(timestamp, self.vault_address)?;
+        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
+        let signature = self
+            .sign_l1_action(wallet, &action, connection_id, timestamp)
+            .await?;
 
         self.post(action, signature, timestamp).await
     }
@@ -576,9 +845,9 @@ impl ExchangeClient {
         leverage: u32,
         coin: &str,
         is_cross: bool,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
 
         let timestamp = next_nonce();
 
@@ -590,8 +859,9 @@ impl ExchangeClient {
         });
         let connection_id = action.hash(timestamp, self.vault_address)?;
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = self
+            .sign_l1_action(wallet, &action, connection_id, timestamp)
+            .await?;
 
         self.post(action, signature, timestamp).await
     }
@@ -600,9 +870,9 @@ impl ExchangeClient {
         &self,
         amount: f64,
         coin: &str,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
 
         let amount = (amount * 1_000_000.0).round() as i64;
         let timestamp = next_nonce();
@@ -615,23 +885,24 @@ impl ExchangeClient {
         });
         let connection_id = action.hash(timestamp, self.vault_address)?;
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = self
+            .sign_l1_action(wallet, &action, connection_id, timestamp)
+            .await?;
 
         self.post(action, signature, timestamp).await
     }
 
     pub async fn approve_agent(
         &self,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<(String, ExchangeResponseStatus)> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
         let key = H256::from(generate_random_key()?).encode_hex()[2..].to_string();
 
         let address = key
             .parse::<LocalWallet>()
-            .map_err(|e| Error::PrivateKeyParse(e.to_string()))?
-            .address();
+            .map_err(|e| Error::PrivateKeyParse(e.to_string()))
+            .map(|agent| Signer::address(&agent))?;
 
         let hyperliquid_chain = if self.http_client.is_mainnet() {
             "Mainnet".to_string()
@@ -647,9 +918,12 @@ impl ExchangeClient {
             agent_name: None,
             nonce,
         };
-        let signature = sign_typed_data(&approve_agent, wallet)?;
+        let digest = typed_data_digest(&approve_agent)?;
         let action = serde_json::to_value(Actions::ApproveAgent(approve_agent))
             .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let signature = self
+            .sign_user_action(wallet, digest, &action, nonce)
+            .await?;
         Ok((key, self.post(action, signature, nonce).await?))
     }
 
@@ -657,9 +931,9 @@ impl ExchangeClient {
         &self,
         amount: &str,
         destination: &str,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
         let hyperliquid_chain = if self.http_client.is_mainnet() {
             "Mainnet".to_string()
         } else {
@@ -674,9 +948,12 @@ impl ExchangeClient {
             amount: amount.to_string(),
             time: timestamp,
         };
-        let signature = sign_typed_data(&withdraw, wallet)?;
+        let digest = typed_data_digest(&withdraw)?;
         let action = serde_json::to_value(Actions::Withdraw3(withdraw))
             .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let signature = self
+            .sign_user_action(wallet, digest, &action, timestamp)
+            .await?;
 
         self.post(action, signature, timestamp).await
     }
@@ -686,9 +963,9 @@ impl ExchangeClient {
         amount: &str,
         destination: &str,
         token: &str,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
         let hyperliquid_chain = if self.http_client.is_mainnet() {
             "Mainnet".to_string()
         } else {
@@ -704,9 +981,12 @@ impl ExchangeClient {
             time: timestamp,
             token: token.to_string(),
         };
-        let signature = sign_typed_data(&spot_send, wallet)?;
+        let digest = typed_data_digest(&spot_send)?;
         let action = serde_json::to_value(Actions::SpotSend(spot_send))
             .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let signature = self
+            .sign_user_action(wallet, digest, &action, timestamp)
+            .await?;
 
         self.post(action, signature, timestamp).await
     }
@@ -714,9 +994,9 @@ impl ExchangeClient {
     pub async fn set_referrer(
         &self,
         code: String,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
         let timestamp = next_nonce();
 
         let action = Actions::SetReferrer(SetReferrer { code });
@@ -724,8 +1004,9 @@ impl ExchangeClient {
         let connection_id = action.hash(timestamp, self.vault_address)?;
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
 
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = self
+            .sign_l1_action(wallet, &action, connection_id, timestamp)
+            .await?;
         self.post(action, signature, timestamp).await
     }
 
@@ -733,9 +1014,9 @@ impl ExchangeClient {
         &self,
         builder: String,
         max_fee_rate: String,
-        wallet: Option<&LocalWallet>,
+        wallet: Option<&dyn HyperliquidSigner>,
     ) -> Result<ExchangeResponseStatus> {
-        let wallet = wallet.unwrap_or(&self.wallet);
+        let wallet = wallet.unwrap_or(self.wallet.as_ref());
         let timestamp = next_nonce();
 
         let hyperliquid_chain = if self.http_client.is_mainnet() {
@@ -755,25 +1036,13 @@ impl ExchangeClient {
         let connection_id = action.hash(timestamp, self.vault_address)?;
         let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
 
-        let is_mainnet = self.http_client.is_mainnet();
-        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        let signature = self
+            .sign_l1_action(wallet, &action, connection_id, timestamp)
+            .await?;
         self.post(action, signature, timestamp).await
     }
 }
 
-fn round_to_decimals(value: f64, decimals: u32) -> f64 {
-    let factor = 10f64.powi(decimals as i32);
-    (value * factor).round() / factor
-}
-
-fn round_to_significant_and_decimal(value: f64, sig_figs: u32, max_decimals: u32) -> f64 {
-    let abs_value = value.abs();
-    let magnitude = abs_value.log10().floor() as i32;
-    let scale = 10f64.powi(sig_figs as i32 - magnitude - 1);
-    let rounded = (abs_value * scale).round() / scale;
-    round_to_decimals(rounded.copysign(value), max_decimals)
-}
-
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -781,7 +1050,9 @@ mod tests {
     use super::*;
     use crate::{
         exchange::order::{Limit, OrderRequest, Trigger},
-        Order,
+        mock_server::{test_utils::*, MockEndpoint, MockFixtures, MockServer},
+        signature::sign_l1_action,
+        ClientTpsl, ExchangeDataStatus, InfoClient, Order,
     };
 
     fn get_wallet() -> Result<LocalWallet> {
@@ -896,6 +1167,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_schedule_cancel_action_encoding() -> Result<()> {
+        let action = Actions::ScheduleCancel(ScheduleCancel {
+            time: Some(1_700_000_000_000),
+        });
+        let mut expected = vec![0x82, 0xa4];
+        expected.extend(b"type");
+        expected.push(0xae);
+        expected.extend(b"scheduleCancel");
+        expected.push(0xa4);
+        expected.extend(b"time");
+        expected.push(0xcf);
+        expected.extend(1_700_000_000_000u64.to_be_bytes());
+        assert_eq!(rmp_serde::to_vec_named(&action).unwrap(), expected);
+
+        // Without a time the action only carries its type, which clears the schedule
+        let action = Actions::ScheduleCancel(ScheduleCancel { time: None });
+        assert_eq!(
+            serde_json::to_value(&action).unwrap(),
+            serde_json::json!({ "type": "scheduleCancel" })
+        );
+        let mut expected = vec![0x81, 0xa4];
+        expected.extend(b"type");
+        expected.push(0xae);
+        expected.extend(b"scheduleCancel");
+        assert_eq!(rmp_serde::to_vec_named(&action).unwrap(), expected);
+        Ok(())
+    }
+
     #[test]
     fn test_cancel_action_hashing() -> Result<()> {
         let wallet = get_wallet()?;
@@ -915,4 +1215,140 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn market_orders_use_the_custom_endpoint() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let client = exchange_client(&server, MAKER_KEY).await;
+        let info = InfoClient::new(None, Some(server.base_url())).await?;
+
+        let opened = client
+            .market_open(MarketOrderParams {
+                asset: "ETH",
+                is_buy: true,
+                sz: 2.0,
+                px: None,
+                slippage: None,
+                cloid: None,
+                wallet: None,
+            })
+            .await?;
+        assert!(
+            matches!(status(opened), ExchangeDataStatus::Filled(filled) if filled.avg_px == "2500")
+        );
+
+        server.set_mid("ETH", 2_600.0);
+        let closed = client
+            .market_close(MarketCloseParams {
+                asset: "ETH",
+                sz: None,
+                px: None,
+                slippage: None,
+                cloid: None,
+                wallet: None,
+            })
+            .await?;
+        assert!(matches!(status(closed), ExchangeDataStatus::Filled(_)));
+
+        let fills = info.user_fills(client.wallet.address()).await?;
+        assert_eq!(fills[0].closed_pnl, "200");
+        assert!(info
+            .user_state(client.wallet.address())
+            .await?
+            .asset_positions
+            .is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bracket_orders_attach_tpsl_legs() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let client = exchange_client(&server, MAKER_KEY).await;
+        let info = InfoClient::new(None, Some(server.base_url())).await?;
+        let tpsl = |trigger_px: f64| {
+            Some(ClientTpsl {
+                trigger_px,
+                limit_px: None,
+            })
+        };
+        let bracket = |limit_px: f64, take_profit, stop_loss| BracketOrderParams {
+            asset: "BTC",
+            is_buy: true,
+            sz: 0.1,
+            limit_px,
+            tif: "Gtc".to_string(),
+            cloid: None,
+            take_profit,
+            stop_loss,
+            wallet: None,
+        };
+
+        // A stop above a long entry would trigger immediately and is rejected before sending
+        let rejected = client
+            .bracket_order(bracket(50_500.0, tpsl(55_000.0), tpsl(51_000.0)))
+            .await;
+        assert!(matches!(rejected, Err(Error::InvalidTpsl(_))));
+        assert!(server
+            .requests()
+            .iter()
+            .all(|request| request.endpoint != MockEndpoint::Exchange));
+
+        let ExchangeResponseStatus::Ok(response) = client
+            .bracket_order(bracket(50_500.0, tpsl(55_000.0), tpsl(45_000.0)))
+            .await?
+        else {
+            panic!("bracket should be accepted");
+        };
+        let statuses = response.data.unwrap().statuses;
+        assert!(matches!(statuses[0], ExchangeDataStatus::Filled(_)));
+        assert!(statuses[1..]
+            .iter()
+            .all(|status| matches!(status, ExchangeDataStatus::Resting(_))));
+        let triggers = info.open_orders(client.wallet.address()).await?;
+        assert_eq!(triggers.len(), 2);
+        assert!(triggers
+            .iter()
+            .all(|order| order.side == "A" && order.sz == "0.1"));
+
+        // Legs of an entry that rests wait for it to fill
+        let ExchangeResponseStatus::Ok(response) = client
+            .bracket_order(bracket(49_000.0, tpsl(55_000.0), None))
+            .await?
+        else {
+            panic!("bracket should be accepted");
+        };
+        let statuses = response.data.unwrap().statuses;
+        assert!(matches!(
+            statuses[..],
+            [
+                ExchangeDataStatus::Resting(_),
+                ExchangeDataStatus::WaitingForFill
+            ]
+        ));
+
+        // Position TP/SL is checked against the mid and sized to the whole position by default
+        server.set_mid("BTC", 56_000.0);
+        let position_tpsl = |take_profit, stop_loss| PositionTpslParams {
+            asset: "BTC",
+            sz: None,
+            take_profit,
+            stop_loss,
+            wallet: None,
+        };
+        let rejected = client
+            .position_tpsl(position_tpsl(tpsl(55_000.0), None))
+            .await;
+        assert!(matches!(rejected, Err(Error::InvalidTpsl(_))));
+        let ExchangeResponseStatus::Ok(response) = client
+            .position_tpsl(position_tpsl(tpsl(60_000.0), tpsl(52_000.0)))
+            .await?
+        else {
+            panic!("position tpsl should be accepted");
+        };
+        assert_eq!(response.data.unwrap().statuses.len(), 2);
+        let legs = info.open_orders(client.wallet.address()).await?;
+        assert_eq!(legs.len(), 5);
+        assert!(legs[3..].iter().all(|order| order.sz == "0"));
+        Ok(())
+    }
 }
@@ -43,3 +43,47 @@ pub enum ExchangeResponseStatus {
     Ok(ExchangeResponse),
     Err(String),
 }
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TwapRunning {
+    pub twap_id: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum TwapOrderStatus {
+    Running(TwapRunning),
+    Error(String),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum TwapCancelStatus {
+    Success,
+    Error(String),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TwapResponseData<T> {
+    pub status: T,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TwapResponse<T> {
+    #[serde(rename = "type")]
+    pub response_type: String,
+    pub data: TwapResponseData<T>,
+}
+
+/// Response to a TWAP action, which carries a single status rather than a list per order
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "status", content = "response")]
+pub enum TwapResponseStatus<T> {
+    Ok(TwapResponse<T>),
+    Err(String),
+}
+
+pub type TwapOrderResponse = TwapResponseStatus<TwapOrderStatus>;
+pub type TwapCancelResponse = TwapResponseStatus<TwapCancelStatus>;
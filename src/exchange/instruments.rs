@@ -0,0 +1,475 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{
+    exchange::order::{Order, OrderRequest},
+    meta::SpotMeta,
+    ClientOrder, ClientOrderRequest, Meta,
+};
+
+/// Smallest order value (USD) the exchange accepts, except for reduce-only orders
+pub const MIN_ORDER_NOTIONAL: f64 = 10.0;
+/// Prices may have at most this many significant figures, unless they are whole numbers
+pub const MAX_PX_SIG_FIGS: u32 = 5;
+const MAX_PERP_PX_DECIMALS: u32 = 6;
+const MAX_SPOT_PX_DECIMALS: u32 = 8;
+const SPOT_ASSET_OFFSET: u32 = 10_000;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum OrderValidationError {
+    #[error("No instrument for asset {0}")]
+    UnknownAsset(String),
+    #[error("{field} for {coin} must be positive, got {value}")]
+    NonPositive {
+        coin: String,
+        field: &'static str,
+        value: String,
+    },
+    #[error("{field} {px} for {coin} has more than {MAX_PX_SIG_FIGS} significant figures or {max_decimals} decimals")]
+    InvalidTick {
+        coin: String,
+        field: &'static str,
+        px: String,
+        max_decimals: u32,
+    },
+    #[error("Size {sz} for {coin} has more than {sz_decimals} decimals")]
+    InvalidLot {
+        coin: String,
+        sz: String,
+        sz_decimals: u32,
+    },
+    #[error("Order value {notional:.2} for {coin} is below the minimum of {min_notional}")]
+    BelowMinNotional {
+        coin: String,
+        notional: f64,
+        min_notional: f64,
+    },
+}
+
+/// What `ExchangeClient` does with order prices and sizes before signing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OrderValidation {
+    /// Reject orders whose price, size or value the exchange would reject
+    Strict,
+    /// Round prices and sizes to the instrument's precision, then validate
+    Round,
+    /// Send orders as given and leave any rejection to the exchange
+    #[default]
+    Disabled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstrumentKind {
+    Perp,
+    Spot,
+}
+
+/// Precision rules for one tradable asset
+#[derive(Debug, Clone)]
+pub struct Instrument {
+    /// Perp coin or spot pair name, e.g. `BTC` or `@1`
+    pub name: String,
+    pub asset: u32,
+    pub kind: InstrumentKind,
+    pub sz_decimals: u32,
+    pub max_px_decimals: u32,
+}
+
+impl Instrument {
+    /// Rounds to 5 significant figures, leaving whole numbers alone, then to the max decimals
+    pub fn round_px(&self, px: f64) -> f64 {
+        let px = if px.abs() >= 10f64.powi(MAX_PX_SIG_FIGS as i32) {
+            px.round()
+        } else {
+            round_to_significant(px, MAX_PX_SIG_FIGS)
+        };
+        round_to_decimals(px, self.max_px_decimals)
+    }
+
+    pub fn round_sz(&self, sz: f64) -> f64 {
+        round_to_decimals(sz, self.sz_decimals)
+    }
+
+    /// Checks a price as sent on the wire
+    pub fn validate_px(&self, field: &'static str, px: &str) -> Result<(), OrderValidationError> {
+        self.positive(field, px)?;
+        let (decimals, sig_figs) = digits(px);
+        if decimals > self.max_px_decimals || (decimals > 0 && sig_figs > MAX_PX_SIG_FIGS) {
+            return Err(OrderValidationError::InvalidTick {
+                coin: self.name.clone(),
+                field,
+                px: px.to_string(),
+                max_decimals: self.max_px_decimals,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks a size as sent on the wire
+    pub fn validate_sz(&self, sz: &str) -> Result<(), OrderValidationError> {
+        self.positive("Size", sz)?;
+        if digits(sz).0 > self.sz_decimals {
+            return Err(OrderValidationError::InvalidLot {
+                coin: self.name.clone(),
+                sz: sz.to_string(),
+                sz_decimals: self.sz_decimals,
+            });
+        }
+        Ok(())
+    }
+
+    fn positive(&self, field: &'static str, value: &str) -> Result<(), OrderValidationError> {
+        match value.parse::<f64>() {
+            Ok(value) if value > 0.0 => Ok(()),
+            _ => Err(OrderValidationError::NonPositive {
+                coin: self.name.clone(),
+                field,
+                value: value.to_string(),
+            }),
+        }
+    }
+}
+
+/// Per-asset precision rules for every perp and spot pair, built once from `Meta` and `SpotMeta`
+#[derive(Debug, Clone, Default)]
+pub struct InstrumentRegistry {
+    by_asset: HashMap<u32, Instrument>,
+    by_name: HashMap<String, u32>,
+}
+
+impl InstrumentRegistry {
+    pub fn new(meta: &Meta, spot_meta: &SpotMeta) -> Self {
+        let mut registry = Self::default();
+        for (index, asset) in meta.universe.iter().enumerate() {
+            registry.insert(
+                Instrument {
+                    name: asset.name.clone(),
+                    asset: index as u32,
+                    kind: InstrumentKind::Perp,
+                    sz_decimals: asset.sz_decimals,
+                    max_px_decimals: MAX_PERP_PX_DECIMALS.saturating_sub(asset.sz_decimals),
+                },
+                None,
+            );
+        }
+
+        let tokens: HashMap<usize, _> = spot_meta
+            .tokens
+            .iter()
+            .map(|token| (token.index, token))
+            .collect();
+        for pair in &spot_meta.universe {
+            // Spot sizes are in the base token
+            let Some(base) = tokens.get(&pair.tokens[0]) else {
+                continue;
+            };
+            let sz_decimals = base.sz_decimals as u32;
+            let alias = tokens
+                .get(&pair.tokens[1])
+                .map(|quote| format!("{}/{}", base.name, quote.name));
+            registry.insert(
+                Instrument {
+                    name: pair.name.clone(),
+                    asset: SPOT_ASSET_OFFSET + pair.index as u32,
+                    kind: InstrumentKind::Spot,
+                    sz_decimals,
+                    max_px_decimals: MAX_SPOT_PX_DECIMALS.saturating_sub(sz_decimals),
+                },
+                alias,
+            );
+        }
+        registry
+    }
+
+    fn insert(&mut self, instrument: Instrument, alias: Option<String>) {
+        self.by_name
+            .insert(instrument.name.clone(), instrument.asset);
+        if let Some(alias) = alias {
+            self.by_name.insert(alias, instrument.asset);
+        }
+        self.by_asset.insert(instrument.asset, instrument);
+    }
+
+    /// Looks up a perp coin, spot pair name or `BASE/QUOTE` alias
+    pub fn get(&self, name: &str) -> Option<&Instrument> {
+        self.by_name
+            .get(name)
+            .and_then(|asset| self.by_asset.get(asset))
+    }
+
+    pub fn get_by_asset(&self, asset: u32) -> Option<&Instrument> {
+        self.by_asset.get(&asset)
+    }
+
+    /// Rounds the order's prices and size to the instrument's precision
+    pub fn round(&self, order: &mut ClientOrderRequest) -> Result<(), OrderValidationError> {
+        let instrument = self
+            .get(&order.asset)
+            .ok_or_else(|| OrderValidationError::UnknownAsset(order.asset.clone()))?;
+        order.limit_px = instrument.round_px(order.limit_px);
+        order.sz = instrument.round_sz(order.sz);
+        if let ClientOrder::Trigger(trigger) = &mut order.order_type {
+            trigger.trigger_px = instrument.round_px(trigger.trigger_px);
+        }
+        Ok(())
+    }
+
+    /// Checks a converted order's prices, size and value against the instrument
+    pub(crate) fn validate(&self, order: &OrderRequest) -> Result<(), OrderValidationError> {
+        let instrument = self
+            .get_by_asset(order.asset)
+            .ok_or_else(|| OrderValidationError::UnknownAsset(order.asset.to_string()))?;
+        instrument.validate_px("Price", &order.limit_px)?;
+        if let Order::Trigger(trigger) = &order.order_type {
+            instrument.validate_px("Trigger price", &trigger.trigger_px)?;
+        }
+        instrument.validate_sz(&order.sz)?;
+
+        if !order.reduce_only {
+            let px = order.limit_px.parse::<f64>().unwrap_or_default();
+            let notional = px * order.sz.parse::<f64>().unwrap_or_default();
+            if notional < MIN_ORDER_NOTIONAL {
+                return Err(OrderValidationError::BelowMinNotional {
+                    coin: instrument.name.clone(),
+                    notional,
+                    min_notional: MIN_ORDER_NOTIONAL,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+// (decimals, significant figures) of a number in wire format, e.g. "0.0012" -> (4, 2)
+fn digits(value: &str) -> (u32, u32) {
+    let value = value.trim_start_matches('-');
+    let decimals = value.split_once('.').map_or(0, |(_, frac)| frac.len()) as u32;
+    let sig_figs = value
+        .chars()
+        .filter(char::is_ascii_digit)
+        .skip_while(|&digit| digit == '0')
+        .count() as u32;
+    (decimals, sig_figs)
+}
+
+fn round_to_decimals(value: f64, decimals: u32) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+fn round_to_significant(value: f64, sig_figs: u32) -> f64 {
+    if value == 0.0 || !value.is_finite() {
+        return value;
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    let scale = 10f64.powi(sig_figs as i32 - magnitude - 1);
+    (value * scale).round() / scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::test_utils::*;
+    use crate::mock_server::{MockEndpoint, MockFixtures, MockServer};
+    use crate::{ClientLimit, ClientTrigger};
+    use crate::{Error, ExchangeDataStatus, InfoClient};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn registry() -> InstrumentRegistry {
+        let meta: Meta = serde_json::from_value(json!({
+            "universe": [
+                { "name": "BTC", "szDecimals": 5 },
+                { "name": "ETH", "szDecimals": 4 }
+            ]
+        }))
+        .unwrap();
+        let spot_meta: SpotMeta = serde_json::from_value(json!({
+            "universe": [{ "tokens": [1, 0], "name": "PURR/USDC", "index": 0, "isCanonical": true }],
+            "tokens": [
+                { "name": "USDC", "szDecimals": 8, "weiDecimals": 8, "index": 0,
+                  "tokenId": "0x6d1e7cde53ba9467b783cb7c530ce054", "isCanonical": true },
+                { "name": "PURR", "szDecimals": 0, "weiDecimals": 5, "index": 1,
+                  "tokenId": "0xc1fb593aeffbeb02f85e0308e9956a90", "isCanonical": true }
+            ]
+        }))
+        .unwrap();
+        InstrumentRegistry::new(&meta, &spot_meta)
+    }
+
+    fn order(asset: &str, limit_px: f64, sz: f64, reduce_only: bool) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: asset.to_string(),
+            is_buy: true,
+            reduce_only,
+            limit_px,
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit {
+                tif: "Gtc".to_string(),
+            }),
+        }
+    }
+
+    fn validate(
+        registry: &InstrumentRegistry,
+        order: ClientOrderRequest,
+    ) -> Result<(), OrderValidationError> {
+        let coin_to_asset: HashMap<String, u32> = [("BTC", 0), ("ETH", 1), ("PURR/USDC", 10_000)]
+            .into_iter()
+            .map(|(coin, asset)| (coin.to_string(), asset))
+            .collect();
+        registry.validate(&order.convert(&coin_to_asset).unwrap())
+    }
+
+    #[test]
+    fn builds_perp_and_spot_instruments() {
+        let registry = registry();
+        let btc = registry.get("BTC").unwrap();
+        assert_eq!((btc.asset, btc.sz_decimals, btc.max_px_decimals), (0, 5, 1));
+        let purr = registry.get("PURR/USDC").unwrap();
+        assert_eq!(purr.kind, InstrumentKind::Spot);
+        assert_eq!(
+            (purr.asset, purr.sz_decimals, purr.max_px_decimals),
+            (10_000, 0, 8)
+        );
+        assert!(registry.get("DOGE").is_none());
+    }
+
+    #[test]
+    fn validates_tick_lot_and_notional() {
+        let registry = registry();
+        assert_eq!(
+            validate(&registry, order("BTC", 50_000.0, 0.001, false)),
+            Ok(())
+        );
+        // Whole prices are fine past 5 significant figures
+        assert_eq!(
+            validate(&registry, order("BTC", 123_456.0, 0.001, false)),
+            Ok(())
+        );
+        assert!(matches!(
+            validate(&registry, order("BTC", 50_000.5, 0.001, false)),
+            Err(OrderValidationError::InvalidTick { .. })
+        ));
+        assert!(matches!(
+            validate(&registry, order("ETH", 2_500.12, 0.01, false)),
+            Err(OrderValidationError::InvalidTick { .. })
+        ));
+        assert!(matches!(
+            validate(&registry, order("BTC", 50_000.0, 0.000001, false)),
+            Err(OrderValidationError::InvalidLot { .. })
+        ));
+        assert!(matches!(
+            validate(&registry, order("BTC", 50_000.0, 0.0001, false)),
+            Err(OrderValidationError::BelowMinNotional { .. })
+        ));
+        // Reduce-only orders may be small
+        assert_eq!(
+            validate(&registry, order("BTC", 50_000.0, 0.0001, true)),
+            Ok(())
+        );
+        assert!(matches!(
+            validate(&registry, order("BTC", -1.0, 0.001, false)),
+            Err(OrderValidationError::NonPositive { field: "Price", .. })
+        ));
+        assert_eq!(
+            validate(&registry, order("PURR/USDC", 0.00012345, 100_000.0, false)),
+            Ok(())
+        );
+        // 0.1 + 0.2 is a hair over 0.3 as a float but is sent as 0.3
+        assert_eq!(
+            validate(&registry, order("ETH", 2_500.0, 0.1 + 0.2, false)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rounds_to_instrument_precision() {
+        let registry = registry();
+        let mut btc = order("BTC", 50_123.456, 0.0012345678, false);
+        btc.order_type = ClientOrder::Trigger(ClientTrigger {
+            is_market: true,
+            trigger_px: 49_876.54,
+            tpsl: "sl".to_string(),
+        });
+        registry.round(&mut btc).unwrap();
+        assert_eq!((btc.limit_px, btc.sz), (50_123.0, 0.00123));
+        let ClientOrder::Trigger(trigger) = &btc.order_type else {
+            unreachable!()
+        };
+        assert_eq!(trigger.trigger_px, 49_877.0);
+
+        let mut eth = order("ETH", 2512.3456, 0.12345, false);
+        registry.round(&mut eth).unwrap();
+        assert_eq!((eth.limit_px, eth.sz), (2_512.3, 0.1235));
+
+        let mut purr = order("PURR/USDC", 0.000123456789, 10.6, false);
+        registry.round(&mut purr).unwrap();
+        assert_eq!((purr.limit_px, purr.sz), (0.00012346, 11.0));
+        assert!(matches!(
+            registry.round(&mut order("DOGE", 1.0, 1.0, false)),
+            Err(OrderValidationError::UnknownAsset(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn orders_off_tick_or_lot_are_rejected_before_signing() -> crate::prelude::Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let client = exchange_client(&server, MAKER_KEY)
+            .await
+            .with_order_validation(OrderValidation::Strict);
+
+        let rejected = client.order(limit(true, 49_000.5, 0.1, "Gtc"), None).await;
+        assert!(matches!(
+            rejected,
+            Err(Error::OrderValidation(
+                OrderValidationError::InvalidTick { .. }
+            ))
+        ));
+        let rejected = client
+            .order(limit(true, 49_000.0, 0.1234567, "Gtc"), None)
+            .await;
+        assert!(matches!(
+            rejected,
+            Err(Error::OrderValidation(
+                OrderValidationError::InvalidLot { .. }
+            ))
+        ));
+        let rejected = client
+            .order(limit(true, 49_000.0, 0.0001, "Gtc"), None)
+            .await;
+        assert!(matches!(
+            rejected,
+            Err(Error::OrderValidation(
+                OrderValidationError::BelowMinNotional { .. }
+            ))
+        ));
+        assert!(server
+            .requests()
+            .iter()
+            .all(|request| request.endpoint != MockEndpoint::Exchange));
+
+        let client = client.with_order_validation(OrderValidation::Round);
+        let ExchangeDataStatus::Resting(resting) = status(
+            client
+                .order(limit(true, 49_000.46, 0.1234567, "Gtc"), None)
+                .await?,
+        ) else {
+            panic!("rounded bid should rest");
+        };
+        let info = InfoClient::new(None, Some(server.base_url())).await?;
+        let order = info
+            .query_order_by_oid(client.wallet.address(), resting.oid)
+            .await?
+            .order
+            .unwrap()
+            .order;
+        assert_eq!(
+            (order.limit_px.as_str(), order.sz.as_str()),
+            ("49000", "0.12346")
+        );
+        Ok(())
+    }
+}
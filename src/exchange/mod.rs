@@ -1,18 +1,41 @@
 mod actions;
+mod algo;
 mod builder;
 mod cancel;
+mod dead_mans_switch;
 mod exchange_client;
 mod exchange_responses;
+mod instruments;
 mod modify;
 mod order;
+mod order_manager;
+mod rejection;
+mod twap;
 
 pub use actions::*;
+pub use algo::{
+    AlgoOrder, AlgoOrderParams, AlgoProgress, AlgoStatus, AlgoStrategy, IcebergParams, PovParams,
+    TwapSchedule, WorkingOrder,
+};
 pub use builder::*;
 pub use cancel::{ClientCancelRequest, ClientCancelRequestCloid};
+pub use dead_mans_switch::{DeadMansSwitch, DeadMansSwitchConfig, MIN_SCHEDULE_CANCEL_DELAY};
 pub use exchange_client::*;
 pub use exchange_responses::*;
+pub use instruments::{
+    Instrument, InstrumentKind, InstrumentRegistry, OrderValidation, OrderValidationError,
+    MAX_PX_SIG_FIGS, MIN_ORDER_NOTIONAL,
+};
 pub use modify::{ClientModifyRequest, ModifyRequest};
+#[cfg(any(test, feature = "mock-server"))]
+pub(crate) use order::OrderRequest;
 pub use order::{
-    ClientLimit, ClientOrder, ClientOrderRequest, ClientTrigger, MarketCloseParams,
-    MarketOrderParams, Order,
+    BracketOrderParams, ClientLimit, ClientOrder, ClientOrderRequest, ClientTpsl, ClientTrigger,
+    DecimalClientOrder, DecimalClientOrderRequest, DecimalClientTrigger, MarketCloseParams,
+    MarketOrderParams, Order, PositionTpslParams,
+};
+pub use order_manager::{
+    ManagedOrder, OrderDrift, OrderFill, OrderManager, OrderManagerConfig, OrderState,
 };
+pub use rejection::{ExchangeRejection, RejectionKind, Retryability};
+pub use twap::{ClientTwapRequest, TwapProgress, TwapTracker};
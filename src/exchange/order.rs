@@ -1,9 +1,11 @@
+use crate::HyperliquidSigner;
 use crate::{
+    decimal::decimal_to_string_for_hashing,
     errors::Error,
     helpers::{float_to_string_for_hashing, uuid_to_hex_string},
     prelude::*,
+    Decimal,
 };
-use ethers::signers::LocalWallet;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -67,7 +69,7 @@ pub struct MarketOrderParams<'a> {
     pub px: Option<f64>,
     pub slippage: Option<f64>,
     pub cloid: Option<Uuid>,
-    pub wallet: Option<&'a LocalWallet>,
+    pub wallet: Option<&'a dyn HyperliquidSigner>,
 }
 
 #[derive(Debug)]
@@ -77,7 +79,103 @@ pub struct MarketCloseParams<'a> {
     pub px: Option<f64>,
     pub slippage: Option<f64>,
     pub cloid: Option<Uuid>,
-    pub wallet: Option<&'a LocalWallet>,
+    pub wallet: Option<&'a dyn HyperliquidSigner>,
+}
+
+/// Take-profit or stop-loss leg. Triggers at `trigger_px`, then executes as a market order, or as a
+/// limit order at `limit_px` when one is given
+#[derive(Debug, Clone)]
+pub struct ClientTpsl {
+    pub trigger_px: f64,
+    pub limit_px: Option<f64>,
+}
+
+impl ClientTpsl {
+    /// Reduce-only trigger order closing `sz` of a position on the side opposite to `close_is_buy`
+    pub(crate) fn leg(
+        &self,
+        asset: &str,
+        close_is_buy: bool,
+        sz: f64,
+        tpsl: &str,
+    ) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: asset.to_string(),
+            is_buy: close_is_buy,
+            reduce_only: true,
+            limit_px: self.limit_px.unwrap_or(self.trigger_px),
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Trigger(ClientTrigger {
+                is_market: self.limit_px.is_none(),
+                trigger_px: self.trigger_px,
+                tpsl: tpsl.to_string(),
+            }),
+        }
+    }
+}
+
+/// Checks that a position on the `is_long` side would take profit above `reference_px` and stop out
+/// below it (the reverse for a short), so neither leg triggers as soon as it is placed
+pub(crate) fn validate_tpsl(
+    is_long: bool,
+    reference_px: f64,
+    take_profit: Option<&ClientTpsl>,
+    stop_loss: Option<&ClientTpsl>,
+) -> Result<()> {
+    if take_profit.is_none() && stop_loss.is_none() {
+        return Err(Error::InvalidTpsl(
+            "at least one of take profit and stop loss is required".to_string(),
+        ));
+    }
+    for (leg, name, above) in [
+        (take_profit, "take profit", is_long),
+        (stop_loss, "stop loss", !is_long),
+    ] {
+        let Some(leg) = leg else { continue };
+        let positive = |px: f64| px.is_finite() && px > 0.0;
+        if !positive(leg.trigger_px) || leg.limit_px.is_some_and(|px| !positive(px)) {
+            return Err(Error::InvalidTpsl(format!(
+                "{name} prices must be positive"
+            )));
+        }
+        if (leg.trigger_px > reference_px) != above || leg.trigger_px == reference_px {
+            return Err(Error::InvalidTpsl(format!(
+                "{name} trigger {} must be {} {} for a {}",
+                leg.trigger_px,
+                if above { "above" } else { "below" },
+                reference_px,
+                if is_long { "long" } else { "short" }
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Entry limit order with take-profit and stop-loss legs, placed as one `normalTpsl` group. The legs
+/// are reduce-only, sized like the entry, and only become active once the entry fills
+#[derive(Debug)]
+pub struct BracketOrderParams<'a> {
+    pub asset: &'a str,
+    pub is_buy: bool,
+    pub sz: f64,
+    pub limit_px: f64,
+    pub tif: String,
+    pub cloid: Option<Uuid>,
+    pub take_profit: Option<ClientTpsl>,
+    pub stop_loss: Option<ClientTpsl>,
+    pub wallet: Option<&'a dyn HyperliquidSigner>,
+}
+
+/// Take-profit and stop-loss for an existing position, placed as one `positionTpsl` group
+#[derive(Debug)]
+pub struct PositionTpslParams<'a> {
+    pub asset: &'a str,
+    /// Size closed when a leg triggers; the whole position when `None`
+    pub sz: Option<f64>,
+    pub take_profit: Option<ClientTpsl>,
+    pub stop_loss: Option<ClientTpsl>,
+    pub wallet: Option<&'a dyn HyperliquidSigner>,
 }
 
 #[derive(Debug)]
@@ -122,3 +220,55 @@ impl ClientOrderRequest {
         })
     }
 }
+
+#[derive(Debug)]
+pub struct DecimalClientTrigger {
+    pub is_market: bool,
+    pub trigger_px: Decimal,
+    pub tpsl: String,
+}
+
+#[derive(Debug)]
+pub enum DecimalClientOrder {
+    Limit(ClientLimit),
+    Trigger(DecimalClientTrigger),
+}
+
+/// Order request with exact decimal price and size; values that don't fit the
+/// 8-decimal wire format are rejected rather than rounded
+#[derive(Debug)]
+pub struct DecimalClientOrderRequest {
+    pub asset: String,
+    pub is_buy: bool,
+    pub reduce_only: bool,
+    pub limit_px: Decimal,
+    pub sz: Decimal,
+    pub cloid: Option<Uuid>,
+    pub order_type: DecimalClientOrder,
+}
+
+impl DecimalClientOrderRequest {
+    pub(crate) fn convert(self, coin_to_asset: &HashMap<String, u32>) -> Result<OrderRequest> {
+        let order_type = match self.order_type {
+            DecimalClientOrder::Limit(limit) => Order::Limit(Limit { tif: limit.tif }),
+            DecimalClientOrder::Trigger(trigger) => Order::Trigger(Trigger {
+                trigger_px: decimal_to_string_for_hashing(trigger.trigger_px)?,
+                is_market: trigger.is_market,
+                tpsl: trigger.tpsl,
+            }),
+        };
+        let &asset = coin_to_asset.get(&self.asset).ok_or(Error::AssetNotFound)?;
+
+        let cloid = self.cloid.map(uuid_to_hex_string);
+
+        Ok(OrderRequest {
+            asset,
+            is_buy: self.is_buy,
+            reduce_only: self.reduce_only,
+            limit_px: decimal_to_string_for_hashing(self.limit_px)?,
+            sz: decimal_to_string_for_hashing(self.sz)?,
+            order_type,
+            cloid,
+        })
+    }
+}
@@ -0,0 +1,1091 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ethers::types::H160;
+use log::{info, warn};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{self, MissedTickBehavior};
+use uuid::Uuid;
+
+use crate::{
+    helpers::uuid_to_hex_string, prelude::*, BasicOrder, BasicOrderInfo, ClientCancelRequest,
+    ClientOrderRequest, Error, ExchangeClient, ExchangeDataStatus, ExchangeRejection,
+    ExchangeResponseStatus, InfoClient, Message, OpenOrdersResponse, Subscription, TradeInfo,
+    EPSILON,
+};
+
+/// How long fills for orders the manager hasn't seen yet are kept
+const ORPHAN_FILL_TTL_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderState {
+    /// Sent, not acknowledged yet
+    Pending,
+    Resting,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected,
+}
+
+impl OrderState {
+    pub fn is_open(self) -> bool {
+        matches!(
+            self,
+            OrderState::Pending | OrderState::Resting | OrderState::PartiallyFilled
+        )
+    }
+
+    // Maps an `orderUpdates` / `orderStatus` status. `None` for statuses that don't move the order,
+    // like `triggered`.
+    fn from_status(status: &str) -> Option<OrderState> {
+        match status {
+            "open" => Some(OrderState::Resting),
+            "filled" => Some(OrderState::Filled),
+            "scheduledCancel" => Some(OrderState::Cancelled),
+            _ if status.ends_with("anceled") => Some(OrderState::Cancelled),
+            _ if status.ends_with("ejected") => Some(OrderState::Rejected),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderFill {
+    pub tid: u64,
+    pub px: f64,
+    pub sz: f64,
+    pub fee: f64,
+    pub time: u64,
+    pub crossed: bool,
+}
+
+/// Local view of one order
+#[derive(Debug, Clone)]
+pub struct ManagedOrder {
+    /// Assigned by the exchange once it acknowledges the order
+    pub oid: Option<u64>,
+    pub cloid: Option<String>,
+    pub coin: String,
+    pub is_buy: bool,
+    pub limit_px: f64,
+    pub orig_sz: f64,
+    pub filled_sz: f64,
+    pub state: OrderState,
+    /// Rejection error or the exchange status that closed the order, e.g. `marginCanceled`
+    pub status_detail: Option<String>,
+    pub fills: Vec<OrderFill>,
+    /// Unix ms
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl ManagedOrder {
+    pub fn remaining_sz(&self) -> f64 {
+        (self.orig_sz - self.filled_sz).max(0.0)
+    }
+
+    /// Average price of the fills seen on the `userFills` channel
+    pub fn average_fill_px(&self) -> Option<f64> {
+        let sz: f64 = self.fills.iter().map(|fill| fill.sz).sum();
+        let notional: f64 = self.fills.iter().map(|fill| fill.px * fill.sz).sum();
+        (sz > 0.0).then(|| notional / sz)
+    }
+}
+
+/// Differences found between the local book and the exchange's open orders
+#[derive(Debug, Clone, Default)]
+pub struct OrderDrift {
+    pub checked_at: u64,
+    /// Open on the exchange but not locally; now tracked
+    pub adopted: Vec<u64>,
+    /// Open locally but gone from the exchange; updated from their final status
+    pub closed: Vec<u64>,
+    /// Open on both sides with a different remaining size; local size corrected
+    pub resized: Vec<u64>,
+    /// Cloids of pending orders the exchange never acknowledged and doesn't know; marked rejected
+    pub unacknowledged: Vec<String>,
+}
+
+impl OrderDrift {
+    pub fn is_clean(&self) -> bool {
+        self.adopted.is_empty()
+            && self.closed.is_empty()
+            && self.resized.is_empty()
+            && self.unacknowledged.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderManagerConfig {
+    /// How often the local book is checked against `open_orders`
+    pub reconcile_interval: Duration,
+    /// Pending orders not acknowledged within this long are looked up by cloid, and marked
+    /// rejected if the exchange doesn't know them
+    pub pending_timeout: Duration,
+    /// Closed orders kept for lookups; the oldest are dropped first
+    pub max_closed_orders: usize,
+}
+
+impl Default for OrderManagerConfig {
+    fn default() -> Self {
+        Self {
+            reconcile_interval: Duration::from_secs(30),
+            pending_timeout: Duration::from_secs(10),
+            max_closed_orders: 1000,
+        }
+    }
+}
+
+/// Order management system keeping a local book of the user's orders, keyed by oid and cloid.
+///
+/// Orders placed through `place` are tracked from the moment they are sent; orders placed any
+/// other way are picked up from the `orderUpdates` channel. Fills come from `userFills`, and a
+/// background task periodically checks the book against `open_orders` to correct drift from
+/// missed messages. Dropping the manager stops the task.
+pub struct OrderManager {
+    inner: Arc<Inner>,
+    task: JoinHandle<()>,
+}
+
+struct Inner {
+    client: Arc<ExchangeClient>,
+    // Separate from the client holding the subscriptions, which lives in the task
+    info_client: InfoClient,
+    user: H160,
+    config: OrderManagerConfig,
+    store: Mutex<OrderStore>,
+}
+
+impl OrderManager {
+    /// Subscribes to the user's order updates and fills and starts reconciling. `info_client` is
+    /// moved into the task so its subscriptions live as long as the manager.
+    pub async fn start(
+        exchange_client: Arc<ExchangeClient>,
+        mut info_client: InfoClient,
+        config: OrderManagerConfig,
+    ) -> Result<Self> {
+        if config.reconcile_interval.is_zero() {
+            return Err(Error::Custom(
+                "Order manager reconcile interval must be positive".to_string(),
+            ));
+        }
+        let user = exchange_client
+            .vault_address
+            .unwrap_or_else(|| exchange_client.wallet.address());
+
+        let (sender, messages) = mpsc::unbounded_channel();
+        info_client
+            .subscribe(Subscription::OrderUpdates { user }, sender.clone())
+            .await?;
+        info_client
+            .subscribe(Subscription::UserFills { user }, sender)
+            .await?;
+
+        let http = &info_client.http_client;
+        let mut snapshot_client =
+            InfoClient::new(Some(http.client.clone()), Some(http.base_url)).await?;
+        snapshot_client.http_client = http.clone();
+        let inner = Arc::new(Inner {
+            client: exchange_client,
+            info_client: snapshot_client,
+            user,
+            store: Mutex::new(OrderStore::new(now_ms())),
+            config,
+        });
+        let task = tokio::spawn(run(Arc::clone(&inner), info_client, messages));
+
+        Ok(Self { inner, task })
+    }
+
+    /// Places the orders, tracking each as pending until the exchange answers. Orders without a
+    /// cloid get a random one so they can be matched to their updates before the oid is known.
+    /// Returns the orders as of the response. If the request fails, they stay pending until
+    /// reconciliation finds them on the exchange or rejects them.
+    pub async fn place(&self, mut orders: Vec<ClientOrderRequest>) -> Result<Vec<ManagedOrder>> {
+        let now = now_ms();
+        let ids: Vec<u64> = {
+            let mut store = self.inner.store.lock().unwrap();
+            orders
+                .iter_mut()
+                .map(|order| {
+                    let cloid = *order.cloid.get_or_insert_with(Uuid::new_v4);
+                    store.insert_pending(order, uuid_to_hex_string(cloid), now)
+                })
+                .collect()
+        };
+
+        let response = self.inner.client.bulk_order(orders, None).await?;
+        let mut store = self.inner.store.lock().unwrap();
+        match response {
+            ExchangeResponseStatus::Ok(response) => {
+                let statuses = response.data.map(|data| data.statuses).unwrap_or_default();
+                for (&id, status) in ids.iter().zip(statuses) {
+                    store.apply_placed(id, status, now_ms());
+                }
+            }
+            ExchangeResponseStatus::Err(e) => {
+                for &id in &ids {
+                    store.apply_placed(id, ExchangeDataStatus::Error(e.clone()), now_ms());
+                }
+            }
+        }
+        Ok(ids
+            .iter()
+            .filter_map(|id| store.orders.get(id).cloned())
+            .collect())
+    }
+
+    /// Cancels an order by oid, marking it cancelled once the exchange confirms
+    pub async fn cancel(&self, oid: u64) -> Result<()> {
+        let coin = self
+            .get(oid)
+            .map(|order| order.coin)
+            .ok_or_else(|| Error::Custom(format!("Order {oid} is not tracked")))?;
+        let response = self
+            .inner
+            .client
+            .cancel(ClientCancelRequest { asset: coin, oid }, None)
+            .await?;
+
+        let status = match response {
+            ExchangeResponseStatus::Ok(response) => response
+                .data
+                .and_then(|data| data.statuses.into_iter().next()),
+            ExchangeResponseStatus::Err(e) => return Err(ExchangeRejection::parse(e).into()),
+        };
+        match status {
+            Some(ExchangeDataStatus::Success) => {
+                let mut store = self.inner.store.lock().unwrap();
+                store.close(oid, OrderState::Cancelled, "canceled", now_ms(), false);
+                Ok(())
+            }
+            Some(ExchangeDataStatus::Error(e)) => Err(ExchangeRejection::parse(e).into()),
+            status => Err(Error::GenericRequest(format!(
+                "Unexpected cancel status: {status:?}"
+            ))),
+        }
+    }
+
+    pub fn get(&self, oid: u64) -> Option<ManagedOrder> {
+        let store = self.inner.store.lock().unwrap();
+        store
+            .by_oid
+            .get(&oid)
+            .and_then(|id| store.orders.get(id))
+            .cloned()
+    }
+
+    /// Looks an order up by its cloid, as a `0x` prefixed hex string
+    pub fn get_by_cloid(&self, cloid: &str) -> Option<ManagedOrder> {
+        let store = self.inner.store.lock().unwrap();
+        let cloid = cloid.to_lowercase();
+        store
+            .by_cloid
+            .get(&cloid)
+            .and_then(|id| store.orders.get(id))
+            .cloned()
+    }
+
+    /// Pending, resting and partially filled orders, oldest first
+    pub fn open_orders(&self) -> Vec<ManagedOrder> {
+        self.orders(|order| order.state.is_open())
+    }
+
+    /// Tracked orders matching `filter`, oldest first
+    pub fn orders(&self, filter: impl Fn(&ManagedOrder) -> bool) -> Vec<ManagedOrder> {
+        let store = self.inner.store.lock().unwrap();
+        let mut orders: Vec<ManagedOrder> = store
+            .orders
+            .values()
+            .filter(|order| filter(order))
+            .cloned()
+            .collect();
+        orders.sort_by_key(|order| order.created_at);
+        orders
+    }
+
+    /// Checks the local book against the exchange right away instead of waiting for the next
+    /// periodic check
+    pub async fn reconcile(&self) -> Result<OrderDrift> {
+        self.inner.reconcile().await
+    }
+
+    /// Result of the latest reconciliation, periodic or not
+    pub fn last_drift(&self) -> Option<OrderDrift> {
+        self.inner.store.lock().unwrap().last_drift.clone()
+    }
+}
+
+impl Drop for OrderManager {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl Inner {
+    async fn reconcile(&self) -> Result<OrderDrift> {
+        let checked_at = now_ms();
+        let open = self.info_client.open_orders(self.user).await?;
+        let mut drift = {
+            let mut store = self.lock_store();
+            store.compare(&open, checked_at, &self.config)
+        };
+
+        // Orders that appeared or disappeared without an update: fetch their full status
+        for &oid in drift.adopted.iter().chain(&drift.closed) {
+            let status = self.info_client.query_order_by_oid(self.user, oid).await?;
+            if let Some(order) = status.order {
+                let snapshot = OrderSnapshot::from_info(&order.order);
+                self.lock_store()
+                    .upsert(&snapshot, &order.status, order.status_timestamp, true);
+            }
+        }
+
+        // A placement whose response was lost may still have landed, and even filled right away
+        for cloid in std::mem::take(&mut drift.unacknowledged) {
+            let order = match Uuid::parse_str(cloid.trim_start_matches("0x")) {
+                Ok(uuid) => {
+                    self.info_client
+                        .query_order_by_cloid(self.user, uuid)
+                        .await?
+                        .order
+                }
+                Err(_) => None,
+            };
+            let mut store = self.lock_store();
+            match order {
+                Some(order) => {
+                    let snapshot = OrderSnapshot::from_info(&order.order);
+                    store.upsert(&snapshot, &order.status, order.status_timestamp, true);
+                }
+                None if store.reject_unacknowledged(&cloid) => drift.unacknowledged.push(cloid),
+                None => {}
+            }
+        }
+
+        let mut store = self.lock_store();
+        store.prune(checked_at, self.config.max_closed_orders);
+        drift.checked_at = checked_at;
+        store.last_drift = Some(drift.clone());
+        Ok(drift)
+    }
+
+    fn lock_store(&self) -> std::sync::MutexGuard<'_, OrderStore> {
+        self.store.lock().unwrap()
+    }
+}
+
+async fn run(
+    inner: Arc<Inner>,
+    // Held so the subscriptions stay open
+    _info_client: InfoClient,
+    mut messages: mpsc::UnboundedReceiver<Message>,
+) {
+    let mut ticker = time::interval_at(
+        time::Instant::now() + inner.config.reconcile_interval,
+        inner.config.reconcile_interval,
+    );
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            message = messages.recv() => match message {
+                Some(message) => inner.lock_store().apply(&message),
+                None => {
+                    warn!("Order manager subscriptions closed, relying on reconciliation");
+                    break;
+                }
+            },
+            _ = ticker.tick() => match inner.reconcile().await {
+                Ok(drift) if !drift.is_clean() => warn!("Order book drift corrected: {drift:?}"),
+                Ok(_) => {}
+                Err(e) => warn!("Order reconciliation failed: {e}"),
+            },
+        }
+    }
+
+    loop {
+        ticker.tick().await;
+        if let Err(e) = inner.reconcile().await {
+            warn!("Order reconciliation failed: {e}");
+        }
+    }
+}
+
+// The order fields shared by `orderUpdates` and `orderStatus`
+struct OrderSnapshot<'a> {
+    oid: u64,
+    cloid: Option<&'a str>,
+    coin: &'a str,
+    side: &'a str,
+    limit_px: &'a str,
+    sz: &'a str,
+    orig_sz: &'a str,
+    timestamp: u64,
+}
+
+impl<'a> OrderSnapshot<'a> {
+    fn from_update(order: &'a BasicOrder) -> Self {
+        Self {
+            oid: order.oid,
+            cloid: order.cloid.as_deref(),
+            coin: &order.coin,
+            side: &order.side,
+            limit_px: &order.limit_px,
+            sz: &order.sz,
+            orig_sz: &order.orig_sz,
+            timestamp: order.timestamp,
+        }
+    }
+
+    fn from_info(order: &'a BasicOrderInfo) -> Self {
+        Self {
+            oid: order.oid,
+            cloid: order.cloid.as_deref(),
+            coin: &order.coin,
+            side: &order.side,
+            limit_px: &order.limit_px,
+            sz: &order.sz,
+            orig_sz: &order.orig_sz,
+            timestamp: order.timestamp,
+        }
+    }
+}
+
+struct OrderStore {
+    started_at: u64,
+    next_id: u64,
+    orders: HashMap<u64, ManagedOrder>,
+    by_oid: HashMap<u64, u64>,
+    by_cloid: HashMap<String, u64>,
+    /// Fills for oids not seen yet, in case they beat the order update
+    orphan_fills: Vec<TradeInfo>,
+    last_drift: Option<OrderDrift>,
+}
+
+impl OrderStore {
+    fn new(started_at: u64) -> Self {
+        Self {
+            started_at,
+            next_id: 0,
+            orders: HashMap::new(),
+            by_oid: HashMap::new(),
+            by_cloid: HashMap::new(),
+            orphan_fills: Vec::new(),
+            last_drift: None,
+        }
+    }
+
+    fn insert(&mut self, order: ManagedOrder) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        if let Some(oid) = order.oid {
+            self.by_oid.insert(oid, id);
+        }
+        if let Some(cloid) = &order.cloid {
+            self.by_cloid.insert(cloid.clone(), id);
+        }
+        self.orders.insert(id, order);
+        id
+    }
+
+    fn insert_pending(&mut self, order: &ClientOrderRequest, cloid: String, now: u64) -> u64 {
+        self.insert(ManagedOrder {
+            oid: None,
+            cloid: Some(cloid),
+            coin: order.asset.clone(),
+            is_buy: order.is_buy,
+            limit_px: order.limit_px,
+            orig_sz: order.sz,
+            filled_sz: 0.0,
+            state: OrderState::Pending,
+            status_detail: None,
+            fills: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    fn link_oid(&mut self, id: u64, oid: u64) {
+        if let Some(order) = self.orders.get_mut(&id) {
+            order.oid = Some(oid);
+            self.by_oid.insert(oid, id);
+        }
+        // Fills that arrived before the oid was known
+        let (matched, orphans): (Vec<_>, Vec<_>) = std::mem::take(&mut self.orphan_fills)
+            .into_iter()
+            .partition(|fill| fill.oid == oid);
+        self.orphan_fills = orphans;
+        for fill in &matched {
+            self.apply_fill(fill);
+        }
+    }
+
+    fn apply_placed(&mut self, id: u64, status: ExchangeDataStatus, now: u64) {
+        match status {
+            ExchangeDataStatus::Resting(resting) => {
+                self.link_oid(id, resting.oid);
+                if let Some(order) = self.orders.get_mut(&id) {
+                    if order.state == OrderState::Pending {
+                        order.state = OrderState::Resting;
+                        order.updated_at = now;
+                    }
+                }
+            }
+            ExchangeDataStatus::Filled(filled) => {
+                self.link_oid(id, filled.oid);
+                if let Some(order) = self.orders.get_mut(&id) {
+                    let total_sz = filled.total_sz.parse().unwrap_or_default();
+                    order.filled_sz = order.filled_sz.max(total_sz);
+                    // An IOC remainder is cancelled, which its order update reports
+                    if order.state.is_open() {
+                        order.state = fill_state(order);
+                        order.updated_at = now;
+                    }
+                }
+            }
+            ExchangeDataStatus::Error(e) => {
+                if let Some(order) = self.orders.get_mut(&id) {
+                    order.state = OrderState::Rejected;
+                    order.status_detail = Some(e);
+                    order.updated_at = now;
+                }
+            }
+            status => warn!("Unexpected order status: {status:?}"),
+        }
+    }
+
+    fn apply(&mut self, message: &Message) {
+        match message {
+            Message::OrderUpdates(updates) => {
+                for update in &updates.data {
+                    let snapshot = OrderSnapshot::from_update(&update.order);
+                    self.upsert(&snapshot, &update.status, update.status_timestamp, false);
+                }
+            }
+            Message::UserFills(fills) => {
+                for fill in &fills.data.fills {
+                    self.apply_fill(fill);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Creates or updates the order from an exchange snapshot. Closed orders only reopen when
+    /// `force` is set, since updates can arrive out of order but reconciliation is authoritative.
+    fn upsert(&mut self, snapshot: &OrderSnapshot, status: &str, status_time: u64, force: bool) {
+        let cloid = snapshot.cloid.map(str::to_lowercase);
+        let id = match self.by_oid.get(&snapshot.oid).copied().or_else(|| {
+            cloid
+                .as_ref()
+                .and_then(|cloid| self.by_cloid.get(cloid).copied())
+        }) {
+            Some(id) => id,
+            None => self.insert(ManagedOrder {
+                oid: None,
+                cloid: cloid.clone(),
+                coin: snapshot.coin.to_string(),
+                is_buy: snapshot.side == "B",
+                limit_px: 0.0,
+                orig_sz: 0.0,
+                filled_sz: 0.0,
+                state: OrderState::Pending,
+                status_detail: None,
+                fills: Vec::new(),
+                created_at: snapshot.timestamp,
+                updated_at: snapshot.timestamp,
+            }),
+        };
+        if self.orders[&id].oid.is_none() {
+            self.link_oid(id, snapshot.oid);
+        }
+
+        let Some(order) = self.orders.get_mut(&id) else {
+            return;
+        };
+        if !order.state.is_open() && !force {
+            return;
+        }
+        let orig_sz = snapshot.orig_sz.parse::<f64>().unwrap_or(order.orig_sz);
+        let remaining = snapshot.sz.parse::<f64>().unwrap_or(orig_sz);
+        order.orig_sz = orig_sz;
+        order.limit_px = snapshot.limit_px.parse().unwrap_or(order.limit_px);
+        order.filled_sz = if force {
+            orig_sz - remaining
+        } else {
+            order.filled_sz.max(orig_sz - remaining)
+        };
+        order.updated_at = order.updated_at.max(status_time);
+        match OrderState::from_status(status) {
+            Some(OrderState::Resting) => order.state = fill_state(order),
+            Some(OrderState::Filled) => order.state = OrderState::Filled,
+            Some(state) => {
+                order.state = state;
+                order.status_detail = Some(status.to_string());
+            }
+            // Triggered orders are live again
+            None if order.state == OrderState::Pending => order.state = fill_state(order),
+            None => {}
+        }
+    }
+
+    fn apply_fill(&mut self, fill: &TradeInfo) {
+        let Some(order) = self
+            .by_oid
+            .get(&fill.oid)
+            .and_then(|id| self.orders.get_mut(id))
+        else {
+            if fill.time >= self.started_at {
+                self.orphan_fills.push(fill.clone());
+            }
+            return;
+        };
+        if order.fills.iter().any(|seen| seen.tid == fill.tid) {
+            return;
+        }
+        let (Ok(px), Ok(sz)) = (fill.px.parse::<f64>(), fill.sz.parse::<f64>()) else {
+            return;
+        };
+        order.fills.push(OrderFill {
+            tid: fill.tid,
+            px,
+            sz,
+            fee: fill.fee.parse().unwrap_or_default(),
+            time: fill.time,
+            crossed: fill.crossed,
+        });
+        let streamed: f64 = order.fills.iter().map(|fill| fill.sz).sum();
+        order.filled_sz = order.filled_sz.max(streamed);
+        order.updated_at = order.updated_at.max(fill.time);
+        if order.state.is_open() {
+            order.state = fill_state(order);
+        }
+    }
+
+    fn close(&mut self, oid: u64, state: OrderState, detail: &str, now: u64, force: bool) {
+        let Some(order) = self.by_oid.get(&oid).and_then(|id| self.orders.get_mut(id)) else {
+            return;
+        };
+        if order.state.is_open() || force {
+            order.state = state;
+            order.status_detail = Some(detail.to_string());
+            order.updated_at = now;
+        }
+    }
+
+    /// Compares the book with the exchange's open orders, fixing sizes in place and listing
+    /// pending orders unacknowledged for too long to look up. Orders touched since `checked_at`
+    /// are skipped, as the snapshot may predate them.
+    fn compare(
+        &mut self,
+        open: &[OpenOrdersResponse],
+        checked_at: u64,
+        config: &OrderManagerConfig,
+    ) -> OrderDrift {
+        let mut drift = OrderDrift::default();
+        let open_oids: HashSet<u64> = open.iter().map(|order| order.oid).collect();
+
+        for exchange_order in open {
+            let local = self
+                .by_oid
+                .get(&exchange_order.oid)
+                .and_then(|id| self.orders.get_mut(id));
+            match local {
+                Some(order) if order.updated_at >= checked_at => {}
+                Some(order) if order.state.is_open() => {
+                    let remaining = exchange_order.sz.parse::<f64>().unwrap_or_default();
+                    if (order.remaining_sz() - remaining).abs() > EPSILON {
+                        order.filled_sz = (order.orig_sz - remaining).max(0.0);
+                        order.state = fill_state(order);
+                        drift.resized.push(exchange_order.oid);
+                    }
+                }
+                _ => drift.adopted.push(exchange_order.oid),
+            }
+        }
+
+        let pending_cutoff = checked_at.saturating_sub(config.pending_timeout.as_millis() as u64);
+        for order in self.orders.values_mut() {
+            if !order.state.is_open() || order.updated_at >= checked_at {
+                continue;
+            }
+            match order.oid {
+                Some(oid) if !open_oids.contains(&oid) => drift.closed.push(oid),
+                None if order.created_at < pending_cutoff => {
+                    drift.unacknowledged.extend(order.cloid.iter().cloned());
+                }
+                _ => {}
+            }
+        }
+
+        // Whatever happened to orders that vanished, they aren't open; their status query
+        // refines this when it can
+        for &oid in &drift.closed {
+            self.close(
+                oid,
+                OrderState::Cancelled,
+                "notOpenOnExchange",
+                checked_at,
+                false,
+            );
+        }
+        drift
+    }
+
+    /// Rejects a pending order the exchange has no record of, unless it was acknowledged in the
+    /// meantime
+    fn reject_unacknowledged(&mut self, cloid: &str) -> bool {
+        let Some(order) = self
+            .by_cloid
+            .get(cloid)
+            .and_then(|id| self.orders.get_mut(id))
+        else {
+            return false;
+        };
+        if order.oid.is_some() || order.state != OrderState::Pending {
+            return false;
+        }
+        order.state = OrderState::Rejected;
+        order.status_detail = Some("No acknowledgement from the exchange".to_string());
+        true
+    }
+
+    fn prune(&mut self, now: u64, max_closed_orders: usize) {
+        self.orphan_fills
+            .retain(|fill| fill.time + ORPHAN_FILL_TTL_MS >= now);
+
+        let mut closed: Vec<(u64, u64)> = self
+            .orders
+            .iter()
+            .filter(|(_, order)| !order.state.is_open())
+            .map(|(&id, order)| (order.updated_at, id))
+            .collect();
+        if closed.len() <= max_closed_orders {
+            return;
+        }
+        closed.sort_unstable();
+        for (_, id) in &closed[..closed.len() - max_closed_orders] {
+            if let Some(order) = self.orders.remove(id) {
+                if let Some(oid) = order.oid {
+                    self.by_oid.remove(&oid);
+                }
+                if let Some(cloid) = order.cloid {
+                    self.by_cloid.remove(&cloid);
+                }
+            }
+        }
+        info!(
+            "Dropped {} closed orders from the order manager",
+            closed.len() - max_closed_orders
+        );
+    }
+}
+
+fn fill_state(order: &ManagedOrder) -> OrderState {
+    if order.filled_sz + EPSILON >= order.orig_sz && order.orig_sz > 0.0 {
+        OrderState::Filled
+    } else if order.filled_sz > EPSILON {
+        OrderState::PartiallyFilled
+    } else {
+        OrderState::Resting
+    }
+}
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::test_utils::*;
+    use crate::mock_server::{MockFixtures, MockServer};
+    use crate::{ClientLimit, ClientOrder};
+    use serde_json::json;
+
+    const CLOID: &str = "0x0123456789abcdef0123456789abcdef";
+
+    fn message(value: serde_json::Value) -> Message {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn order_update(oid: u64, sz: &str, status: &str, time: u64) -> Message {
+        message(json!({
+            "channel": "orderUpdates",
+            "data": [{
+                "order": {
+                    "coin": "BTC", "side": "B", "limitPx": "50000", "sz": sz, "oid": oid,
+                    "timestamp": 1_000, "origSz": "1.0", "cloid": CLOID
+                },
+                "status": status,
+                "statusTimestamp": time
+            }]
+        }))
+    }
+
+    fn fill(oid: u64, tid: u64, sz: &str, time: u64) -> Message {
+        message(json!({
+            "channel": "userFills",
+            "data": { "user": "0x0000000000000000000000000000000000000001", "fills": [{
+                "coin": "BTC", "side": "B", "px": "50000", "sz": sz, "time": time, "hash": "0x0",
+                "startPosition": "0", "dir": "Open Long", "closedPnl": "0", "oid": oid,
+                "cloid": CLOID, "crossed": false, "fee": "1", "tid": tid
+            }]}
+        }))
+    }
+
+    fn pending_order(store: &mut OrderStore, cloid: &str) -> u64 {
+        let request = ClientOrderRequest {
+            asset: "BTC".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px: 50_000.0,
+            sz: 1.0,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit {
+                tif: "Gtc".to_string(),
+            }),
+        };
+        store.insert_pending(&request, cloid.to_string(), 1_000)
+    }
+
+    #[test]
+    fn follows_an_order_through_its_lifecycle() {
+        let mut store = OrderStore::new(0);
+        let id = pending_order(&mut store, CLOID);
+
+        // The fill and the order update both beat the order response
+        store.apply(&fill(7, 1, "0.4", 1_100));
+        store.apply(&order_update(7, "0.6", "open", 1_100));
+        assert_eq!(store.orders[&id].oid, Some(7));
+        assert_eq!(store.orders[&id].state, OrderState::PartiallyFilled);
+        assert_eq!(store.orders[&id].fills.len(), 1);
+
+        store.apply_placed(
+            id,
+            ExchangeDataStatus::Resting(crate::RestingOrder { oid: 7 }),
+            1_200,
+        );
+        assert_eq!(store.orders[&id].state, OrderState::PartiallyFilled);
+
+        // A resent fill is not counted twice
+        store.apply(&fill(7, 1, "0.4", 1_100));
+        store.apply(&fill(7, 2, "0.6", 1_300));
+        let order = &store.orders[&id];
+        assert_eq!(order.state, OrderState::Filled);
+        assert_eq!((order.filled_sz, order.remaining_sz()), (1.0, 0.0));
+        assert_eq!(order.average_fill_px(), Some(50_000.0));
+
+        // A late update can't reopen it
+        store.apply(&order_update(7, "0.6", "open", 1_150));
+        assert_eq!(store.orders[&id].state, OrderState::Filled);
+
+        let mut rejected = OrderStore::new(0);
+        let id = pending_order(&mut rejected, CLOID);
+        rejected.apply_placed(
+            id,
+            ExchangeDataStatus::Error("Insufficient margin".to_string()),
+            1,
+        );
+        assert_eq!(rejected.orders[&id].state, OrderState::Rejected);
+
+        let mut cancelled = OrderStore::new(0);
+        cancelled.apply(&order_update(9, "1.0", "open", 1_000));
+        cancelled.apply(&order_update(9, "1.0", "marginCanceled", 1_100));
+        let order = &cancelled.orders[&cancelled.by_oid[&9]];
+        assert_eq!(order.state, OrderState::Cancelled);
+        assert_eq!(order.status_detail.as_deref(), Some("marginCanceled"));
+    }
+
+    #[test]
+    fn reconciliation_reports_drift() {
+        let config = OrderManagerConfig::default();
+        let mut store = OrderStore::new(0);
+        store.apply(&order_update(1, "1.0", "open", 1_000));
+        let gone = store.by_oid[&1];
+        let mut other = order_update(2, "1.0", "open", 1_000);
+        if let Message::OrderUpdates(updates) = &mut other {
+            updates.data[0].order.cloid = None;
+        }
+        store.apply(&other);
+        let stale = pending_order(&mut store, "0xstale");
+
+        let open = |oid, sz: &str| OpenOrdersResponse {
+            coin: "BTC".to_string(),
+            limit_px: "50000".to_string(),
+            oid,
+            side: "B".to_string(),
+            sz: sz.to_string(),
+            timestamp: 1_000,
+        };
+        let drift = store.compare(&[open(2, "0.25"), open(3, "1.0")], 20_000, &config);
+        assert_eq!(drift.adopted, vec![3]);
+        assert_eq!(drift.closed, vec![1]);
+        assert_eq!(drift.resized, vec![2]);
+        assert_eq!(drift.unacknowledged, vec!["0xstale".to_string()]);
+        assert_eq!(store.orders[&gone].state, OrderState::Cancelled);
+        assert_eq!(store.orders[&store.by_oid[&2]].filled_sz, 0.75);
+        // Only rejected once the exchange has been asked about it
+        assert_eq!(store.orders[&stale].state, OrderState::Pending);
+        assert!(store.reject_unacknowledged("0xstale"));
+        assert_eq!(store.orders[&stale].state, OrderState::Rejected);
+
+        let drift = store.compare(&[open(2, "0.25")], 30_000, &config);
+        assert!(drift.is_clean());
+    }
+
+    #[tokio::test]
+    async fn unacknowledged_orders_are_looked_up_before_rejecting() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let client = Arc::new(exchange_client(&server, MAKER_KEY).await);
+        // Not subscribed to anything, so only the lookups can tell what became of the orders
+        let mut inner = Inner {
+            client: Arc::clone(&client),
+            info_client: InfoClient::new(None, Some(server.base_url())).await?,
+            user: client.wallet.address(),
+            config: OrderManagerConfig {
+                pending_timeout: Duration::ZERO,
+                ..Default::default()
+            },
+            store: Mutex::new(OrderStore::new(now_ms())),
+        };
+        let with_cloid = |order| ClientOrderRequest {
+            cloid: Some(Uuid::new_v4()),
+            ..order
+        };
+        let landed = with_cloid(limit(true, 51_000.0, 0.1, "Ioc"));
+        let lost = with_cloid(limit(true, 49_000.0, 0.1, "Gtc"));
+        let lost_cloid = uuid_to_hex_string(lost.cloid.unwrap());
+        let ids: Vec<u64> = {
+            let mut store = inner.lock_store();
+            [&landed, &lost]
+                .into_iter()
+                .map(|order| {
+                    let cloid = uuid_to_hex_string(order.cloid.unwrap());
+                    store.insert_pending(order, cloid, now_ms())
+                })
+                .collect()
+        };
+        // The IOC fills on the exchange, but as far as the manager knows it is still pending
+        assert!(matches!(
+            status(client.order(landed, None).await?),
+            ExchangeDataStatus::Filled(_)
+        ));
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        // Orders stay pending while they can't be looked up
+        let broken = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        broken.set_info_response("orderStatus", json!({ "unexpected": true }));
+        let info_client = std::mem::replace(
+            &mut inner.info_client,
+            InfoClient::new(None, Some(broken.base_url())).await?,
+        );
+        assert!(inner.reconcile().await.is_err());
+        assert!(ids
+            .iter()
+            .all(|id| inner.lock_store().orders[id].state == OrderState::Pending));
+
+        inner.info_client = info_client;
+        let drift = inner.reconcile().await?;
+        assert_eq!(drift.unacknowledged, vec![lost_cloid]);
+        let store = inner.lock_store();
+        assert_eq!(store.orders[&ids[0]].state, OrderState::Filled);
+        assert!(store.orders[&ids[0]].oid.is_some());
+        assert_eq!(store.orders[&ids[1]].state, OrderState::Rejected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn order_manager_tracks_orders_from_updates_and_fills() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let client = Arc::new(exchange_client(&server, MAKER_KEY).await);
+        let taker = exchange_client(&server, TAKER_KEY).await;
+        let config = OrderManagerConfig {
+            reconcile_interval: Duration::from_millis(200),
+            ..Default::default()
+        };
+        let oms = OrderManager::start(
+            Arc::clone(&client),
+            InfoClient::new(None, Some(server.base_url())).await?,
+            config,
+        )
+        .await?;
+        // Let the subscriptions land before anything trades
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let placed = oms
+            .place(vec![
+                limit(true, 49_000.0, 0.2, "Gtc"),
+                limit(true, 51_000.0, 0.1, "Gtc"),
+            ])
+            .await?;
+        assert_eq!(
+            (placed[0].state, placed[1].state),
+            (OrderState::Resting, OrderState::Filled)
+        );
+        assert!(placed.iter().all(|order| order.cloid.is_some()));
+        let resting = placed[0].oid.unwrap();
+        let filled = placed[1].oid.unwrap();
+
+        let wait_for = |oid: u64, done: fn(&crate::ManagedOrder) -> bool| {
+            let oms = &oms;
+            async move {
+                let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+                loop {
+                    match oms.get(oid) {
+                        Some(order) if done(&order) => return order,
+                        order => assert!(
+                            tokio::time::Instant::now() < deadline,
+                            "order stuck at {order:?}"
+                        ),
+                    }
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+            }
+        };
+        assert_eq!(
+            wait_for(filled, |order| !order.fills.is_empty())
+                .await
+                .average_fill_px(),
+            Some(50_000.0)
+        );
+
+        status(
+            taker
+                .order(limit(false, 49_000.0, 0.05, "Ioc"), None)
+                .await?,
+        );
+        let partial = wait_for(resting, |order| order.state == OrderState::PartiallyFilled).await;
+        assert!((partial.remaining_sz() - 0.15).abs() < EPSILON);
+        assert_eq!(
+            oms.get_by_cloid(partial.cloid.as_deref().unwrap())
+                .unwrap()
+                .oid,
+            Some(resting)
+        );
+
+        // Orders placed around the manager are picked up from their updates
+        let ExchangeDataStatus::Resting(external) = status(
+            client
+                .order(limit(false, 52_000.0, 0.1, "Gtc"), None)
+                .await?,
+        ) else {
+            panic!("expected a resting order");
+        };
+        wait_for(external.oid, |order| order.state == OrderState::Resting).await;
+
+        oms.cancel(resting).await?;
+        assert_eq!(oms.get(resting).unwrap().state, OrderState::Cancelled);
+        assert_eq!(oms.open_orders().len(), 1);
+
+        let drift = oms.reconcile().await?;
+        assert!(drift.is_clean(), "unexpected drift: {drift:?}");
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(oms.last_drift().is_some_and(|drift| drift.is_clean()));
+        Ok(())
+    }
+}
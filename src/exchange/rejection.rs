@@ -0,0 +1,311 @@
+use thiserror::Error;
+
+use crate::{
+    ExchangeDataStatus, ExchangeResponseStatus, TwapCancelResponse, TwapCancelStatus,
+    TwapOrderResponse, TwapOrderStatus, TwapResponseStatus,
+};
+
+/// Whether sending a rejected request again can succeed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retryability {
+    /// Transient; the same request can be signed again and resent right away
+    Retryable,
+    /// The same request may succeed after waiting, e.g. for rate limits to refill
+    RetryAfterBackoff,
+    /// The request or the account must change before it can succeed
+    NotRetryable,
+    /// The request may or may not have been applied; look up what happened before sending it
+    /// again
+    OutcomeUnknown,
+}
+
+impl Retryability {
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            Retryability::Retryable | Retryability::RetryAfterBackoff
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectionKind {
+    InsufficientMargin,
+    InsufficientBalance,
+    ReduceOnlyWouldIncrease,
+    InvalidTickSize,
+    InvalidSize,
+    BelowMinNotional,
+    PostOnlyWouldCross,
+    IocCouldNotMatch,
+    PriceTooFarFromReference,
+    OpenInterestCap,
+    TooManyOpenOrders,
+    RateLimited,
+    /// The oid or cloid was never placed, or is already cancelled or filled
+    UnknownOrder,
+    UnknownAsset,
+    /// The signer isn't a known user or approved API wallet
+    UnknownUser,
+    /// The nonce is too old or too far ahead
+    InvalidNonce,
+    /// The nonce was used before, so a resent request may already have been applied
+    DuplicateNonce,
+    /// A message this SDK doesn't recognise
+    Other,
+}
+
+impl RejectionKind {
+    pub fn retryability(self) -> Retryability {
+        match self {
+            RejectionKind::DuplicateNonce => Retryability::OutcomeUnknown,
+            RejectionKind::RateLimited | RejectionKind::OpenInterestCap => {
+                Retryability::RetryAfterBackoff
+            }
+            _ => Retryability::NotRetryable,
+        }
+    }
+}
+
+// Checked in order against the lowercased message; every fragment of an entry must match
+const PATTERNS: &[(&[&str], RejectionKind)] = &[
+    (&["insufficient margin"], RejectionKind::InsufficientMargin),
+    (
+        &["insufficient balance"],
+        RejectionKind::InsufficientBalance,
+    ),
+    (
+        &["reduce only", "increase"],
+        RejectionKind::ReduceOnlyWouldIncrease,
+    ),
+    (
+        &["post only", "immediately matched"],
+        RejectionKind::PostOnlyWouldCross,
+    ),
+    (
+        &["could not immediately match"],
+        RejectionKind::IocCouldNotMatch,
+    ),
+    (&["tick size"], RejectionKind::InvalidTickSize),
+    (&["invalid price"], RejectionKind::InvalidTickSize),
+    (&["invalid size"], RejectionKind::InvalidSize),
+    (&["invalid twap size"], RejectionKind::InvalidSize),
+    (&["minimum value"], RejectionKind::BelowMinNotional),
+    (
+        &["reference price"],
+        RejectionKind::PriceTooFarFromReference,
+    ),
+    (&["open interest"], RejectionKind::OpenInterestCap),
+    (&["too many open orders"], RejectionKind::TooManyOpenOrders),
+    (&["too many", "requests"], RejectionKind::RateLimited),
+    (&["rate limit"], RejectionKind::RateLimited),
+    (&["never placed"], RejectionKind::UnknownOrder),
+    (&["canceled or filled"], RejectionKind::UnknownOrder),
+    (&["asset", "does not exist"], RejectionKind::UnknownAsset),
+    (&["does not exist"], RejectionKind::UnknownUser),
+    (&["duplicate nonce"], RejectionKind::DuplicateNonce),
+    (&["nonce"], RejectionKind::InvalidNonce),
+];
+
+/// A request the exchange refused, classified from its error message
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{message}")]
+pub struct ExchangeRejection {
+    pub kind: RejectionKind,
+    /// The message as the exchange sent it
+    pub message: String,
+}
+
+impl ExchangeRejection {
+    pub fn parse(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let lower = message.to_lowercase();
+        let kind = PATTERNS
+            .iter()
+            .find(|(fragments, _)| fragments.iter().all(|fragment| lower.contains(fragment)))
+            .map_or(RejectionKind::Other, |&(_, kind)| kind);
+        Self { kind, message }
+    }
+
+    pub fn retryability(&self) -> Retryability {
+        self.kind.retryability()
+    }
+}
+
+impl ExchangeDataStatus {
+    pub fn rejection(&self) -> Option<ExchangeRejection> {
+        match self {
+            ExchangeDataStatus::Error(e) => Some(ExchangeRejection::parse(e.as_str())),
+            _ => None,
+        }
+    }
+}
+
+impl ExchangeResponseStatus {
+    /// The rejection of the whole request, or else of the first order in it that was rejected;
+    /// check each status's `rejection` to tell orders in a batch apart
+    pub fn rejection(&self) -> Option<ExchangeRejection> {
+        match self {
+            ExchangeResponseStatus::Err(e) => Some(ExchangeRejection::parse(e.as_str())),
+            ExchangeResponseStatus::Ok(response) => response
+                .data
+                .as_ref()?
+                .statuses
+                .iter()
+                .find_map(ExchangeDataStatus::rejection),
+        }
+    }
+}
+
+impl TwapOrderResponse {
+    pub fn rejection(&self) -> Option<ExchangeRejection> {
+        match self {
+            TwapResponseStatus::Err(e) => Some(ExchangeRejection::parse(e.as_str())),
+            TwapResponseStatus::Ok(response) => match &response.data.status {
+                TwapOrderStatus::Error(e) => Some(ExchangeRejection::parse(e.as_str())),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl TwapCancelResponse {
+    pub fn rejection(&self) -> Option<ExchangeRejection> {
+        match self {
+            TwapResponseStatus::Err(e) => Some(ExchangeRejection::parse(e.as_str())),
+            TwapResponseStatus::Ok(response) => match &response.data.status {
+                TwapCancelStatus::Error(e) => Some(ExchangeRejection::parse(e.as_str())),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::test_utils::*;
+    use crate::mock_server::{MockFixtures, MockServer};
+    use crate::{prelude::Result, ClientCancelRequest, ExchangeResponseStatus};
+
+    #[test]
+    fn classifies_known_exchange_messages() {
+        let cases = [
+            (
+                "Insufficient margin to place order. asset=0",
+                RejectionKind::InsufficientMargin,
+            ),
+            (
+                "Reduce only order would increase position. asset=3",
+                RejectionKind::ReduceOnlyWouldIncrease,
+            ),
+            (
+                "Price must be divisible by tick size. asset=0",
+                RejectionKind::InvalidTickSize,
+            ),
+            (
+                "Order must have minimum value of $10. asset=0",
+                RejectionKind::BelowMinNotional,
+            ),
+            (
+                "Post only order would have immediately matched, bbo was 49990@50010. asset=0",
+                RejectionKind::PostOnlyWouldCross,
+            ),
+            (
+                "Order could not immediately match against any resting orders. asset=0",
+                RejectionKind::IocCouldNotMatch,
+            ),
+            (
+                "Order price cannot be more than 80% away from the reference price",
+                RejectionKind::PriceTooFarFromReference,
+            ),
+            (
+                "Too many cumulative requests sent (10001 > 10000) for cumulative volume traded",
+                RejectionKind::RateLimited,
+            ),
+            (
+                "Order was never placed, already canceled, or filled.",
+                RejectionKind::UnknownOrder,
+            ),
+            ("Asset 42 does not exist.", RejectionKind::UnknownAsset),
+            (
+                "User or API Wallet 0x0101 does not exist.",
+                RejectionKind::UnknownUser,
+            ),
+            (
+                "Invalid nonce: duplicate nonce 1700000000000",
+                RejectionKind::DuplicateNonce,
+            ),
+            (
+                "Invalid nonce: 1600000000000 is too far in the past",
+                RejectionKind::InvalidNonce,
+            ),
+            ("Something new went wrong", RejectionKind::Other),
+        ];
+        for (message, kind) in cases {
+            assert_eq!(ExchangeRejection::parse(message).kind, kind, "{message}");
+        }
+    }
+
+    #[test]
+    fn only_transient_rejections_are_retryable() {
+        assert_eq!(
+            RejectionKind::RateLimited.retryability(),
+            Retryability::RetryAfterBackoff
+        );
+        assert!(!RejectionKind::InvalidNonce.retryability().is_retryable());
+        assert_eq!(
+            RejectionKind::DuplicateNonce.retryability(),
+            Retryability::OutcomeUnknown
+        );
+        assert!(!Retryability::OutcomeUnknown.is_retryable());
+        assert!(!RejectionKind::InsufficientMargin
+            .retryability()
+            .is_retryable());
+        assert!(!RejectionKind::Other.retryability().is_retryable());
+    }
+
+    #[tokio::test]
+    async fn rejections_are_classified() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let client = exchange_client(&server, MAKER_KEY).await;
+        server.register_user(client.wallet.address());
+
+        let response = client
+            .order(limit(true, 50_500.0, 0.1, "Alo"), None)
+            .await?;
+        let rejection = response.rejection().unwrap();
+        assert_eq!(rejection.kind, RejectionKind::PostOnlyWouldCross);
+        assert_eq!(rejection.retryability(), Retryability::NotRetryable);
+
+        let response = client
+            .cancel(
+                ClientCancelRequest {
+                    asset: "BTC".to_string(),
+                    oid: 12_345,
+                },
+                None,
+            )
+            .await?;
+        assert_eq!(
+            response.rejection().map(|rejection| rejection.kind),
+            Some(RejectionKind::UnknownOrder)
+        );
+
+        let stranger = exchange_client(&server, STRANGER_KEY).await;
+        let response = stranger
+            .order(limit(true, 49_000.0, 0.1, "Gtc"), None)
+            .await?;
+        assert!(matches!(response, ExchangeResponseStatus::Err(_)));
+        assert_eq!(
+            response.rejection().map(|rejection| rejection.kind),
+            Some(RejectionKind::UnknownUser)
+        );
+
+        let resting = client
+            .order(limit(true, 49_000.0, 0.1, "Gtc"), None)
+            .await?;
+        assert!(resting.rejection().is_none());
+        Ok(())
+    }
+}
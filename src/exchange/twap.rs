@@ -0,0 +1,284 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{helpers::float_to_string_for_hashing, prelude::*, Error, Message};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TwapRequest {
+    #[serde(rename = "a", alias = "asset")]
+    pub asset: u32,
+    #[serde(rename = "b", alias = "isBuy")]
+    pub is_buy: bool,
+    #[serde(rename = "s", alias = "sz")]
+    pub sz: String,
+    #[serde(rename = "r", alias = "reduceOnly")]
+    pub reduce_only: bool,
+    #[serde(rename = "m", alias = "minutes")]
+    pub minutes: u32,
+    #[serde(rename = "t", alias = "randomize")]
+    pub randomize: bool,
+}
+
+/// Exchange-side TWAP: `sz` is executed in slices spread over `minutes` (5 to 1440), optionally
+/// with randomized slice timing
+#[derive(Debug)]
+pub struct ClientTwapRequest {
+    pub asset: String,
+    pub is_buy: bool,
+    pub sz: f64,
+    pub reduce_only: bool,
+    pub minutes: u32,
+    pub randomize: bool,
+}
+
+impl ClientTwapRequest {
+    pub(crate) fn convert(self, coin_to_asset: &HashMap<String, u32>) -> Result<TwapRequest> {
+        let &asset = coin_to_asset.get(&self.asset).ok_or(Error::AssetNotFound)?;
+
+        Ok(TwapRequest {
+            asset,
+            is_buy: self.is_buy,
+            sz: float_to_string_for_hashing(self.sz),
+            reduce_only: self.reduce_only,
+            minutes: self.minutes,
+            randomize: self.randomize,
+        })
+    }
+}
+
+/// Execution so far of one TWAP
+#[derive(Debug, Clone, Default)]
+pub struct TwapProgress {
+    pub twap_id: u64,
+    /// Known once a slice has filled or the TWAP shows up in the history channel
+    pub coin: Option<String>,
+    /// Total size to execute, from the history channel
+    pub target_sz: Option<f64>,
+    pub filled_sz: f64,
+    pub filled_notional: f64,
+    pub fees: f64,
+    pub closed_pnl: f64,
+    pub slices: usize,
+    pub last_fill_time: Option<u64>,
+    /// Latest status from the history channel: `activated`, `finished`, `terminated` or `error`
+    pub status: Option<String>,
+    pub status_description: Option<String>,
+}
+
+impl TwapProgress {
+    pub fn average_px(&self) -> Option<f64> {
+        (self.filled_sz > 0.0).then(|| self.filled_notional / self.filled_sz)
+    }
+
+    pub fn remaining_sz(&self) -> Option<f64> {
+        self.target_sz
+            .map(|target| (target - self.filled_sz).max(0.0))
+    }
+
+    /// Whether the TWAP has stopped executing, whether it completed or not
+    pub fn is_done(&self) -> bool {
+        self.status
+            .as_deref()
+            .is_some_and(|status| status != "activated")
+    }
+}
+
+/// Follows TWAP execution from the `userTwapSliceFills` and `userTwapHistory` channels.
+///
+/// Feed it every message from those subscriptions; fills already counted are skipped, so the
+/// snapshots sent again after a reconnect don't double up.
+#[derive(Debug, Default)]
+pub struct TwapTracker {
+    twaps: HashMap<u64, TwapProgress>,
+    seen_fills: HashSet<u64>,
+}
+
+impl TwapTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies a websocket message and returns the ids of the TWAPs it changed, in ascending
+    /// order. Messages from other channels are ignored.
+    pub fn apply(&mut self, message: &Message) -> Vec<u64> {
+        let mut changed = Vec::new();
+        match message {
+            Message::UserTwapSliceFills(slice_fills) => {
+                for slice in &slice_fills.data.twap_slice_fills {
+                    if !self.seen_fills.insert(slice.fill.tid) {
+                        continue;
+                    }
+                    let fill = &slice.fill;
+                    let (Ok(px), Ok(sz)) = (fill.px.parse::<f64>(), fill.sz.parse::<f64>()) else {
+                        continue;
+                    };
+
+                    let progress = self.entry(slice.twap_id);
+                    progress.coin = Some(fill.coin.clone());
+                    progress.filled_sz += sz;
+                    progress.filled_notional += px * sz;
+                    progress.fees += fill.fee.parse::<f64>().unwrap_or_default();
+                    progress.closed_pnl += fill.closed_pnl.parse::<f64>().unwrap_or_default();
+                    progress.slices += 1;
+                    progress.last_fill_time = progress.last_fill_time.max(Some(fill.time));
+                    changed.push(slice.twap_id);
+                }
+            }
+            Message::UserTwapHistory(history) => {
+                for entry in &history.data.history {
+                    let Some(twap_id) = entry.twap_id else {
+                        continue;
+                    };
+                    let progress = self.entry(twap_id);
+                    progress.coin = Some(entry.state.coin.clone());
+                    progress.target_sz = entry.state.sz.parse::<f64>().ok();
+                    progress.status = Some(entry.status.status.clone());
+                    progress.status_description = entry.status.description.clone();
+                    changed.push(twap_id);
+                }
+            }
+            _ => {}
+        }
+        changed.sort_unstable();
+        changed.dedup();
+        changed
+    }
+
+    pub fn get(&self, twap_id: u64) -> Option<&TwapProgress> {
+        self.twaps.get(&twap_id)
+    }
+
+    /// TWAPs that haven't finished or been terminated
+    pub fn active(&self) -> impl Iterator<Item = &TwapProgress> {
+        self.twaps.values().filter(|progress| !progress.is_done())
+    }
+
+    fn entry(&mut self, twap_id: u64) -> &mut TwapProgress {
+        self.twaps.entry(twap_id).or_insert_with(|| TwapProgress {
+            twap_id,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::test_utils::*;
+    use crate::mock_server::{MockFixtures, MockServer};
+    use crate::{TwapCancelStatus, TwapOrderStatus, TwapResponseStatus};
+    use serde_json::json;
+
+    fn slice_fill(twap_id: u64, tid: u64, px: &str, sz: &str) -> serde_json::Value {
+        json!({
+            "twapId": twap_id,
+            "fill": {
+                "coin": "BTC", "side": "A", "px": px, "sz": sz, "time": 1_700_000_000_000u64 + tid,
+                "hash": "0x0", "startPosition": "1.0", "dir": "Close Long", "closedPnl": "10",
+                "oid": 0, "cloid": null, "crossed": true, "fee": "0.5", "tid": tid
+            }
+        })
+    }
+
+    #[test]
+    fn tracks_slice_fills_and_status() {
+        let user = "0x0000000000000000000000000000000000000001";
+        let mut tracker = TwapTracker::new();
+
+        let history = |status: &str| {
+            serde_json::from_value::<Message>(json!({
+                "channel": "userTwapHistory",
+                "data": { "user": user, "history": [{
+                    "time": 1_700_000_000u64,
+                    "twapId": 7,
+                    "state": {
+                        "coin": "BTC", "user": user, "side": "A", "sz": "1.0", "executedSz": "0.0",
+                        "executedNtl": "0.0", "minutes": 30, "reduceOnly": true, "randomize": false,
+                        "timestamp": 1_700_000_000_000u64
+                    },
+                    "status": { "status": status }
+                }]}
+            }))
+            .unwrap()
+        };
+        assert_eq!(tracker.apply(&history("activated")), vec![7]);
+
+        let fills = serde_json::from_value::<Message>(json!({
+            "channel": "userTwapSliceFills",
+            "data": { "isSnapshot": true, "user": user, "twapSliceFills": [
+                slice_fill(7, 1, "50000", "0.25"),
+                slice_fill(7, 2, "51000", "0.25")
+            ]}
+        }))
+        .unwrap();
+        assert_eq!(tracker.apply(&fills), vec![7]);
+        // The snapshot resent after a reconnect is not counted twice
+        assert!(tracker.apply(&fills).is_empty());
+
+        let progress = tracker.get(7).unwrap();
+        assert_eq!(progress.slices, 2);
+        assert_eq!(progress.filled_sz, 0.5);
+        assert_eq!(progress.average_px(), Some(50_500.0));
+        assert_eq!(progress.remaining_sz(), Some(0.5));
+        assert_eq!((progress.fees, progress.closed_pnl), (1.0, 20.0));
+        assert_eq!(tracker.active().count(), 1);
+
+        tracker.apply(&history("terminated"));
+        assert!(tracker.get(7).unwrap().is_done());
+        assert_eq!(tracker.active().count(), 0);
+
+        // Slices of several TWAPs can arrive interleaved
+        let fills = serde_json::from_value::<Message>(json!({
+            "channel": "userTwapSliceFills",
+            "data": { "isSnapshot": false, "user": user, "twapSliceFills": [
+                slice_fill(9, 3, "50000", "0.1"),
+                slice_fill(8, 4, "50000", "0.1"),
+                slice_fill(9, 5, "50000", "0.1")
+            ]}
+        }))
+        .unwrap();
+        assert_eq!(tracker.apply(&fills), vec![8, 9]);
+    }
+
+    #[tokio::test]
+    async fn twap_orders_can_be_placed_and_canceled() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let client = exchange_client(&server, MAKER_KEY).await;
+        let twap = |minutes| ClientTwapRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            sz: 2.0,
+            reduce_only: false,
+            minutes,
+            randomize: true,
+        };
+
+        let TwapResponseStatus::Ok(placed) = client.twap_order(twap(30), None).await? else {
+            panic!("TWAP order was rejected");
+        };
+        let TwapOrderStatus::Running(running) = placed.data.status else {
+            panic!("TWAP is not running: {:?}", placed.data.status);
+        };
+
+        let TwapResponseStatus::Ok(too_short) = client.twap_order(twap(1), None).await? else {
+            panic!("TWAP order was rejected");
+        };
+        assert!(matches!(too_short.data.status, TwapOrderStatus::Error(_)));
+
+        let TwapResponseStatus::Ok(canceled) =
+            client.twap_cancel("ETH", running.twap_id, None).await?
+        else {
+            panic!("TWAP cancel was rejected");
+        };
+        assert!(matches!(canceled.data.status, TwapCancelStatus::Success));
+
+        let TwapResponseStatus::Ok(again) =
+            client.twap_cancel("ETH", running.twap_id, None).await?
+        else {
+            panic!("TWAP cancel was rejected");
+        };
+        assert!(matches!(again.data.status, TwapCancelStatus::Error(_)));
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ use chrono::prelude::Utc;
 use lazy_static::lazy_static;
 use log::info;
 use rand::{thread_rng, Rng};
+use std::str::FromStr;
 use std::sync::atomic::{AtomicU64, Ordering};
 use uuid::Uuid;
 
@@ -76,21 +77,114 @@ pub fn bps_diff(x: f64, y: f64) -> u16 {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+/// Chain an endpoint's actions are signed for
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SigningChain {
+    Mainnet,
+    Testnet,
+}
+
+impl FromStr for SigningChain {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mainnet" => Ok(SigningChain::Mainnet),
+            "testnet" => Ok(SigningChain::Testnet),
+            _ => Err(Error::GenericParse(format!("Invalid signing chain: {s}"))),
+        }
+    }
+}
+
+/// URLs of a custom endpoint and the chain actions sent to it are signed for
+#[derive(Debug, PartialEq, Eq)]
+pub struct CustomUrl {
+    pub api: String,
+    pub ws: String,
+    pub chain: SigningChain,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum BaseUrl {
     Localhost,
     Testnet,
     Mainnet,
+    /// A proxy, mock server or private node, created with `BaseUrl::custom`
+    Custom(&'static CustomUrl),
 }
 
 impl BaseUrl {
+    /// Custom endpoint whose websocket is served at `/ws` on the same host
+    pub fn custom(api: &str, chain: SigningChain) -> Self {
+        let api = api.trim_end_matches('/');
+        Self::custom_with_ws(api, &ws_url_for(api), chain)
+    }
+
+    /// Custom endpoint with its websocket elsewhere. Each call allocates URLs that live for the
+    /// rest of the program so `BaseUrl` stays `Copy`; create endpoints once at startup and copy
+    /// them, not per request.
+    pub fn custom_with_ws(api: &str, ws: &str, chain: SigningChain) -> Self {
+        BaseUrl::Custom(Box::leak(Box::new(CustomUrl {
+            api: api.trim_end_matches('/').to_string(),
+            ws: ws.to_string(),
+            chain,
+        })))
+    }
+
     pub(crate) fn get_url(&self) -> String {
         match self {
             BaseUrl::Localhost => LOCAL_API_URL.to_string(),
             BaseUrl::Mainnet => MAINNET_API_URL.to_string(),
             BaseUrl::Testnet => TESTNET_API_URL.to_string(),
+            BaseUrl::Custom(custom) => custom.api.clone(),
         }
     }
+
+    pub(crate) fn get_ws_url(&self) -> String {
+        match self {
+            BaseUrl::Custom(custom) => custom.ws.clone(),
+            _ => ws_url_for(&self.get_url()),
+        }
+    }
+
+    /// Chain actions sent here are signed for
+    pub fn signing_chain(&self) -> SigningChain {
+        match self {
+            BaseUrl::Mainnet => SigningChain::Mainnet,
+            BaseUrl::Custom(custom) => custom.chain,
+            BaseUrl::Localhost | BaseUrl::Testnet => SigningChain::Testnet,
+        }
+    }
+
+    /// Whether actions sent here are signed for mainnet
+    pub fn is_mainnet(&self) -> bool {
+        self.signing_chain() == SigningChain::Mainnet
+    }
+}
+
+impl FromStr for BaseUrl {
+    type Err = Error;
+
+    /// Accepts a profile name (`mainnet`, `testnet`, `localhost`) or one of their API URLs. Other
+    /// endpoints need their signing chain spelled out, so they go through `BaseUrl::custom`.
+    fn from_str(s: &str) -> Result<Self> {
+        let url = s.trim().trim_end_matches('/');
+        match url.to_ascii_lowercase().as_str() {
+            "mainnet" => Ok(BaseUrl::Mainnet),
+            "testnet" => Ok(BaseUrl::Testnet),
+            "localhost" => Ok(BaseUrl::Localhost),
+            _ if url == MAINNET_API_URL => Ok(BaseUrl::Mainnet),
+            _ if url == TESTNET_API_URL => Ok(BaseUrl::Testnet),
+            _ if url == LOCAL_API_URL => Ok(BaseUrl::Localhost),
+            _ => Err(Error::GenericParse(format!(
+                "Unknown base URL {s}; use BaseUrl::custom to choose its signing chain"
+            ))),
+        }
+    }
+}
+
+fn ws_url_for(api: &str) -> String {
+    format!("ws{}/ws", api.strip_prefix("http").unwrap_or(api))
 }
 
 lazy_static! {
@@ -101,6 +195,41 @@ lazy_static! {
 mod tests {
     use super::*;
 
+    #[test]
+    fn base_url_profiles_and_custom_endpoints() {
+        assert_eq!("testnet".parse::<BaseUrl>().unwrap(), BaseUrl::Testnet);
+        assert_eq!(
+            format!("{MAINNET_API_URL}/").parse::<BaseUrl>().unwrap(),
+            BaseUrl::Mainnet
+        );
+        assert!(BaseUrl::Mainnet.is_mainnet());
+        assert_eq!(
+            BaseUrl::Testnet.get_ws_url(),
+            "wss://api.hyperliquid-testnet.xyz/ws"
+        );
+
+        // Unknown URLs don't silently become testnet-signing endpoints
+        assert!("https://proxy.example.com".parse::<BaseUrl>().is_err());
+        let proxy = BaseUrl::custom("https://proxy.example.com/", SigningChain::Mainnet);
+        assert_eq!(proxy.get_url(), "https://proxy.example.com");
+        assert_eq!(proxy.get_ws_url(), "wss://proxy.example.com/ws");
+        assert!(proxy.is_mainnet());
+        let copy = proxy;
+        assert_eq!(copy, proxy);
+        let node = BaseUrl::custom_with_ws(
+            "http://10.0.0.1:3001",
+            "ws://10.0.0.2/ws",
+            SigningChain::Testnet,
+        );
+        assert_eq!(node.get_ws_url(), "ws://10.0.0.2/ws");
+        assert_eq!(node.signing_chain(), SigningChain::Testnet);
+        assert_eq!(
+            "Mainnet".parse::<SigningChain>().unwrap(),
+            SigningChain::Mainnet
+        );
+        assert!("api.hyperliquid.xyz".parse::<BaseUrl>().is_err());
+    }
+
     #[test]
     fn float_to_string_for_hashing_test() {
         assert_eq!(float_to_string_for_hashing(0.), "0".to_string());
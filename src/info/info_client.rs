@@ -4,18 +4,22 @@ use crate::{
         OrderInfo, RecentTradesResponse, UserFillsResponse, UserStateResponse,
     },
     meta::{Meta, SpotMeta, SpotMetaAndAssetCtxs},
+    helpers::uuid_to_hex_string,
     prelude::*,
     req::HttpClient,
     ws::{Subscription, WsManager},
-    BaseUrl, Error, Message, OrderStatusResponse, ReferralResponse, UserFeesResponse,
-    UserFundingResponse, UserTokenBalanceResponse,
+    BaseUrl, Error, LedgerUpdateData, Message, OrderStatusResponse, ReferralResponse, UserFeesResponse,
+    UserFundingResponse, UserRateLimitResponse, UserTokenBalanceResponse, RateLimiter,
+    RetryPolicy, CircuitBreaker,
 };
 
 use ethers::types::H160;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::mpsc::UnboundedSender;
+use uuid::Uuid;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -52,6 +56,13 @@ pub enum InfoRequest {
         user: H160,
         oid: u64,
     },
+    /// Serializes as `orderStatus`; requests of that type deserialize as `OrderStatus`
+    #[serde(rename = "orderStatus", skip_deserializing)]
+    OrderStatusByCloid {
+        user: H160,
+        /// The cloid as a hex string, which the API accepts in place of an oid
+        oid: String,
+    },
     Meta,
     SpotMeta,
     SpotMetaAndAssetCtxs,
@@ -60,6 +71,12 @@ pub enum InfoRequest {
         user: H160,
     },
     #[serde(rename_all = "camelCase")]
+    UserFillsByTime {
+        user: H160,
+        start_time: u64,
+        end_time: Option<u64>,
+    },
+    #[serde(rename_all = "camelCase")]
     FundingHistory {
         coin: String,
         start_time: u64,
@@ -71,6 +88,12 @@ pub enum InfoRequest {
         start_time: u64,
         end_time: Option<u64>,
     },
+    #[serde(rename_all = "camelCase")]
+    UserNonFundingLedgerUpdates {
+        user: H160,
+        start_time: u64,
+        end_time: Option<u64>,
+    },
     L2Book {
         coin: String,
     },
@@ -87,6 +110,9 @@ pub enum InfoRequest {
     HistoricalOrders {
         user: H160,
     },
+    UserRateLimit {
+        user: H160,
+    },
 }
 
 #[derive(Debug)]
@@ -114,26 +140,47 @@ impl InfoClient {
         reconnect: bool,
     ) -> Result<InfoClient> {
         let client = client.unwrap_or_default();
-        let base_url = base_url.unwrap_or(BaseUrl::Mainnet).get_url();
+        let base_url = base_url.unwrap_or(BaseUrl::Mainnet);
 
         Ok(InfoClient {
-            http_client: HttpClient { client, base_url },
+            http_client: HttpClient {
+                client,
+                base_url,
+                rate_limiter: None,
+                retry_policy: None,
+                circuit_breaker: None,
+            },
             ws_manager: None,
             reconnect,
         })
     }
 
+    /// Throttles this client's requests with `rate_limiter`, which may be shared with other clients
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.http_client.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Retries requests that fail for transient reasons according to `retry_policy`
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.http_client.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Fails requests fast while `circuit_breaker` considers the API down
+    pub fn with_circuit_breaker(mut self, circuit_breaker: Arc<CircuitBreaker>) -> Self {
+        self.http_client.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
     pub async fn subscribe(
         &mut self,
         subscription: Subscription,
         sender_channel: UnboundedSender<Message>,
     ) -> Result<u32> {
         if self.ws_manager.is_none() {
-            let ws_manager = WsManager::new(
-                format!("ws{}/ws", &self.http_client.base_url[4..]),
-                self.reconnect,
-            )
-            .await?;
+            let ws_manager =
+                WsManager::new(self.http_client.base_url.get_ws_url(), self.reconnect).await?;
             self.ws_manager = Some(ws_manager);
         }
 
@@ -149,11 +196,8 @@ impl InfoClient {
 
     pub async fn unsubscribe(&mut self, subscription_id: u32) -> Result<()> {
         if self.ws_manager.is_none() {
-            let ws_manager = WsManager::new(
-                format!("ws{}/ws", &self.http_client.base_url[4..]),
-                self.reconnect,
-            )
-            .await?;
+            let ws_manager =
+                WsManager::new(self.http_client.base_url.get_ws_url(), self.reconnect).await?;
             self.ws_manager = Some(ws_manager);
         }
 
@@ -195,6 +239,12 @@ impl InfoClient {
         self.send_info_request(input).await
     }
 
+    /// Requests used and allowed for `address`, for seeding a `RateLimiter`
+    pub async fn user_rate_limit(&self, address: H160) -> Result<UserRateLimitResponse> {
+        let input = InfoRequest::UserRateLimit { user: address };
+        self.send_info_request(input).await
+    }
+
     pub async fn user_fees(&self, address: H160) -> Result<UserFeesResponse> {
         let input = InfoRequest::UserFees { user: address };
         self.send_info_request(input).await
@@ -225,6 +275,20 @@ impl InfoClient {
         self.send_info_request(input).await
     }
 
+    pub async fn user_fills_by_time(
+        &self,
+        address: H160,
+        start_time: u64,
+        end_time: Option<u64>,
+    ) -> Result<Vec<UserFillsResponse>> {
+        let input = InfoRequest::UserFillsByTime {
+            user: address,
+            start_time,
+            end_time,
+        };
+        self.send_info_request(input).await
+    }
+
     pub async fn funding_history(
         &self,
         coin: String,
@@ -253,6 +317,20 @@ impl InfoClient {
         self.send_info_request(input).await
     }
 
+    pub async fn user_non_funding_ledger_updates(
+        &self,
+        user: H160,
+        start_time: u64,
+        end_time: Option<u64>,
+    ) -> Result<Vec<LedgerUpdateData>> {
+        let input = InfoRequest::UserNonFundingLedgerUpdates {
+            user,
+            start_time,
+            end_time,
+        };
+        self.send_info_request(input).await
+    }
+
     pub async fn recent_trades(&self, coin: String) -> Result<Vec<RecentTradesResponse>> {
         let input = InfoRequest::RecentTrades { coin };
         self.send_info_request(input).await
@@ -286,6 +364,14 @@ impl InfoClient {
         self.send_info_request(input).await
     }
 
+    pub async fn query_order_by_cloid(&self, address: H160, cloid: Uuid) -> Result<OrderStatusResponse> {
+        let input = InfoRequest::OrderStatusByCloid {
+            user: address,
+            oid: uuid_to_hex_string(cloid),
+        };
+        self.send_info_request(input).await
+    }
+
     pub async fn query_referral_state(&self, address: H160) -> Result<ReferralResponse> {
         let input = InfoRequest::Referral { user: address };
         self.send_info_request(input).await
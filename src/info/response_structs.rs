@@ -1,10 +1,10 @@
 use crate::{
     info::{AssetPosition, Level, MarginSummary},
-    DailyUserVlm, Delta, FeeSchedule, OrderInfo, Referrer, ReferrerState, UserTokenBalance,
+    DailyUserVlm, Delta, FeeSchedule, FillLiquidation, OrderInfo, Referrer, ReferrerState, UserTokenBalance,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct UserStateResponse {
     pub asset_positions: Vec<AssetPosition>,
@@ -54,6 +54,11 @@ pub struct UserFillsResponse {
     pub sz: String,
     pub time: u64,
     pub fee: String,
+    pub tid: u64,
+    #[serde(default)]
+    pub liquidation: Option<FillLiquidation>,
+    #[serde(default)]
+    pub builder_fee: Option<String>,
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -123,6 +128,14 @@ pub struct OrderStatusResponse {
     pub order: Option<OrderInfo>,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserRateLimitResponse {
+    pub cum_vlm: String,
+    pub n_requests_used: u64,
+    pub n_requests_cap: u64,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ReferralResponse {
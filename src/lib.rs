@@ -1,23 +1,35 @@
 #![deny(unreachable_pub)]
 mod consts;
+mod decimal;
 mod errors;
 mod exchange;
 mod helpers;
 mod info;
 mod market_maker;
 mod meta;
+#[cfg(any(test, feature = "mock-server"))]
+pub mod mock_server;
 pub mod prelude;
 mod proxy_digest;
+mod rate_limit;
 mod req;
+mod retry;
 mod signature;
 mod ws;
 pub mod risk_management;
 
 pub use consts::{EPSILON, LOCAL_API_URL, MAINNET_API_URL, TESTNET_API_URL};
+pub use decimal::{decimal_to_f64, parse_decimal, parse_optional_decimal, Decimal};
 pub use errors::Error;
 pub use exchange::*;
-pub use helpers::{bps_diff, truncate_float, BaseUrl};
+pub use helpers::{bps_diff, truncate_float, BaseUrl, CustomUrl, SigningChain};
 pub use info::{info_client::*, *};
 pub use market_maker::{MarketMaker, MarketMakerInput, MarketMakerRestingOrder};
 pub use meta::{AssetMeta, Meta};
+pub use rate_limit::{
+    RateLimitConfig, RateLimitMode, RateLimiter, RequestWeight, ADDRESS_THROTTLED_INTERVAL,
+    IP_WEIGHT_PER_MINUTE,
+};
+pub use retry::{ApiHealth, CircuitBreaker, CircuitBreakerConfig, CircuitState, RetryPolicy};
+pub use signature::{HyperliquidSigner, KeystoreSigner, RemoteSigner, SigningKind, SigningRequest};
 pub use ws::*;
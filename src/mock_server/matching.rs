@@ -0,0 +1,869 @@
+use std::collections::{HashMap, HashSet};
+
+use ethers::types::{Signature, H160, H256};
+use serde_json::{json, Value};
+
+use super::verify::recover_signer;
+use super::WsEvent;
+use crate::{Actions, Order, OrderRequest, Subscription, EPSILON, MIN_SCHEDULE_CANCEL_DELAY};
+
+const TAKER_FEE_RATE: f64 = 0.00045;
+const MAKER_FEE_RATE: f64 = 0.00015;
+const DEFAULT_LEVERAGE: u32 = 20;
+pub(super) const DEFAULT_BALANCE: f64 = 100_000.0;
+
+const UNKNOWN_ORDER: &str = "Order was never placed, already canceled, or filled.";
+
+#[derive(Debug, Clone)]
+struct MockOrder {
+    user: H160,
+    oid: u64,
+    coin: String,
+    is_buy: bool,
+    limit_px: f64,
+    sz: f64,
+    orig_sz: f64,
+    reduce_only: bool,
+    tif: String,
+    trigger: Option<(String, bool, String)>,
+    cloid: Option<String>,
+    timestamp: u64,
+}
+
+#[derive(Debug, Clone)]
+struct OrderRecord {
+    order: MockOrder,
+    status: String,
+    status_timestamp: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct MockPosition {
+    szi: f64,
+    entry_px: f64,
+}
+
+#[derive(Debug, Clone)]
+struct MockAccount {
+    /// Deposits plus realized PnL, net of fees
+    balance: f64,
+    positions: HashMap<String, MockPosition>,
+    /// Per-coin `(is_cross, leverage)`
+    leverage: HashMap<String, (bool, u32)>,
+}
+
+impl MockAccount {
+    fn new(balance: f64) -> Self {
+        Self {
+            balance,
+            positions: HashMap::new(),
+            leverage: HashMap::new(),
+        }
+    }
+}
+
+/// Order book, accounts and auth state behind the mock `/exchange` endpoint.
+///
+/// Orders cross against resting orders from other users first, in price-time priority. Whatever is
+/// left and still crosses the scripted mid fills at the mid, so the mid acts as unlimited liquidity.
+/// Trigger orders rest until canceled but never fire. The legs of a `normalTpsl` group are placed
+/// only if their entry fills right away; otherwise they report `waitingForFill` and are dropped.
+/// `positionTpsl` legs of size 0 cover the whole position and rest with size 0.
+/// Scheduled cancels fire on the first request after their time. TWAPs are accepted and can be
+/// canceled, but never execute any slices.
+pub(super) struct MockExchange {
+    meta: Value,
+    /// `(name, max_leverage)` per perp asset index
+    coins: Vec<(String, u32)>,
+    mids: HashMap<String, f64>,
+    resting: Vec<MockOrder>,
+    history: HashMap<u64, OrderRecord>,
+    fills: Vec<(H160, Value)>,
+    accounts: HashMap<H160, MockAccount>,
+    agents: HashMap<H160, H160>,
+    registered_users: HashSet<H160>,
+    used_nonces: HashMap<H160, HashSet<u64>>,
+    /// Scheduled cancel time (unix ms) per user
+    scheduled_cancels: HashMap<H160, u64>,
+    /// Running TWAPs by id, with their owner and asset
+    twaps: HashMap<u64, (H160, u32)>,
+    next_oid: u64,
+    next_tid: u64,
+    events: Vec<WsEvent>,
+}
+
+impl MockExchange {
+    pub(super) fn new(meta: Value, mids: HashMap<String, f64>) -> Self {
+        let coins = meta["universe"]
+            .as_array()
+            .map(|universe| {
+                universe
+                    .iter()
+                    .map(|asset| {
+                        let name = asset["name"].as_str().unwrap_or_default().to_string();
+                        let max_leverage = asset["maxLeverage"].as_u64().unwrap_or(50) as u32;
+                        (name, max_leverage)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            meta,
+            coins,
+            mids,
+            resting: Vec::new(),
+            history: HashMap::new(),
+            fills: Vec::new(),
+            accounts: HashMap::new(),
+            agents: HashMap::new(),
+            registered_users: HashSet::new(),
+            used_nonces: HashMap::new(),
+            scheduled_cancels: HashMap::new(),
+            twaps: HashMap::new(),
+            next_oid: 1,
+            next_tid: 1,
+            events: Vec::new(),
+        }
+    }
+
+    pub(super) fn meta(&self) -> &Value {
+        &self.meta
+    }
+
+    pub(super) fn all_mids(&self) -> Value {
+        let mids: HashMap<&String, String> =
+            self.mids.iter().map(|(coin, px)| (coin, px.to_string())).collect();
+        json!(mids)
+    }
+
+    pub(super) fn set_mid(&mut self, coin: &str, px: f64) {
+        self.mids.insert(coin.to_string(), px);
+        let mids = self.all_mids();
+        self.publish(&Subscription::AllMids, json!({ "mids": mids }));
+    }
+
+    pub(super) fn set_balance(&mut self, user: H160, balance: f64) {
+        self.account(user).balance = balance;
+    }
+
+    pub(super) fn register_user(&mut self, user: H160) {
+        self.registered_users.insert(user);
+    }
+
+    /// Websocket messages produced since the last call
+    pub(super) fn take_events(&mut self) -> Vec<WsEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Handles a signed `/exchange` payload. `Err` means the action itself could not be parsed,
+    /// which the real API reports as an HTTP error rather than an error status.
+    pub(super) fn handle(
+        &mut self,
+        action: Value,
+        signature: &Signature,
+        nonce: u64,
+        vault_address: Option<H160>,
+    ) -> std::result::Result<Value, String> {
+        let action: Actions = serde_json::from_value(action)
+            .map_err(|e| format!("Failed to deserialize the JSON body into the target type: {e}"))?;
+
+        let signer = match recover_signer(&action, signature, nonce, vault_address) {
+            Ok(signer) => signer,
+            Err(err) => return Ok(error_response(&err.to_string())),
+        };
+        let user = self.agents.get(&signer).copied().unwrap_or(signer);
+        if !self.registered_users.is_empty() && !self.registered_users.contains(&user) {
+            return Ok(error_response(&format!(
+                "User or API Wallet {signer:?} does not exist."
+            )));
+        }
+        if !self.used_nonces.entry(signer).or_default().insert(nonce) {
+            return Ok(error_response(&format!("Invalid nonce: duplicate nonce {nonce}")));
+        }
+        let user = vault_address.unwrap_or(user);
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        self.fire_scheduled_cancels(now);
+
+        Ok(match action {
+            Actions::Order(bulk_order) => {
+                let mut statuses: Vec<Value> = Vec::new();
+                for order in &bulk_order.orders {
+                    let entry_filled = statuses.first().is_none_or(|entry| entry.get("filled").is_some());
+                    if bulk_order.grouping == "normalTpsl" && !entry_filled {
+                        statuses.push(json!("waitingForFill"));
+                    } else {
+                        let position_tpsl = bulk_order.grouping == "positionTpsl";
+                        statuses.push(self.place_order(user, order, position_tpsl, now));
+                    }
+                }
+                statuses_response("order", statuses)
+            }
+            Actions::Cancel(bulk_cancel) => {
+                let statuses = bulk_cancel
+                    .cancels
+                    .iter()
+                    .map(|cancel| self.cancel(user, cancel.asset, |o| o.oid == cancel.oid, now))
+                    .collect();
+                statuses_response("cancel", statuses)
+            }
+            Actions::CancelByCloid(bulk_cancel) => {
+                let statuses = bulk_cancel
+                    .cancels
+                    .iter()
+                    .map(|cancel| {
+                        let cloid = cancel.cloid.as_str();
+                        self.cancel(user, cancel.asset, |o| o.cloid.as_deref() == Some(cloid), now)
+                    })
+                    .collect();
+                statuses_response("cancel", statuses)
+            }
+            Actions::BatchModify(bulk_modify) => {
+                let statuses = bulk_modify
+                    .modifies
+                    .iter()
+                    .map(|modify| {
+                        match self.remove_resting(|o| o.user == user && o.oid == modify.oid) {
+                            Some(order) => {
+                                self.record(&order, "canceled", now);
+                                self.place_order(user, &modify.order, false, now)
+                            }
+                            None => json!({ "error": "Cannot modify canceled or filled order" }),
+                        }
+                    })
+                    .collect();
+                statuses_response("batchModify", statuses)
+            }
+            Actions::UpdateLeverage(update) => {
+                match self.coins.get(update.asset as usize).cloned() {
+                    Some((coin, max_leverage)) if update.leverage >= 1 && update.leverage <= max_leverage => {
+                        self.account(user).leverage.insert(coin, (update.is_cross, update.leverage));
+                        default_response()
+                    }
+                    Some(_) => error_response("Invalid leverage value"),
+                    None => error_response(&format!("Asset {} does not exist.", update.asset)),
+                }
+            }
+            Actions::ScheduleCancel(schedule) => match schedule.time {
+                Some(time) if time < now + MIN_SCHEDULE_CANCEL_DELAY.as_millis() as u64 => {
+                    error_response("Scheduled cancel time too early, must be at least 5 seconds from now.")
+                }
+                Some(time) => {
+                    self.scheduled_cancels.insert(user, time);
+                    default_response()
+                }
+                None => {
+                    self.scheduled_cancels.remove(&user);
+                    default_response()
+                }
+            },
+            Actions::TwapOrder(twap_order) => {
+                let twap = &twap_order.twap;
+                let status = if self.coins.get(twap.asset as usize).is_none() {
+                    json!({ "error": format!("Asset {} does not exist.", twap.asset) })
+                } else if !(5..=1440).contains(&twap.minutes) {
+                    json!({ "error": "TWAP duration must be between 5 minutes and 24 hours." })
+                } else if twap.sz.parse::<f64>().map_or(true, |sz| sz <= 0.0) {
+                    json!({ "error": "Invalid TWAP size." })
+                } else {
+                    let twap_id = self.next_oid;
+                    self.next_oid += 1;
+                    self.twaps.insert(twap_id, (user, twap.asset));
+                    json!({ "running": { "twapId": twap_id } })
+                };
+                twap_response("twapOrder", status)
+            }
+            Actions::TwapCancel(cancel) => match self.twaps.get(&cancel.twap_id) {
+                Some(&(owner, asset)) if owner == user && asset == cancel.asset => {
+                    self.twaps.remove(&cancel.twap_id);
+                    twap_response("twapCancel", json!("success"))
+                }
+                _ => twap_response("twapCancel", json!({ "error": "TWAP was never placed, already canceled, or filled." })),
+            },
+            Actions::ApproveAgent(approve_agent) => {
+                self.agents.insert(approve_agent.agent_address, user);
+                default_response()
+            }
+            Actions::UsdSend(usd_send) => {
+                let destination = usd_send.destination.parse::<H160>().ok();
+                match (usd_send.amount.parse::<f64>(), destination) {
+                    (Ok(amount), Some(destination)) if amount > 0.0 => {
+                        if self.withdrawable(user) + EPSILON < amount {
+                            error_response("Insufficient balance for transfer")
+                        } else {
+                            self.account(user).balance -= amount;
+                            self.account(destination).balance += amount;
+                            default_response()
+                        }
+                    }
+                    _ => error_response("Invalid transfer"),
+                }
+            }
+            Actions::Withdraw3(withdraw) => match withdraw.amount.parse::<f64>() {
+                Ok(amount) if amount > 0.0 && amount <= self.withdrawable(user) + EPSILON => {
+                    self.account(user).balance -= amount;
+                    default_response()
+                }
+                _ => error_response("Insufficient balance for withdrawal"),
+            },
+            _ => default_response(),
+        })
+    }
+
+    fn place_order(&mut self, user: H160, request: &OrderRequest, position_tpsl: bool, now: u64) -> Value {
+        let Some(coin) = self.coin_name(request.asset) else {
+            return json!({ "error": format!("Asset {} does not exist.", request.asset) });
+        };
+        let limit_px = match request.limit_px.parse::<f64>() {
+            Ok(px) if px > 0.0 => px,
+            _ => return json!({ "error": "Order has invalid price." }),
+        };
+        let sz = match request.sz.parse::<f64>() {
+            Ok(sz) if sz > 0.0 => sz,
+            Ok(sz) if sz == 0.0 && position_tpsl && matches!(request.order_type, Order::Trigger(_)) => 0.0,
+            _ => return json!({ "error": "Order has invalid size." }),
+        };
+        let position = self.position_size(user, &coin);
+        if request.reduce_only && (position == 0.0 || (position > 0.0) == request.is_buy) {
+            return json!({ "error": "Reduce only order would increase position." });
+        }
+
+        let (tif, trigger) = match &request.order_type {
+            Order::Limit(limit) => (limit.tif.clone(), None),
+            Order::Trigger(trigger) => (
+                "Gtc".to_string(),
+                Some((trigger.trigger_px.clone(), trigger.is_market, trigger.tpsl.clone())),
+            ),
+        };
+        let oid = self.next_oid;
+        self.next_oid += 1;
+        let mut order = MockOrder {
+            user,
+            oid,
+            coin,
+            is_buy: request.is_buy,
+            limit_px,
+            sz,
+            orig_sz: sz,
+            reduce_only: request.reduce_only,
+            tif: tif.clone(),
+            trigger,
+            cloid: request.cloid.clone(),
+            timestamp: now,
+        };
+
+        if order.trigger.is_some() {
+            self.rest(order, now);
+            return json!({ "resting": { "oid": oid } });
+        }
+        if tif == "Alo" && self.would_cross(&order) {
+            return json!({ "error": "Post only order would have immediately matched" });
+        }
+
+        let (filled, notional) = self.match_order(&mut order, now);
+        if order.sz > EPSILON {
+            if tif == "Ioc" {
+                self.record(&order, "canceled", now);
+                if filled == 0.0 {
+                    return json!({
+                        "error": format!("Order could not immediately match against any resting orders. asset={}", request.asset)
+                    });
+                }
+            } else {
+                self.rest(order, now);
+                return json!({ "resting": { "oid": oid } });
+            }
+        } else {
+            self.record(&order, "filled", now);
+        }
+        json!({
+            "filled": {
+                "totalSz": filled.to_string(),
+                "avgPx": (notional / filled).to_string(),
+                "oid": oid
+            }
+        })
+    }
+
+    /// Fills `taker` against the book, then against the mid. Returns `(size, notional)` filled.
+    fn match_order(&mut self, taker: &mut MockOrder, now: u64) -> (f64, f64) {
+        let mut filled = 0.0;
+        let mut notional = 0.0;
+
+        while taker.sz > EPSILON {
+            let best = self
+                .resting
+                .iter()
+                .enumerate()
+                .filter(|(_, o)| {
+                    o.coin == taker.coin
+                        && o.user != taker.user
+                        && o.is_buy != taker.is_buy
+                        && o.trigger.is_none()
+                        && crosses(taker, o.limit_px)
+                })
+                .min_by(|(_, a), (_, b)| {
+                    let (a, b) = if taker.is_buy {
+                        (a.limit_px, b.limit_px)
+                    } else {
+                        (-a.limit_px, -b.limit_px)
+                    };
+                    a.total_cmp(&b)
+                })
+                .map(|(i, _)| i);
+            let Some(index) = best else {
+                break;
+            };
+
+            let px = self.resting[index].limit_px;
+            let sz = taker.sz.min(self.resting[index].sz);
+            let maker = self.resting[index].clone();
+            self.execute_fill(&maker, px, sz, false, now);
+            self.execute_fill(taker, px, sz, true, now);
+            taker.sz -= sz;
+            filled += sz;
+            notional += px * sz;
+
+            self.resting[index].sz -= sz;
+            if self.resting[index].sz <= EPSILON {
+                let maker = self.resting.remove(index);
+                self.record(&maker, "filled", now);
+            }
+        }
+
+        if taker.sz > EPSILON {
+            if let Some(&mid) = self.mids.get(&taker.coin) {
+                if crosses(taker, mid) {
+                    let sz = taker.sz;
+                    self.execute_fill(taker, mid, sz, true, now);
+                    taker.sz = 0.0;
+                    filled += sz;
+                    notional += mid * sz;
+                }
+            }
+        }
+        (filled, notional)
+    }
+
+    fn would_cross(&self, order: &MockOrder) -> bool {
+        let crosses_book = self.resting.iter().any(|o| {
+            o.coin == order.coin
+                && o.user != order.user
+                && o.is_buy != order.is_buy
+                && o.trigger.is_none()
+                && crosses(order, o.limit_px)
+        });
+        crosses_book || self.mids.get(&order.coin).is_some_and(|&mid| crosses(order, mid))
+    }
+
+    fn execute_fill(&mut self, order: &MockOrder, px: f64, sz: f64, crossed: bool, now: u64) {
+        let tid = self.next_tid;
+        self.next_tid += 1;
+
+        let account = self
+            .accounts
+            .entry(order.user)
+            .or_insert_with(|| MockAccount::new(DEFAULT_BALANCE));
+        let position = account.positions.entry(order.coin.clone()).or_default();
+        let start = position.szi;
+        let signed_sz = if order.is_buy { sz } else { -sz };
+        let end = start + signed_sz;
+
+        let reducing = start != 0.0 && start.signum() != signed_sz.signum();
+        let closed_pnl = if reducing {
+            sz.min(start.abs()) * (px - position.entry_px) * start.signum()
+        } else {
+            0.0
+        };
+        let dir = match (start, end) {
+            (s, e) if s > 0.0 && e < -EPSILON => "Long > Short",
+            (s, e) if s < 0.0 && e > EPSILON => "Short > Long",
+            (s, _) if s > 0.0 && reducing => "Close Long",
+            (s, _) if s < 0.0 && reducing => "Close Short",
+            _ if order.is_buy => "Open Long",
+            _ => "Open Short",
+        };
+
+        position.entry_px = if end.abs() <= EPSILON {
+            0.0
+        } else if !reducing {
+            (start.abs() * position.entry_px + sz * px) / end.abs()
+        } else if start.signum() != end.signum() {
+            px
+        } else {
+            position.entry_px
+        };
+        position.szi = if end.abs() <= EPSILON { 0.0 } else { end };
+
+        let fee = px * sz * if crossed { TAKER_FEE_RATE } else { MAKER_FEE_RATE };
+        account.balance += closed_pnl - fee;
+
+        let fill = json!({
+            "coin": order.coin,
+            "px": px.to_string(),
+            "sz": sz.to_string(),
+            "side": if order.is_buy { "B" } else { "A" },
+            "time": now,
+            "startPosition": start.to_string(),
+            "dir": dir,
+            "closedPnl": closed_pnl.to_string(),
+            "hash": format!("{:?}", H256::from_low_u64_be(tid)),
+            "oid": order.oid,
+            "cloid": order.cloid,
+            "crossed": crossed,
+            "fee": fee.to_string(),
+            "feeToken": "USDC",
+            "tid": tid
+        });
+        self.fills.push((order.user, fill.clone()));
+        self.publish(
+            &Subscription::UserFills { user: order.user },
+            json!({ "isSnapshot": false, "user": order.user, "fills": [fill.clone()] }),
+        );
+        self.publish(
+            &Subscription::UserEvents { user: order.user },
+            json!({ "fills": [fill] }),
+        );
+    }
+
+    fn cancel(
+        &mut self,
+        user: H160,
+        asset: u32,
+        matches: impl Fn(&MockOrder) -> bool,
+        now: u64,
+    ) -> Value {
+        let coin = self.coin_name(asset);
+        match self.remove_resting(|o| o.user == user && Some(&o.coin) == coin.as_ref() && matches(o)) {
+            Some(order) => {
+                self.record(&order, "canceled", now);
+                json!("success")
+            }
+            None => json!({ "error": UNKNOWN_ORDER }),
+        }
+    }
+
+    /// Cancels every resting order of users whose scheduled cancel time has passed
+    pub(super) fn fire_scheduled_cancels(&mut self, now: u64) {
+        let due: Vec<H160> = self
+            .scheduled_cancels
+            .iter()
+            .filter(|(_, &time)| time <= now)
+            .map(|(&user, _)| user)
+            .collect();
+        for user in due {
+            self.scheduled_cancels.remove(&user);
+            while let Some(order) = self.remove_resting(|o| o.user == user) {
+                self.record(&order, "scheduledCancel", now);
+            }
+        }
+    }
+
+    fn rest(&mut self, order: MockOrder, now: u64) {
+        self.record(&order, "open", now);
+        self.resting.push(order);
+    }
+
+    fn remove_resting(&mut self, matches: impl Fn(&MockOrder) -> bool) -> Option<MockOrder> {
+        let index = self.resting.iter().position(matches)?;
+        Some(self.resting.remove(index))
+    }
+
+    /// Stores the order's latest status and pushes it to `orderUpdates` subscribers
+    fn record(&mut self, order: &MockOrder, status: &str, now: u64) {
+        self.history.insert(
+            order.oid,
+            OrderRecord {
+                order: order.clone(),
+                status: status.to_string(),
+                status_timestamp: now,
+            },
+        );
+        self.publish(
+            &Subscription::OrderUpdates { user: order.user },
+            json!([{
+                "order": {
+                    "coin": order.coin,
+                    "side": side(order),
+                    "limitPx": order.limit_px.to_string(),
+                    "sz": order.sz.to_string(),
+                    "oid": order.oid,
+                    "timestamp": order.timestamp,
+                    "origSz": order.orig_sz.to_string(),
+                    "cloid": order.cloid
+                },
+                "status": status,
+                "statusTimestamp": now
+            }]),
+        );
+    }
+
+    fn publish(&mut self, subscription: &Subscription, data: Value) {
+        self.events.push(WsEvent::message(subscription, data));
+    }
+
+    fn account(&mut self, user: H160) -> &mut MockAccount {
+        self.accounts
+            .entry(user)
+            .or_insert_with(|| MockAccount::new(DEFAULT_BALANCE))
+    }
+
+    fn coin_name(&self, asset: u32) -> Option<String> {
+        if asset >= 10_000 {
+            return Some(format!("@{}", asset - 10_000));
+        }
+        self.coins.get(asset as usize).map(|(name, _)| name.clone())
+    }
+
+    fn max_leverage(&self, coin: &str) -> u32 {
+        self.coins
+            .iter()
+            .find(|(name, _)| name == coin)
+            .map(|(_, max_leverage)| *max_leverage)
+            .unwrap_or(DEFAULT_LEVERAGE)
+    }
+
+    fn position_size(&self, user: H160, coin: &str) -> f64 {
+        self.accounts
+            .get(&user)
+            .and_then(|account| account.positions.get(coin))
+            .map(|position| position.szi)
+            .unwrap_or(0.0)
+    }
+
+    fn withdrawable(&self, user: H160) -> f64 {
+        let state = self.clearinghouse_state(user);
+        state["withdrawable"]
+            .as_str()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0.0)
+    }
+
+    /// `clearinghouseState` for `user`, with positions marked at the scripted mids
+    pub(super) fn clearinghouse_state(&self, user: H160) -> Value {
+        let empty = MockAccount::new(DEFAULT_BALANCE);
+        let account = self.accounts.get(&user).unwrap_or(&empty);
+
+        let mut asset_positions = Vec::new();
+        let mut unrealized = 0.0;
+        let mut margin_used = 0.0;
+        let mut total_ntl = 0.0;
+        let mut signed_ntl = 0.0;
+        let mut coins: Vec<&String> = account.positions.keys().collect();
+        coins.sort();
+        for coin in coins {
+            let position = &account.positions[coin];
+            if position.szi == 0.0 {
+                continue;
+            }
+            let max_leverage = self.max_leverage(coin);
+            let (is_cross, leverage) = account
+                .leverage
+                .get(coin)
+                .copied()
+                .unwrap_or((true, DEFAULT_LEVERAGE.min(max_leverage)));
+            let mark = self.mids.get(coin).copied().unwrap_or(position.entry_px);
+            let value = position.szi.abs() * mark;
+            let pnl = position.szi * (mark - position.entry_px);
+            let margin = value / leverage as f64;
+            let entry_margin = position.szi.abs() * position.entry_px / leverage as f64;
+
+            unrealized += pnl;
+            margin_used += margin;
+            total_ntl += value;
+            signed_ntl += position.szi * mark;
+            asset_positions.push(json!({
+                "type": "oneWay",
+                "position": {
+                    "coin": coin,
+                    "entryPx": position.entry_px.to_string(),
+                    "leverage": {
+                        "type": if is_cross { "cross" } else { "isolated" },
+                        "value": leverage,
+                        "rawUsd": if is_cross { None } else { Some((-position.szi * position.entry_px + entry_margin).to_string()) }
+                    },
+                    "liquidationPx": null,
+                    "marginUsed": margin.to_string(),
+                    "positionValue": value.to_string(),
+                    "returnOnEquity": if entry_margin > 0.0 { pnl / entry_margin } else { 0.0 }.to_string(),
+                    "szi": position.szi.to_string(),
+                    "unrealizedPnl": pnl.to_string(),
+                    "maxLeverage": max_leverage,
+                    "cumFunding": { "allTime": "0", "sinceOpen": "0", "sinceChange": "0" }
+                }
+            }));
+        }
+
+        let account_value = account.balance + unrealized;
+        let summary = json!({
+            "accountValue": account_value.to_string(),
+            "totalMarginUsed": margin_used.to_string(),
+            "totalNtlPos": total_ntl.to_string(),
+            "totalRawUsd": (account_value - signed_ntl).to_string()
+        });
+        json!({
+            "assetPositions": asset_positions,
+            "crossMarginSummary": summary,
+            "marginSummary": summary,
+            "withdrawable": (account_value - margin_used).max(0.0).to_string(),
+            "time": chrono::Utc::now().timestamp_millis()
+        })
+    }
+
+    pub(super) fn open_orders(&self, user: H160) -> Value {
+        let orders: Vec<Value> = self
+            .resting
+            .iter()
+            .filter(|o| o.user == user)
+            .map(|o| {
+                json!({
+                    "coin": o.coin,
+                    "limitPx": o.limit_px.to_string(),
+                    "oid": o.oid,
+                    "side": side(o),
+                    "sz": o.sz.to_string(),
+                    "timestamp": o.timestamp,
+                    "origSz": o.orig_sz.to_string(),
+                    "cloid": o.cloid,
+                    "reduceOnly": o.reduce_only,
+                    "isTrigger": o.trigger.is_some()
+                })
+            })
+            .collect();
+        json!(orders)
+    }
+
+    pub(super) fn user_fills(&self, user: H160) -> Value {
+        let fills: Vec<&Value> = self
+            .fills
+            .iter()
+            .rev()
+            .filter(|(fill_user, _)| *fill_user == user)
+            .map(|(_, fill)| fill)
+            .collect();
+        json!(fills)
+    }
+
+    pub(super) fn order_status_by_cloid(&self, user: H160, cloid: &str) -> Value {
+        let oid = self
+            .history
+            .values()
+            .map(|record| &record.order)
+            .filter(|order| order.user == user && order.cloid.as_deref().is_some_and(|c| c.eq_ignore_ascii_case(cloid)))
+            .map(|order| order.oid)
+            .max();
+        match oid {
+            Some(oid) => self.order_status(user, oid),
+            None => json!({ "status": "unknownOid" }),
+        }
+    }
+
+    pub(super) fn order_status(&self, user: H160, oid: u64) -> Value {
+        let Some(record) = self.history.get(&oid).filter(|r| r.order.user == user) else {
+            return json!({ "status": "unknownOid" });
+        };
+        let order = &record.order;
+        let (trigger_px, is_market, tpsl) = order
+            .trigger
+            .clone()
+            .unwrap_or(("0.0".to_string(), false, String::new()));
+        let order_type = match (order.trigger.is_some(), is_market, tpsl.as_str()) {
+            (false, _, _) => "Limit",
+            (true, true, "tp") => "Take Profit Market",
+            (true, false, "tp") => "Take Profit Limit",
+            (true, true, _) => "Stop Market",
+            (true, false, _) => "Stop Limit",
+        };
+        json!({
+            "status": "order",
+            "order": {
+                "order": {
+                    "coin": order.coin,
+                    "side": side(order),
+                    "limitPx": order.limit_px.to_string(),
+                    "sz": order.sz.to_string(),
+                    "oid": order.oid,
+                    "timestamp": order.timestamp,
+                    "triggerCondition": if order.trigger.is_some() { format!("Price {trigger_px}") } else { "N/A".to_string() },
+                    "isTrigger": order.trigger.is_some(),
+                    "triggerPx": trigger_px,
+                    "isPositionTpsl": false,
+                    "reduceOnly": order.reduce_only,
+                    "orderType": order_type,
+                    "origSz": order.orig_sz.to_string(),
+                    "tif": order.tif,
+                    "cloid": order.cloid
+                },
+                "status": record.status,
+                "statusTimestamp": record.status_timestamp
+            }
+        })
+    }
+
+    /// Resting limit orders aggregated by price, bids first
+    pub(super) fn l2_book(&self, coin: &str) -> Value {
+        let side_levels = |is_buy: bool| {
+            let mut levels: Vec<(f64, f64, u64)> = Vec::new();
+            for order in self
+                .resting
+                .iter()
+                .filter(|o| o.coin == coin && o.is_buy == is_buy && o.trigger.is_none())
+            {
+                match levels.iter_mut().find(|(px, _, _)| *px == order.limit_px) {
+                    Some(level) => {
+                        level.1 += order.sz;
+                        level.2 += 1;
+                    }
+                    None => levels.push((order.limit_px, order.sz, 1)),
+                }
+            }
+            levels.sort_by(|a, b| if is_buy { b.0.total_cmp(&a.0) } else { a.0.total_cmp(&b.0) });
+            levels
+                .into_iter()
+                .map(|(px, sz, n)| json!({ "px": px.to_string(), "sz": sz.to_string(), "n": n }))
+                .collect::<Vec<_>>()
+        };
+        json!({
+            "coin": coin,
+            "time": chrono::Utc::now().timestamp_millis(),
+            "levels": [side_levels(true), side_levels(false)]
+        })
+    }
+}
+
+fn crosses(order: &MockOrder, px: f64) -> bool {
+    if order.is_buy {
+        order.limit_px >= px
+    } else {
+        order.limit_px <= px
+    }
+}
+
+fn side(order: &MockOrder) -> &'static str {
+    if order.is_buy {
+        "B"
+    } else {
+        "A"
+    }
+}
+
+fn statuses_response(response_type: &str, statuses: Vec<Value>) -> Value {
+    json!({
+        "status": "ok",
+        "response": { "type": response_type, "data": { "statuses": statuses } }
+    })
+}
+
+fn twap_response(response_type: &str, status: Value) -> Value {
+    json!({
+        "status": "ok",
+        "response": { "type": response_type, "data": { "status": status } }
+    })
+}
+
+fn default_response() -> Value {
+    json!({ "status": "ok", "response": { "type": "default" } })
+}
+
+fn error_response(message: &str) -> Value {
+    json!({ "status": "err", "response": message })
+}
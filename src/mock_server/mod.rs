@@ -0,0 +1,660 @@
+mod matching;
+#[cfg(test)]
+pub(crate) mod test_utils;
+mod verify;
+
+use std::collections::HashMap;
+use std::fs;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_codec::{Decoder, Encoder};
+use actix_http::ws;
+use actix_web::dev::ServerHandle;
+use actix_web::http::{header, StatusCode};
+use actix_web::web::{self, Bytes, BytesMut};
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer};
+use ethers::types::{Signature, H160};
+use futures::channel::mpsc;
+use futures_util::StreamExt;
+use log::debug;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::prelude::*;
+use crate::{BaseUrl, Error, SigningChain, Subscription};
+use matching::MockExchange;
+
+/// Scripted responses for the mock server
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MockFixtures {
+    /// Responses to `/info` requests keyed by request type, e.g. `"meta"` or `"fundingHistory"`.
+    /// `meta` and `allMids` seed the matching engine; other entries take precedence over the
+    /// state the mock computes itself.
+    #[serde(default)]
+    pub info: HashMap<String, Value>,
+}
+
+impl MockFixtures {
+    /// Loads fixtures from a JSON file of the form `{"info": {"<type>": <response>}}`
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| Error::JsonParse(format!("{path}: {e}")))
+    }
+
+    /// Adds or replaces the response for an info request type
+    pub fn with_info(mut self, request_type: &str, response: Value) -> Self {
+        self.info.insert(request_type.to_string(), response);
+        self
+    }
+}
+
+/// Endpoint a fault applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockEndpoint {
+    Info,
+    Exchange,
+    /// The websocket handshake
+    Ws,
+}
+
+/// Failure injected into requests to an endpoint
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockFault {
+    /// Delay the response
+    Latency(Duration),
+    /// Respond with the given HTTP status and body instead of handling the request
+    Error { status: u16, body: String },
+    /// Close the connection without responding
+    Disconnect,
+    /// Handle the request, then close the connection instead of responding
+    DropResponse,
+}
+
+/// A request the mock received, for assertions in tests
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub endpoint: MockEndpoint,
+    pub body: Value,
+}
+
+#[derive(Debug)]
+struct ScheduledFault {
+    endpoint: MockEndpoint,
+    fault: MockFault,
+    remaining: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum WsEvent {
+    Message { subscription: Value, text: String },
+    Disconnect,
+}
+
+impl WsEvent {
+    fn message(subscription: &Subscription, data: Value) -> Self {
+        let subscription = serde_json::to_value(subscription).unwrap_or_default();
+        let channel = match subscription["type"].as_str().unwrap_or_default() {
+            "userEvents" => "user".to_string(),
+            other => other.to_string(),
+        };
+        let text = json!({ "channel": channel, "data": data }).to_string();
+        WsEvent::Message { subscription, text }
+    }
+}
+
+struct Shared {
+    exchange: Mutex<MockExchange>,
+    info_fixtures: Mutex<HashMap<String, Value>>,
+    faults: Mutex<Vec<ScheduledFault>>,
+    requests: Mutex<Vec<MockRequest>>,
+    ws_sender: broadcast::Sender<WsEvent>,
+}
+
+impl Shared {
+    fn flush_events(&self) {
+        let events = self.exchange.lock().unwrap().take_events();
+        for event in events {
+            // No receivers just means nobody is subscribed
+            let _ = self.ws_sender.send(event);
+        }
+    }
+
+    /// Consumes the faults scheduled for `endpoint`. Returns the total latency to add and the
+    /// first fault that replaces the response, if any.
+    fn take_faults(&self, endpoint: MockEndpoint) -> (Duration, Option<MockFault>) {
+        let mut faults = self.faults.lock().unwrap();
+        let mut latency = Duration::ZERO;
+        let mut terminal = None;
+        for scheduled in faults.iter_mut().filter(|f| f.endpoint == endpoint) {
+            if scheduled.remaining == Some(0) {
+                continue;
+            }
+            match &scheduled.fault {
+                MockFault::Latency(delay) => latency += *delay,
+                fault if terminal.is_none() => terminal = Some(fault.clone()),
+                _ => continue,
+            }
+            if let Some(remaining) = scheduled.remaining.as_mut() {
+                *remaining -= 1;
+            }
+        }
+        faults.retain(|f| f.remaining != Some(0));
+        (latency, terminal)
+    }
+}
+
+/// Local stand-in for the Hyperliquid API serving `/info`, `/exchange` and the `/ws` feed on one port.
+///
+/// `/exchange` payloads have their signatures recovered and orders run through a small matching
+/// engine, so `ExchangeClient` calls behave like they would against the real API. Any `/info`
+/// request type can be scripted through `MockFixtures`. Tests can inject latency, errors and
+/// dropped connections per endpoint. The server stops when dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    /// Built once, since every custom `BaseUrl` is allocated for the rest of the program
+    base_url: BaseUrl,
+    shared: Arc<Shared>,
+    handle: ServerHandle,
+}
+
+impl MockServer {
+    /// Starts serving on `addr`; use port 0 to pick a free port
+    pub async fn start(addr: &str, fixtures: MockFixtures) -> Result<Self> {
+        let listener = std::net::TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+
+        let mut info_fixtures = fixtures.info;
+        let meta = info_fixtures.remove("meta").unwrap_or_else(default_meta);
+        let mids = info_fixtures
+            .remove("allMids")
+            .map(|mids| parse_mids(&mids))
+            .transpose()?
+            .unwrap_or_else(default_mids);
+
+        let (ws_sender, _) = broadcast::channel(1024);
+        let shared = Arc::new(Shared {
+            exchange: Mutex::new(MockExchange::new(meta, mids)),
+            info_fixtures: Mutex::new(info_fixtures),
+            faults: Mutex::new(Vec::new()),
+            requests: Mutex::new(Vec::new()),
+            ws_sender,
+        });
+
+        let data = web::Data::from(Arc::clone(&shared));
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .route(
+                    "/info",
+                    web::post().to(|body: Bytes, shared: web::Data<Shared>| {
+                        handle_http(MockEndpoint::Info, body, shared)
+                    }),
+                )
+                .route(
+                    "/exchange",
+                    web::post().to(|body: Bytes, shared: web::Data<Shared>| {
+                        handle_http(MockEndpoint::Exchange, body, shared)
+                    }),
+                )
+                .route("/ws", web::get().to(serve_websocket))
+                .default_service(web::to(|| async { respond(404, "Not Found") }))
+        })
+        .workers(1)
+        .disable_signals()
+        .listen(listener)?
+        .run();
+        let handle = server.handle();
+        tokio::spawn(server);
+
+        Ok(Self {
+            addr,
+            base_url: BaseUrl::custom(&format!("http://{addr}"), SigningChain::Testnet),
+            shared,
+            handle,
+        })
+    }
+
+    /// Base URL to point clients at, e.g. `http://127.0.0.1:3001`
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Websocket URL, as `InfoClient` derives it from the base URL
+    pub fn ws_url(&self) -> String {
+        format!("ws://{}/ws", self.addr)
+    }
+
+    /// Endpoint for `InfoClient` and `ExchangeClient`. Actions are signed for testnet.
+    pub fn base_url(&self) -> BaseUrl {
+        self.base_url
+    }
+
+    /// Scripts the response for an info request type, replacing any computed response
+    pub fn set_info_response(&self, request_type: &str, response: Value) {
+        self.shared
+            .info_fixtures
+            .lock()
+            .unwrap()
+            .insert(request_type.to_string(), response);
+    }
+
+    /// Moves a coin's mid price and pushes the new mids to `allMids` subscribers
+    pub fn set_mid(&self, coin: &str, px: f64) {
+        self.shared.exchange.lock().unwrap().set_mid(coin, px);
+        self.shared.flush_events();
+    }
+
+    /// Sets a user's USDC balance (accounts start with 100,000)
+    pub fn set_balance(&self, user: H160, balance: f64) {
+        self.shared.exchange.lock().unwrap().set_balance(user, balance);
+    }
+
+    /// Only accepts actions signed by registered users or their approved agents. Until a user is
+    /// registered, any valid signature is accepted.
+    pub fn register_user(&self, user: H160) {
+        self.shared.exchange.lock().unwrap().register_user(user);
+    }
+
+    /// Pushes `data` to every websocket subscribed to `subscription`
+    pub fn push_ws(&self, subscription: &Subscription, data: Value) {
+        let _ = self.shared.ws_sender.send(WsEvent::message(subscription, data));
+    }
+
+    /// Applies `fault` to the next `times` requests to `endpoint`, or to all of them if `None`
+    pub fn inject_fault(&self, endpoint: MockEndpoint, fault: MockFault, times: Option<usize>) {
+        self.shared.faults.lock().unwrap().push(ScheduledFault {
+            endpoint,
+            fault,
+            remaining: times,
+        });
+    }
+
+    /// Removes all injected faults
+    pub fn clear_faults(&self) {
+        self.shared.faults.lock().unwrap().clear();
+    }
+
+    /// Drops every open websocket without a close handshake, like a network failure
+    pub fn disconnect_websockets(&self) {
+        let _ = self.shared.ws_sender.send(WsEvent::Disconnect);
+    }
+
+    /// Requests received so far, oldest first
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.shared.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.disconnect_websockets();
+        // Sending the stop command doesn't need the returned future to be polled
+        drop(self.handle.stop(false));
+    }
+}
+
+fn respond(status: u16, body: impl Into<String>) -> HttpResponse {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    HttpResponse::build(status)
+        .content_type("application/json")
+        .body(body.into())
+}
+
+/// A response whose body fails before it is written, so the client sees the connection drop
+fn dropped_connection() -> HttpResponse {
+    HttpResponse::Ok().streaming(futures::stream::once(async {
+        Err::<Bytes, _>(actix_web::error::ErrorInternalServerError("Connection dropped"))
+    }))
+}
+
+/// Sleeps for any injected latency, then returns the response to send instead of handling the
+/// request, or else the fault to apply after handling it
+async fn apply_faults(
+    shared: &Shared,
+    endpoint: MockEndpoint,
+) -> std::result::Result<Option<MockFault>, HttpResponse> {
+    let (latency, fault) = shared.take_faults(endpoint);
+    if !latency.is_zero() {
+        tokio::time::sleep(latency).await;
+    }
+    match fault {
+        Some(MockFault::Disconnect) => Err(dropped_connection()),
+        Some(MockFault::Error { status, body }) => Err(respond(status, body)),
+        fault => Ok(fault),
+    }
+}
+
+async fn handle_http(endpoint: MockEndpoint, body: Bytes, shared: web::Data<Shared>) -> HttpResponse {
+    let Ok(body) = serde_json::from_slice::<Value>(&body) else {
+        return respond(422, "Failed to deserialize the JSON body");
+    };
+    shared.requests.lock().unwrap().push(MockRequest {
+        endpoint,
+        body: body.clone(),
+    });
+    let fault = match apply_faults(&shared, endpoint).await {
+        Ok(fault) => fault,
+        Err(response) => return response,
+    };
+
+    let response = match endpoint {
+        MockEndpoint::Exchange => handle_exchange(&shared, body),
+        _ => handle_info(&shared, &body),
+    };
+    shared.flush_events();
+    if fault == Some(MockFault::DropResponse) {
+        return dropped_connection();
+    }
+    match response {
+        Ok(response) => respond(200, response.to_string()),
+        Err(message) => respond(422, message),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExchangeRequest {
+    action: Value,
+    signature: Signature,
+    nonce: u64,
+    #[serde(default)]
+    vault_address: Option<H160>,
+}
+
+fn handle_exchange(shared: &Shared, body: Value) -> std::result::Result<Value, String> {
+    let request: ExchangeRequest = serde_json::from_value(body)
+        .map_err(|e| format!("Failed to deserialize the JSON body into the target type: {e}"))?;
+    shared.exchange.lock().unwrap().handle(
+        request.action,
+        &request.signature,
+        request.nonce,
+        request.vault_address,
+    )
+}
+
+fn handle_info(shared: &Shared, body: &Value) -> std::result::Result<Value, String> {
+    let request_type = body["type"].as_str().unwrap_or_default();
+    if let Some(response) = shared.info_fixtures.lock().unwrap().get(request_type) {
+        return Ok(response.clone());
+    }
+    let mut exchange = shared.exchange.lock().unwrap();
+    exchange.fire_scheduled_cancels(chrono::Utc::now().timestamp_millis() as u64);
+
+    let user = || {
+        serde_json::from_value::<H160>(body["user"].clone())
+            .map_err(|e| format!("Failed to deserialize the JSON body into the target type: {e}"))
+    };
+    match request_type {
+        "meta" => Ok(exchange.meta().clone()),
+        "allMids" => Ok(exchange.all_mids()),
+        "spotMeta" => Ok(json!({ "universe": [], "tokens": [] })),
+        "clearinghouseState" => Ok(exchange.clearinghouse_state(user()?)),
+        "openOrders" | "frontendOpenOrders" => Ok(exchange.open_orders(user()?)),
+        "userFills" => Ok(exchange.user_fills(user()?)),
+        "orderStatus" => match body["oid"].as_str() {
+            Some(cloid) => Ok(exchange.order_status_by_cloid(user()?, cloid)),
+            None => {
+                let oid = body["oid"].as_u64().ok_or("Missing oid")?;
+                Ok(exchange.order_status(user()?, oid))
+            }
+        },
+        "l2Book" => Ok(exchange.l2_book(body["coin"].as_str().unwrap_or_default())),
+        "userRateLimit" => {
+            user()?;
+            let used = shared.requests.lock().unwrap().iter().filter(|request| request.endpoint == MockEndpoint::Exchange).count();
+            Ok(json!({ "cumVlm": "0.0", "nRequestsUsed": used, "nRequestsCap": 10_000 }))
+        }
+        _ => Err(format!("No fixture for info request type {request_type:?}")),
+    }
+}
+
+type WsSender = mpsc::UnboundedSender<std::result::Result<Bytes, actix_web::Error>>;
+
+async fn serve_websocket(request: HttpRequest, payload: web::Payload, shared: web::Data<Shared>) -> HttpResponse {
+    // There is no request to handle before the connection drops
+    match apply_faults(&shared, MockEndpoint::Ws).await {
+        Err(response) => return response,
+        Ok(Some(MockFault::DropResponse)) => return dropped_connection(),
+        Ok(_) => {}
+    }
+    if let Err(err) = ws::verify_handshake(request.head()) {
+        return HttpResponse::from_error(err);
+    }
+    let key = request
+        .headers()
+        .get(header::SEC_WEBSOCKET_KEY)
+        .map(|key| ws::hash_key(key.as_bytes()))
+        .unwrap_or_default();
+
+    let (sender, receiver) = mpsc::unbounded();
+    actix_web::rt::spawn(run_websocket(payload, sender, shared.into_inner()));
+    HttpResponse::build(StatusCode::SWITCHING_PROTOCOLS)
+        .upgrade("websocket")
+        .insert_header((header::SEC_WEBSOCKET_ACCEPT, &key[..]))
+        .streaming(receiver)
+}
+
+/// Decodes client frames from `payload` and writes replies and subscribed events to `sender`,
+/// which streams them out as the upgraded response body
+async fn run_websocket(mut payload: web::Payload, sender: WsSender, shared: Arc<Shared>) {
+    let mut codec = ws::Codec::new();
+    let mut buffer = BytesMut::new();
+    let mut events = shared.ws_sender.subscribe();
+    let mut subscriptions: Vec<Value> = Vec::new();
+
+    loop {
+        let outgoing = tokio::select! {
+            chunk = payload.next() => {
+                let Some(Ok(chunk)) = chunk else { return };
+                buffer.extend_from_slice(&chunk);
+                let mut outgoing = Vec::new();
+                loop {
+                    match codec.decode(&mut buffer) {
+                        Ok(Some(ws::Frame::Text(text))) => outgoing.extend(
+                            handle_ws_request(&String::from_utf8_lossy(&text), &mut subscriptions, &shared)
+                                .into_iter()
+                                .map(|text| ws::Message::Text(text.into())),
+                        ),
+                        Ok(Some(ws::Frame::Ping(data))) => outgoing.push(ws::Message::Pong(data)),
+                        Ok(Some(ws::Frame::Close(_))) => return,
+                        Ok(Some(_)) => {}
+                        Ok(None) => break,
+                        Err(err) => {
+                            debug!("Mock websocket closed: {err}");
+                            return;
+                        }
+                    }
+                }
+                outgoing
+            },
+            event = events.recv() => match event {
+                Ok(WsEvent::Message { subscription, text }) if subscriptions.contains(&subscription) => {
+                    vec![ws::Message::Text(text.into())]
+                }
+                Ok(WsEvent::Message { .. }) | Err(RecvError::Lagged(_)) => Vec::new(),
+                Ok(WsEvent::Disconnect) | Err(RecvError::Closed) => {
+                    // Failing the body drops the connection without a close frame
+                    let _ = sender.unbounded_send(Err(actix_web::error::ErrorInternalServerError(
+                        "Websocket dropped",
+                    )));
+                    return;
+                }
+            },
+        };
+        for message in outgoing {
+            let mut frame = BytesMut::new();
+            if codec.encode(message, &mut frame).is_err() || sender.unbounded_send(Ok(frame.freeze())).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Replies to a client websocket request. Subscribing to `allMids` also sends the current mids.
+fn handle_ws_request(text: &str, subscriptions: &mut Vec<Value>, shared: &Shared) -> Vec<String> {
+    let Ok(request) = serde_json::from_str::<Value>(text) else {
+        return vec![json!({ "channel": "error", "data": format!("Invalid request: {text}") }).to_string()];
+    };
+    let subscription = request["subscription"].clone();
+    match request["method"].as_str() {
+        Some("ping") => vec![json!({ "channel": "pong" }).to_string()],
+        Some("subscribe") => {
+            if !subscriptions.contains(&subscription) {
+                subscriptions.push(subscription.clone());
+            }
+            let mut replies = vec![json!({ "channel": "subscriptionResponse", "data": request }).to_string()];
+            if subscription["type"] == "allMids" {
+                let mids = shared.exchange.lock().unwrap().all_mids();
+                replies.push(json!({ "channel": "allMids", "data": { "mids": mids } }).to_string());
+            }
+            replies
+        }
+        Some("unsubscribe") => {
+            subscriptions.retain(|existing| *existing != subscription);
+            vec![json!({ "channel": "subscriptionResponse", "data": request }).to_string()]
+        }
+        _ => vec![json!({ "channel": "error", "data": format!("Unknown method: {text}") }).to_string()],
+    }
+}
+
+fn parse_mids(mids: &Value) -> Result<HashMap<String, f64>> {
+    let mids: HashMap<String, String> =
+        serde_json::from_value(mids.clone()).map_err(|e| Error::JsonParse(e.to_string()))?;
+    mids.into_iter()
+        .map(|(coin, px)| {
+            px.parse::<f64>()
+                .map(|px| (coin, px))
+                .map_err(|_| Error::FloatStringParse)
+        })
+        .collect()
+}
+
+fn default_meta() -> Value {
+    json!({
+        "universe": [
+            { "name": "BTC", "szDecimals": 5, "maxLeverage": 50 },
+            { "name": "ETH", "szDecimals": 4, "maxLeverage": 50 },
+            { "name": "SOL", "szDecimals": 2, "maxLeverage": 20 }
+        ]
+    })
+}
+
+fn default_mids() -> HashMap<String, f64> {
+    [("BTC", 50_000.0), ("ETH", 2_500.0), ("SOL", 150.0)]
+        .into_iter()
+        .map(|(coin, px)| (coin.to_string(), px))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_utils::*;
+    use super::*;
+    use crate::req::HttpClient;
+    use crate::ws::WsManager;
+    use crate::{ClientCancelRequest, ExchangeDataStatus, ExchangeResponseStatus, InfoClient, Message};
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+    fn http_client(server: &MockServer) -> HttpClient {
+        HttpClient {
+            client: reqwest::Client::new(),
+            base_url: server.base_url(),
+            rate_limiter: None,
+            retry_policy: None,
+            circuit_breaker: None,
+        }
+    }
+
+    async fn next_message(receiver: &mut UnboundedReceiver<Message>) -> Message {
+        tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("timed out waiting for websocket message")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn signed_orders_match_against_the_book() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let maker = exchange_client(&server, MAKER_KEY).await;
+        let taker = exchange_client(&server, TAKER_KEY).await;
+        server.register_user(maker.wallet.address());
+        server.register_user(taker.wallet.address());
+
+        let ExchangeDataStatus::Resting(resting) = status(maker.order(limit(true, 49_000.0, 0.5, "Gtc"), None).await?) else {
+            panic!("bid below the mid should rest");
+        };
+        let ExchangeDataStatus::Filled(filled) = status(taker.order(limit(false, 48_000.0, 0.2, "Ioc"), None).await?) else {
+            panic!("crossing sell should fill against the bid");
+        };
+        assert_eq!((filled.total_sz.as_str(), filled.avg_px.as_str()), ("0.2", "49000"));
+        assert!(matches!(
+            status(maker.order(limit(false, 49_500.0, 0.1, "Alo"), None).await?),
+            ExchangeDataStatus::Error(_)
+        ));
+
+        let info = InfoClient::new(None, Some(server.base_url())).await?;
+        assert_eq!(info.open_orders(maker.wallet.address()).await?[0].sz, "0.3");
+
+        let cancel = || ClientCancelRequest { asset: "BTC".to_string(), oid: resting.oid };
+        assert!(matches!(status(maker.cancel(cancel(), None).await?), ExchangeDataStatus::Success));
+        assert!(matches!(status(maker.cancel(cancel(), None).await?), ExchangeDataStatus::Error(_)));
+
+        assert_eq!(info.user_state(maker.wallet.address()).await?.asset_positions[0].position.szi, "0.2");
+        assert_eq!(info.user_state(taker.wallet.address()).await?.asset_positions[0].position.szi, "-0.2");
+
+        // Signatures from wallets the mock doesn't know are rejected
+        let stranger = exchange_client(&server, STRANGER_KEY).await;
+        let response = stranger.order(limit(true, 49_000.0, 0.1, "Gtc"), None).await?;
+        assert!(matches!(response, ExchangeResponseStatus::Err(message) if message.contains("does not exist")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn faults_and_websocket_reconnects() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let client = http_client(&server);
+        let mids = || json!({ "type": "allMids" }).to_string();
+
+        server.inject_fault(MockEndpoint::Info, MockFault::Error { status: 500, body: "boom".to_string() }, Some(1));
+        server.inject_fault(MockEndpoint::Info, MockFault::Disconnect, Some(1));
+        assert!(matches!(client.post("/info", mids()).await, Err(Error::ServerRequest { status_code: 500, .. })));
+        assert!(client.post("/info", mids()).await.is_err());
+
+        server.inject_fault(MockEndpoint::Info, MockFault::Latency(Duration::from_millis(200)), Some(1));
+        let started = std::time::Instant::now();
+        client.post("/info", mids()).await?;
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(server.requests().len(), 3);
+
+        let (sender, mut receiver) = unbounded_channel();
+        let mut ws = WsManager::new(server.ws_url(), true).await?;
+        ws.add_subscription(serde_json::to_string(&Subscription::AllMids).unwrap(), sender).await?;
+        let Message::AllMids(snapshot) = next_message(&mut receiver).await else {
+            panic!("expected an allMids snapshot");
+        };
+        assert_eq!(snapshot.data.mids["BTC"], "50000");
+
+        server.disconnect_websockets();
+        while !matches!(next_message(&mut receiver).await, Message::NoData) {}
+        server.set_mid("BTC", 51_000.0);
+        // The reconnected manager resubscribes and gets a fresh snapshot
+        loop {
+            if let Message::AllMids(mids) = next_message(&mut receiver).await {
+                if mids.data.mids["BTC"] == "51000" {
+                    break;
+                }
+            }
+        }
+
+        server.push_ws(&Subscription::AllMids, json!({ "mids": { "BTC": "52000" } }));
+        let Message::AllMids(pushed) = next_message(&mut receiver).await else {
+            panic!("expected the pushed mids");
+        };
+        assert_eq!(pushed.data.mids["BTC"], "52000");
+        Ok(())
+    }
+}
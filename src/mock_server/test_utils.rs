@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use ethers::signers::LocalWallet;
+use serde_json::json;
+
+use super::{MockServer, WsEvent};
+use crate::{
+    ClientLimit, ClientOrder, ClientOrderRequest, ExchangeClient, ExchangeDataStatus,
+    ExchangeResponseStatus, Subscription,
+};
+
+/// Key of the wallet most tests trade from
+pub(crate) const MAKER_KEY: &str =
+    "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e";
+/// Key of a second wallet to trade against the maker
+pub(crate) const TAKER_KEY: &str =
+    "0101010101010101010101010101010101010101010101010101010101010101";
+/// Key of a wallet that is never registered with the mock
+pub(crate) const STRANGER_KEY: &str =
+    "0202020202020202020202020202020202020202020202020202020202020202";
+
+pub(crate) async fn exchange_client(server: &MockServer, key: &str) -> ExchangeClient {
+    let wallet = key.parse::<LocalWallet>().unwrap();
+    ExchangeClient::new(None, wallet, Some(server.base_url()), None, None)
+        .await
+        .unwrap()
+}
+
+pub(crate) fn limit(is_buy: bool, px: f64, sz: f64, tif: &str) -> ClientOrderRequest {
+    ClientOrderRequest {
+        asset: "BTC".to_string(),
+        is_buy,
+        reduce_only: false,
+        limit_px: px,
+        sz,
+        cloid: None,
+        order_type: ClientOrder::Limit(ClientLimit {
+            tif: tif.to_string(),
+        }),
+    }
+}
+
+/// Status of the first order in an accepted response
+pub(crate) fn status(response: ExchangeResponseStatus) -> ExchangeDataStatus {
+    match response {
+        ExchangeResponseStatus::Ok(response) => response.data.unwrap().statuses[0].clone(),
+        ExchangeResponseStatus::Err(err) => panic!("unexpected error status: {err}"),
+    }
+}
+
+/// Keeps pushing a BTC book of 49,990 / 50,010 around the 50,000 mid until aborted
+pub(crate) fn push_btc_book(server: &MockServer) -> tokio::task::JoinHandle<()> {
+    let sender = server.shared.ws_sender.clone();
+    let subscription = Subscription::L2Book {
+        coin: "BTC".to_string(),
+    };
+    tokio::spawn(async move {
+        loop {
+            let book = json!({
+                "coin": "BTC",
+                "time": chrono::Utc::now().timestamp_millis(),
+                "levels": [[{ "px": "49990", "sz": "1", "n": 1 }], [{ "px": "50010", "sz": "1", "n": 1 }]]
+            });
+            let _ = sender.send(WsEvent::message(&subscription, book));
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+}
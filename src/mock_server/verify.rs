@@ -0,0 +1,20 @@
+use ethers::types::{Signature, H160};
+
+use crate::{prelude::*, signature::action_digest, Actions, Error};
+
+/// Recovers the address that signed an `/exchange` payload.
+///
+/// User-signed actions (transfers, withdrawals, agent approvals) are checked against their own
+/// EIP-712 types; everything else is an L1 action signed as an `Agent` over the action hash. The
+/// mock is never mainnet, so L1 actions are expected to use the testnet source.
+pub(super) fn recover_signer(
+    action: &Actions,
+    signature: &Signature,
+    nonce: u64,
+    vault_address: Option<H160>,
+) -> Result<H160> {
+    let (_, digest) = action_digest(action, nonce, vault_address, false)?;
+    signature
+        .recover(digest)
+        .map_err(|e| Error::SignatureFailure(e.to_string()))
+}
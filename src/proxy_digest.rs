@@ -1,5 +1,7 @@
 // For synchronous signing.
 // Needed to duplicate our own copy because it wasn't possible to import from ethers-signers.
+// generic-array 0.14.8+ deprecates its own API in favour of 1.x, which k256 does not use yet.
+#![allow(deprecated)]
 use ethers::prelude::k256::{
     elliptic_curve::generic_array::GenericArray,
     sha2::{
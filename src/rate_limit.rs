@@ -0,0 +1,342 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::debug;
+use serde_json::Value;
+use tokio::time::Instant;
+
+use crate::{prelude::*, Error, UserRateLimitResponse};
+
+/// REST weight Hyperliquid allows per IP per minute
+pub const IP_WEIGHT_PER_MINUTE: u32 = 1200;
+/// Once an address has spent its request budget, it may still act this often
+pub const ADDRESS_THROTTLED_INTERVAL: Duration = Duration::from_secs(10);
+// Batched exchange actions cost one extra unit of IP weight per this many orders or cancels
+const EXCHANGE_BATCH_WEIGHT_STEP: usize = 40;
+
+/// What a `RateLimiter` does with a request that is over budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitMode {
+    /// Wait until the budget allows the request
+    #[default]
+    Queue,
+    /// Fail with `Error::RateLimited` without sending
+    Reject,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub weight_per_minute: u32,
+    pub mode: RateLimitMode,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            weight_per_minute: IP_WEIGHT_PER_MINUTE,
+            mode: RateLimitMode::default(),
+        }
+    }
+}
+
+/// Cost of one REST request against the IP and address limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestWeight {
+    pub ip: u32,
+    /// Orders, cancels or modifies in an exchange action; zero for info requests
+    pub address: u64,
+}
+
+impl RequestWeight {
+    pub fn of(url_path: &str, body: &Value) -> Self {
+        if url_path != "/exchange" {
+            let ip = match body["type"].as_str().unwrap_or_default() {
+                "l2Book"
+                | "allMids"
+                | "clearinghouseState"
+                | "orderStatus"
+                | "spotClearinghouseState"
+                | "exchangeStatus" => 2,
+                "userRole" => 60,
+                _ => 20,
+            };
+            return Self { ip, address: 0 };
+        }
+
+        let action = &body["action"];
+        let batch = ["orders", "cancels", "modifies"]
+            .iter()
+            .find_map(|key| action[key].as_array())
+            .map_or(1, |items| items.len().max(1));
+        Self {
+            ip: 1 + (batch / EXCHANGE_BATCH_WEIGHT_STEP) as u32,
+            address: batch as u64,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct AddressBudget {
+    remaining: u64,
+    next_throttled_at: Instant,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    tokens: f64,
+    refilled_at: Instant,
+    address: Option<AddressBudget>,
+}
+
+/// Token bucket over Hyperliquid's REST limits, shared by every client it is attached to.
+///
+/// The IP bucket refills continuously at `weight_per_minute`. The address budget is only
+/// enforced once seeded from `InfoClient::user_rate_limit`, and drains by one per order, cancel
+/// or modify; past that, one exchange request is let through every `ADDRESS_THROTTLED_INTERVAL`
+/// as the exchange does.
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    state: Mutex<LimiterState>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        let state = LimiterState {
+            tokens: config.weight_per_minute as f64,
+            refilled_at: Instant::now(),
+            address: None,
+        };
+        Self {
+            config,
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Seeds the address budget with the account's real usage; call again as volume grows
+    pub fn seed_address_budget(&self, limit: &UserRateLimitResponse) {
+        let remaining = limit.n_requests_cap.saturating_sub(limit.n_requests_used);
+        let mut state = self.state.lock().unwrap();
+        let next_throttled_at = state
+            .address
+            .as_ref()
+            .map_or_else(Instant::now, |budget| budget.next_throttled_at);
+        state.address = Some(AddressBudget {
+            remaining,
+            next_throttled_at,
+        });
+    }
+
+    /// IP weight that can be spent right now
+    pub fn available_weight(&self) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state, Instant::now());
+        state.tokens as u32
+    }
+
+    /// Address requests left before throttling, if the budget has been seeded
+    pub fn address_requests_remaining(&self) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        state.address.as_ref().map(|budget| budget.remaining)
+    }
+
+    /// Waits for, or rejects, budget for a request about to be posted to `url_path`
+    pub(crate) async fn acquire(&self, url_path: &str, body: &str) -> Result<()> {
+        let body = serde_json::from_str(body).unwrap_or(Value::Null);
+        let weight = RequestWeight::of(url_path, &body);
+        loop {
+            let wait = match self.try_acquire(weight, Instant::now()) {
+                Ok(()) => return Ok(()),
+                Err(wait) => wait,
+            };
+            if self.config.mode == RateLimitMode::Reject {
+                return Err(Error::RateLimited(wait));
+            }
+            debug!("Rate limited, waiting {wait:?} for {url_path} weight {weight:?}");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    // Takes `weight` from both budgets, or leaves them untouched and says how long to wait
+    fn try_acquire(
+        &self,
+        weight: RequestWeight,
+        now: Instant,
+    ) -> std::result::Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state, now);
+
+        // A request heavier than the whole bucket goes through once the bucket is full
+        let needed = (weight.ip as f64).min(self.config.weight_per_minute as f64);
+        if state.tokens < needed {
+            let per_second = self.config.weight_per_minute as f64 / 60.0;
+            return Err(Duration::from_secs_f64(
+                (needed - state.tokens) / per_second,
+            ));
+        }
+
+        if weight.address > 0 {
+            if let Some(budget) = &mut state.address {
+                if budget.remaining >= weight.address {
+                    budget.remaining -= weight.address;
+                } else if now >= budget.next_throttled_at {
+                    budget.remaining = 0;
+                    budget.next_throttled_at = now + ADDRESS_THROTTLED_INTERVAL;
+                } else {
+                    return Err(budget.next_throttled_at - now);
+                }
+            }
+        }
+
+        state.tokens -= needed;
+        Ok(())
+    }
+
+    fn refill(&self, state: &mut LimiterState, now: Instant) {
+        let capacity = self.config.weight_per_minute as f64;
+        let elapsed = now.saturating_duration_since(state.refilled_at);
+        state.tokens = (state.tokens + elapsed.as_secs_f64() * capacity / 60.0).min(capacity);
+        state.refilled_at = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::test_utils::*;
+    use crate::mock_server::{MockFixtures, MockServer};
+    use crate::{InfoClient, Retryability};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn limiter(weight_per_minute: u32) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            weight_per_minute,
+            mode: RateLimitMode::Reject,
+        })
+    }
+
+    #[test]
+    fn weighs_info_types_and_exchange_batches() {
+        let weight = |path, body| RequestWeight::of(path, &body).ip;
+        assert_eq!(weight("/info", json!({ "type": "allMids" })), 2);
+        assert_eq!(weight("/info", json!({ "type": "openOrders" })), 20);
+        assert_eq!(weight("/info", json!({ "type": "userRole" })), 60);
+
+        let orders = json!({ "action": { "type": "order", "orders": vec![json!({}); 85] } });
+        assert_eq!(
+            RequestWeight::of("/exchange", &orders),
+            RequestWeight { ip: 3, address: 85 }
+        );
+        let schedule_cancel = json!({ "action": { "type": "scheduleCancel" } });
+        assert_eq!(
+            RequestWeight::of("/exchange", &schedule_cancel),
+            RequestWeight { ip: 1, address: 1 }
+        );
+    }
+
+    #[test]
+    fn ip_bucket_refills_over_time() {
+        let limiter = limiter(60);
+        let start = Instant::now();
+        let info = RequestWeight { ip: 20, address: 0 };
+        for _ in 0..3 {
+            assert_eq!(limiter.try_acquire(info, start), Ok(()));
+        }
+        assert_eq!(
+            limiter.try_acquire(info, start),
+            Err(Duration::from_secs(20))
+        );
+        assert_eq!(
+            limiter.try_acquire(info, start + Duration::from_secs(20)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn spent_address_budget_throttles_exchange_requests() {
+        let limiter = limiter(1200);
+        limiter.seed_address_budget(&UserRateLimitResponse {
+            cum_vlm: "0.0".to_string(),
+            n_requests_used: 98,
+            n_requests_cap: 100,
+        });
+        let start = Instant::now();
+        let order = RequestWeight { ip: 1, address: 2 };
+        assert_eq!(limiter.try_acquire(order, start), Ok(()));
+        assert_eq!(limiter.address_requests_remaining(), Some(0));
+
+        // Past the budget, one request per interval is still allowed
+        assert_eq!(limiter.try_acquire(order, start), Ok(()));
+        let later = start + Duration::from_secs(4);
+        assert_eq!(
+            limiter.try_acquire(order, later),
+            Err(ADDRESS_THROTTLED_INTERVAL - Duration::from_secs(4))
+        );
+        // Info requests don't count against the address
+        assert_eq!(
+            limiter.try_acquire(RequestWeight { ip: 2, address: 0 }, later),
+            Ok(())
+        );
+    }
+
+    #[tokio::test]
+    async fn rate_limiter_rejects_requests_over_budget() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            weight_per_minute: 50,
+            mode: RateLimitMode::Reject,
+        }));
+        let client = exchange_client(&server, MAKER_KEY)
+            .await
+            .with_rate_limiter(Arc::clone(&limiter));
+        let info = InfoClient::new(None, Some(server.base_url()))
+            .await?
+            .with_rate_limiter(Arc::clone(&limiter));
+        let user = client.wallet.address();
+
+        // openOrders weighs 20, so the third in a row is over the 50 budget and never sent
+        info.open_orders(user).await?;
+        info.open_orders(user).await?;
+        let sent = server.requests().len();
+        let rejected = info.open_orders(user).await;
+        assert!(matches!(rejected, Err(Error::RateLimited(_))));
+        assert_eq!(
+            rejected.unwrap_err().retryability(),
+            Retryability::RetryAfterBackoff
+        );
+        assert_eq!(server.requests().len(), sent);
+
+        // Exchange actions are cheap on the IP bucket but count against the seeded address budget
+        let limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            mode: RateLimitMode::Reject,
+            ..Default::default()
+        }));
+        let client = client.with_rate_limiter(Arc::clone(&limiter));
+        let budget = InfoClient::new(None, Some(server.base_url()))
+            .await?
+            .user_rate_limit(user)
+            .await?;
+        assert_eq!(budget.n_requests_cap, 10_000);
+        limiter.seed_address_budget(&UserRateLimitResponse {
+            n_requests_used: budget.n_requests_cap - 2,
+            ..budget
+        });
+
+        let orders = vec![
+            limit(true, 49_000.0, 0.1, "Gtc"),
+            limit(true, 48_000.0, 0.1, "Gtc"),
+        ];
+        client.bulk_order(orders, None).await?;
+        assert_eq!(limiter.address_requests_remaining(), Some(0));
+        // Past the budget one request gets through per throttle interval
+        client
+            .order(limit(true, 47_000.0, 0.1, "Gtc"), None)
+            .await?;
+        let rejected = client.order(limit(true, 46_000.0, 0.1, "Gtc"), None).await;
+        assert!(
+            matches!(rejected, Err(Error::RateLimited(wait)) if wait <= ADDRESS_THROTTLED_INTERVAL)
+        );
+        Ok(())
+    }
+}
@@ -1,6 +1,12 @@
-use crate::{prelude::*, BaseUrl, Error};
+use crate::{
+    prelude::*, retry::is_transient, BaseUrl, CircuitBreaker, Error, ExchangeRejection,
+    RateLimiter, RejectionKind, RetryPolicy,
+};
+use log::warn;
 use reqwest::{Client, Response};
 use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
 
 #[derive(Deserialize, Debug)]
 struct ErrorData {
@@ -9,10 +15,15 @@ struct ErrorData {
     msg: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct HttpClient {
     pub client: Client,
-    pub base_url: String,
+    pub base_url: BaseUrl,
+    /// Throttles requests before they are sent; may be shared between clients
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    pub retry_policy: Option<RetryPolicy>,
+    /// Fails requests fast while the API is down; may be shared between clients
+    pub circuit_breaker: Option<Arc<CircuitBreaker>>,
 }
 
 async fn parse_response(response: Response) -> Result<String> {
@@ -50,14 +61,96 @@ async fn parse_response(response: Response) -> Result<String> {
     })
 }
 
+// The rejection in an exchange response that refuses the whole request for a reused nonce
+fn duplicate_nonce(text: &str) -> Option<ExchangeRejection> {
+    let response = serde_json::from_str::<Value>(text).ok()?;
+    if response["status"] != "err" {
+        return None;
+    }
+    let rejection = ExchangeRejection::parse(response["response"].as_str()?);
+    (rejection.kind == RejectionKind::DuplicateNonce).then_some(rejection)
+}
+
+// Server errors and transport failures can come after the request was handled
+fn may_have_applied(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::ServerRequest { .. } | Error::GenericRequest(_)
+    )
+}
+
 impl HttpClient {
     pub async fn post(&self, url_path: &'static str, data: String) -> Result<String> {
-        let full_url = format!("{}{url_path}", self.base_url);
-        let request = self
+        let retries = self
+            .retry_policy
+            .as_ref()
+            .map_or(0, |policy| policy.retries_for(url_path, &data));
+        let mut retry = 0;
+        // An earlier attempt at an exchange action that may have been applied
+        let mut unconfirmed: Option<Error> = None;
+        loop {
+            let result = self.attempt(url_path, &data).await;
+            match (result, &self.retry_policy) {
+                // A resend is rejected for its nonce only if an earlier attempt reached the
+                // exchange, whose response was lost
+                (Ok(text), _) if retry > 0 && url_path == "/exchange" => {
+                    return match duplicate_nonce(&text) {
+                        Some(rejection) => Err(Error::OutcomeUnknown(Box::new(rejection.into()))),
+                        None => Ok(text),
+                    };
+                }
+                (Err(err), Some(policy)) if retry < retries && is_transient(&err) => {
+                    let delay = policy.backoff(retry);
+                    warn!("Retrying {url_path} in {delay:?} after error: {err}");
+                    if url_path == "/exchange" && may_have_applied(&err) {
+                        unconfirmed = Some(err);
+                    }
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                }
+                // The exchange may have applied an action whose response was lost
+                (Err(err), _) if url_path == "/exchange" && may_have_applied(&err) => {
+                    return Err(Error::OutcomeUnknown(Box::new(err)))
+                }
+                // A resend refused by the rate limiter, the circuit breaker or the exchange
+                // doesn't say what happened to the attempt before it
+                (Err(err), _) => {
+                    return Err(match unconfirmed {
+                        Some(unconfirmed) => Error::OutcomeUnknown(Box::new(unconfirmed)),
+                        None => err,
+                    })
+                }
+                (result, _) => return result,
+            }
+        }
+    }
+
+    async fn attempt(&self, url_path: &'static str, data: &str) -> Result<String> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire(url_path, data).await?;
+        }
+        // Checked right before sending, so an admitted half-open probe is always recorded
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.check()?;
+        }
+        let result = self.send(url_path, data.to_string()).await;
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.record(&result);
+        }
+        result
+    }
+
+    async fn send(&self, url_path: &'static str, data: String) -> Result<String> {
+        let full_url = format!("{}{url_path}", self.base_url.get_url());
+        let mut request = self
             .client
             .post(full_url)
             .header("Content-Type", "application/json")
-            .body(data)
+            .body(data);
+        if let Some(timeout) = self.retry_policy.as_ref().and_then(|policy| policy.timeout) {
+            request = request.timeout(timeout);
+        }
+        let request = request
             .build()
             .map_err(|e| Error::GenericRequest(e.to_string()))?;
         let result = self
@@ -69,6 +162,6 @@ impl HttpClient {
     }
 
     pub fn is_mainnet(&self) -> bool {
-        self.base_url == BaseUrl::Mainnet.get_url()
+        self.base_url.is_mainnet()
     }
 }
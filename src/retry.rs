@@ -0,0 +1,467 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rand::{thread_rng, Rng};
+use serde_json::Value;
+use tokio::time::Instant;
+
+use crate::{prelude::*, Error};
+
+/// How `HttpClient` retries requests that failed for transient reasons: 5xx responses, 429s,
+/// timeouts and dropped connections.
+///
+/// Info requests are always safe to retry. Exchange requests are resent byte for byte, so their
+/// nonce stops the exchange from applying them twice, but that doesn't say whether an earlier
+/// attempt landed: the resend is then rejected as a duplicate nonce, which `HttpClient` returns as
+/// `Error::OutcomeUnknown`, the same as an exchange request that fails in transit. Orders are only
+/// retried when every one carries a cloid to look that outcome up by, and TWAP orders never are.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts after the first
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each one after
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Randomizes each delay between half and all of its value, so clients don't retry in step
+    pub jitter: bool,
+    /// Per-attempt timeout
+    pub timeout: Option<Duration>,
+    pub retry_exchange: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(5),
+            jitter: true,
+            timeout: Some(Duration::from_secs(10)),
+            retry_exchange: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (zero-based)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        if self.jitter {
+            delay.mul_f64(thread_rng().gen_range(0.5..=1.0))
+        } else {
+            delay
+        }
+    }
+
+    pub(crate) fn retries_for(&self, url_path: &str, body: &str) -> u32 {
+        if url_path != "/exchange" {
+            return self.max_retries;
+        }
+        let resendable = self.retry_exchange
+            && serde_json::from_str::<Value>(body).is_ok_and(|body| is_resendable_action(&body));
+        if resendable {
+            self.max_retries
+        } else {
+            0
+        }
+    }
+}
+
+fn is_resendable_action(body: &Value) -> bool {
+    let action = &body["action"];
+    body["nonce"].is_u64()
+        && match action["type"].as_str() {
+            Some("order") => action["orders"]
+                .as_array()
+                .is_some_and(|orders| orders.iter().all(|order| order["c"].is_string())),
+            Some("twapOrder") => false,
+            _ => true,
+        }
+}
+
+/// Whether a failed attempt is worth another try
+pub(crate) fn is_transient(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::ServerRequest { .. }
+            | Error::GenericRequest(_)
+            | Error::ClientRequest {
+                status_code: 429,
+                ..
+            }
+    )
+}
+
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failed attempts that open the circuit
+    pub failure_threshold: u32,
+    /// How long an open circuit fails fast before letting a probe request through
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// The API looks down; requests fail with `Error::CircuitOpen` without being sent
+    Open,
+    /// The cooldown has passed and one probe request is deciding whether to close again
+    HalfOpen,
+}
+
+/// Snapshot of what a `CircuitBreaker` has seen of the API
+#[derive(Debug, Clone)]
+pub struct ApiHealth {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    /// Time since the last request that got a response
+    pub since_last_success: Option<Duration>,
+}
+
+impl ApiHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.state == CircuitState::Closed
+    }
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    state: CircuitState,
+    consecutive_failures: u32,
+    open_until: Instant,
+    probe_in_flight: bool,
+    last_error: Option<String>,
+    last_success: Option<Instant>,
+}
+
+/// Stops sending requests after repeated server errors or dropped connections, and lets one
+/// through per cooldown until the API answers again. Share one between clients hitting the same
+/// API so they all back off together.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                open_until: Instant::now(),
+                probe_in_flight: false,
+                last_error: None,
+                last_success: None,
+            }),
+        }
+    }
+
+    pub fn health(&self) -> ApiHealth {
+        let state = self.state.lock().unwrap();
+        ApiHealth {
+            state: state.state,
+            consecutive_failures: state.consecutive_failures,
+            last_error: state.last_error.clone(),
+            since_last_success: state.last_success.map(|at| at.elapsed()),
+        }
+    }
+
+    /// Fails fast while the circuit is open
+    pub(crate) fn check(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        match state.state {
+            CircuitState::Closed => Ok(()),
+            CircuitState::Open if now >= state.open_until => {
+                state.state = CircuitState::HalfOpen;
+                state.probe_in_flight = true;
+                Ok(())
+            }
+            CircuitState::HalfOpen if !state.probe_in_flight => {
+                state.probe_in_flight = true;
+                Ok(())
+            }
+            CircuitState::Open => Err(Error::CircuitOpen(state.open_until - now)),
+            CircuitState::HalfOpen => Err(Error::CircuitOpen(self.config.cooldown)),
+        }
+    }
+
+    /// Records the outcome of a request that was sent. Only server errors and transport failures
+    /// count against the API; any other response shows it is up.
+    pub(crate) fn record<T>(&self, result: &Result<T>) {
+        let mut state = self.state.lock().unwrap();
+        state.probe_in_flight = false;
+        match result {
+            Err(error @ (Error::ServerRequest { .. } | Error::GenericRequest(_))) => {
+                state.consecutive_failures += 1;
+                state.last_error = Some(error.to_string());
+                if state.state == CircuitState::HalfOpen
+                    || state.consecutive_failures >= self.config.failure_threshold
+                {
+                    state.state = CircuitState::Open;
+                    state.open_until = Instant::now() + self.config.cooldown;
+                }
+            }
+            _ => {
+                state.state = CircuitState::Closed;
+                state.consecutive_failures = 0;
+                state.last_success = Some(Instant::now());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::test_utils::*;
+    use crate::mock_server::{MockEndpoint, MockFault, MockFixtures, MockServer};
+    use crate::{
+        ClientOrderRequest, ExchangeDataStatus, ExchangeRejection, InfoClient, RejectionKind,
+        Retryability,
+    };
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..Default::default()
+        };
+        let delays: Vec<_> = (0..6).map(|retry| policy.backoff(retry)).collect();
+        assert_eq!(delays[0], Duration::from_millis(250));
+        assert_eq!(delays[2], Duration::from_secs(1));
+        assert_eq!(delays[5], Duration::from_secs(5));
+
+        let jittered = RetryPolicy::default().backoff(1);
+        assert!(jittered >= Duration::from_millis(250) && jittered <= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn exchange_orders_retry_only_with_cloids() {
+        let policy = RetryPolicy::default();
+        let retries = |body: Value| policy.retries_for("/exchange", &body.to_string());
+        let order = |cloid: Option<&str>| json!({ "a": 0, "c": cloid });
+        assert_eq!(policy.retries_for("/info", "{}"), 3);
+        assert_eq!(
+            retries(json!({ "action": { "type": "cancel", "cancels": [] }, "nonce": 1 })),
+            3
+        );
+        assert_eq!(
+            retries(
+                json!({ "action": { "type": "order", "orders": [order(Some("0x01"))] }, "nonce": 1 })
+            ),
+            3
+        );
+        assert_eq!(
+            retries(
+                json!({ "action": { "type": "order", "orders": [order(Some("0x01")), order(None)] }, "nonce": 1 })
+            ),
+            0
+        );
+        assert_eq!(
+            retries(json!({ "action": { "type": "twapOrder" }, "nonce": 1 })),
+            0
+        );
+    }
+
+    #[test]
+    fn breaker_opens_after_repeated_failures_and_probes_after_cooldown() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 2,
+            cooldown: Duration::ZERO,
+        });
+        let down: Result<()> = Err(Error::ServerRequest {
+            status_code: 502,
+            error_message: "Bad Gateway".to_string(),
+        });
+        breaker.record(&down);
+        assert!(breaker.health().is_healthy());
+        breaker.record(&down);
+        assert_eq!(breaker.health().state, CircuitState::Open);
+
+        // The cooldown has passed, so one probe goes out while others still fail fast
+        assert!(breaker.check().is_ok());
+        assert!(matches!(breaker.check(), Err(Error::CircuitOpen(_))));
+        breaker.record(&down);
+        assert_eq!(breaker.health().state, CircuitState::Open);
+
+        assert!(breaker.check().is_ok());
+        breaker.record(&Ok(()));
+        let health = breaker.health();
+        assert!(health.is_healthy());
+        assert_eq!(health.consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried_and_trip_the_circuit_breaker() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let client = exchange_client(&server, MAKER_KEY)
+            .await
+            .with_retry_policy(policy.clone());
+        let info = InfoClient::new(None, Some(server.base_url()))
+            .await?
+            .with_retry_policy(policy);
+        let bad_gateway = || MockFault::Error {
+            status: 502,
+            body: "Bad Gateway".to_string(),
+        };
+        let attempts = |endpoint| {
+            server
+                .requests()
+                .iter()
+                .filter(|request| request.endpoint == endpoint)
+                .count()
+        };
+
+        server.inject_fault(MockEndpoint::Info, bad_gateway(), Some(2));
+        server.inject_fault(MockEndpoint::Info, MockFault::Disconnect, Some(1));
+        let sent = attempts(MockEndpoint::Info);
+        assert_eq!(info.all_mids().await?["BTC"], "50000");
+        assert_eq!(attempts(MockEndpoint::Info) - sent, 4);
+
+        // Without a cloid a resent order's outcome can't be looked up, so it isn't retried
+        server.inject_fault(MockEndpoint::Exchange, bad_gateway(), Some(1));
+        let failed = client.order(limit(true, 49_000.0, 0.1, "Gtc"), None).await;
+        let Err(Error::OutcomeUnknown(failed)) = failed else {
+            panic!("expected an unknown outcome, got {failed:?}");
+        };
+        assert!(matches!(
+            *failed,
+            Error::ServerRequest {
+                status_code: 502,
+                ..
+            }
+        ));
+        assert_eq!(attempts(MockEndpoint::Exchange), 1);
+
+        server.inject_fault(MockEndpoint::Exchange, bad_gateway(), Some(1));
+        let order = ClientOrderRequest {
+            cloid: Some(uuid::Uuid::new_v4()),
+            ..limit(true, 49_000.0, 0.1, "Gtc")
+        };
+        assert!(matches!(
+            status(client.order(order, None).await?),
+            ExchangeDataStatus::Resting(_)
+        ));
+        assert_eq!(attempts(MockEndpoint::Exchange), 3);
+
+        // The order lands but its response is lost, so the resend is turned away for its nonce
+        let open_orders = info.open_orders(client.wallet.address()).await?.len();
+        server.inject_fault(MockEndpoint::Exchange, MockFault::DropResponse, Some(1));
+        let order = ClientOrderRequest {
+            cloid: Some(uuid::Uuid::new_v4()),
+            ..limit(true, 49_000.0, 0.1, "Gtc")
+        };
+        let unknown = client.order(order, None).await;
+        let Err(Error::OutcomeUnknown(unknown)) = unknown else {
+            panic!("expected an unknown outcome, got {unknown:?}");
+        };
+        assert!(matches!(
+            *unknown,
+            Error::Rejected(ExchangeRejection {
+                kind: RejectionKind::DuplicateNonce,
+                ..
+            })
+        ));
+        assert_eq!(attempts(MockEndpoint::Exchange), 5);
+        assert_eq!(
+            info.open_orders(client.wallet.address()).await?.len(),
+            open_orders + 1
+        );
+
+        let breaker = Arc::new(CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 2,
+            cooldown: Duration::from_secs(60),
+        }));
+        let info = info.with_circuit_breaker(Arc::clone(&breaker));
+        server.inject_fault(MockEndpoint::Info, bad_gateway(), None);
+        // The circuit opens on the second failure, cutting the remaining retries short
+        let sent = attempts(MockEndpoint::Info);
+        assert!(matches!(info.all_mids().await, Err(Error::CircuitOpen(_))));
+        assert_eq!(attempts(MockEndpoint::Info) - sent, 2);
+        let health = breaker.health();
+        assert_eq!(health.state, CircuitState::Open);
+        assert!(health.last_error.unwrap().contains("Bad Gateway"));
+
+        // An open circuit fails fast without sending
+        let sent = attempts(MockEndpoint::Info);
+        assert!(matches!(info.all_mids().await, Err(Error::CircuitOpen(_))));
+        assert_eq!(attempts(MockEndpoint::Info), sent);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn refused_resends_of_failed_exchange_requests_are_outcome_unknown() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let breaker = Arc::new(CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            cooldown: Duration::from_secs(60),
+        }));
+        let client = exchange_client(&server, MAKER_KEY)
+            .await
+            .with_retry_policy(RetryPolicy {
+                base_delay: Duration::from_millis(10),
+                ..Default::default()
+            })
+            .with_circuit_breaker(breaker);
+        server.inject_fault(
+            MockEndpoint::Exchange,
+            MockFault::Error {
+                status: 502,
+                body: "Bad Gateway".to_string(),
+            },
+            Some(1),
+        );
+
+        // The breaker opens on the 502, which may have come after the order was placed, so
+        // refusing the resend must not read as a failure worth retrying
+        let order = ClientOrderRequest {
+            cloid: Some(uuid::Uuid::new_v4()),
+            ..limit(true, 49_000.0, 0.1, "Gtc")
+        };
+        let failed = client.order(order, None).await;
+        let Err(Error::OutcomeUnknown(failed)) = failed else {
+            panic!("expected an unknown outcome, got {failed:?}");
+        };
+        assert!(matches!(
+            *failed,
+            Error::ServerRequest {
+                status_code: 502,
+                ..
+            }
+        ));
+        assert_eq!(
+            Error::OutcomeUnknown(failed).retryability(),
+            Retryability::OutcomeUnknown
+        );
+        let sent = server
+            .requests()
+            .iter()
+            .filter(|request| request.endpoint == MockEndpoint::Exchange)
+            .count();
+        assert_eq!(sent, 1);
+        Ok(())
+    }
+}
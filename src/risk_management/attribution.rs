@@ -0,0 +1,359 @@
+use std::collections::{BTreeMap, HashSet};
+
+use ethers::types::H160;
+use serde::{Deserialize, Serialize};
+
+use crate::info::info_client::InfoClient;
+use crate::prelude::*;
+use crate::risk_management::RiskConfig;
+use crate::{
+    decimal_to_f64, parse_decimal, LedgerUpdate, LedgerUpdateData, UserFillsResponse,
+    UserFundingResponse,
+};
+
+/// Maximum number of fills the API returns per `userFillsByTime` request
+const FILLS_PAGE_SIZE: usize = 2000;
+
+/// Settings for periodic PnL attribution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttributionConfig {
+    pub enabled: bool,
+    /// Length of each stored attribution window
+    pub interval_seconds: u64,
+}
+
+impl Default for AttributionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_seconds: 3600,
+        }
+    }
+}
+
+/// Decomposition of the change in perp account equity over an interval.
+/// All amounts are in USD; gains are positive except `fees`, which is the amount paid.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PnlAttribution {
+    pub start_time: u64,                          // interval start (ms)
+    pub end_time: u64,                            // interval end (ms)
+    pub trading_pnl: f64,                         // closed PnL from regular fills
+    pub trading_pnl_by_coin: BTreeMap<String, f64>,
+    pub funding: f64,                             // funding received (negative when paid)
+    pub funding_by_coin: BTreeMap<String, f64>,
+    pub fees: f64,                                // trading fees paid, net of rebates
+    pub fees_by_coin: BTreeMap<String, f64>,
+    pub liquidation_losses: f64,                  // closed PnL and fees of fills where this account was liquidated
+    pub liquidation_count: usize,
+    pub transfers: f64,                           // net deposits, withdrawals and vault/sub-account flows
+    pub start_equity: Option<f64>,                // account value near the interval start, from logged history
+    pub end_equity: Option<f64>,
+    pub unrealized_pnl_change: Option<f64>,       // change in open-position PnL, from logged history
+    pub unexplained: Option<f64>,                 // equity change not accounted for by the components above
+}
+
+impl PnlAttribution {
+    /// Builds an attribution from already fetched fills, funding payments and ledger updates.
+    /// Only entries inside `[start_time, end_time]` are counted.
+    pub fn from_events(
+        user: H160,
+        start_time: u64,
+        end_time: u64,
+        fills: &[UserFillsResponse],
+        fundings: &[UserFundingResponse],
+        ledger_updates: &[LedgerUpdateData],
+    ) -> Result<Self> {
+        let in_window = |time: u64| time >= start_time && time <= end_time;
+        let mut attribution = PnlAttribution {
+            start_time,
+            end_time,
+            ..Default::default()
+        };
+
+        for fill in fills.iter().filter(|f| in_window(f.time) && !is_spot_coin(&f.coin)) {
+            let closed_pnl = parse_amount("closedPnl", &fill.closed_pnl)?;
+            let fee = parse_amount("fee", &fill.fee)?;
+
+            // Fills where we were the liquidator, or that don't say who was liquidated, are regular trades
+            let liquidated = fill
+                .liquidation
+                .as_ref()
+                .and_then(|l| l.liquidated_user)
+                .is_some_and(|u| u == user);
+            if liquidated {
+                attribution.liquidation_losses += closed_pnl - fee;
+                continue;
+            }
+
+            attribution.trading_pnl += closed_pnl;
+            *attribution.trading_pnl_by_coin.entry(fill.coin.clone()).or_default() += closed_pnl;
+            attribution.fees += fee;
+            *attribution.fees_by_coin.entry(fill.coin.clone()).or_default() += fee;
+        }
+
+        for funding in fundings.iter().filter(|f| in_window(f.time)) {
+            let usdc = parse_amount("usdc", &funding.delta.usdc)?;
+            attribution.funding += usdc;
+            *attribution.funding_by_coin.entry(funding.delta.coin.clone()).or_default() += usdc;
+        }
+
+        for update in ledger_updates.iter().filter(|u| in_window(u.time)) {
+            if let LedgerUpdate::LedgerLiquidation(_) = update.delta {
+                attribution.liquidation_count += 1;
+            }
+            attribution.transfers += ledger_transfer_amount(user, &update.delta)?;
+        }
+
+        Ok(attribution)
+    }
+
+    /// Sum of all attributed components
+    pub fn explained_change(&self) -> f64 {
+        self.trading_pnl + self.funding - self.fees
+            + self.liquidation_losses
+            + self.transfers
+            + self.unrealized_pnl_change.unwrap_or(0.0)
+    }
+
+    /// Adds the observed equity and unrealized PnL at both ends of the interval,
+    /// as `(account_value, total_unrealized_pnl)` pairs, and computes the unexplained residual
+    pub fn with_equity(mut self, start: Option<(f64, f64)>, end: Option<(f64, f64)>) -> Self {
+        if let (Some((start_equity, start_upnl)), Some((end_equity, end_upnl))) = (start, end) {
+            self.start_equity = Some(start_equity);
+            self.end_equity = Some(end_equity);
+            self.unrealized_pnl_change = Some(end_upnl - start_upnl);
+            self.unexplained = Some((end_equity - start_equity) - self.explained_change());
+        }
+        self
+    }
+}
+
+/// Spot fills (`@<index>` or `BASE/QUOTE`) don't affect perp equity
+pub(crate) fn is_spot_coin(coin: &str) -> bool {
+    coin.starts_with('@') || coin.contains('/')
+}
+
+fn parse_amount(field: &str, value: &str) -> Result<f64> {
+    parse_decimal(field, value).map(decimal_to_f64)
+}
+
+/// Signed effect of a non-funding ledger update on perp equity
+fn ledger_transfer_amount(user: H160, delta: &LedgerUpdate) -> Result<f64> {
+    let amount = match delta {
+        LedgerUpdate::Deposit(deposit) => parse_amount("usdc", &deposit.usdc)?,
+        LedgerUpdate::Withdraw(withdraw) => {
+            -(parse_amount("usdc", &withdraw.usdc)? + parse_amount("fee", &withdraw.fee)?)
+        }
+        LedgerUpdate::InternalTransfer(transfer) => {
+            let usdc = parse_amount("usdc", &transfer.usdc)?;
+            if transfer.user == user {
+                -(usdc + parse_amount("fee", &transfer.fee)?)
+            } else if transfer.destination == user {
+                usdc
+            } else {
+                0.0
+            }
+        }
+        LedgerUpdate::SubAccountTransfer(transfer) => {
+            let usdc = parse_amount("usdc", &transfer.usdc)?;
+            if transfer.user == user {
+                -usdc
+            } else if transfer.destination == user {
+                usdc
+            } else {
+                0.0
+            }
+        }
+        LedgerUpdate::VaultDeposit(vault) | LedgerUpdate::VaultCreate(vault) => {
+            -parse_amount("usdc", &vault.usdc)?
+        }
+        LedgerUpdate::VaultDistribution(vault) => parse_amount("usdc", &vault.usdc)?,
+        LedgerUpdate::VaultWithdraw(withdraw) => {
+            parse_amount("netWithdrawnUsd", &withdraw.net_withdrawn_usd)?
+        }
+        LedgerUpdate::VaultLeaderCommission(commission) => parse_amount("usdc", &commission.usdc)?,
+        LedgerUpdate::AccountClassTransfer(transfer) => {
+            let usdc = parse_amount("usdc", &transfer.usdc)?;
+            if transfer.to_perp {
+                usdc
+            } else {
+                -usdc
+            }
+        }
+        // Spot balances and liquidation markers don't move USDC in or out of the perp account
+        LedgerUpdate::SpotTransfer(_)
+        | LedgerUpdate::SpotGenesis(_)
+        | LedgerUpdate::LedgerLiquidation(_) => 0.0,
+    };
+    Ok(amount)
+}
+
+/// Fetches fills, funding and ledger updates and attributes PnL over an interval
+pub struct PnlAttributor {
+    info_client: InfoClient,
+    config: RiskConfig,
+    last_end_time: Option<u64>,
+}
+
+impl PnlAttributor {
+    /// Creates a new attributor with the provided configuration
+    pub async fn new(config: RiskConfig) -> Result<Self> {
+        let info_client = InfoClient::new(None, Some(config.base_url)).await?;
+
+        Ok(Self {
+            info_client,
+            config,
+            last_end_time: None,
+        })
+    }
+
+    /// Updates the configuration
+    pub fn update_config(&mut self, config: RiskConfig) {
+        if config.wallet_address != self.config.wallet_address {
+            self.last_end_time = None;
+        }
+        self.config = config;
+    }
+
+    /// End of the most recently stored attribution window (ms)
+    pub fn last_end_time(&self) -> Option<u64> {
+        self.last_end_time
+    }
+
+    /// Records the end of the most recently stored attribution window (ms)
+    pub fn set_last_end_time(&mut self, end_time: u64) {
+        self.last_end_time = Some(end_time);
+    }
+
+    /// Attributes PnL between two timestamps (ms). Equity figures are not filled in.
+    pub async fn attribute(&self, start_time: u64, end_time: u64) -> Result<PnlAttribution> {
+        let user = self.config.wallet_address;
+        let fills = fetch_fills_by_time(&self.info_client, user, start_time, end_time).await?;
+        let fundings = self
+            .info_client
+            .user_funding_history(user, start_time, Some(end_time))
+            .await?;
+        let ledger_updates = self
+            .info_client
+            .user_non_funding_ledger_updates(user, start_time, Some(end_time))
+            .await?;
+
+        PnlAttribution::from_events(user, start_time, end_time, &fills, &fundings, &ledger_updates)
+    }
+}
+
+/// Fetches all fills in the interval, paging past the per-request limit
+pub(crate) async fn fetch_fills_by_time(
+    info_client: &InfoClient,
+    user: H160,
+    start_time: u64,
+    end_time: u64,
+) -> Result<Vec<UserFillsResponse>> {
+    let mut fills = Vec::new();
+    let mut seen = HashSet::new();
+    let mut page_start = start_time;
+    loop {
+        let page = info_client
+            .user_fills_by_time(user, page_start, Some(end_time))
+            .await?;
+        match merge_fills_page(&mut fills, &mut seen, page, end_time) {
+            Some(next_start) => page_start = next_start,
+            None => break,
+        }
+    }
+    Ok(fills)
+}
+
+/// Adds the fills of a page that haven't been seen yet, and returns where the next page starts
+/// if this one was full. Pages start at the last fill's millisecond, since more fills from that
+/// millisecond may not have fit.
+fn merge_fills_page(
+    fills: &mut Vec<UserFillsResponse>,
+    seen: &mut HashSet<u64>,
+    page: Vec<UserFillsResponse>,
+    end_time: u64,
+) -> Option<u64> {
+    let page_len = page.len();
+    let last_time = page.iter().map(|f| f.time).max()?;
+    let before = fills.len();
+    fills.extend(page.into_iter().filter(|fill| seen.insert(fill.tid)));
+
+    if page_len < FILLS_PAGE_SIZE || last_time >= end_time {
+        None
+    } else if fills.len() > before {
+        Some(last_time)
+    } else {
+        // A whole page within one millisecond; the rest of it can't be reached
+        Some(last_time + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribution_from_events() -> Result<()> {
+        let user: H160 = "0x0000000000000000000000000000000000000001".parse().unwrap();
+        let fills: Vec<UserFillsResponse> = serde_json::from_value(serde_json::json!([
+            {"closedPnl": "120.5", "coin": "ETH", "crossed": true, "dir": "Close Long", "hash": "0x1",
+             "oid": 1, "px": "3000", "side": "A", "startPosition": "1", "sz": "1", "time": 1500, "fee": "1.5", "tid": 1},
+            {"closedPnl": "-40", "coin": "BTC", "crossed": true, "dir": "Close Short", "hash": "0x2",
+             "oid": 2, "px": "60000", "side": "B", "startPosition": "-0.1", "sz": "0.1", "time": 1600, "fee": "0.5", "tid": 2,
+             "liquidation": {"liquidatedUser": "0x0000000000000000000000000000000000000001",
+                             "markPx": "60000", "method": "market"}},
+            {"closedPnl": "10", "coin": "BTC", "crossed": true, "dir": "Close Short", "hash": "0x7",
+             "oid": 4, "px": "60000", "side": "B", "startPosition": "-0.1", "sz": "0.1", "time": 1650, "fee": "0.5", "tid": 4,
+             "liquidation": {"markPx": "60000", "method": "market"}},
+            {"closedPnl": "999", "coin": "ETH", "crossed": true, "dir": "Close Long", "hash": "0x3",
+             "oid": 3, "px": "3000", "side": "A", "startPosition": "1", "sz": "1", "time": 5000, "fee": "1", "tid": 3}
+        ]))?;
+        let fundings: Vec<UserFundingResponse> = serde_json::from_value(serde_json::json!([
+            {"time": 1700, "hash": "0x4", "delta": {"type": "funding", "coin": "ETH", "usdc": "-2.25",
+             "szi": "1", "fundingRate": "0.0001"}}
+        ]))?;
+        let ledger: Vec<LedgerUpdateData> = serde_json::from_value(serde_json::json!([
+            {"time": 1800, "hash": "0x5", "delta": {"type": "deposit", "usdc": "1000"}},
+            {"time": 1900, "hash": "0x6", "delta": {"type": "withdraw", "usdc": "100", "nonce": 1, "fee": "1"}}
+        ]))?;
+
+        let attribution = PnlAttribution::from_events(user, 1000, 2000, &fills, &fundings, &ledger)?
+            .with_equity(Some((10_000.0, 50.0)), Some((10_960.0, 60.0)));
+
+        // A liquidation fill that doesn't name the liquidated user counts as a regular trade
+        assert_eq!(attribution.trading_pnl, 130.5);
+        assert_eq!(attribution.trading_pnl_by_coin["ETH"], 120.5);
+        assert_eq!(attribution.fees, 2.0);
+        assert_eq!(attribution.liquidation_losses, -40.5);
+        assert_eq!(attribution.funding, -2.25);
+        assert_eq!(attribution.transfers, 899.0);
+        assert_eq!(attribution.unrealized_pnl_change, Some(10.0));
+        assert!((attribution.unexplained.unwrap() - (960.0 - 994.75)).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn full_pages_resume_at_the_last_fill_millisecond() {
+        let fill = |tid: u64, time: u64| -> UserFillsResponse {
+            serde_json::from_value(serde_json::json!({
+                "closedPnl": "0", "coin": "ETH", "crossed": true, "dir": "Open Long", "hash": "0x1",
+                "oid": 1, "px": "3000", "side": "B", "startPosition": "0", "sz": "1", "time": time,
+                "fee": "0", "tid": tid
+            }))
+            .unwrap()
+        };
+        let mut fills = Vec::new();
+        let mut seen = HashSet::new();
+
+        // The last millisecond of a full page may continue on the next one
+        let page = (0..FILLS_PAGE_SIZE as u64).map(|tid| fill(tid, 100 + tid / 1000)).collect();
+        assert_eq!(merge_fills_page(&mut fills, &mut seen, page, 1000), Some(101));
+        let page = (1000..FILLS_PAGE_SIZE as u64 + 5).map(|tid| fill(tid, 101)).collect();
+        assert_eq!(merge_fills_page(&mut fills, &mut seen, page, 1000), None);
+        assert_eq!(fills.len(), FILLS_PAGE_SIZE + 5);
+
+        // A full page with nothing new moves past its millisecond instead of looping
+        let page = (0..FILLS_PAGE_SIZE as u64).map(|tid| fill(tid, 101)).collect();
+        assert_eq!(merge_fills_page(&mut fills, &mut seen, page, 1000), Some(102));
+    }
+}
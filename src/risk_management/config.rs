@@ -5,8 +5,9 @@ use std::fs;
 use std::path::Path;
 use std::str::FromStr;
 
-use crate::helpers::BaseUrl;
+use crate::helpers::{BaseUrl, SigningChain};
 use crate::prelude::*;
+use crate::risk_management::{AttributionConfig, LiquidationForecastConfig, PositionSourceConfig, RiskScoringModel, ScoringModelSetting, VolatilityConfig, VolatilityEstimator};
 use crate::Error;
 
 /// Configuration for the risk management system
@@ -21,6 +22,25 @@ pub struct RiskConfig {
     // Risk thresholds
     pub risk_limits: RiskLimits,
     
+    // Realized volatility estimation
+    pub volatility: VolatilityConfig,
+    
+    // Component weights for position risk scores and portfolio heat
+    pub scoring_model: RiskScoringModel,
+    
+    // Parse API values as exact decimals and fail on malformed numbers
+    pub decimal_mode: bool,
+    
+    // Periodic PnL attribution
+    pub attribution: AttributionConfig,
+    
+    // Liquidation probability forecast
+    pub liquidation_forecast: LiquidationForecastConfig,
+    
+    // Account state source, and an optional file to record fetched states to
+    pub position_source: PositionSourceConfig,
+    pub record_positions_path: Option<String>,
+    
     // Logging configuration
     pub enable_logging: bool,
     pub log_to_console: bool,
@@ -38,6 +58,24 @@ pub struct RiskLimits {
     pub min_distance_to_liq: f64,
     pub max_correlation: f64,
     pub max_margin_utilization: f64,
+    #[serde(default = "default_min_liq_distance_sigma")]
+    pub min_liq_distance_sigma: f64,
+    #[serde(default = "default_max_net_beta_exposure_usd")]
+    pub max_net_beta_exposure_usd: f64,
+    #[serde(default = "default_max_liquidation_probability")]
+    pub max_liquidation_probability: f64,
+}
+
+fn default_min_liq_distance_sigma() -> f64 {
+    3.0
+}
+
+fn default_max_net_beta_exposure_usd() -> f64 {
+    250000.0
+}
+
+fn default_max_liquidation_probability() -> f64 {
+    0.05
 }
 
 /// User settings that can be modified through the dashboard
@@ -45,6 +83,8 @@ pub struct RiskLimits {
 pub struct UserSettings {
     pub wallet_address: String,
     pub risk_limits: RiskLimits,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scoring_model: Option<ScoringModelSetting>,
 }
 
 impl Default for RiskLimits {
@@ -57,6 +97,9 @@ impl Default for RiskLimits {
             min_distance_to_liq: 10.0,
             max_correlation: 0.7,
             max_margin_utilization: 80.0,
+            min_liq_distance_sigma: default_min_liq_distance_sigma(),
+            max_net_beta_exposure_usd: default_max_net_beta_exposure_usd(),
+            max_liquidation_probability: default_max_liquidation_probability(),
         }
     }
 }
@@ -79,15 +122,22 @@ impl RiskConfig {
             .map_err(|_| Error::Custom("Invalid WALLET_ADDRESS format".to_string()))?;
         
         // Optional values with defaults
-        let base_url_str = env::var("API_URL").unwrap_or_else(|_| "https://api.hyperliquid.xyz".to_string());
+        let base_url_str = env::var("API_URL").unwrap_or_else(|_| "mainnet".to_string());
         
-        // Parse the base URL string to determine which endpoint to use
-        let base_url = if base_url_str.contains("localhost") {
-            BaseUrl::Localhost
-        } else if base_url_str.contains("testnet") {
-            BaseUrl::Testnet
-        } else {
-            BaseUrl::Mainnet
+        // A network profile or its URL. Any other URL is a custom endpoint (proxy, private node, mock
+        // server), which must name the chain its actions are signed for.
+        let base_url = match BaseUrl::from_str(&base_url_str) {
+            Ok(base_url) => base_url,
+            Err(_) if base_url_str.starts_with("http://") || base_url_str.starts_with("https://") => {
+                let chain = env::var("API_CHAIN")
+                    .map_err(|_| Error::Custom("API_CHAIN must be set to mainnet or testnet for a custom API_URL".to_string()))?
+                    .parse::<SigningChain>()?;
+                match env::var("API_WS_URL") {
+                    Ok(ws_url) => BaseUrl::custom_with_ws(&base_url_str, &ws_url, chain),
+                    Err(_) => BaseUrl::custom(&base_url_str, chain),
+                }
+            }
+            Err(err) => return Err(err),
         };
         
         let database_url = env::var("SUPABASE_URL").ok();
@@ -105,11 +155,24 @@ impl RiskConfig {
             .map(|v| v.to_lowercase() == "true")
             .unwrap_or(false);
         
+        let decimal_mode = env::var("DECIMAL_MODE")
+            .map(|v| v.to_lowercase() == "true")
+            .unwrap_or(false);
+        
         let log_interval_seconds = env::var("LOG_INTERVAL_SECONDS")
             .ok()
             .and_then(|s| s.parse::<u64>().ok())
             .unwrap_or(60);
         
+        // Scoring model - first try user settings, then the RISK_SCORING_PRESET environment variable
+        let scoring_model = match user_settings.as_ref().and_then(|s| s.scoring_model.as_ref()) {
+            Some(setting) => setting.resolve()?,
+            None => match env::var("RISK_SCORING_PRESET") {
+                Ok(name) => RiskScoringModel::preset(&name)?,
+                Err(_) => RiskScoringModel::default(),
+            },
+        };
+        
         // Risk limits - first try user settings, then environment variables
         let risk_limits = if let Some(settings) = user_settings {
             settings.risk_limits
@@ -117,12 +180,58 @@ impl RiskConfig {
             Self::risk_limits_from_env()
         };
         
+        let volatility = Self::volatility_from_env();
+        
+        let mut attribution = AttributionConfig::default();
+        if let Ok(val) = env::var("PNL_ATTRIBUTION_ENABLED") {
+            attribution.enabled = val.to_lowercase() == "true";
+        }
+        if let Ok(val) = env::var("PNL_ATTRIBUTION_INTERVAL_SECONDS") {
+            if let Ok(num) = val.parse::<u64>() {
+                attribution.interval_seconds = num;
+            }
+        }
+        
+        let mut liquidation_forecast = LiquidationForecastConfig::default();
+        if let Ok(val) = env::var("LIQ_FORECAST_ENABLED") {
+            liquidation_forecast.enabled = val.to_lowercase() == "true";
+        }
+        if let Ok(val) = env::var("LIQ_FORECAST_SIMULATIONS") {
+            if let Ok(num) = val.parse::<usize>() {
+                liquidation_forecast.simulations = num;
+            }
+        }
+        if let Ok(val) = env::var("LIQ_FORECAST_SEED") {
+            if let Ok(num) = val.parse::<u64>() {
+                liquidation_forecast.seed = num;
+            }
+        }
+        
+        let position_source = match env::var("POSITION_SOURCE").map(|v| v.to_lowercase()) {
+            Ok(source) if source == "replay" => PositionSourceConfig::Replay {
+                path: env::var("POSITION_REPLAY_PATH")
+                    .map_err(|_| Error::Custom("POSITION_REPLAY_PATH must be set when POSITION_SOURCE=replay".to_string()))?,
+            },
+            Ok(source) if source != "live" => {
+                return Err(Error::Custom(format!("Unknown POSITION_SOURCE: {}. Valid sources are: live, replay", source)));
+            }
+            _ => PositionSourceConfig::Live,
+        };
+        let record_positions_path = env::var("POSITION_RECORD_PATH").ok();
+        
         Ok(Self {
             wallet_address,
             base_url,
             database_url,
             database_key,
             risk_limits,
+            volatility,
+            scoring_model,
+            decimal_mode,
+            attribution,
+            liquidation_forecast,
+            position_source,
+            record_positions_path,
             enable_logging,
             log_to_console,
             log_to_database,
@@ -176,9 +285,71 @@ impl RiskConfig {
             }
         }
         
+        if let Ok(val) = env::var("MIN_LIQ_DISTANCE_SIGMA") {
+            if let Ok(num) = val.parse::<f64>() {
+                limits.min_liq_distance_sigma = num;
+            }
+        }
+        
+        if let Ok(val) = env::var("MAX_NET_BETA_EXPOSURE_USD") {
+            if let Ok(num) = val.parse::<f64>() {
+                limits.max_net_beta_exposure_usd = num;
+            }
+        }
+        
+        if let Ok(val) = env::var("MAX_LIQUIDATION_PROBABILITY") {
+            if let Ok(num) = val.parse::<f64>() {
+                limits.max_liquidation_probability = num;
+            }
+        }
+        
         limits
     }
     
+    /// Loads volatility estimation settings from environment variables or uses defaults
+    fn volatility_from_env() -> VolatilityConfig {
+        let mut volatility = VolatilityConfig::default();
+        
+        if let Ok(val) = env::var("VOLATILITY_ENABLED") {
+            volatility.enabled = val.to_lowercase() == "true";
+        }
+        
+        if let Ok(val) = env::var("VOLATILITY_ESTIMATOR") {
+            match val.parse::<VolatilityEstimator>() {
+                Ok(estimator) => volatility.estimator = estimator,
+                Err(e) => eprintln!("Warning: {}", e),
+            }
+        }
+        
+        if let Ok(val) = env::var("VOLATILITY_CANDLE_INTERVAL") {
+            volatility.candle_interval = val;
+        }
+        
+        if let Ok(val) = env::var("VOLATILITY_LOOKBACK_HOURS") {
+            if let Ok(num) = val.parse::<u64>() {
+                volatility.lookback_hours = num;
+            }
+        }
+        
+        if let Ok(val) = env::var("VOLATILITY_EWMA_LAMBDA") {
+            if let Ok(num) = val.parse::<f64>() {
+                volatility.ewma_lambda = num;
+            }
+        }
+        
+        if let Ok(val) = env::var("VOLATILITY_REFRESH_SECONDS") {
+            if let Ok(num) = val.parse::<u64>() {
+                volatility.refresh_interval_seconds = num;
+            }
+        }
+        
+        if let Ok(val) = env::var("VOLATILITY_SCORING") {
+            volatility.use_for_scoring = val.to_lowercase() == "true";
+        }
+        
+        volatility
+    }
+    
     /// Loads user settings from a JSON file
     fn load_user_settings() -> Option<UserSettings> {
         let settings_path = Path::new("user_settings.json");
@@ -227,6 +398,15 @@ impl RiskConfig {
         UserSettings {
             wallet_address: format!("0x{:x}", self.wallet_address),
             risk_limits: self.risk_limits.clone(),
+            scoring_model: Some(self.scoring_model_setting()),
+        }
+    }
+    
+    /// Describes the scoring model by preset name when it matches one, by its weights otherwise
+    fn scoring_model_setting(&self) -> ScoringModelSetting {
+        match RiskScoringModel::preset(&self.scoring_model.name) {
+            Ok(preset) if preset == self.scoring_model => ScoringModelSetting::Preset(preset.name),
+            _ => ScoringModelSetting::Custom(self.scoring_model.clone()),
         }
     }
     
@@ -236,13 +416,21 @@ impl RiskConfig {
         let wallet_address = H160::from_str(&settings.wallet_address)
             .map_err(|_| Error::Custom("Invalid wallet address format".to_string()))?;
         
+        // Leave the scoring model unchanged when the settings don't mention one
+        let scoring_model = match &settings.scoring_model {
+            Some(setting) => setting.resolve()?,
+            None => self.scoring_model.clone(),
+        };
+        
         self.wallet_address = wallet_address;
         self.risk_limits = settings.risk_limits.clone();
+        self.scoring_model = scoring_model;
         
         // Save the settings to file
         Self::save_user_settings(&UserSettings {
             wallet_address: settings.wallet_address,
             risk_limits: self.risk_limits.clone(),
+            scoring_model: Some(self.scoring_model_setting()),
         })?;
         
         Ok(())
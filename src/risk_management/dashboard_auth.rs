@@ -0,0 +1,488 @@
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::prelude::*;
+use crate::Error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Cookie carrying the signed dashboard session
+pub const SESSION_COOKIE_NAME: &str = "hl_dashboard_session";
+
+/// Header that must echo the session's CSRF token on state-changing requests
+pub const CSRF_HEADER_NAME: &str = "X-CSRF-Token";
+
+const PASSWORD_HASH_SCHEME: &str = "pbkdf2-sha256";
+const PASSWORD_HASH_ITERATIONS: u32 = 100_000;
+const DEFAULT_SESSION_TTL_SECONDS: u64 = 12 * 60 * 60;
+const MIN_SESSION_SECRET_LEN: usize = 32;
+
+/// What an authenticated dashboard client may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DashboardRole {
+    /// Read analyses, history and settings
+    ReadOnly,
+    /// Everything, including changing settings
+    Admin,
+}
+
+impl DashboardRole {
+    /// Whether this role includes the permissions of `required`
+    pub fn allows(self, required: DashboardRole) -> bool {
+        self >= required
+    }
+}
+
+impl FromStr for DashboardRole {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "read_only" | "readonly" | "read" | "viewer" => Ok(DashboardRole::ReadOnly),
+            "admin" => Ok(DashboardRole::Admin),
+            _ => Err(Error::Custom(format!(
+                "Unknown dashboard role: {}. Valid roles are: read_only, admin",
+                s
+            ))),
+        }
+    }
+}
+
+/// How a request proved who it is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    Token,
+    Basic,
+    Session,
+}
+
+/// An authenticated dashboard client
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DashboardIdentity {
+    pub name: String,
+    pub role: DashboardRole,
+    pub method: AuthMethod,
+    /// CSRF token bound to the session; only cookie sessions carry one
+    pub csrf_token: Option<String>,
+    #[serde(skip)]
+    session_id: Option<String>,
+    #[serde(skip)]
+    expires_at: u64,
+}
+
+impl DashboardIdentity {
+    /// Cookies are sent by the browser on their own, so only session requests need a CSRF token
+    pub fn requires_csrf(&self) -> bool {
+        self.method == AuthMethod::Session
+    }
+}
+
+/// A static API token, stored as a SHA-256 hash
+#[derive(Debug, Clone)]
+pub struct ApiToken {
+    pub name: String,
+    pub role: DashboardRole,
+    token_hash: [u8; 32],
+}
+
+impl ApiToken {
+    pub fn new(name: &str, role: DashboardRole, token: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            role,
+            token_hash: Sha256::digest(token.as_bytes()).into(),
+        }
+    }
+}
+
+/// A user for HTTP basic auth and the login endpoint
+#[derive(Debug, Clone)]
+pub struct DashboardUser {
+    pub username: String,
+    pub role: DashboardRole,
+    /// Output of `hash_password`
+    pub password_hash: String,
+}
+
+/// Dashboard API credentials and session settings
+#[derive(Debug, Clone)]
+pub struct DashboardAuthConfig {
+    pub api_tokens: Vec<ApiToken>,
+    pub users: Vec<DashboardUser>,
+    /// Key for signing session cookies; random per process when not configured, so sessions end on restart
+    pub session_secret: Vec<u8>,
+    pub session_ttl_seconds: u64,
+    /// Only send the session cookie over HTTPS
+    pub secure_cookies: bool,
+}
+
+impl Default for DashboardAuthConfig {
+    fn default() -> Self {
+        let mut session_secret = vec![0u8; MIN_SESSION_SECRET_LEN];
+        thread_rng().fill_bytes(&mut session_secret);
+        Self {
+            api_tokens: Vec::new(),
+            users: Vec::new(),
+            session_secret,
+            session_ttl_seconds: DEFAULT_SESSION_TTL_SECONDS,
+            secure_cookies: false,
+        }
+    }
+}
+
+impl DashboardAuthConfig {
+    /// Loads credentials from environment variables. Authentication stays off unless tokens or users are set.
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+
+        // name:role:token, comma separated
+        if let Ok(val) = env::var("DASHBOARD_API_TOKENS") {
+            for entry in val.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                let parts: Vec<&str> = entry.splitn(3, ':').collect();
+                if parts.len() != 3 || parts[2].is_empty() {
+                    return Err(Error::Custom(
+                        "DASHBOARD_API_TOKENS entries must look like name:role:token".to_string(),
+                    ));
+                }
+                config.api_tokens.push(ApiToken::new(parts[0], parts[1].parse()?, parts[2]));
+            }
+        }
+
+        // username:role:password_hash, comma separated
+        if let Ok(val) = env::var("DASHBOARD_USERS") {
+            for entry in val.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                let parts: Vec<&str> = entry.splitn(3, ':').collect();
+                if parts.len() != 3 || !parts[2].starts_with(PASSWORD_HASH_SCHEME) {
+                    return Err(Error::Custom(format!(
+                        "DASHBOARD_USERS entries must look like username:role:{}$...",
+                        PASSWORD_HASH_SCHEME
+                    )));
+                }
+                config.users.push(DashboardUser {
+                    username: parts[0].to_string(),
+                    role: parts[1].parse()?,
+                    password_hash: parts[2].to_string(),
+                });
+            }
+        }
+
+        if let Ok(secret) = env::var("DASHBOARD_SESSION_SECRET") {
+            if secret.len() < MIN_SESSION_SECRET_LEN {
+                return Err(Error::Custom(format!(
+                    "DASHBOARD_SESSION_SECRET must be at least {} characters",
+                    MIN_SESSION_SECRET_LEN
+                )));
+            }
+            config.session_secret = secret.into_bytes();
+        }
+
+        if let Ok(val) = env::var("DASHBOARD_SESSION_TTL_SECONDS") {
+            if let Ok(num) = val.parse::<u64>() {
+                config.session_ttl_seconds = num;
+            }
+        }
+
+        config.secure_cookies = env::var("DASHBOARD_SECURE_COOKIES")
+            .map(|v| v.to_lowercase() == "true")
+            .unwrap_or(false);
+
+        Ok(config)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.api_tokens.is_empty() || !self.users.is_empty()
+    }
+}
+
+/// A session issued by a successful login
+#[derive(Debug, Clone)]
+pub struct DashboardSession {
+    pub identity: DashboardIdentity,
+    /// Value for the `SESSION_COOKIE_NAME` cookie
+    pub cookie_value: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SessionClaims {
+    sid: String,
+    user: String,
+    role: DashboardRole,
+    exp: u64,
+    csrf: String,
+}
+
+/// Authenticates dashboard API requests with static tokens, HTTP basic or signed session cookies
+#[derive(Debug)]
+pub struct DashboardAuth {
+    config: DashboardAuthConfig,
+    // Logged out session ids and when they would have expired
+    revoked_sessions: Mutex<HashMap<String, u64>>,
+}
+
+impl DashboardAuth {
+    pub fn new(config: DashboardAuthConfig) -> Self {
+        Self {
+            config,
+            revoked_sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &DashboardAuthConfig {
+        &self.config
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_enabled()
+    }
+
+    /// Authenticates an `Authorization` header holding a bearer token or basic credentials
+    pub fn authenticate_header(&self, header: &str) -> Option<DashboardIdentity> {
+        let (scheme, credentials) = header.trim().split_once(' ')?;
+        match scheme.to_lowercase().as_str() {
+            "bearer" => self.authenticate_token(credentials.trim()),
+            "basic" => {
+                let decoded = BASE64.decode(credentials.trim()).ok()?;
+                let decoded = String::from_utf8(decoded).ok()?;
+                let (username, password) = decoded.split_once(':')?;
+                let user = self.verify_user(username, password)?;
+                Some(DashboardIdentity {
+                    name: user.username.clone(),
+                    role: user.role,
+                    method: AuthMethod::Basic,
+                    csrf_token: None,
+                    session_id: None,
+                    expires_at: 0,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn authenticate_token(&self, token: &str) -> Option<DashboardIdentity> {
+        let token_hash: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+        self.config
+            .api_tokens
+            .iter()
+            .find(|t| constant_time_eq(&t.token_hash, &token_hash))
+            .map(|t| DashboardIdentity {
+                name: t.name.clone(),
+                role: t.role,
+                method: AuthMethod::Token,
+                csrf_token: None,
+                session_id: None,
+                expires_at: 0,
+            })
+    }
+
+    fn verify_user(&self, username: &str, password: &str) -> Option<&DashboardUser> {
+        match self.config.users.iter().find(|u| u.username == username) {
+            Some(user) if verify_password(password, &user.password_hash) => Some(user),
+            Some(_) => None,
+            None => {
+                // Spend the same time as a real check so usernames can't be probed by timing
+                let _ = verify_password(password, DUMMY_PASSWORD_HASH.as_str());
+                None
+            }
+        }
+    }
+
+    /// Checks a username and password and issues a signed session for them
+    pub fn login(&self, username: &str, password: &str) -> Option<DashboardSession> {
+        let user = self.verify_user(username, password)?;
+        let claims = SessionClaims {
+            sid: random_hex(16),
+            user: user.username.clone(),
+            role: user.role,
+            exp: now_seconds() + self.config.session_ttl_seconds,
+            csrf: random_hex(32),
+        };
+        let payload = hex::encode(serde_json::to_vec(&claims).ok()?);
+        let cookie_value = format!("{}.{}", payload, hex::encode(self.sign(payload.as_bytes())));
+        Some(DashboardSession {
+            identity: claims.into_identity(),
+            cookie_value,
+        })
+    }
+
+    /// Verifies a session cookie's signature, expiry and that it hasn't been logged out
+    pub fn authenticate_session(&self, cookie_value: &str) -> Option<DashboardIdentity> {
+        let (payload, signature) = cookie_value.split_once('.')?;
+        let mut mac = HmacSha256::new_from_slice(&self.config.session_secret).ok()?;
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&hex::decode(signature).ok()?).ok()?;
+
+        let claims: SessionClaims = serde_json::from_slice(&hex::decode(payload).ok()?).ok()?;
+        if claims.exp <= now_seconds() || self.revoked_sessions.lock().unwrap().contains_key(&claims.sid) {
+            return None;
+        }
+        Some(claims.into_identity())
+    }
+
+    /// Ends a session before it expires
+    pub fn logout(&self, identity: &DashboardIdentity) {
+        if let Some(session_id) = &identity.session_id {
+            let now = now_seconds();
+            let mut revoked = self.revoked_sessions.lock().unwrap();
+            revoked.retain(|_, expires_at| *expires_at > now);
+            revoked.insert(session_id.clone(), identity.expires_at);
+        }
+    }
+
+    /// Checks the CSRF header of a state-changing request; only session requests need one
+    pub fn verify_csrf(&self, identity: &DashboardIdentity, header: Option<&str>) -> bool {
+        match (&identity.csrf_token, header) {
+            _ if !identity.requires_csrf() => true,
+            (Some(expected), Some(provided)) => constant_time_eq(expected.as_bytes(), provided.as_bytes()),
+            _ => false,
+        }
+    }
+
+    fn sign(&self, payload: &[u8]) -> Vec<u8> {
+        let mut mac = HmacSha256::new_from_slice(&self.config.session_secret)
+            .expect("HMAC accepts keys of any length");
+        mac.update(payload);
+        mac.finalize().into_bytes().to_vec()
+    }
+}
+
+impl SessionClaims {
+    fn into_identity(self) -> DashboardIdentity {
+        DashboardIdentity {
+            name: self.user,
+            role: self.role,
+            method: AuthMethod::Session,
+            csrf_token: Some(self.csrf),
+            session_id: Some(self.sid),
+            expires_at: self.exp,
+        }
+    }
+}
+
+lazy_static! {
+    static ref DUMMY_PASSWORD_HASH: String = hash_password("dummy password");
+}
+
+/// Hashes a password for `DASHBOARD_USERS` as `pbkdf2-sha256$<iterations>$<salt>$<hash>`
+pub fn hash_password(password: &str) -> String {
+    let mut salt = [0u8; 16];
+    thread_rng().fill_bytes(&mut salt);
+    let mut hash = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), &salt, PASSWORD_HASH_ITERATIONS, &mut hash);
+    format!(
+        "{}${}${}${}",
+        PASSWORD_HASH_SCHEME,
+        PASSWORD_HASH_ITERATIONS,
+        hex::encode(salt),
+        hex::encode(hash)
+    )
+}
+
+/// Checks a password against the output of `hash_password`
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    let parts: Vec<&str> = password_hash.split('$').collect();
+    let [scheme, iterations, salt, expected] = parts.as_slice() else {
+        return false;
+    };
+    let (Ok(iterations), Ok(salt), Ok(expected)) =
+        (iterations.parse::<u32>(), hex::decode(salt), hex::decode(expected))
+    else {
+        return false;
+    };
+    if *scheme != PASSWORD_HASH_SCHEME || expected.is_empty() {
+        return false;
+    }
+    let mut hash = vec![0u8; expected.len()];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), &salt, iterations, &mut hash);
+    constant_time_eq(&hash, &expected)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+fn now_seconds() -> u64 {
+    chrono::Utc::now().timestamp().max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth() -> DashboardAuth {
+        DashboardAuth::new(DashboardAuthConfig {
+            api_tokens: vec![ApiToken::new("ci", DashboardRole::ReadOnly, "secret-token")],
+            users: vec![DashboardUser {
+                username: "ops".to_string(),
+                role: DashboardRole::Admin,
+                password_hash: hash_password("hunter2"),
+            }],
+            ..DashboardAuthConfig::default()
+        })
+    }
+
+    #[test]
+    fn tokens_basic_auth_and_sessions() {
+        let auth = auth();
+        assert!(auth.is_enabled());
+
+        let token = auth.authenticate_header("Bearer secret-token").unwrap();
+        assert_eq!((token.role, token.method), (DashboardRole::ReadOnly, AuthMethod::Token));
+        assert!(!token.role.allows(DashboardRole::Admin));
+        assert!(auth.authenticate_header("Bearer wrong").is_none());
+
+        let basic = auth
+            .authenticate_header(&format!("Basic {}", BASE64.encode("ops:hunter2")))
+            .unwrap();
+        assert_eq!((basic.role, basic.method), (DashboardRole::Admin, AuthMethod::Basic));
+        assert!(auth.verify_csrf(&basic, None));
+        assert!(auth
+            .authenticate_header(&format!("Basic {}", BASE64.encode("ops:wrong")))
+            .is_none());
+
+        assert!(auth.login("ops", "wrong").is_none());
+        let session = auth.login("ops", "hunter2").unwrap();
+        let identity = auth.authenticate_session(&session.cookie_value).unwrap();
+        assert_eq!(identity, session.identity);
+        assert!(identity.requires_csrf());
+        assert!(auth.verify_csrf(&identity, identity.csrf_token.as_deref()));
+        assert!(!auth.verify_csrf(&identity, Some("forged")));
+        assert!(!auth.verify_csrf(&identity, None));
+
+        // Tampered payloads fail the signature check
+        let (payload, signature) = session.cookie_value.split_once('.').unwrap();
+        let mut claims: SessionClaims = serde_json::from_slice(&hex::decode(payload).unwrap()).unwrap();
+        claims.user = "someone-else".to_string();
+        let forged = format!("{}.{}", hex::encode(serde_json::to_vec(&claims).unwrap()), signature);
+        assert!(auth.authenticate_session(&forged).is_none());
+
+        auth.logout(&identity);
+        assert!(auth.authenticate_session(&session.cookie_value).is_none());
+    }
+
+    #[test]
+    fn password_hashes_round_trip() {
+        let hash = hash_password("correct horse");
+        assert!(hash.starts_with("pbkdf2-sha256$"));
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("battery staple", &hash));
+        assert!(!verify_password("correct horse", "plaintext"));
+        assert!(!DashboardAuth::new(DashboardAuthConfig::default()).is_enabled());
+    }
+}
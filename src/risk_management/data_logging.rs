@@ -9,13 +9,17 @@ use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, AUTHORIZATION};
 use crate::prelude::*;
 use crate::Error;
 use crate::risk_management::{
-    LogEntry, Position, PortfolioMetrics, PositionMetrics, RiskConfig, RiskWarning
+    HistoryExporter, LogEntry, PnlAttribution, Position, PortfolioMetrics, PositionMetrics, RiskConfig, RiskWarning
 };
 
+// Encoded export output is handed on once this much has accumulated
+const EXPORT_CHUNK_BYTES: usize = 64 * 1024;
+
 /// Data logging system for storing position and risk metric data
 pub struct DataLogger {
     config: RiskConfig,
     log_file_path: Option<String>,
+    attribution_file_path: Option<String>,
     http_client: Option<Client>,
 }
 
@@ -27,6 +31,11 @@ impl DataLogger {
         } else {
             None
         };
+        let attribution_file_path = if config.enable_logging {
+            Some("hyperliquid_pnl_attribution.jsonl".to_string())
+        } else {
+            None
+        };
         
         // Initialize HTTP client for Supabase if needed
         let http_client = if config.log_to_database && config.database_url.is_some() && config.database_key.is_some() {
@@ -39,6 +48,7 @@ impl DataLogger {
         Self {
             config,
             log_file_path,
+            attribution_file_path,
             http_client,
         }
     }
@@ -90,7 +100,7 @@ impl DataLogger {
     fn log_to_console(&self, log_entry: &LogEntry) -> Result<()> {
         // Format timestamp
         let datetime: DateTime<Utc> = DateTime::from_timestamp(log_entry.timestamp as i64, 0)
-            .unwrap_or_else(|| Utc::now());
+            .unwrap_or_else(Utc::now);
         
         println!("========== HYPERLIQUID RISK REPORT ==========");
         println!("Time: {}", datetime.format("%Y-%m-%d %H:%M:%S UTC"));
@@ -105,6 +115,9 @@ impl DataLogger {
         println!("  Account Value:        ${:.2}", log_entry.portfolio_metrics.account_value);
         println!("  Unrealized PnL:       ${:.2}", log_entry.portfolio_metrics.total_unrealized_pnl);
         println!("  Average Leverage:     {:.2}x", log_entry.portfolio_metrics.average_leverage);
+        println!("  Net Delta (per 1%):   ${:.2}", log_entry.portfolio_metrics.net_dollar_delta);
+        println!("  Gross Delta (per 1%): ${:.2}", log_entry.portfolio_metrics.gross_dollar_delta);
+        println!("  BTC-Equiv Exposure:   ${:.2}", log_entry.portfolio_metrics.btc_equivalent_exposure);
         println!();
         
         // Position details
@@ -151,7 +164,6 @@ impl DataLogger {
         let _file_exists = path.exists();
         
         let mut file = OpenOptions::new()
-            .write(true)
             .create(true)
             .append(true)
             .open(path)
@@ -362,7 +374,7 @@ impl DataLogger {
             }
             
             // Sort by timestamp (newest first)
-            log_entries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+            log_entries.sort_by_key(|entry| std::cmp::Reverse(entry.timestamp));
             
             // Limit the number of entries
             if log_entries.len() > limit {
@@ -373,6 +385,45 @@ impl DataLogger {
         Ok(log_entries)
     }
     
+    /// Streams logged entries with timestamps in `[start, end]` (unix seconds, either bound optional)
+    /// through the exporter in file order, handing encoded output to `sink` in chunks of about
+    /// `EXPORT_CHUNK_BYTES`. The log is read line by line, so ranges of any size use constant memory.
+    /// Returns the number of entries exported.
+    pub fn export_history(
+        &self,
+        exporter: &mut HistoryExporter,
+        start: Option<u64>,
+        end: Option<u64>,
+        mut sink: impl FnMut(Vec<u8>) -> Result<()>,
+    ) -> Result<usize> {
+        let mut buffer = exporter.start();
+        let mut exported = 0;
+        
+        if let Some(path) = self.log_file_path.as_ref().filter(|path| Path::new(path).exists()) {
+            let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+            for line in std::io::BufRead::lines(reader) {
+                let line = line?;
+                let entry = match serde_json::from_str::<LogEntry>(&line) {
+                    Ok(entry) => entry,
+                    Err(_) => continue,
+                };
+                if start.is_some_and(|start| entry.timestamp < start) || end.is_some_and(|end| entry.timestamp > end) {
+                    continue;
+                }
+                
+                buffer.extend_from_slice(&exporter.push(&entry)?);
+                exported += 1;
+                if buffer.len() >= EXPORT_CHUNK_BYTES {
+                    sink(std::mem::take(&mut buffer))?;
+                }
+            }
+        }
+        
+        buffer.extend_from_slice(&exporter.finish()?);
+        sink(buffer)?;
+        Ok(exported)
+    }
+    
     /// Retrieves time series data for a specific metric
     /// Returns a vector of (timestamp, value) pairs sorted by timestamp
     pub fn get_time_series_data(&self, metric_name: &str, limit: usize) -> Result<Vec<(u64, f64)>> {
@@ -389,6 +440,12 @@ impl DataLogger {
                 "account_value" => Some(entry.portfolio_metrics.account_value),
                 "total_position_value" => Some(entry.portfolio_metrics.total_position_value),
                 "average_leverage" => Some(entry.portfolio_metrics.average_leverage),
+                "net_dollar_delta" => Some(entry.portfolio_metrics.net_dollar_delta),
+                "gross_dollar_delta" => Some(entry.portfolio_metrics.gross_dollar_delta),
+                "btc_equivalent_exposure" => Some(entry.portfolio_metrics.btc_equivalent_exposure),
+                "cross_liquidation_probability_1h" => entry.portfolio_metrics.cross_liquidation_forecast.as_ref().map(|f| f.one_hour),
+                "cross_liquidation_probability_8h" => entry.portfolio_metrics.cross_liquidation_forecast.as_ref().map(|f| f.eight_hours),
+                "cross_liquidation_probability_24h" => entry.portfolio_metrics.cross_liquidation_forecast.as_ref().map(|f| f.twenty_four_hours),
                 _ => None,
             };
             
@@ -398,7 +455,7 @@ impl DataLogger {
         }
         
         // Sort by timestamp (oldest first for time series)
-        time_series.sort_by(|a, b| a.0.cmp(&b.0));
+        time_series.sort_by_key(|point| point.0);
         
         Ok(time_series)
     }
@@ -423,6 +480,14 @@ impl DataLogger {
                     "position_size_ratio" => Some(position_metric.position_size_ratio),
                     "risk_score" => Some(position_metric.risk_score),
                     "contribution_to_portfolio" => Some(position_metric.contribution_to_portfolio),
+                    "daily_volatility" => position_metric.daily_volatility,
+                    "liquidation_distance_sigma" => position_metric.liquidation_distance_sigma,
+                    "liquidation_probability_24h" => position_metric.liquidation_probability_24h,
+                    "liquidation_probability_1h" => position_metric.liquidation_forecast.as_ref().map(|f| f.one_hour),
+                    "liquidation_probability_8h" => position_metric.liquidation_forecast.as_ref().map(|f| f.eight_hours),
+                    "dollar_delta" => Some(position_metric.dollar_delta),
+                    "beta_to_btc" => position_metric.beta_to_btc,
+                    "btc_equivalent_exposure" => Some(position_metric.btc_equivalent_exposure),
                     _ => None,
                 };
                 
@@ -433,11 +498,91 @@ impl DataLogger {
         }
         
         // Sort by timestamp (oldest first for time series)
-        time_series.sort_by(|a, b| a.0.cmp(&b.0));
+        time_series.sort_by_key(|point| point.0);
         
         Ok(time_series)
     }
     
+    /// Returns `(account_value, total_unrealized_pnl)` from the logged entry closest to `timestamp` (seconds),
+    /// if one was logged within two logging intervals of it
+    pub fn equity_at(&self, timestamp: u64) -> Result<Option<(f64, f64)>> {
+        Ok(self.equity_near(&[timestamp])?[0])
+    }
+    
+    /// `equity_at` for several timestamps, reading the log line by line in a single pass
+    pub fn equity_near(&self, timestamps: &[u64]) -> Result<Vec<Option<(f64, f64)>>> {
+        let tolerance = (self.config.log_interval_seconds * 2).max(60);
+        // Closest entry so far for each timestamp, with its distance
+        let mut closest: Vec<Option<(u64, (f64, f64))>> = vec![None; timestamps.len()];
+        
+        if let Some(path) = self.log_file_path.as_ref().filter(|path| Path::new(path).exists()) {
+            let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+            for line in std::io::BufRead::lines(reader) {
+                let line = line?;
+                let entry = match serde_json::from_str::<LogEntry>(&line) {
+                    Ok(entry) => entry,
+                    Err(_) => continue,
+                };
+                for (timestamp, closest) in timestamps.iter().zip(closest.iter_mut()) {
+                    let distance = entry.timestamp.abs_diff(*timestamp);
+                    if distance <= tolerance && closest.is_none_or(|(best, _)| distance < best) {
+                        let metrics = &entry.portfolio_metrics;
+                        *closest = Some((distance, (metrics.account_value, metrics.total_unrealized_pnl)));
+                    }
+                }
+            }
+        }
+        
+        Ok(closest.into_iter().map(|closest| closest.map(|(_, equity)| equity)).collect())
+    }
+    
+    /// Appends a PnL attribution to the attribution history file
+    pub fn log_pnl_attribution(&self, attribution: &PnlAttribution) -> Result<()> {
+        let Some(ref path) = self.attribution_file_path else {
+            return Ok(());
+        };
+        
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| Error::Custom(format!("Failed to open attribution file: {}", e)))?;
+        
+        let json_line = serde_json::to_string(attribution)
+            .map_err(|e| Error::Custom(format!("Failed to serialize attribution: {}", e)))?;
+        
+        writeln!(file, "{}", json_line)
+            .map_err(|e| Error::Custom(format!("Failed to write to attribution file: {}", e)))?;
+        
+        Ok(())
+    }
+    
+    /// Retrieves stored PnL attributions, newest first
+    pub fn get_pnl_attribution_history(&self, limit: usize) -> Result<Vec<PnlAttribution>> {
+        let mut attributions = Vec::new();
+        
+        if let Some(ref path) = self.attribution_file_path {
+            if !Path::new(path).exists() {
+                return Ok(vec![]);
+            }
+            
+            let file = std::fs::File::open(path)?;
+            let reader = std::io::BufReader::new(file);
+            
+            for line in std::io::BufRead::lines(reader) {
+                let line = line?;
+                if let Ok(attribution) = serde_json::from_str::<PnlAttribution>(&line) {
+                    attributions.push(attribution);
+                }
+            }
+            
+            attributions.sort_by_key(|a| std::cmp::Reverse(a.end_time));
+            attributions.truncate(limit);
+        }
+        
+        Ok(attributions)
+    }
+    
     /// Logs risk analysis data
     pub async fn log_risk_data(&self, analysis: &crate::risk_management::RiskAnalysisResult) -> Result<()> {
         if !self.config.enable_logging {
@@ -0,0 +1,413 @@
+use std::collections::{HashMap, HashSet};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::prelude::*;
+use crate::risk_management::{
+    PortfolioMetrics, Position, RiskAnalysisResult, RiskSeverity, RiskWarning, RiskWarningType,
+};
+use crate::Error;
+
+/// Number of events buffered per subscriber before the slowest one starts missing events
+const EVENT_BUS_CAPACITY: usize = 256;
+
+/// Event emitted by the risk management system as state changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum RiskEvent {
+    PositionOpened {
+        position: Position,
+    },
+    PositionClosed {
+        /// Last seen state of the closed position
+        position: Position,
+    },
+    PositionResized {
+        previous_size: f64,
+        position: Position,
+    },
+    /// A warning appeared, or an active warning changed severity
+    WarningRaised {
+        warning: RiskWarning,
+    },
+    WarningCleared {
+        warning: RiskWarning,
+    },
+    AnalysisCompleted {
+        timestamp: u64,
+        portfolio_metrics: PortfolioMetrics,
+        position_count: usize,
+        warning_count: usize,
+    },
+    /// Account data could not be refreshed; metrics are as of `last_success` (unix seconds)
+    DataStale {
+        source: String,
+        error: String,
+        last_success: Option<u64>,
+    },
+}
+
+/// Kind of a `RiskEvent`, used for filtering
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum RiskEventKind {
+    PositionOpened,
+    PositionClosed,
+    PositionResized,
+    WarningRaised,
+    WarningCleared,
+    AnalysisCompleted,
+    DataStale,
+}
+
+impl std::str::FromStr for RiskEventKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('_', "").as_str() {
+            "positionopened" => Ok(Self::PositionOpened),
+            "positionclosed" => Ok(Self::PositionClosed),
+            "positionresized" => Ok(Self::PositionResized),
+            "warningraised" => Ok(Self::WarningRaised),
+            "warningcleared" => Ok(Self::WarningCleared),
+            "analysiscompleted" => Ok(Self::AnalysisCompleted),
+            "datastale" => Ok(Self::DataStale),
+            _ => Err(Error::Custom(format!("Unknown risk event type: {}", s))),
+        }
+    }
+}
+
+impl RiskEvent {
+    /// Returns the kind of this event
+    pub fn kind(&self) -> RiskEventKind {
+        match self {
+            RiskEvent::PositionOpened { .. } => RiskEventKind::PositionOpened,
+            RiskEvent::PositionClosed { .. } => RiskEventKind::PositionClosed,
+            RiskEvent::PositionResized { .. } => RiskEventKind::PositionResized,
+            RiskEvent::WarningRaised { .. } => RiskEventKind::WarningRaised,
+            RiskEvent::WarningCleared { .. } => RiskEventKind::WarningCleared,
+            RiskEvent::AnalysisCompleted { .. } => RiskEventKind::AnalysisCompleted,
+            RiskEvent::DataStale { .. } => RiskEventKind::DataStale,
+        }
+    }
+
+    /// Returns the coin the event relates to, if any
+    pub fn coin(&self) -> Option<&str> {
+        match self {
+            RiskEvent::PositionOpened { position }
+            | RiskEvent::PositionClosed { position }
+            | RiskEvent::PositionResized { position, .. } => Some(&position.coin),
+            RiskEvent::WarningRaised { warning } | RiskEvent::WarningCleared { warning } => {
+                warning.related_position.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the severity of warning events
+    pub fn severity(&self) -> Option<&RiskSeverity> {
+        match self {
+            RiskEvent::WarningRaised { warning } | RiskEvent::WarningCleared { warning } => Some(&warning.severity),
+            _ => None,
+        }
+    }
+}
+
+/// Selects which events a subscription receives. Empty criteria match everything.
+#[derive(Debug, Clone, Default)]
+pub struct RiskEventFilter {
+    kinds: Option<HashSet<RiskEventKind>>,
+    coins: Option<HashSet<String>>,
+    min_severity: Option<RiskSeverity>,
+}
+
+impl RiskEventFilter {
+    /// Creates a filter that matches every event
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Only match events of the given kinds
+    pub fn kinds(mut self, kinds: impl IntoIterator<Item = RiskEventKind>) -> Self {
+        self.kinds = Some(kinds.into_iter().collect());
+        self
+    }
+
+    /// Only match events related to the given coins; events without a coin are not matched
+    pub fn coins(mut self, coins: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.coins = Some(coins.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Only match warning events at or above the given severity; other event kinds are unaffected
+    pub fn min_severity(mut self, severity: RiskSeverity) -> Self {
+        self.min_severity = Some(severity);
+        self
+    }
+
+    /// Returns whether the event passes the filter
+    pub fn matches(&self, event: &RiskEvent) -> bool {
+        if let Some(kinds) = &self.kinds {
+            if !kinds.contains(&event.kind()) {
+                return false;
+            }
+        }
+        if let Some(coins) = &self.coins {
+            if !event.coin().map(|coin| coins.contains(coin)).unwrap_or(false) {
+                return false;
+            }
+        }
+        if let (Some(min_severity), Some(severity)) = (&self.min_severity, event.severity()) {
+            if severity < min_severity {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Broadcast channel for risk events. Cloning shares the same channel.
+#[derive(Debug, Clone)]
+pub struct RiskEventBus {
+    sender: broadcast::Sender<RiskEvent>,
+}
+
+impl Default for RiskEventBus {
+    fn default() -> Self {
+        Self::new(EVENT_BUS_CAPACITY)
+    }
+}
+
+impl RiskEventBus {
+    /// Creates a bus that buffers up to `capacity` events per subscriber
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    /// Sends an event to all current subscribers; events published with no subscribers are dropped
+    pub fn publish(&self, event: RiskEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Subscribes to events published from now on that pass the filter
+    pub fn subscribe(&self, filter: RiskEventFilter) -> RiskEventSubscription {
+        RiskEventSubscription {
+            receiver: self.sender.subscribe(),
+            filter,
+        }
+    }
+
+    /// Returns the number of active subscriptions
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+/// Receiving end of a filtered subscription
+pub struct RiskEventSubscription {
+    receiver: broadcast::Receiver<RiskEvent>,
+    filter: RiskEventFilter,
+}
+
+impl RiskEventSubscription {
+    /// Waits for the next matching event. A subscriber that falls too far behind skips the missed
+    /// events with a warning. Returns an error once the bus has been dropped.
+    pub async fn recv(&mut self) -> Result<RiskEvent> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) if self.filter.matches(&event) => return Ok(event),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Risk event subscriber lagged, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    return Err(Error::Custom("Risk event bus closed".to_string()));
+                }
+            }
+        }
+    }
+
+    /// Returns the next matching event if one is already buffered
+    pub fn try_recv(&mut self) -> Option<RiskEvent> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(event) if self.filter.matches(&event) => return Some(event),
+                Ok(_) | Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+/// Remembers the previous analysis so consecutive analyses can be turned into change events
+#[derive(Debug, Default)]
+pub struct RiskEventTracker {
+    positions: Option<HashMap<String, Position>>,
+    warnings: HashMap<(RiskWarningType, Option<String>), RiskWarning>,
+}
+
+impl RiskEventTracker {
+    /// Returns the events describing what changed since the previous analysis, ending with
+    /// `AnalysisCompleted`. The first analysis reports every warning but no position changes.
+    /// Warnings are identified by type and related position.
+    pub fn diff(&mut self, analysis: &RiskAnalysisResult, timestamp: u64) -> Vec<RiskEvent> {
+        let mut events = Vec::new();
+
+        let current: HashMap<String, Position> = analysis
+            .positions
+            .iter()
+            .map(|p| (p.coin.clone(), p.clone()))
+            .collect();
+        if let Some(previous) = &self.positions {
+            for (coin, position) in &current {
+                match previous.get(coin) {
+                    None => events.push(RiskEvent::PositionOpened { position: position.clone() }),
+                    Some(old) if old.size != position.size => events.push(RiskEvent::PositionResized {
+                        previous_size: old.size,
+                        position: position.clone(),
+                    }),
+                    Some(_) => {}
+                }
+            }
+            for (coin, position) in previous {
+                if !current.contains_key(coin) {
+                    events.push(RiskEvent::PositionClosed { position: position.clone() });
+                }
+            }
+        }
+        self.positions = Some(current);
+
+        let mut active = HashMap::new();
+        for warning in &analysis.warnings {
+            let key = (warning.warning_type.clone(), warning.related_position.clone());
+            // Several warnings can share a key; keep the most severe, which comes first
+            if active.contains_key(&key) {
+                continue;
+            }
+            let is_new = self
+                .warnings
+                .get(&key)
+                .map(|previous| previous.severity != warning.severity)
+                .unwrap_or(true);
+            if is_new {
+                events.push(RiskEvent::WarningRaised { warning: warning.clone() });
+            }
+            active.insert(key, warning.clone());
+        }
+        for (key, warning) in self.warnings.drain() {
+            if !active.contains_key(&key) {
+                events.push(RiskEvent::WarningCleared { warning });
+            }
+        }
+        self.warnings = active;
+
+        events.push(RiskEvent::AnalysisCompleted {
+            timestamp,
+            portfolio_metrics: analysis.portfolio_metrics.clone(),
+            position_count: analysis.positions.len(),
+            warning_count: analysis.warnings.len(),
+        });
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(coin: &str, size: f64) -> Position {
+        Position {
+            coin: coin.to_string(),
+            size,
+            entry_price: Some(100.0),
+            leverage: 5.0,
+            liquidation_price: None,
+            unrealized_pnl: 0.0,
+            margin_used: 20.0,
+            position_value: 100.0 * size.abs(),
+            return_on_equity: 0.0,
+            is_cross: true,
+            max_leverage: 50,
+            exact: None,
+        }
+    }
+
+    fn warning(coin: &str, severity: RiskSeverity) -> RiskWarning {
+        RiskWarning {
+            warning_type: RiskWarningType::LiquidationRisk,
+            severity,
+            message: String::new(),
+            suggested_action: String::new(),
+            related_position: Some(coin.to_string()),
+        }
+    }
+
+    fn analysis(positions: Vec<Position>, warnings: Vec<RiskWarning>) -> RiskAnalysisResult {
+        RiskAnalysisResult {
+            positions,
+            portfolio_metrics: serde_json::from_value(serde_json::json!({
+                "portfolio_heat": 0.0, "concentration_score": 0.0, "risk_adjusted_return": 0.0,
+                "margin_utilization": 0.0, "total_unrealized_pnl": 0.0, "account_value": 0.0,
+                "total_position_value": 0.0, "average_leverage": 0.0
+            }))
+            .unwrap(),
+            position_metrics: Vec::new(),
+            warnings,
+        }
+    }
+
+    #[test]
+    fn diff_reports_position_and_warning_changes() {
+        let mut tracker = RiskEventTracker::default();
+        let first = tracker.diff(&analysis(vec![position("BTC", 1.0), position("ETH", 2.0)], vec![]), 1);
+        assert_eq!(first.iter().map(RiskEvent::kind).collect::<Vec<_>>(), vec![RiskEventKind::AnalysisCompleted]);
+
+        let second = tracker.diff(
+            &analysis(
+                vec![position("BTC", 1.5), position("SOL", 3.0)],
+                vec![warning("BTC", RiskSeverity::High)],
+            ),
+            2,
+        );
+        let mut kinds: Vec<RiskEventKind> = second.iter().map(RiskEvent::kind).collect();
+        kinds.sort_by_key(|kind| *kind as u8);
+        assert_eq!(
+            kinds,
+            vec![
+                RiskEventKind::PositionOpened,
+                RiskEventKind::PositionClosed,
+                RiskEventKind::PositionResized,
+                RiskEventKind::WarningRaised,
+                RiskEventKind::AnalysisCompleted,
+            ]
+        );
+
+        // Same warning again is not re-raised; once gone it is cleared
+        let third = tracker.diff(&analysis(vec![], vec![warning("BTC", RiskSeverity::High)]), 3);
+        assert!(!third.iter().any(|e| e.kind() == RiskEventKind::WarningRaised));
+        let fourth = tracker.diff(&analysis(vec![], vec![]), 4);
+        assert!(fourth.iter().any(|e| e.kind() == RiskEventKind::WarningCleared));
+    }
+
+    #[test]
+    fn subscriptions_apply_filters() {
+        let bus = RiskEventBus::default();
+        let mut btc_warnings = bus.subscribe(
+            RiskEventFilter::all()
+                .kinds([RiskEventKind::WarningRaised])
+                .coins(["BTC"])
+                .min_severity(RiskSeverity::High),
+        );
+
+        bus.publish(RiskEvent::WarningRaised { warning: warning("BTC", RiskSeverity::Medium) });
+        bus.publish(RiskEvent::WarningRaised { warning: warning("ETH", RiskSeverity::Critical) });
+        bus.publish(RiskEvent::PositionOpened { position: position("BTC", 1.0) });
+        bus.publish(RiskEvent::WarningRaised { warning: warning("BTC", RiskSeverity::Critical) });
+
+        let event = btc_warnings.try_recv().unwrap();
+        assert_eq!(event.severity(), Some(&RiskSeverity::Critical));
+        assert!(btc_warnings.try_recv().is_none());
+    }
+}
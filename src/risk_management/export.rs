@@ -0,0 +1,471 @@
+use std::str::FromStr;
+
+use crate::prelude::*;
+use crate::Error;
+#[cfg(feature = "export-parquet")]
+use super::parquet::ParquetWriter;
+use super::{LiquidationForecast, LogEntry};
+
+// Rows per Parquet row group; each group is written out as soon as it fills
+#[cfg(feature = "export-parquet")]
+const PARQUET_ROW_GROUP_SIZE: usize = 10_000;
+
+/// Which logged history to export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportDataset {
+    /// One row per logged analysis with its portfolio metrics
+    Snapshots,
+    /// One row per position per logged analysis
+    Positions,
+    /// One row per warning per logged analysis
+    Warnings,
+}
+
+impl ExportDataset {
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportDataset::Snapshots => "snapshots",
+            ExportDataset::Positions => "positions",
+            ExportDataset::Warnings => "warnings",
+        }
+    }
+
+    /// Every column of the dataset, in default order
+    pub fn columns(self) -> &'static [(&'static str, ExportColumnKind)] {
+        use ExportColumnKind::*;
+        match self {
+            ExportDataset::Snapshots => &[
+                ("timestamp", Int),
+                ("portfolio_heat", Float),
+                ("concentration_score", Float),
+                ("risk_adjusted_return", Float),
+                ("margin_utilization", Float),
+                ("total_unrealized_pnl", Float),
+                ("account_value", Float),
+                ("total_position_value", Float),
+                ("average_leverage", Float),
+                ("net_dollar_delta", Float),
+                ("gross_dollar_delta", Float),
+                ("btc_equivalent_exposure", Float),
+                ("position_count", Int),
+                ("warning_count", Int),
+                ("cross_liquidation_probability_1h", Float),
+                ("cross_liquidation_probability_8h", Float),
+                ("cross_liquidation_probability_24h", Float),
+            ],
+            ExportDataset::Positions => &[
+                ("timestamp", Int),
+                ("coin", Text),
+                ("size", Float),
+                ("entry_price", Float),
+                ("leverage", Float),
+                ("liquidation_price", Float),
+                ("unrealized_pnl", Float),
+                ("margin_used", Float),
+                ("position_value", Float),
+                ("return_on_equity", Float),
+                ("is_cross", Bool),
+                ("max_leverage", Int),
+                ("distance_to_liquidation", Float),
+                ("position_size_ratio", Float),
+                ("risk_score", Float),
+                ("contribution_to_portfolio", Float),
+                ("daily_volatility", Float),
+                ("liquidation_distance_sigma", Float),
+                ("liquidation_probability_1h", Float),
+                ("liquidation_probability_8h", Float),
+                ("liquidation_probability_24h", Float),
+                ("dollar_delta", Float),
+                ("beta_to_btc", Float),
+                ("btc_equivalent_exposure", Float),
+            ],
+            ExportDataset::Warnings => &[
+                ("timestamp", Int),
+                ("warning_type", Text),
+                ("severity", Text),
+                ("related_position", Text),
+                ("message", Text),
+                ("suggested_action", Text),
+            ],
+        }
+    }
+
+    // All columns of every row a log entry contributes, in `columns()` order
+    fn rows(self, entry: &LogEntry) -> Vec<Vec<ExportValue>> {
+        let timestamp = ExportValue::Int(entry.timestamp as i64);
+        match self {
+            ExportDataset::Snapshots => {
+                let metrics = &entry.portfolio_metrics;
+                let forecast = metrics.cross_liquidation_forecast.as_ref();
+                vec![vec![
+                    timestamp,
+                    metrics.portfolio_heat.into(),
+                    metrics.concentration_score.into(),
+                    metrics.risk_adjusted_return.into(),
+                    metrics.margin_utilization.into(),
+                    metrics.total_unrealized_pnl.into(),
+                    metrics.account_value.into(),
+                    metrics.total_position_value.into(),
+                    metrics.average_leverage.into(),
+                    metrics.net_dollar_delta.into(),
+                    metrics.gross_dollar_delta.into(),
+                    metrics.btc_equivalent_exposure.into(),
+                    ExportValue::Int(entry.positions.len() as i64),
+                    ExportValue::Int(entry.warnings.len() as i64),
+                    forecast.map(|f| f.one_hour).into(),
+                    forecast.map(|f| f.eight_hours).into(),
+                    forecast.map(|f| f.twenty_four_hours).into(),
+                ]]
+            }
+            ExportDataset::Positions => entry
+                .position_metrics
+                .iter()
+                .map(|metrics| {
+                    let position = &metrics.position;
+                    let forecast: Option<&LiquidationForecast> = metrics.liquidation_forecast.as_ref();
+                    vec![
+                        timestamp.clone(),
+                        ExportValue::Text(position.coin.clone()),
+                        position.size.into(),
+                        position.entry_price.into(),
+                        position.leverage.into(),
+                        position.liquidation_price.into(),
+                        position.unrealized_pnl.into(),
+                        position.margin_used.into(),
+                        position.position_value.into(),
+                        position.return_on_equity.into(),
+                        ExportValue::Bool(position.is_cross),
+                        ExportValue::Int(position.max_leverage as i64),
+                        metrics.distance_to_liquidation.into(),
+                        metrics.position_size_ratio.into(),
+                        metrics.risk_score.into(),
+                        metrics.contribution_to_portfolio.into(),
+                        metrics.daily_volatility.into(),
+                        metrics.liquidation_distance_sigma.into(),
+                        forecast.map(|f| f.one_hour).into(),
+                        forecast.map(|f| f.eight_hours).into(),
+                        forecast.map(|f| f.twenty_four_hours).or(metrics.liquidation_probability_24h).into(),
+                        metrics.dollar_delta.into(),
+                        metrics.beta_to_btc.into(),
+                        metrics.btc_equivalent_exposure.into(),
+                    ]
+                })
+                .collect(),
+            ExportDataset::Warnings => entry
+                .warnings
+                .iter()
+                .map(|warning| {
+                    vec![
+                        timestamp.clone(),
+                        ExportValue::Text(format!("{:?}", warning.warning_type)),
+                        ExportValue::Text(format!("{:?}", warning.severity)),
+                        warning.related_position.clone().map_or(ExportValue::Null, ExportValue::Text),
+                        ExportValue::Text(warning.message.clone()),
+                        ExportValue::Text(warning.suggested_action.clone()),
+                    ]
+                })
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for ExportDataset {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "snapshots" => Ok(ExportDataset::Snapshots),
+            "positions" => Ok(ExportDataset::Positions),
+            "warnings" => Ok(ExportDataset::Warnings),
+            _ => Err(Error::Custom(format!(
+                "Unknown export dataset: {}. Valid datasets are: snapshots, positions, warnings",
+                s
+            ))),
+        }
+    }
+}
+
+/// Output format of a history export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    /// One JSON object per line
+    Ndjson,
+    /// Requires the `export-parquet` feature
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "ndjson" | "jsonl" => Ok(ExportFormat::Ndjson),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => Err(Error::Custom(format!(
+                "Unknown export format: {}. Valid formats are: csv, ndjson, parquet",
+                s
+            ))),
+        }
+    }
+}
+
+/// Type of an export column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportColumnKind {
+    Float,
+    Int,
+    Text,
+    Bool,
+}
+
+/// A single exported cell
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportValue {
+    Null,
+    Float(f64),
+    Int(i64),
+    Text(String),
+    Bool(bool),
+}
+
+impl From<f64> for ExportValue {
+    fn from(value: f64) -> Self {
+        ExportValue::Float(value)
+    }
+}
+
+impl From<Option<f64>> for ExportValue {
+    fn from(value: Option<f64>) -> Self {
+        value.map_or(ExportValue::Null, ExportValue::Float)
+    }
+}
+
+enum Encoder {
+    Csv,
+    Ndjson,
+    #[cfg(feature = "export-parquet")]
+    Parquet(Box<ParquetWriter>),
+}
+
+/// Encodes logged history entries as rows of one dataset, chunk by chunk, so exports of any size
+/// can be streamed without holding them in memory
+pub struct HistoryExporter {
+    dataset: ExportDataset,
+    format: ExportFormat,
+    // Indices into `dataset.columns()` of the selected columns, in output order
+    selected: Vec<usize>,
+    encoder: Encoder,
+}
+
+impl HistoryExporter {
+    /// `columns` selects and orders the output columns; every column of the dataset when `None`
+    pub fn new(dataset: ExportDataset, format: ExportFormat, columns: Option<&[String]>) -> Result<Self> {
+        let available = dataset.columns();
+        let selected = match columns {
+            Some(columns) if !columns.is_empty() => columns
+                .iter()
+                .map(|column| {
+                    available.iter().position(|(name, _)| name == column).ok_or_else(|| {
+                        Error::Custom(format!(
+                            "Unknown {} column: {}. Valid columns are: {}",
+                            dataset.name(),
+                            column,
+                            available.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+            _ => (0..available.len()).collect(),
+        };
+
+        let encoder = match format {
+            ExportFormat::Csv => Encoder::Csv,
+            ExportFormat::Ndjson => Encoder::Ndjson,
+            #[cfg(feature = "export-parquet")]
+            ExportFormat::Parquet => Encoder::Parquet(Box::new(ParquetWriter::new(
+                selected
+                    .iter()
+                    .map(|&i| (available[i].0.to_string(), available[i].1))
+                    .collect(),
+                PARQUET_ROW_GROUP_SIZE,
+            )?)),
+            #[cfg(not(feature = "export-parquet"))]
+            ExportFormat::Parquet => {
+                return Err(Error::Custom(
+                    "Parquet exports require the export-parquet feature".to_string(),
+                ))
+            }
+        };
+
+        Ok(Self { dataset, format, selected, encoder })
+    }
+
+    pub fn dataset(&self) -> ExportDataset {
+        self.dataset
+    }
+
+    pub fn format(&self) -> ExportFormat {
+        self.format
+    }
+
+    /// Names of the output columns, in order
+    pub fn column_names(&self) -> Vec<&'static str> {
+        self.selected.iter().map(|&i| self.dataset.columns()[i].0).collect()
+    }
+
+    /// Bytes that open the output: the CSV header, nothing for NDJSON and Parquet
+    pub fn start(&mut self) -> Vec<u8> {
+        match &mut self.encoder {
+            Encoder::Csv => {
+                let mut header = self.column_names().join(",");
+                header.push('\n');
+                header.into_bytes()
+            }
+            Encoder::Ndjson => Vec::new(),
+            #[cfg(feature = "export-parquet")]
+            Encoder::Parquet(writer) => writer.start(),
+        }
+    }
+
+    /// Encodes the rows a log entry contributes; Parquet returns nothing until a row group fills
+    pub fn push(&mut self, entry: &LogEntry) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        for row in self.dataset.rows(entry) {
+            let mut row: Vec<Option<ExportValue>> = row.into_iter().map(Some).collect();
+            let values: Vec<ExportValue> = self.selected.iter().map(|&i| row[i].take().unwrap_or(ExportValue::Null)).collect();
+            match &mut self.encoder {
+                Encoder::Csv => {
+                    let cells: Vec<String> = values.iter().map(csv_cell).collect();
+                    out.extend_from_slice(cells.join(",").as_bytes());
+                    out.push(b'\n');
+                }
+                Encoder::Ndjson => {
+                    let fields: Vec<String> = self
+                        .selected
+                        .iter()
+                        .zip(&values)
+                        .map(|(&i, value)| format!("{}:{}", serde_json::Value::from(self.dataset.columns()[i].0), json_value(value)))
+                        .collect();
+                    out.push(b'{');
+                    out.extend_from_slice(fields.join(",").as_bytes());
+                    out.extend_from_slice(b"}\n");
+                }
+                #[cfg(feature = "export-parquet")]
+                Encoder::Parquet(writer) => out.extend_from_slice(&writer.push_row(values)?),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Bytes that close the output: buffered Parquet rows and the footer
+    pub fn finish(&mut self) -> Result<Vec<u8>> {
+        match &mut self.encoder {
+            #[cfg(feature = "export-parquet")]
+            Encoder::Parquet(writer) => writer.finish(),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+fn csv_cell(value: &ExportValue) -> String {
+    match value {
+        ExportValue::Null => String::new(),
+        ExportValue::Float(v) if v.is_finite() => v.to_string(),
+        ExportValue::Float(_) => String::new(),
+        ExportValue::Int(v) => v.to_string(),
+        ExportValue::Bool(v) => v.to_string(),
+        ExportValue::Text(v) if v.contains([',', '"', '\n', '\r']) => format!("\"{}\"", v.replace('"', "\"\"")),
+        ExportValue::Text(v) => v.clone(),
+    }
+}
+
+fn json_value(value: &ExportValue) -> serde_json::Value {
+    match value {
+        ExportValue::Null => serde_json::Value::Null,
+        // Non-finite floats have no JSON representation and become null
+        ExportValue::Float(v) => serde_json::Number::from_f64(*v).map_or(serde_json::Value::Null, serde_json::Value::Number),
+        ExportValue::Int(v) => (*v).into(),
+        ExportValue::Text(v) => v.clone().into(),
+        ExportValue::Bool(v) => (*v).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::risk_management::position_source::tests::{offline_config, user_state};
+    use crate::risk_management::{RiskManagementSystem, RiskSeverity, RiskWarning, RiskWarningType, ScriptedPositionSource};
+
+    async fn log_entry() -> Result<LogEntry> {
+        let script = ScriptedPositionSource::new();
+        script.push_state(user_state("1.5", "100000"));
+        let mut system = RiskManagementSystem::with_position_source(offline_config(), Box::new(script)).await?;
+        let analysis = system.analyze_risk_profile().await?;
+
+        let mut entry = LogEntry::new(analysis.positions, analysis.portfolio_metrics, analysis.position_metrics, vec![]);
+        entry.timestamp = 1_700_000_000;
+        entry.warnings.push(RiskWarning {
+            warning_type: RiskWarningType::HighLeverage,
+            severity: RiskSeverity::High,
+            message: "Leverage is \"high\", reduce it".to_string(),
+            suggested_action: "Reduce BTC".to_string(),
+            related_position: None,
+        });
+        Ok(entry)
+    }
+
+    fn export(dataset: ExportDataset, format: ExportFormat, columns: Option<&[String]>, entry: &LogEntry) -> Result<Vec<u8>> {
+        let mut exporter = HistoryExporter::new(dataset, format, columns)?;
+        let mut out = exporter.start();
+        out.extend(exporter.push(entry)?);
+        out.extend(exporter.finish()?);
+        Ok(out)
+    }
+
+    #[tokio::test]
+    async fn exports_selected_columns_in_each_format() -> Result<()> {
+        let entry = log_entry().await?;
+
+        let columns = ["severity".to_string(), "message".to_string(), "related_position".to_string()];
+        let csv = String::from_utf8(export(ExportDataset::Warnings, ExportFormat::Csv, Some(&columns), &entry)?).unwrap();
+        assert_eq!(csv, "severity,message,related_position\nHigh,\"Leverage is \"\"high\"\", reduce it\",\n");
+
+        let columns = ["coin".to_string(), "size".to_string(), "is_cross".to_string()];
+        let ndjson = String::from_utf8(export(ExportDataset::Positions, ExportFormat::Ndjson, Some(&columns), &entry)?).unwrap();
+        assert_eq!(ndjson, "{\"coin\":\"BTC\",\"size\":1.5,\"is_cross\":true}\n");
+
+        #[cfg(feature = "export-parquet")]
+        {
+            let parquet = export(ExportDataset::Snapshots, ExportFormat::Parquet, None, &entry)?;
+            assert!(parquet.starts_with(b"PAR1") && parquet.ends_with(b"PAR1"));
+            let footer_len = u32::from_le_bytes(parquet[parquet.len() - 8..parquet.len() - 4].try_into().unwrap()) as usize;
+            assert!(footer_len > 0 && footer_len < parquet.len() - 12);
+        }
+        #[cfg(not(feature = "export-parquet"))]
+        assert!(HistoryExporter::new(ExportDataset::Snapshots, ExportFormat::Parquet, None).is_err());
+
+        let unknown = ["coin".to_string(), "pnl".to_string()];
+        assert!(HistoryExporter::new(ExportDataset::Positions, ExportFormat::Csv, Some(&unknown)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use crate::risk_management::Position;
+
+/// Coin used as the market factor for beta calculations
+pub const BETA_BENCHMARK_COIN: &str = "BTC";
+
+/// Minimum number of overlapping returns required before a beta is reported
+const MIN_BETA_SAMPLES: usize = 10;
+
+/// Signed notional of a position in USD (positive for longs, negative for shorts)
+pub fn signed_notional(position: &Position) -> f64 {
+    position.position_value.abs() * position.size.signum()
+}
+
+/// Profit or loss in USD for a 1% move in the position's coin
+pub fn dollar_delta(position: &Position) -> f64 {
+    signed_notional(position) * 0.01
+}
+
+/// Ordinary least squares beta of `returns` against `benchmark_returns`,
+/// using only the samples whose timestamps appear in both series
+pub fn regression_beta(returns: &[(u64, f64)], benchmark_returns: &[(u64, f64)]) -> Option<f64> {
+    let benchmark: HashMap<u64, f64> = benchmark_returns.iter().copied().collect();
+    let pairs: Vec<(f64, f64)> = returns
+        .iter()
+        .filter_map(|(time, r)| benchmark.get(time).map(|b| (*r, *b)))
+        .collect();
+    if pairs.len() < MIN_BETA_SAMPLES {
+        return None;
+    }
+
+    let n = pairs.len() as f64;
+    let mean_r = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_b = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+    let covariance = pairs.iter().map(|(r, b)| (r - mean_r) * (b - mean_b)).sum::<f64>();
+    let benchmark_variance = pairs.iter().map(|(_, b)| (b - mean_b).powi(2)).sum::<f64>();
+
+    if benchmark_variance <= f64::EPSILON {
+        None
+    } else {
+        Some(covariance / benchmark_variance)
+    }
+}
+
+/// Pearson correlation of two return series, using only the samples whose timestamps appear in both
+pub fn return_correlation(returns: &[(u64, f64)], other_returns: &[(u64, f64)]) -> Option<f64> {
+    let other: HashMap<u64, f64> = other_returns.iter().copied().collect();
+    let pairs: Vec<(f64, f64)> = returns
+        .iter()
+        .filter_map(|(time, r)| other.get(time).map(|o| (*r, *o)))
+        .collect();
+    if pairs.len() < MIN_BETA_SAMPLES {
+        return None;
+    }
+
+    let n = pairs.len() as f64;
+    let mean_r = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_o = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+    let covariance = pairs.iter().map(|(r, o)| (r - mean_r) * (o - mean_o)).sum::<f64>();
+    let variance_r = pairs.iter().map(|(r, _)| (r - mean_r).powi(2)).sum::<f64>();
+    let variance_o = pairs.iter().map(|(_, o)| (o - mean_o).powi(2)).sum::<f64>();
+
+    if variance_r <= f64::EPSILON || variance_o <= f64::EPSILON {
+        None
+    } else {
+        Some((covariance / (variance_r * variance_o).sqrt()).clamp(-1.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn beta_of_scaled_series() {
+        let benchmark: Vec<(u64, f64)> = (0..30u64)
+            .map(|i| (i, if i % 3 == 0 { 0.02 } else { -0.01 }))
+            .collect();
+        let levered: Vec<(u64, f64)> = benchmark.iter().map(|(t, r)| (*t, 1.5 * r + 0.001)).collect();
+
+        let beta = regression_beta(&levered, &benchmark).unwrap();
+        assert!((beta - 1.5).abs() < 1e-9);
+        assert!((regression_beta(&benchmark, &benchmark).unwrap() - 1.0).abs() < 1e-9);
+        assert!(regression_beta(&levered[..5], &benchmark).is_none());
+        assert!((return_correlation(&levered, &benchmark).unwrap() - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+
+use crate::info::info_client::InfoClient;
+use crate::prelude::*;
+use crate::risk_management::{fetch_fills_by_time, is_spot_coin, RiskConfig};
+use crate::{decimal_to_f64, parse_decimal, UserFeesResponse, UserFillsResponse};
+
+/// Number of days of volume that determine the fee tier
+const TIER_VOLUME_DAYS: usize = 14;
+
+const MS_PER_DAY: u64 = 86_400_000;
+
+/// Fee tier standing and realized trading costs for an account
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeeAnalytics {
+    /// 0 for the base tier, otherwise the VIP tier number
+    pub tier: usize,
+    pub fourteen_day_volume: f64,
+    pub fourteen_day_maker_volume: f64,
+    pub fourteen_day_taker_volume: f64,
+    /// 14-day volume at which the next tier starts, if there is a higher tier
+    pub next_tier_volume: Option<f64>,
+    pub volume_to_next_tier: Option<f64>,
+    pub next_tier_maker_rate: Option<f64>,
+    pub next_tier_taker_rate: Option<f64>,
+    /// Current rates charged to the account, after discounts
+    pub maker_rate: f64,
+    pub taker_rate: f64,
+    pub referral_discount: f64,
+    /// Realized rates over the lookback: fees divided by filled notional
+    pub realized_maker_rate: Option<f64>,
+    pub realized_taker_rate: Option<f64>,
+    pub maker_fill_volume: f64,
+    pub taker_fill_volume: f64,
+    /// Exchange fees net of rebates, excluding builder fees
+    pub fees_paid: f64,
+    pub builder_fees_paid: f64,
+    pub lookback_days: f64,
+    /// Exchange plus builder fees over the lookback, scaled to 30 days
+    pub projected_monthly_fees: f64,
+}
+
+impl FeeAnalytics {
+    /// Builds the analytics from a `userFees` response and the fills of the last `lookback_days`
+    pub fn from_responses(
+        user_fees: &UserFeesResponse,
+        fills: &[UserFillsResponse],
+        lookback_days: f64,
+    ) -> Result<Self> {
+        // Tier volume: the most recent 14 days of maker plus taker volume
+        let mut daily_volume = user_fees.daily_user_vlm.iter().collect::<Vec<_>>();
+        daily_volume.sort_by(|a, b| b.date.cmp(&a.date));
+        let mut maker_volume = 0.0;
+        let mut taker_volume = 0.0;
+        for day in daily_volume.iter().take(TIER_VOLUME_DAYS) {
+            maker_volume += parse_amount("userAdd", &day.user_add)?;
+            taker_volume += parse_amount("userCross", &day.user_cross)?;
+        }
+        let volume = maker_volume + taker_volume;
+
+        let vip_tiers = &user_fees.fee_schedule.tiers.vip;
+        let mut cutoffs = Vec::with_capacity(vip_tiers.len());
+        for vip in vip_tiers {
+            cutoffs.push(parse_amount("ntlCutoff", &vip.ntl_cutoff)?);
+        }
+        let tier = cutoffs.iter().take_while(|&&cutoff| volume >= cutoff).count();
+        let next_tier = vip_tiers.get(tier);
+        let next_tier_volume = cutoffs.get(tier).copied();
+
+        let mut analytics = FeeAnalytics {
+            tier,
+            fourteen_day_volume: volume,
+            fourteen_day_maker_volume: maker_volume,
+            fourteen_day_taker_volume: taker_volume,
+            next_tier_volume,
+            volume_to_next_tier: next_tier_volume.map(|cutoff| cutoff - volume),
+            next_tier_maker_rate: next_tier.map(|t| parse_amount("add", &t.add)).transpose()?,
+            next_tier_taker_rate: next_tier.map(|t| parse_amount("cross", &t.cross)).transpose()?,
+            maker_rate: parse_amount("userAddRate", &user_fees.user_add_rate)?,
+            taker_rate: parse_amount("userCrossRate", &user_fees.user_cross_rate)?,
+            referral_discount: parse_amount("activeReferralDiscount", &user_fees.active_referral_discount)?,
+            lookback_days,
+            ..Default::default()
+        };
+
+        // Realized costs from perp fills; a crossed fill took liquidity. Spot fees are charged in
+        // the received token rather than USDC, and a fill's fee already includes its builder fee.
+        let mut maker_fees = 0.0;
+        let mut taker_fees = 0.0;
+        for fill in fills.iter().filter(|fill| !is_spot_coin(&fill.coin)) {
+            let notional = parse_amount("px", &fill.px)? * parse_amount("sz", &fill.sz)?;
+            let builder_fee = fill
+                .builder_fee
+                .as_ref()
+                .map(|builder_fee| parse_amount("builderFee", builder_fee))
+                .transpose()?
+                .unwrap_or(0.0);
+            analytics.builder_fees_paid += builder_fee;
+            let fee = parse_amount("fee", &fill.fee)? - builder_fee;
+            if fill.crossed {
+                analytics.taker_fill_volume += notional;
+                taker_fees += fee;
+            } else {
+                analytics.maker_fill_volume += notional;
+                maker_fees += fee;
+            }
+        }
+        analytics.fees_paid = maker_fees + taker_fees;
+        analytics.realized_maker_rate =
+            (analytics.maker_fill_volume > 0.0).then(|| maker_fees / analytics.maker_fill_volume);
+        analytics.realized_taker_rate =
+            (analytics.taker_fill_volume > 0.0).then(|| taker_fees / analytics.taker_fill_volume);
+        if lookback_days > 0.0 {
+            analytics.projected_monthly_fees =
+                (analytics.fees_paid + analytics.builder_fees_paid) / lookback_days * 30.0;
+        }
+
+        Ok(analytics)
+    }
+}
+
+fn parse_amount(field: &str, value: &str) -> Result<f64> {
+    parse_decimal(field, value).map(decimal_to_f64)
+}
+
+/// Fetches fee schedule and fills to compute fee analytics
+pub struct FeeAnalyzer {
+    info_client: InfoClient,
+    config: RiskConfig,
+}
+
+impl FeeAnalyzer {
+    /// Creates a new fee analyzer with the provided configuration
+    pub async fn new(config: RiskConfig) -> Result<Self> {
+        let info_client = InfoClient::new(None, Some(config.base_url)).await?;
+
+        Ok(Self { info_client, config })
+    }
+
+    /// Updates the configuration
+    pub fn update_config(&mut self, config: RiskConfig) {
+        self.config = config;
+    }
+
+    /// Computes fee analytics using the fills of the last `lookback_days` days
+    pub async fn analyze(&self, lookback_days: u64) -> Result<FeeAnalytics> {
+        let user = self.config.wallet_address;
+        let user_fees = self.info_client.user_fees(user).await?;
+
+        let end_time = chrono::Utc::now().timestamp_millis() as u64;
+        let start_time = end_time.saturating_sub(lookback_days * MS_PER_DAY);
+        let fills = fetch_fills_by_time(&self.info_client, user, start_time, end_time).await?;
+
+        FeeAnalytics::from_responses(&user_fees, &fills, lookback_days as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tier_and_realized_rates() -> Result<()> {
+        let daily: Vec<serde_json::Value> = (1..=15)
+            .map(|day| {
+                serde_json::json!({
+                    "date": format!("2024-01-{:02}", day),
+                    "exchange": "1000000000",
+                    "userAdd": "200000",
+                    "userCross": "300000"
+                })
+            })
+            .collect();
+        let user_fees: UserFeesResponse = serde_json::from_value(serde_json::json!({
+            "activeReferralDiscount": "0.04",
+            "dailyUserVlm": daily,
+            "feeSchedule": {
+                "add": "0.0001", "cross": "0.00035", "referralDiscount": "0.04",
+                "tiers": {
+                    "mm": [],
+                    "vip": [
+                        {"add": "0.00008", "cross": "0.0003", "ntlCutoff": "5000000"},
+                        {"add": "0.00004", "cross": "0.00025", "ntlCutoff": "25000000"}
+                    ]
+                }
+            },
+            "userAddRate": "0.000077",
+            "userCrossRate": "0.000288"
+        }))?;
+        let fills: Vec<UserFillsResponse> = serde_json::from_value(serde_json::json!([
+            {"closedPnl": "0", "coin": "ETH", "crossed": true, "dir": "Open Long", "hash": "0x1",
+             "oid": 1, "px": "2000", "side": "B", "startPosition": "0", "sz": "5", "time": 1, "fee": "3.88", "tid": 1,
+             "builderFee": "1"},
+            {"closedPnl": "0", "coin": "ETH", "crossed": false, "dir": "Close Long", "hash": "0x2",
+             "oid": 2, "px": "2000", "side": "A", "startPosition": "5", "sz": "5", "time": 2, "fee": "0.77", "tid": 2},
+            {"closedPnl": "0", "coin": "@107", "crossed": true, "dir": "Buy", "hash": "0x3",
+             "oid": 3, "px": "20", "side": "B", "startPosition": "0", "sz": "100", "time": 3, "fee": "0.035", "tid": 3}
+        ]))?;
+
+        let analytics = FeeAnalytics::from_responses(&user_fees, &fills, 15.0)?;
+
+        // 14 most recent days at 500k per day
+        assert_eq!(analytics.fourteen_day_volume, 7_000_000.0);
+        assert_eq!(analytics.tier, 1);
+        assert_eq!(analytics.next_tier_volume, Some(25_000_000.0));
+        assert_eq!(analytics.volume_to_next_tier, Some(18_000_000.0));
+        assert!((analytics.realized_taker_rate.unwrap() - 0.000288).abs() < 1e-12);
+        assert!((analytics.realized_maker_rate.unwrap() - 0.000077).abs() < 1e-12);
+        // The builder fee is part of the fill's fee, and the spot fill is left out
+        assert_eq!(analytics.taker_fill_volume, 10_000.0);
+        assert!((analytics.fees_paid - 3.65).abs() < 1e-9);
+        assert_eq!(analytics.builder_fees_paid, 1.0);
+        assert!((analytics.projected_monthly_fees - 4.65 * 2.0).abs() < 1e-9);
+        Ok(())
+    }
+}
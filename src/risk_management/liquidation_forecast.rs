@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::risk_management::{barrier_hit_probability, signed_notional, Position};
+
+/// Forecast horizons in hours, matching the fields of `LiquidationForecast`
+const HORIZON_HOURS: [usize; 3] = [1, 8, 24];
+
+/// Settings for the liquidation probability forecast
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiquidationForecastConfig {
+    pub enabled: bool,
+    /// Number of Monte Carlo paths for the cross-margin account
+    pub simulations: usize,
+    /// Price steps simulated per hour; liquidation is checked after every step
+    pub steps_per_hour: usize,
+    /// Seed for the path generator, so consecutive runs on the same inputs agree
+    pub seed: u64,
+}
+
+impl Default for LiquidationForecastConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            simulations: 2000,
+            steps_per_hour: 4,
+            seed: 42,
+        }
+    }
+}
+
+/// Probability (0-1) of being liquidated within each horizon
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LiquidationForecast {
+    pub one_hour: f64,
+    pub eight_hours: f64,
+    pub twenty_four_hours: f64,
+}
+
+impl LiquidationForecast {
+    /// Closed-form forecast for a single position from its liquidation distance in daily-sigma units
+    pub fn from_sigma_distance(sigma_distance: f64) -> Self {
+        let probability = |hours: usize| barrier_hit_probability(sigma_distance, hours as f64 / 24.0);
+        Self {
+            one_hour: probability(HORIZON_HOURS[0]),
+            eight_hours: probability(HORIZON_HOURS[1]),
+            twenty_four_hours: probability(HORIZON_HOURS[2]),
+        }
+    }
+
+    /// Returns `(hours, probability)` pairs from the shortest horizon to the longest
+    pub fn horizons(&self) -> [(usize, f64); 3] {
+        [
+            (HORIZON_HOURS[0], self.one_hour),
+            (HORIZON_HOURS[1], self.eight_hours),
+            (HORIZON_HOURS[2], self.twenty_four_hours),
+        ]
+    }
+}
+
+/// Monte Carlo forecast for the cross-margin account as a whole.
+///
+/// Prices of the held coins follow correlated driftless log-normal paths using each coin's daily sigma
+/// (in percent) and the pairwise return correlations. The account is liquidated on a path once cross
+/// equity falls below the maintenance margin of its cross positions, taken as half the initial margin
+/// at each coin's max leverage. Coin pairs without a correlation estimate are treated as independent.
+/// Returns `None` when there are no cross positions or a cross position has no volatility estimate.
+pub fn forecast_cross_liquidation(
+    positions: &[Position],
+    account_value: f64,
+    daily_volatility: &HashMap<String, f64>,
+    correlations: &HashMap<(String, String), f64>,
+    config: &LiquidationForecastConfig,
+) -> Option<LiquidationForecast> {
+    let cross: Vec<&Position> = positions.iter().filter(|p| p.is_cross && p.size != 0.0).collect();
+    if cross.is_empty() || config.simulations == 0 {
+        return None;
+    }
+
+    // Isolated margin is ring-fenced and can't absorb cross losses
+    let isolated_margin: f64 = positions.iter().filter(|p| !p.is_cross).map(|p| p.margin_used).sum();
+    let cross_equity = account_value - isolated_margin;
+
+    let steps_per_hour = config.steps_per_hour.max(1);
+    let step_days = 1.0 / (24 * steps_per_hour) as f64;
+    let mut step_sigmas = Vec::with_capacity(cross.len());
+    let mut notionals = Vec::with_capacity(cross.len());
+    let mut maintenance = Vec::with_capacity(cross.len());
+    for position in &cross {
+        let daily_sigma = *daily_volatility.get(&position.coin)? / 100.0;
+        step_sigmas.push(daily_sigma * step_days.sqrt());
+        notionals.push(signed_notional(position));
+        maintenance.push(position.position_value.abs() / (2.0 * position.max_leverage.max(1) as f64));
+    }
+
+    if cross_equity < maintenance.iter().sum::<f64>() {
+        return Some(LiquidationForecast { one_hour: 1.0, eight_hours: 1.0, twenty_four_hours: 1.0 });
+    }
+
+    let n = cross.len();
+    let mut matrix = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..n {
+            matrix[i][j] = if i == j {
+                1.0
+            } else {
+                lookup_correlation(correlations, &cross[i].coin, &cross[j].coin).unwrap_or(0.0)
+            };
+        }
+    }
+    let factor = nearest_cholesky(matrix);
+
+    let horizon_steps: Vec<usize> = HORIZON_HOURS.iter().map(|h| h * steps_per_hour).collect();
+    let total_steps = horizon_steps[horizon_steps.len() - 1];
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut hits = [0usize; 3];
+    let mut shocks = vec![0.0; n];
+    let mut log_moves = vec![0.0; n];
+
+    for _ in 0..config.simulations {
+        log_moves.iter_mut().for_each(|x| *x = 0.0);
+        for step in 1..=total_steps {
+            shocks.iter_mut().for_each(|z| *z = standard_normal(&mut rng));
+            for i in 0..n {
+                let correlated: f64 = (0..=i).map(|k| factor[i][k] * shocks[k]).sum();
+                log_moves[i] += correlated * step_sigmas[i];
+            }
+
+            let mut equity = cross_equity;
+            let mut required = 0.0;
+            for i in 0..n {
+                let ratio = log_moves[i].exp();
+                equity += notionals[i] * (ratio - 1.0);
+                required += maintenance[i] * ratio;
+            }
+            if equity < required {
+                for (hit, &steps) in hits.iter_mut().zip(&horizon_steps) {
+                    if step <= steps {
+                        *hit += 1;
+                    }
+                }
+                break;
+            }
+        }
+    }
+
+    let simulations = config.simulations as f64;
+    Some(LiquidationForecast {
+        one_hour: hits[0] as f64 / simulations,
+        eight_hours: hits[1] as f64 / simulations,
+        twenty_four_hours: hits[2] as f64 / simulations,
+    })
+}
+
+fn lookup_correlation(correlations: &HashMap<(String, String), f64>, a: &str, b: &str) -> Option<f64> {
+    correlations
+        .get(&(a.to_string(), b.to_string()))
+        .or_else(|| correlations.get(&(b.to_string(), a.to_string())))
+        .copied()
+}
+
+/// Lower-triangular Cholesky factor of a correlation matrix. Pairwise estimates need not form a valid
+/// matrix, so off-diagonal terms are shrunk toward zero until the decomposition succeeds.
+fn nearest_cholesky(mut matrix: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    loop {
+        if let Some(factor) = cholesky(&matrix) {
+            return factor;
+        }
+        for (i, row) in matrix.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                if i != j {
+                    *value *= 0.9;
+                }
+            }
+        }
+    }
+}
+
+fn cholesky(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut factor = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|k| factor[i][k] * factor[j][k]).sum();
+            if i == j {
+                let diagonal = matrix[i][i] - sum;
+                if diagonal <= 1e-12 {
+                    return None;
+                }
+                factor[i][j] = diagonal.sqrt();
+            } else {
+                factor[i][j] = (matrix[i][j] - sum) / factor[j][j];
+            }
+        }
+    }
+    Some(factor)
+}
+
+// Box-Muller transform
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cross_position(coin: &str, size: f64, price: f64) -> Position {
+        Position {
+            coin: coin.to_string(),
+            size,
+            entry_price: Some(price),
+            leverage: 10.0,
+            liquidation_price: None,
+            unrealized_pnl: 0.0,
+            margin_used: (size * price).abs() / 10.0,
+            position_value: (size * price).abs(),
+            return_on_equity: 0.0,
+            is_cross: true,
+            max_leverage: 50,
+            exact: None,
+        }
+    }
+
+    #[test]
+    fn correlation_changes_cross_account_risk() {
+        // Long BTC hedged with short ETH: the hedge only helps when the coins move together
+        let positions = vec![cross_position("BTC", 1.0, 50_000.0), cross_position("ETH", -20.0, 2_500.0)];
+        let volatility: HashMap<String, f64> =
+            [("BTC".to_string(), 4.0), ("ETH".to_string(), 4.0)].into_iter().collect();
+        let config = LiquidationForecastConfig { simulations: 500, ..Default::default() };
+        let forecast_with = |rho: f64| {
+            let correlations = [(("BTC".to_string(), "ETH".to_string()), rho)].into_iter().collect();
+            forecast_cross_liquidation(&positions, 5_000.0, &volatility, &correlations, &config).unwrap()
+        };
+
+        let independent = forecast_with(0.0);
+        let hedged = forecast_with(0.95);
+        assert!(independent.twenty_four_hours > hedged.twenty_four_hours);
+        assert!(independent.one_hour <= independent.eight_hours);
+        assert!(independent.eight_hours <= independent.twenty_four_hours);
+
+        // Already below maintenance
+        let underwater = forecast_cross_liquidation(&positions, 500.0, &volatility, &HashMap::new(), &config);
+        assert_eq!(underwater.unwrap().one_hour, 1.0);
+    }
+
+    #[test]
+    fn closed_form_grows_with_horizon() {
+        let forecast = LiquidationForecast::from_sigma_distance(2.0);
+        assert!(forecast.one_hour < forecast.eight_hours);
+        assert!(forecast.eight_hours < forecast.twenty_four_hours);
+        assert!((forecast.twenty_four_hours - barrier_hit_probability(2.0, 1.0)).abs() < 1e-12);
+        assert!(cholesky(&[vec![1.0, 1.2], vec![1.2, 1.0]]).is_none());
+    }
+}
@@ -4,6 +4,19 @@ mod data_logging;
 mod risk_limits;
 mod types;
 mod config;
+mod volatility;
+mod exposure;
+mod scoring;
+mod attribution;
+mod fees;
+mod liquidation_forecast;
+mod events;
+mod position_source;
+mod dashboard_auth;
+mod service;
+mod export;
+#[cfg(feature = "export-parquet")]
+mod parquet;
 
 pub use position_tracking::*;
 pub use risk_calculation::*;
@@ -11,8 +24,22 @@ pub use data_logging::*;
 pub use risk_limits::*;
 pub use types::*;
 pub use config::*;
+pub use volatility::*;
+pub use exposure::*;
+pub use scoring::*;
+pub use attribution::*;
+pub use fees::*;
+pub use liquidation_forecast::*;
+pub use events::*;
+pub use position_source::*;
+pub use dashboard_auth::*;
+pub use service::*;
+pub use export::*;
+
+use log::warn;
 
 use crate::prelude::*;
+use crate::Error;
 
 /// Main Risk Management System interface that brings together all components
 pub struct RiskManagementSystem {
@@ -20,24 +47,65 @@ pub struct RiskManagementSystem {
     risk_calculator: RiskCalculator,
     data_logger: DataLogger,
     risk_limiter: RiskLimiter,
+    volatility_tracker: VolatilityTracker,
+    pnl_attributor: PnlAttributor,
+    fee_analyzer: FeeAnalyzer,
+    event_bus: RiskEventBus,
+    event_tracker: RiskEventTracker,
+    last_successful_update: Option<u64>,
 }
 
 impl RiskManagementSystem {
     /// Creates a new Risk Management System with the provided configuration
     pub async fn new(config: RiskConfig) -> Result<Self> {
         let position_tracker = PositionTracker::new(config.clone()).await?;
+        Self::with_position_tracker(config, position_tracker).await
+    }
+    
+    /// Creates a Risk Management System that reads account state from the given source instead
+    /// of the one selected in the configuration, e.g. a replay or scripted source for offline runs
+    pub async fn with_position_source(config: RiskConfig, source: Box<dyn PositionSource>) -> Result<Self> {
+        let position_tracker = PositionTracker::with_source(config.clone(), source);
+        Self::with_position_tracker(config, position_tracker).await
+    }
+    
+    async fn with_position_tracker(config: RiskConfig, position_tracker: PositionTracker) -> Result<Self> {
         let risk_calculator = RiskCalculator::new(config.clone());
         let data_logger = DataLogger::new(config.clone());
         let risk_limiter = RiskLimiter::new(config.clone());
+        let volatility_tracker = VolatilityTracker::new(config.clone()).await?;
+        let mut pnl_attributor = PnlAttributor::new(config.clone()).await?;
+        let fee_analyzer = FeeAnalyzer::new(config.clone()).await?;
+        
+        // Continue stored attribution history where it left off
+        if let Some(latest) = data_logger.get_pnl_attribution_history(1)?.first() {
+            pnl_attributor.set_last_end_time(latest.end_time);
+        }
 
         Ok(Self {
             position_tracker,
             risk_calculator,
             data_logger,
             risk_limiter,
+            volatility_tracker,
+            pnl_attributor,
+            fee_analyzer,
+            event_bus: RiskEventBus::default(),
+            event_tracker: RiskEventTracker::default(),
+            last_successful_update: None,
         })
     }
 
+    /// Returns a handle to the event bus; clones publish to and subscribe on the same channel
+    pub fn event_bus(&self) -> RiskEventBus {
+        self.event_bus.clone()
+    }
+    
+    /// Subscribes to risk events that pass the filter
+    pub fn subscribe(&self, filter: RiskEventFilter) -> RiskEventSubscription {
+        self.event_bus.subscribe(filter)
+    }
+
     /// Gets the current configuration
     pub fn get_config(&self) -> RiskConfig {
         self.position_tracker.config.clone()
@@ -52,6 +120,9 @@ impl RiskManagementSystem {
         self.position_tracker.update_config(config.clone());
         self.risk_calculator.update_config(config.clone());
         self.risk_limiter.update_config(config.clone());
+        self.volatility_tracker.update_config(config.clone());
+        self.pnl_attributor.update_config(config.clone());
+        self.fee_analyzer.update_config(config.clone());
         self.data_logger.update_config(config);
         
         Ok(())
@@ -59,11 +130,25 @@ impl RiskManagementSystem {
 
     /// Performs a full analysis of the current risk profile, calculating all metrics
     pub async fn analyze_risk_profile(&mut self) -> Result<RiskAnalysisResult> {
-        // Get current positions
-        let positions = self.position_tracker.get_current_positions().await?;
+        // Get current positions and account summary from one account state
+        let (positions, account_summary) = match self.position_tracker.get_snapshot().await {
+            Ok(snapshot) => snapshot,
+            Err(e) => return Err(self.report_stale_data("user_state", e)),
+        };
         
-        // Get account summary
-        let account_summary = self.position_tracker.get_account_summary().await?;
+        // Refresh realized volatility for the held coins; stale estimates are kept on failure
+        if self.position_tracker.config.volatility.enabled {
+            let mut coins: Vec<String> = positions.iter().map(|p| p.coin.clone()).collect();
+            if !coins.is_empty() && !coins.iter().any(|c| c == BETA_BENCHMARK_COIN) {
+                coins.push(BETA_BENCHMARK_COIN.to_string());
+            }
+            if let Err(e) = self.volatility_tracker.refresh(&coins).await {
+                warn!("Failed to refresh volatility estimates: {}", e);
+            }
+            self.risk_calculator.update_volatility(self.volatility_tracker.daily_sigmas());
+            self.risk_calculator.update_betas(self.volatility_tracker.betas(BETA_BENCHMARK_COIN));
+            self.risk_calculator.update_correlations(self.volatility_tracker.correlations());
+        }
         
         // Calculate risk metrics
         let portfolio_metrics = self.risk_calculator.calculate_portfolio_metrics(&positions, &account_summary)?;
@@ -75,27 +160,93 @@ impl RiskManagementSystem {
         // Log the data
         self.data_logger.log_metrics(&positions, &portfolio_metrics, &position_metrics, &warnings)?;
         
-        // Return the complete analysis
-        Ok(RiskAnalysisResult {
+        // Store PnL attribution once per attribution window; a failure here doesn't fail the analysis
+        if self.position_tracker.config.attribution.enabled {
+            if let Err(e) = self.record_pnl_attribution().await {
+                warn!("Failed to record PnL attribution: {}", e);
+            }
+        }
+        
+        let analysis = RiskAnalysisResult {
             positions,
             portfolio_metrics,
             position_metrics,
             warnings,
-        })
+        };
+        
+        // Publish what changed since the previous analysis
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.last_successful_update = Some(now);
+        for event in self.event_tracker.diff(&analysis, now) {
+            self.event_bus.publish(event);
+        }
+        
+        // Return the complete analysis
+        Ok(analysis)
+    }
+    
+    /// Publishes a `DataStale` event for a failed fetch and hands the error back
+    fn report_stale_data(&self, source: &str, error: Error) -> Error {
+        self.event_bus.publish(RiskEvent::DataStale {
+            source: source.to_string(),
+            error: error.to_string(),
+            last_success: self.last_successful_update,
+        });
+        error
+    }
+
+    /// Attributes the change in equity between two timestamps (ms) to trading, funding, fees,
+    /// liquidations and transfers. Equity and unrealized PnL come from the logged history when available.
+    pub async fn attribute_pnl(&self, start_time: u64, end_time: u64) -> Result<PnlAttribution> {
+        attribute_with_equity(&self.pnl_attributor, &self.data_logger, start_time, end_time).await
+    }
+    
+    /// Returns stored PnL attributions, newest first
+    pub fn get_pnl_attribution_history(&self, limit: usize) -> Result<Vec<PnlAttribution>> {
+        self.data_logger.get_pnl_attribution_history(limit)
+    }
+    
+    /// Computes fee tier standing and realized trading costs over the last `lookback_days` days
+    pub async fn get_fee_analytics(&self, lookback_days: u64) -> Result<FeeAnalytics> {
+        self.fee_analyzer.analyze(lookback_days).await
+    }
+    
+    /// Attributes and stores the window since the last stored attribution, if a full window has passed
+    async fn record_pnl_attribution(&mut self) -> Result<()> {
+        let interval_ms = self.position_tracker.config.attribution.interval_seconds * 1000;
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let start_time = self.pnl_attributor.last_end_time()
+            .unwrap_or_else(|| now.saturating_sub(interval_ms));
+        if now.saturating_sub(start_time) < interval_ms {
+            return Ok(());
+        }
+        
+        let attribution = self.attribute_pnl(start_time, now).await?;
+        self.data_logger.log_pnl_attribution(&attribution)?;
+        self.pnl_attributor.set_last_end_time(now);
+        Ok(())
+    }
+    
+    /// Returns the cached realized volatility estimates per coin
+    pub fn get_volatility_estimates(&self) -> &std::collections::HashMap<String, CoinVolatility> {
+        self.volatility_tracker.estimates()
     }
 
     /// Provides a simple summary of the current risk status
     pub async fn get_risk_summary(&mut self) -> Result<RiskSummary> {
         let analysis = self.analyze_risk_profile().await?;
-        
-        Ok(RiskSummary {
-            portfolio_heat: analysis.portfolio_metrics.portfolio_heat,
-            highest_risk_position: analysis.position_metrics.iter()
-                .max_by(|a, b| a.risk_score.partial_cmp(&b.risk_score).unwrap_or(std::cmp::Ordering::Equal))
-                .map(|p| (p.position.clone(), p.risk_score)),
-            warning_count: analysis.warnings.len(),
-            margin_utilization: analysis.portfolio_metrics.margin_utilization,
-            account_value: analysis.portfolio_metrics.account_value,
-        })
+        Ok(RiskSummary::from_analysis(&analysis))
     }
-} 
\ No newline at end of file
+}
+
+/// Attributes PnL over an interval and adds the logged account equity closest to each end
+async fn attribute_with_equity(
+    pnl_attributor: &PnlAttributor,
+    data_logger: &DataLogger,
+    start_time: u64,
+    end_time: u64,
+) -> Result<PnlAttribution> {
+    let attribution = pnl_attributor.attribute(start_time, end_time).await?;
+    let equity = data_logger.equity_near(&[start_time / 1000, end_time / 1000])?;
+    Ok(attribution.with_equity(equity[0], equity[1]))
+}
@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use ::parquet::basic::{ConvertedType, Repetition, Type as PhysicalType};
+use ::parquet::column::writer::ColumnWriter;
+use ::parquet::data_type::ByteArray;
+use ::parquet::errors::ParquetError;
+use ::parquet::file::properties::WriterProperties;
+use ::parquet::file::writer::SerializedFileWriter;
+use ::parquet::schema::types::Type;
+
+use super::{ExportColumnKind, ExportValue};
+use crate::prelude::*;
+use crate::Error;
+
+/// Streams history exports through the `parquet` crate's file writer: a flat schema of optional
+/// columns, uncompressed, with a row group written out each time `row_group_size` rows have been
+/// buffered. Every method returns the bytes to append to the output.
+pub(crate) struct ParquetWriter {
+    kinds: Vec<ExportColumnKind>,
+    row_group_size: usize,
+    rows: Vec<Vec<ExportValue>>,
+    // Taken by `finish`, which writes the footer
+    writer: Option<SerializedFileWriter<Vec<u8>>>,
+}
+
+impl ParquetWriter {
+    pub(crate) fn new(columns: Vec<(String, ExportColumnKind)>, row_group_size: usize) -> Result<Self> {
+        let fields = columns
+            .iter()
+            .map(|(name, kind)| {
+                let field =
+                    Type::primitive_type_builder(name, physical_type(*kind)).with_repetition(Repetition::OPTIONAL);
+                let field = match kind {
+                    ExportColumnKind::Text => field.with_converted_type(ConvertedType::UTF8),
+                    _ => field,
+                };
+                field.build().map(Arc::new)
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(parquet_error)?;
+        let schema = Type::group_type_builder("schema").with_fields(fields).build().map_err(parquet_error)?;
+        let writer =
+            SerializedFileWriter::new(Vec::new(), Arc::new(schema), Arc::new(WriterProperties::builder().build()))
+                .map_err(parquet_error)?;
+
+        Ok(Self {
+            kinds: columns.into_iter().map(|(_, kind)| kind).collect(),
+            row_group_size: row_group_size.max(1),
+            rows: Vec::new(),
+            writer: Some(writer),
+        })
+    }
+
+    /// Whatever the file writer has written so far; the magic number goes out with the first row group
+    pub(crate) fn start(&mut self) -> Vec<u8> {
+        self.writer.as_mut().map(|writer| std::mem::take(writer.inner_mut())).unwrap_or_default()
+    }
+
+    /// Buffers a row, returning a complete row group once enough rows have been collected
+    pub(crate) fn push_row(&mut self, row: Vec<ExportValue>) -> Result<Vec<u8>> {
+        self.rows.push(row);
+        if self.rows.len() >= self.row_group_size {
+            self.flush_row_group()
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Writes the remaining rows and the footer
+    pub(crate) fn finish(&mut self) -> Result<Vec<u8>> {
+        let mut out = self.flush_row_group()?;
+        if let Some(writer) = self.writer.take() {
+            out.extend(writer.into_inner().map_err(parquet_error)?);
+        }
+        Ok(out)
+    }
+
+    fn flush_row_group(&mut self) -> Result<Vec<u8>> {
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(Vec::new());
+        };
+        if !self.rows.is_empty() {
+            let mut row_group = writer.next_row_group().map_err(parquet_error)?;
+            for (i, kind) in self.kinds.iter().enumerate() {
+                let mut column = row_group
+                    .next_column()
+                    .map_err(parquet_error)?
+                    .ok_or_else(|| Error::Custom("Parquet schema has fewer columns than the export".to_string()))?;
+                let values: Vec<&ExportValue> = self.rows.iter().map(|row| &row[i]).collect();
+                write_column(column.untyped(), *kind, &values).map_err(parquet_error)?;
+                column.close().map_err(parquet_error)?;
+            }
+            row_group.close().map_err(parquet_error)?;
+            self.rows.clear();
+        }
+        // The file writer tracks its own offsets, so the bytes written so far can be handed out
+        Ok(std::mem::take(writer.inner_mut()))
+    }
+}
+
+fn physical_type(kind: ExportColumnKind) -> PhysicalType {
+    match kind {
+        ExportColumnKind::Bool => PhysicalType::BOOLEAN,
+        ExportColumnKind::Int => PhysicalType::INT64,
+        ExportColumnKind::Float => PhysicalType::DOUBLE,
+        ExportColumnKind::Text => PhysicalType::BYTE_ARRAY,
+    }
+}
+
+// Values that don't match the column's type are written as nulls
+fn write_column(
+    column: &mut ColumnWriter<'_>,
+    kind: ExportColumnKind,
+    values: &[&ExportValue],
+) -> std::result::Result<(), ParquetError> {
+    let levels: Vec<i16> = values.iter().map(|value| i16::from(value_matches(kind, value))).collect();
+    match column {
+        ColumnWriter::BoolColumnWriter(writer) => {
+            let data: Vec<bool> = values
+                .iter()
+                .filter_map(|value| match value {
+                    ExportValue::Bool(v) => Some(*v),
+                    _ => None,
+                })
+                .collect();
+            writer.write_batch(&data, Some(&levels), None)?;
+        }
+        ColumnWriter::Int64ColumnWriter(writer) => {
+            let data: Vec<i64> = values
+                .iter()
+                .filter_map(|value| match value {
+                    ExportValue::Int(v) => Some(*v),
+                    _ => None,
+                })
+                .collect();
+            writer.write_batch(&data, Some(&levels), None)?;
+        }
+        ColumnWriter::DoubleColumnWriter(writer) => {
+            let data: Vec<f64> = values
+                .iter()
+                .filter_map(|value| match value {
+                    ExportValue::Float(v) => Some(*v),
+                    _ => None,
+                })
+                .collect();
+            writer.write_batch(&data, Some(&levels), None)?;
+        }
+        ColumnWriter::ByteArrayColumnWriter(writer) => {
+            let data: Vec<ByteArray> = values
+                .iter()
+                .filter_map(|value| match value {
+                    ExportValue::Text(v) => Some(ByteArray::from(v.as_str())),
+                    _ => None,
+                })
+                .collect();
+            writer.write_batch(&data, Some(&levels), None)?;
+        }
+        _ => return Err(ParquetError::General(format!("Unexpected column writer for {:?} values", kind))),
+    }
+    Ok(())
+}
+
+fn value_matches(kind: ExportColumnKind, value: &ExportValue) -> bool {
+    matches!(
+        (kind, value),
+        (ExportColumnKind::Bool, ExportValue::Bool(_))
+            | (ExportColumnKind::Int, ExportValue::Int(_))
+            | (ExportColumnKind::Float, ExportValue::Float(_))
+            | (ExportColumnKind::Text, ExportValue::Text(_))
+    )
+}
+
+fn parquet_error(e: ParquetError) -> Error {
+    Error::Custom(format!("Parquet encoding error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::parquet::file::reader::{FileReader, SerializedFileReader};
+    use ::parquet::record::Field;
+
+    fn row(i: i64) -> Vec<ExportValue> {
+        // Every column has nulls, in different places, and booleans span more than one byte
+        vec![
+            if i % 3 == 0 { ExportValue::Null } else { ExportValue::Float(i as f64 * 1.5) },
+            if i % 4 == 1 { ExportValue::Null } else { ExportValue::Int(-i * 1_000) },
+            if i == 5 { ExportValue::Null } else { ExportValue::Text(format!("row \"{i}\"")) },
+            if i % 5 == 2 { ExportValue::Null } else { ExportValue::Bool(i % 2 == 0) },
+        ]
+    }
+
+    fn field(value: &ExportValue) -> Field {
+        match value {
+            ExportValue::Null => Field::Null,
+            ExportValue::Float(v) => Field::Double(*v),
+            ExportValue::Int(v) => Field::Long(*v),
+            ExportValue::Text(v) => Field::Str(v.clone()),
+            ExportValue::Bool(v) => Field::Bool(*v),
+        }
+    }
+
+    #[test]
+    fn files_are_streamed_in_row_groups() -> Result<()> {
+        let columns = [
+            ("px", ExportColumnKind::Float),
+            ("sz", ExportColumnKind::Int),
+            ("note", ExportColumnKind::Text),
+            ("is_cross", ExportColumnKind::Bool),
+        ];
+        let mut writer = ParquetWriter::new(columns.iter().map(|(name, kind)| (name.to_string(), *kind)).collect(), 4)?;
+        let mut file = writer.start();
+        for i in 0..11 {
+            let chunk = writer.push_row(row(i))?;
+            // Nothing is written until a row group fills
+            assert_eq!(chunk.is_empty(), i % 4 != 3);
+            file.extend(chunk);
+        }
+        file.extend(writer.finish()?);
+
+        let reader = SerializedFileReader::new(bytes::Bytes::from(file)).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.file_metadata().num_rows(), 11);
+        assert_eq!(metadata.num_row_groups(), 3);
+        let schema: Vec<_> = metadata
+            .file_metadata()
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|column| (column.name().to_string(), column.physical_type()))
+            .collect();
+        assert_eq!(
+            schema,
+            [
+                ("px".to_string(), PhysicalType::DOUBLE),
+                ("sz".to_string(), PhysicalType::INT64),
+                ("note".to_string(), PhysicalType::BYTE_ARRAY),
+                ("is_cross".to_string(), PhysicalType::BOOLEAN),
+            ]
+        );
+
+        let rows: Vec<Vec<Field>> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap().get_column_iter().map(|(_, field)| field.clone()).collect())
+            .collect();
+        let expected: Vec<Vec<Field>> = (0..11).map(|i| row(i).iter().map(field).collect()).collect();
+        assert_eq!(rows, expected);
+        Ok(())
+    }
+}
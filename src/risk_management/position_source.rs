@@ -0,0 +1,296 @@
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use ethers::types::H160;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::BaseUrl;
+use crate::info::info_client::InfoClient;
+use crate::prelude::*;
+use crate::{Error, UserStateResponse};
+
+/// Where the position tracker reads account state from
+#[derive(Debug, Clone, PartialEq)]
+pub enum PositionSourceConfig {
+    /// Poll `clearinghouseState` from the API
+    Live,
+    /// Replay snapshots recorded in a JSON lines file
+    Replay { path: String },
+}
+
+/// Provides the account state the risk pipeline runs on
+#[async_trait]
+pub trait PositionSource: Send + Sync {
+    /// Returns the account state for `user`
+    async fn user_state(&mut self, user: H160) -> Result<UserStateResponse>;
+}
+
+/// Reads account state from the Hyperliquid API
+pub struct LivePositionSource {
+    info_client: InfoClient,
+}
+
+impl LivePositionSource {
+    /// Creates a live source for the given API
+    pub async fn new(base_url: BaseUrl) -> Result<Self> {
+        let info_client = InfoClient::new(None, Some(base_url)).await?;
+        Ok(Self { info_client })
+    }
+}
+
+#[async_trait]
+impl PositionSource for LivePositionSource {
+    async fn user_state(&mut self, user: H160) -> Result<UserStateResponse> {
+        self.info_client.user_state(user).await
+    }
+}
+
+/// A recorded account state and when it was taken (unix ms)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserStateSnapshot {
+    pub timestamp: u64,
+    pub user_state: UserStateResponse,
+}
+
+/// Replays recorded snapshots in timestamp order, one per request, and errors once they run out
+pub struct ReplayPositionSource {
+    snapshots: VecDeque<UserStateSnapshot>,
+    current_timestamp: Option<u64>,
+}
+
+impl ReplayPositionSource {
+    /// Loads snapshots from a JSON lines file with one `UserStateSnapshot` per line
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let snapshots = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str::<UserStateSnapshot>(line).map_err(|e| {
+                    Error::JsonParse(format!("{} line {}: {}", path, i + 1, e))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::from_snapshots(snapshots))
+    }
+
+    /// Creates a replay source from snapshots already in memory
+    pub fn from_snapshots(mut snapshots: Vec<UserStateSnapshot>) -> Self {
+        snapshots.sort_by_key(|snapshot| snapshot.timestamp);
+        Self {
+            snapshots: snapshots.into(),
+            current_timestamp: None,
+        }
+    }
+
+    /// Timestamp (unix ms) of the snapshot most recently returned
+    pub fn current_timestamp(&self) -> Option<u64> {
+        self.current_timestamp
+    }
+
+    /// Number of snapshots not yet replayed
+    pub fn remaining(&self) -> usize {
+        self.snapshots.len()
+    }
+}
+
+#[async_trait]
+impl PositionSource for ReplayPositionSource {
+    async fn user_state(&mut self, _user: H160) -> Result<UserStateResponse> {
+        let snapshot = self
+            .snapshots
+            .pop_front()
+            .ok_or_else(|| Error::Custom("Position replay exhausted".to_string()))?;
+        self.current_timestamp = Some(snapshot.timestamp);
+        Ok(snapshot.user_state)
+    }
+}
+
+/// In-memory source returning scripted states or errors in order. The last step repeats once the
+/// script runs out. Clones share the same script, so steps can be pushed after handing one off.
+#[derive(Clone, Default)]
+pub struct ScriptedPositionSource {
+    steps: Arc<Mutex<VecDeque<Result<UserStateResponse>>>>,
+}
+
+impl ScriptedPositionSource {
+    /// Creates an empty script
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a state to return
+    pub fn push_state(&self, user_state: UserStateResponse) {
+        self.steps.lock().unwrap().push_back(Ok(user_state));
+    }
+
+    /// Appends an error to return, e.g. to simulate an API outage
+    pub fn push_error(&self, error: Error) {
+        self.steps.lock().unwrap().push_back(Err(error));
+    }
+}
+
+#[async_trait]
+impl PositionSource for ScriptedPositionSource {
+    async fn user_state(&mut self, _user: H160) -> Result<UserStateResponse> {
+        let mut steps = self.steps.lock().unwrap();
+        match steps.len() {
+            0 => Err(Error::Custom("Scripted position source is empty".to_string())),
+            1 => steps[0].clone(),
+            _ => steps.pop_front().unwrap(),
+        }
+    }
+}
+
+/// Wraps another source and appends every state it returns to a JSON lines file that
+/// `ReplayPositionSource` can read back
+pub struct RecordingPositionSource<S> {
+    inner: S,
+    path: String,
+}
+
+impl<S: PositionSource> RecordingPositionSource<S> {
+    /// Records the states returned by `inner` to `path`
+    pub fn new(inner: S, path: &str) -> Self {
+        Self {
+            inner,
+            path: path.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: PositionSource> PositionSource for RecordingPositionSource<S> {
+    async fn user_state(&mut self, user: H160) -> Result<UserStateResponse> {
+        let user_state = self.inner.user_state(user).await?;
+        let snapshot = UserStateSnapshot {
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            user_state,
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&snapshot)?)?;
+        Ok(snapshot.user_state)
+    }
+}
+
+/// Builds the source selected by the configuration, recording its output if `record_path` is set
+pub async fn position_source_from_config(
+    source: &PositionSourceConfig,
+    base_url: BaseUrl,
+    record_path: Option<&str>,
+) -> Result<Box<dyn PositionSource>> {
+    let source: Box<dyn PositionSource> = match source {
+        PositionSourceConfig::Live => Box::new(LivePositionSource::new(base_url).await?),
+        PositionSourceConfig::Replay { path } => Box::new(ReplayPositionSource::from_file(path)?),
+    };
+    Ok(match record_path {
+        Some(path) => Box::new(RecordingPositionSource::new(source, path)),
+        None => source,
+    })
+}
+
+#[async_trait]
+impl PositionSource for Box<dyn PositionSource> {
+    async fn user_state(&mut self, user: H160) -> Result<UserStateResponse> {
+        (**self).user_state(user).await
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::risk_management::{
+        AttributionConfig, LiquidationForecastConfig, RiskConfig, RiskEventFilter, RiskEventKind, RiskLimits,
+        RiskManagementSystem, RiskScoringModel, VolatilityConfig,
+    };
+
+    pub(crate) fn user_state(btc_size: &str, account_value: &str) -> UserStateResponse {
+        serde_json::from_value(serde_json::json!({
+            "assetPositions": [{
+                "type": "oneWay",
+                "position": {
+                    "coin": "BTC", "entryPx": "50000", "liquidationPx": "40000",
+                    "leverage": {"type": "cross", "value": 5},
+                    "marginUsed": "10000", "positionValue": "50000", "returnOnEquity": "0",
+                    "szi": btc_size, "unrealizedPnl": "0", "maxLeverage": 50,
+                    "cumFunding": {"allTime": "0", "sinceOpen": "0", "sinceChange": "0"}
+                }
+            }],
+            "crossMarginSummary": {"accountValue": account_value, "totalMarginUsed": "10000", "totalNtlPos": "50000", "totalRawUsd": "0"},
+            "marginSummary": {"accountValue": account_value, "totalMarginUsed": "10000", "totalNtlPos": "50000", "totalRawUsd": "0"},
+            "withdrawable": "0"
+        }))
+        .unwrap()
+    }
+
+    pub(crate) fn offline_config() -> RiskConfig {
+        RiskConfig {
+            wallet_address: H160::zero(),
+            base_url: BaseUrl::Localhost,
+            database_url: None,
+            database_key: None,
+            risk_limits: RiskLimits::default(),
+            volatility: VolatilityConfig { enabled: false, ..Default::default() },
+            scoring_model: RiskScoringModel::default(),
+            decimal_mode: true,
+            attribution: AttributionConfig { enabled: false, ..Default::default() },
+            liquidation_forecast: LiquidationForecastConfig::default(),
+            position_source: PositionSourceConfig::Live,
+            record_positions_path: None,
+            enable_logging: false,
+            log_to_console: false,
+            log_to_database: false,
+            log_interval_seconds: 60,
+        }
+    }
+
+    #[tokio::test]
+    async fn recorded_states_replay_in_order() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("position_replay_{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        let script = ScriptedPositionSource::new();
+        script.push_state(user_state("1.0", "100000"));
+        script.push_state(user_state("2.0", "90000"));
+        let mut recorder = RecordingPositionSource::new(script, path);
+        recorder.user_state(H160::zero()).await?;
+        recorder.user_state(H160::zero()).await?;
+
+        let mut replay = ReplayPositionSource::from_file(path)?;
+        fs::remove_file(path)?;
+        assert_eq!(replay.remaining(), 2);
+        assert_eq!(replay.user_state(H160::zero()).await?.asset_positions[0].position.szi, "1.0");
+        assert_eq!(replay.user_state(H160::zero()).await?.margin_summary.account_value, "90000");
+        assert!(replay.current_timestamp().is_some());
+        assert!(replay.user_state(H160::zero()).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn risk_system_runs_on_scripted_source() -> Result<()> {
+        let script = ScriptedPositionSource::new();
+        script.push_state(user_state("1.0", "100000"));
+        script.push_error(Error::Custom("outage".to_string()));
+        script.push_state(user_state("2.0", "100000"));
+
+        let mut system = RiskManagementSystem::with_position_source(offline_config(), Box::new(script)).await?;
+        let mut events = system.subscribe(
+            RiskEventFilter::all().kinds([RiskEventKind::PositionResized, RiskEventKind::DataStale]),
+        );
+
+        let first = system.analyze_risk_profile().await?;
+        assert_eq!(first.positions[0].size, 1.0);
+        assert_eq!(first.portfolio_metrics.account_value, 100000.0);
+        assert!(system.analyze_risk_profile().await.is_err());
+        system.analyze_risk_profile().await?;
+
+        assert_eq!(events.try_recv().map(|e| e.kind()), Some(RiskEventKind::DataStale));
+        assert_eq!(events.try_recv().map(|e| e.kind()), Some(RiskEventKind::PositionResized));
+        Ok(())
+    }
+}
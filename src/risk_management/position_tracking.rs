@@ -1,27 +1,36 @@
 use ethers::types::H160;
-use crate::info::info_client::InfoClient;
 use crate::prelude::*;
-use crate::risk_management::{Position, RiskConfig};
+use crate::risk_management::{position_source_from_config, Position, PositionSource, RiskConfig};
+use crate::{decimal_to_f64, parse_decimal, Decimal, UserStateResponse};
 
 /// Position tracking system for monitoring open trading positions
 pub struct PositionTracker {
-    info_client: InfoClient,
+    source: Box<dyn PositionSource>,
     wallet_address: H160,
     last_positions: Vec<Position>,
     pub config: RiskConfig,
 }
 
 impl PositionTracker {
-    /// Creates a new position tracker with the provided configuration
+    /// Creates a new position tracker reading from the source selected in the configuration
     pub async fn new(config: RiskConfig) -> Result<Self> {
-        let info_client = InfoClient::new(None, Some(config.base_url)).await?;
+        let source = position_source_from_config(
+            &config.position_source,
+            config.base_url,
+            config.record_positions_path.as_deref(),
+        ).await?;
         
-        Ok(Self {
-            info_client,
+        Ok(Self::with_source(config, source))
+    }
+    
+    /// Creates a new position tracker reading from the given source
+    pub fn with_source(config: RiskConfig, source: Box<dyn PositionSource>) -> Self {
+        Self {
+            source,
             wallet_address: config.wallet_address,
             last_positions: Vec::new(),
             config,
-        })
+        }
     }
     
     /// Updates the configuration
@@ -30,19 +39,22 @@ impl PositionTracker {
         self.config = config;
     }
     
-    /// Fetches current positions from the Hyperliquid API
-    pub async fn get_current_positions(&mut self) -> Result<Vec<Position>> {
-        // Get user state from the API
-        let user_state = self.info_client.user_state(self.wallet_address).await?;
+    /// Fetches positions and the account summary from a single account state
+    pub async fn get_snapshot(&mut self) -> Result<(Vec<Position>, AccountSummary)> {
+        let user_state = self.source.user_state(self.wallet_address).await?;
+        let account_summary = self.account_summary_from(&user_state)?;
+        let positions = self.positions_from(user_state)?;
         
-        // Convert API response to our Position structs
-        let positions: Vec<Position> = user_state
-            .asset_positions
-            .into_iter()
-            .map(Position::from)
-            // Only include positions with non-zero size
-            .filter(|pos| pos.size.abs() > 0.0)
-            .collect();
+        // Update last positions
+        self.last_positions = positions.clone();
+        
+        Ok((positions, account_summary))
+    }
+    
+    /// Fetches current positions from the position source
+    pub async fn get_current_positions(&mut self) -> Result<Vec<Position>> {
+        let user_state = self.source.user_state(self.wallet_address).await?;
+        let positions = self.positions_from(user_state)?;
         
         // Update last positions
         self.last_positions = positions.clone();
@@ -50,20 +62,51 @@ impl PositionTracker {
         Ok(positions)
     }
     
+    fn positions_from(&self, user_state: UserStateResponse) -> Result<Vec<Position>> {
+        // Convert API response to our Position structs; in decimal mode a malformed
+        // number fails the fetch instead of becoming zero
+        let positions: Vec<Position> = if self.config.decimal_mode {
+            user_state
+                .asset_positions
+                .iter()
+                .map(Position::try_from_asset_position)
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            user_state
+                .asset_positions
+                .into_iter()
+                .map(Position::from)
+                .collect()
+        };
+        // Only include positions with non-zero size
+        Ok(positions
+            .into_iter()
+            .filter(|pos| pos.size.abs() > 0.0)
+            .collect())
+    }
+    
     /// Returns the most recently fetched positions without making a new API call
     pub fn get_last_positions(&self) -> Vec<Position> {
         self.last_positions.clone()
     }
     
     /// Fetches account summary information
-    pub async fn get_account_summary(&self) -> Result<AccountSummary> {
-        let user_state = self.info_client.user_state(self.wallet_address).await?;
+    pub async fn get_account_summary(&mut self) -> Result<AccountSummary> {
+        let user_state = self.source.user_state(self.wallet_address).await?;
+        self.account_summary_from(&user_state)
+    }
+    
+    fn account_summary_from(&self, user_state: &UserStateResponse) -> Result<AccountSummary> {
+        if self.config.decimal_mode {
+            return AccountSummary::try_from_user_state(user_state);
+        }
         
         Ok(AccountSummary {
             account_value: user_state.margin_summary.account_value.parse::<f64>().unwrap_or(0.0),
             margin_used: user_state.margin_summary.total_margin_used.parse::<f64>().unwrap_or(0.0),
             total_position_notional: user_state.margin_summary.total_ntl_pos.parse::<f64>().unwrap_or(0.0),
             withdrawable: user_state.withdrawable.parse::<f64>().unwrap_or(0.0),
+            exact_account_value: None,
         })
     }
     
@@ -83,4 +126,19 @@ pub struct AccountSummary {
     pub margin_used: f64,
     pub total_position_notional: f64,
     pub withdrawable: f64,
+    pub exact_account_value: Option<Decimal>,
+}
+
+impl AccountSummary {
+    /// Builds an account summary, returning an error if any numeric field fails to parse
+    pub fn try_from_user_state(user_state: &UserStateResponse) -> Result<Self> {
+        let account_value = parse_decimal("accountValue", &user_state.margin_summary.account_value)?;
+        Ok(AccountSummary {
+            account_value: decimal_to_f64(account_value),
+            margin_used: decimal_to_f64(parse_decimal("totalMarginUsed", &user_state.margin_summary.total_margin_used)?),
+            total_position_notional: decimal_to_f64(parse_decimal("totalNtlPos", &user_state.margin_summary.total_ntl_pos)?),
+            withdrawable: decimal_to_f64(parse_decimal("withdrawable", &user_state.withdrawable)?),
+            exact_account_value: Some(account_value),
+        })
+    }
 } 
\ No newline at end of file
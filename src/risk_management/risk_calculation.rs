@@ -1,15 +1,30 @@
+use std::collections::HashMap;
+
 use crate::prelude::*;
-use crate::risk_management::{Position, PortfolioMetrics, PositionMetrics, RiskConfig, AccountSummary};
+use crate::decimal_to_f64;
+use crate::risk_management::{
+    barrier_hit_probability, dollar_delta, forecast_cross_liquidation, signed_notional, Position, PortfolioMetrics, PositionMetrics,
+    RiskConfig, AccountSummary, ExactPortfolioTotals, ExactPosition, HeatBreakdown, LiquidationForecast,
+    RiskScoreBreakdown, BETA_BENCHMARK_COIN
+};
 
 /// Risk calculation engine for assessing position and portfolio risks
 pub struct RiskCalculator {
     config: RiskConfig,
+    daily_volatility: HashMap<String, f64>,
+    betas: HashMap<String, f64>,
+    correlations: HashMap<(String, String), f64>,
 }
 
 impl RiskCalculator {
     /// Creates a new risk calculator with the provided configuration
     pub fn new(config: RiskConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            daily_volatility: HashMap::new(),
+            betas: HashMap::new(),
+            correlations: HashMap::new(),
+        }
     }
     
     /// Updates the configuration
@@ -17,6 +32,35 @@ impl RiskCalculator {
         self.config = config;
     }
     
+    /// Updates the daily sigma (in percent) used for volatility-normalized metrics
+    pub fn update_volatility(&mut self, daily_volatility: HashMap<String, f64>) {
+        self.daily_volatility = daily_volatility;
+    }
+    
+    /// Updates the per-coin betas against BTC used for factor exposure
+    pub fn update_betas(&mut self, betas: HashMap<String, f64>) {
+        self.betas = betas;
+    }
+    
+    /// Updates the pairwise return correlations used for the cross-margin liquidation forecast
+    pub fn update_correlations(&mut self, correlations: HashMap<(String, String), f64>) {
+        self.correlations = correlations;
+    }
+    
+    /// Returns a coin's beta to BTC; BTC itself is 1 by definition
+    fn beta_to_btc(&self, coin: &str) -> Option<f64> {
+        if coin == BETA_BENCHMARK_COIN {
+            Some(1.0)
+        } else {
+            self.betas.get(coin).copied()
+        }
+    }
+    
+    /// Signed notional scaled by beta; coins without an estimate are treated as moving one-for-one with BTC
+    fn btc_equivalent_exposure(&self, position: &Position) -> f64 {
+        signed_notional(position) * self.beta_to_btc(&position.coin).unwrap_or(1.0)
+    }
+    
     /// Calculates portfolio-level risk metrics
     pub fn calculate_portfolio_metrics(&self, positions: &[Position], account_summary: &AccountSummary) -> Result<PortfolioMetrics> {
         if positions.is_empty() {
@@ -29,11 +73,23 @@ impl RiskCalculator {
                 account_value: account_summary.account_value,
                 total_position_value: 0.0,
                 average_leverage: 0.0,
+                net_dollar_delta: 0.0,
+                gross_dollar_delta: 0.0,
+                btc_equivalent_exposure: 0.0,
+                heat_breakdown: HeatBreakdown::default(),
+                exact_totals: None,
+                cross_liquidation_forecast: None,
             });
         }
         
+        // Exact sums are available when positions were parsed in decimal mode
+        let exact_totals = Self::calculate_exact_totals(positions, account_summary);
+        
         // Calculate total values
-        let total_position_value: f64 = positions.iter().map(|p| p.position_value.abs()).sum();
+        let total_position_value: f64 = match &exact_totals {
+            Some(totals) => decimal_to_f64(totals.total_position_value),
+            None => positions.iter().map(|p| p.position_value.abs()).sum(),
+        };
         
         // Separate cross and isolated positions for margin calculations
         let cross_positions: Vec<&Position> = positions.iter().filter(|p| p.is_cross).collect();
@@ -46,9 +102,15 @@ impl RiskCalculator {
         let isolated_margin_used: f64 = isolated_positions.iter().map(|p| p.margin_used).sum();
         
         // Total margin used is the sum of cross and isolated margins
-        let total_margin_used: f64 = cross_margin_used + isolated_margin_used;
+        let total_margin_used: f64 = match &exact_totals {
+            Some(totals) => decimal_to_f64(totals.total_margin_used),
+            None => cross_margin_used + isolated_margin_used,
+        };
         
-        let total_unrealized_pnl: f64 = positions.iter().map(|p| p.unrealized_pnl).sum();
+        let total_unrealized_pnl: f64 = match &exact_totals {
+            Some(totals) => decimal_to_f64(totals.total_unrealized_pnl),
+            None => positions.iter().map(|p| p.unrealized_pnl).sum(),
+        };
         
         // Use the account value from the account summary
         let account_value = account_summary.account_value;
@@ -90,12 +152,13 @@ impl RiskCalculator {
         let concentration_score = self.calculate_concentration_score(positions);
         
         // Calculate portfolio heat (0-100, higher means more risky)
-        let portfolio_heat = self.calculate_portfolio_heat(
+        let heat_breakdown = self.calculate_portfolio_heat(
             positions, 
             margin_utilization, 
             average_leverage, 
             concentration_score
         );
+        let portfolio_heat = heat_breakdown.total;
         
         // Calculate risk-adjusted return
         let total_return: f64 = positions.iter()
@@ -108,6 +171,26 @@ impl RiskCalculator {
             0.0
         };
         
+        // Factor exposure: PnL per 1% move and beta-adjusted BTC exposure
+        let net_dollar_delta: f64 = positions.iter().map(dollar_delta).sum();
+        let gross_dollar_delta: f64 = positions.iter().map(|p| dollar_delta(p).abs()).sum();
+        let btc_equivalent_exposure: f64 = positions.iter()
+            .map(|p| self.btc_equivalent_exposure(p))
+            .sum();
+        
+        // Probability the cross account as a whole is liquidated, accounting for correlated moves
+        let cross_liquidation_forecast = if self.config.liquidation_forecast.enabled {
+            forecast_cross_liquidation(
+                positions,
+                account_value,
+                &self.daily_volatility,
+                &self.correlations,
+                &self.config.liquidation_forecast,
+            )
+        } else {
+            None
+        };
+        
         Ok(PortfolioMetrics {
             portfolio_heat,
             concentration_score,
@@ -117,6 +200,26 @@ impl RiskCalculator {
             account_value,
             total_position_value,
             average_leverage,
+            net_dollar_delta,
+            gross_dollar_delta,
+            btc_equivalent_exposure,
+            heat_breakdown,
+            exact_totals,
+            cross_liquidation_forecast,
+        })
+    }
+    
+    /// Sums PnL, notional and margin exactly when every position carries decimal values
+    fn calculate_exact_totals(positions: &[Position], account_summary: &AccountSummary) -> Option<ExactPortfolioTotals> {
+        let exact: Vec<&ExactPosition> = positions.iter()
+            .map(|p| p.exact.as_ref())
+            .collect::<Option<Vec<_>>>()?;
+        
+        Some(ExactPortfolioTotals {
+            total_unrealized_pnl: exact.iter().map(|e| e.unrealized_pnl).sum(),
+            total_position_value: exact.iter().map(|e| e.position_value.abs()).sum(),
+            total_margin_used: exact.iter().map(|e| e.margin_used).sum(),
+            account_value: account_summary.exact_account_value,
         })
     }
     
@@ -139,6 +242,15 @@ impl RiskCalculator {
             // Calculate distance to liquidation
             let distance_to_liquidation = self.calculate_distance_to_liquidation(position);
             
+            // Normalize the distance by the coin's realized volatility, if known
+            let daily_volatility = self.daily_volatility.get(&position.coin).copied();
+            let liquidation_distance_sigma = self.calculate_liquidation_distance_sigma(position);
+            let liquidation_probability_24h = liquidation_distance_sigma
+                .map(|sigma_distance| barrier_hit_probability(sigma_distance, 1.0));
+            let liquidation_forecast = liquidation_distance_sigma
+                .filter(|_| self.config.liquidation_forecast.enabled)
+                .map(LiquidationForecast::from_sigma_distance);
+            
             // Calculate position size ratio relative to account value
             let position_size_ratio = if account_value > 0.0 {
                 (position.margin_used / account_value) * 100.0
@@ -147,11 +259,12 @@ impl RiskCalculator {
             };
             
             // Calculate overall risk score for the position (0-100)
-            let risk_score = self.calculate_position_risk_score(
+            let risk_score_breakdown = self.calculate_position_risk_score(
                 position,
                 distance_to_liquidation,
                 position_size_ratio
             );
+            let risk_score = risk_score_breakdown.total;
             
             // Calculate position's contribution to overall portfolio risk
             let contribution_to_portfolio = if total_position_value > 0.0 {
@@ -166,6 +279,14 @@ impl RiskCalculator {
                 position_size_ratio,
                 risk_score,
                 contribution_to_portfolio,
+                daily_volatility,
+                liquidation_distance_sigma,
+                liquidation_probability_24h,
+                dollar_delta: dollar_delta(position),
+                beta_to_btc: self.beta_to_btc(&position.coin),
+                btc_equivalent_exposure: self.btc_equivalent_exposure(position),
+                risk_score_breakdown,
+                liquidation_forecast,
             });
         }
         
@@ -183,7 +304,7 @@ impl RiskCalculator {
         let liquidation_price = position.liquidation_price.unwrap();
         
         // Calculate the distance based on the position direction
-        let distance = if position.size > 0.0 {
+        if position.size > 0.0 {
             // Long position - liquidation price is below entry
             if liquidation_price < entry_price {
                 ((entry_price - liquidation_price) / entry_price) * 100.0
@@ -199,56 +320,73 @@ impl RiskCalculator {
                 // Unexpected case, return a high value
                 100.0
             }
-        };
+        }
+    }
+    
+    /// Calculates the gap between mark price and liquidation price in daily-sigma units
+    fn calculate_liquidation_distance_sigma(&self, position: &Position) -> Option<f64> {
+        let daily_sigma = *self.daily_volatility.get(&position.coin)? / 100.0;
+        let liquidation_price = position.liquidation_price?;
+        let mark_price = position.mark_price()?;
+        if daily_sigma <= 0.0 || liquidation_price <= 0.0 {
+            return None;
+        }
         
-        distance
+        // Log distance in the adverse direction; zero if the mark is already through the liquidation price
+        let log_distance = if position.size > 0.0 {
+            (mark_price / liquidation_price).ln()
+        } else {
+            (liquidation_price / mark_price).ln()
+        };
+        Some(log_distance.max(0.0) / daily_sigma)
     }
     
-    /// Calculates a risk score (0-100) for a position
+    /// Returns the liquidation distance relative to its configured minimum (1.0 = at the threshold).
+    /// Uses daily-sigma units when volatility scoring is enabled and an estimate exists, percentages otherwise.
+    fn liquidation_distance_ratio(&self, position: &Position, distance_to_liquidation: f64) -> f64 {
+        if self.config.volatility.use_for_scoring {
+            if let Some(sigma_distance) = self.calculate_liquidation_distance_sigma(position) {
+                return sigma_distance / self.config.risk_limits.min_liq_distance_sigma;
+            }
+        }
+        distance_to_liquidation / self.config.risk_limits.min_distance_to_liq
+    }
+    
+    /// Calculates a risk score (0-100) for a position, broken down by component
     fn calculate_position_risk_score(
         &self, 
         position: &Position,
         distance_to_liquidation: f64,
         position_size_ratio: f64,
-    ) -> f64 {
-        // Leverage component (0-40 points)
+    ) -> RiskScoreBreakdown {
+        let weights = &self.config.scoring_model.position;
+        
+        // Leverage component
         // For isolated margin, leverage risk is higher since it can't use margin from other positions
         let max_leverage = self.config.risk_limits.max_leverage;
-        let leverage_factor = if position.is_cross {
-            // Cross margin positions have slightly lower risk
-            (position.leverage / max_leverage) * 35.0
-        } else {
-            // Isolated margin positions have higher risk
-            (position.leverage / max_leverage) * 40.0
-        };
+        let leverage_factor = (position.leverage / max_leverage) * weights.leverage.for_position(position.is_cross);
         
-        // Liquidation distance component (0-40 points)
+        // Liquidation distance component
         // Smaller distance = higher risk
-        // For isolated margin, liquidation risk is more localized
-        let min_distance = self.config.risk_limits.min_distance_to_liq;
-        let distance_factor = if distance_to_liquidation < min_distance {
-            if position.is_cross {
-                // Cross margin has more buffer before liquidation
-                35.0 * (1.0 - (distance_to_liquidation / min_distance))
-            } else {
-                // Isolated margin has higher liquidation risk
-                40.0 * (1.0 - (distance_to_liquidation / min_distance))
-            }
+        let distance_ratio = self.liquidation_distance_ratio(position, distance_to_liquidation);
+        let distance_factor = if distance_ratio < 1.0 {
+            weights.liquidation.for_position(position.is_cross) * (1.0 - distance_ratio)
         } else {
             0.0
         };
         
-        // Position size component (0-20 points for cross, 0-25 for isolated)
+        // Position size component
         let max_position_pct = self.config.risk_limits.max_position_pct;
-        let size_factor = if position.is_cross {
-            (position_size_ratio / max_position_pct) * 20.0
-        } else {
-            (position_size_ratio / max_position_pct) * 25.0
-        };
+        let size_factor = (position_size_ratio / max_position_pct) * weights.size.for_position(position.is_cross);
         
         // Sum all factors and cap at 100
         let score = leverage_factor + distance_factor + size_factor;
-        score.min(100.0).max(0.0)
+        RiskScoreBreakdown {
+            leverage: leverage_factor,
+            liquidation: distance_factor,
+            size: size_factor,
+            total: score.clamp(0.0, 100.0),
+        }
     }
     
     /// Calculates concentration score based on position distribution
@@ -280,17 +418,19 @@ impl RiskCalculator {
         let min_hhi = 1.0 / positions.len() as f64;
         let normalized_score = ((sum_squared_shares - min_hhi) / (1.0 - min_hhi)) * 100.0;
         
-        normalized_score.min(100.0).max(0.0)
+        normalized_score.clamp(0.0, 100.0)
     }
     
-    /// Calculates portfolio heat based on multiple risk factors
+    /// Calculates portfolio heat based on multiple risk factors, broken down by component
     fn calculate_portfolio_heat(
         &self,
         positions: &[Position],
         margin_utilization: f64,
         _average_leverage: f64, // Prefix with underscore since we're not using it anymore
         concentration_score: f64,
-    ) -> f64 {
+    ) -> HeatBreakdown {
+        let weights = &self.config.scoring_model.heat;
+        
         // Separate cross and isolated positions
         let cross_positions: Vec<&Position> = positions.iter().filter(|p| p.is_cross).collect();
         let isolated_positions: Vec<&Position> = positions.iter().filter(|p| !p.is_cross).collect();
@@ -300,7 +440,7 @@ impl RiskCalculator {
         let cross_position_value: f64 = cross_positions.iter().map(|p| p.position_value.abs()).sum();
         let isolated_position_value: f64 = isolated_positions.iter().map(|p| p.position_value.abs()).sum();
         
-        // Leverage component (0-30 points)
+        // Leverage component
         let max_leverage = self.config.risk_limits.max_leverage;
         
         // Calculate weighted leverage factor based on position types
@@ -309,7 +449,7 @@ impl RiskCalculator {
                 .map(|p| p.leverage * p.position_value.abs())
                 .sum::<f64>() / cross_position_value;
             
-            (cross_avg_leverage / max_leverage) * 25.0 * (cross_position_value / total_position_value)
+            (cross_avg_leverage / max_leverage) * weights.leverage.cross * (cross_position_value / total_position_value)
         } else {
             0.0
         };
@@ -319,40 +459,44 @@ impl RiskCalculator {
                 .map(|p| p.leverage * p.position_value.abs())
                 .sum::<f64>() / isolated_position_value;
             
-            (isolated_avg_leverage / max_leverage) * 30.0 * (isolated_position_value / total_position_value)
+            (isolated_avg_leverage / max_leverage) * weights.leverage.isolated * (isolated_position_value / total_position_value)
         } else {
             0.0
         };
         
         let leverage_factor = cross_leverage_factor + isolated_leverage_factor;
         
-        // Margin utilization component (0-40 points)
+        // Margin utilization component
         let max_margin = self.config.risk_limits.max_margin_utilization;
-        let margin_factor = (margin_utilization / max_margin) * 40.0;
+        let margin_factor = (margin_utilization / max_margin) * weights.margin_utilization;
         
-        // Concentration component (0-20 points)
-        let concentration_factor = concentration_score * 0.2;
+        // Concentration component (score is 0-100)
+        let concentration_factor = (concentration_score / 100.0) * weights.concentration;
         
-        // Liquidation risk component (0-10 points)
+        // Liquidation risk component
         // Isolated positions have higher liquidation risk
-        let cross_liquidation_factor = cross_positions.iter()
-            .map(|p| self.calculate_distance_to_liquidation(p))
-            .filter(|&d| d < self.config.risk_limits.min_distance_to_liq)
-            .map(|d| 8.0 * (1.0 - (d / self.config.risk_limits.min_distance_to_liq)))
-            .sum::<f64>()
-            .min(8.0) * (cross_position_value / total_position_value);
-            
-        let isolated_liquidation_factor = isolated_positions.iter()
-            .map(|p| self.calculate_distance_to_liquidation(p))
-            .filter(|&d| d < self.config.risk_limits.min_distance_to_liq)
-            .map(|d| 10.0 * (1.0 - (d / self.config.risk_limits.min_distance_to_liq)))
-            .sum::<f64>()
-            .min(10.0) * (isolated_position_value / total_position_value);
-            
-        let liquidation_factor = cross_liquidation_factor + isolated_liquidation_factor;
+        let liquidation_component = |group: &[&Position], weight: f64, group_value: f64| {
+            if total_position_value <= 0.0 {
+                return 0.0;
+            }
+            group.iter()
+                .map(|p| self.liquidation_distance_ratio(p, self.calculate_distance_to_liquidation(p)))
+                .filter(|&ratio| ratio < 1.0)
+                .map(|ratio| weight * (1.0 - ratio))
+                .sum::<f64>()
+                .min(weight) * (group_value / total_position_value)
+        };
+        let liquidation_factor = liquidation_component(&cross_positions, weights.liquidation.cross, cross_position_value)
+            + liquidation_component(&isolated_positions, weights.liquidation.isolated, isolated_position_value);
         
         // Sum all factors and cap at 100
         let heat = leverage_factor + margin_factor + concentration_factor + liquidation_factor;
-        heat.min(100.0).max(0.0)
+        HeatBreakdown {
+            leverage: leverage_factor,
+            margin_utilization: margin_factor,
+            concentration: concentration_factor,
+            liquidation: liquidation_factor,
+            total: heat.clamp(0.0, 100.0),
+        }
     }
-} 
\ No newline at end of file
+}
@@ -1,6 +1,6 @@
 use crate::prelude::*;
 use crate::risk_management::{
-    PortfolioMetrics, PositionMetrics, RiskConfig, RiskWarning, RiskWarningType, RiskSeverity
+    LiquidationForecast, PortfolioMetrics, PositionMetrics, RiskConfig, RiskWarning, RiskWarningType, RiskSeverity
 };
 
 /// Risk limits and warnings system for enforcing risk management boundaries
@@ -104,6 +104,50 @@ impl RiskLimiter {
             });
         }
         
+        // Check net beta-adjusted exposure
+        let max_beta_exposure = self.config.risk_limits.max_net_beta_exposure_usd;
+        let beta_exposure = metrics.btc_equivalent_exposure.abs();
+        if max_beta_exposure > 0.0 && beta_exposure > max_beta_exposure * 0.9 {
+            let severity = if beta_exposure >= max_beta_exposure * 1.25 {
+                RiskSeverity::Critical
+            } else if beta_exposure >= max_beta_exposure {
+                RiskSeverity::High
+            } else {
+                RiskSeverity::Medium
+            };
+            let direction = if metrics.btc_equivalent_exposure >= 0.0 { "long" } else { "short" };
+            
+            warnings.push(RiskWarning {
+                warning_type: RiskWarningType::BetaExposureExceeded,
+                severity,
+                message: format!(
+                    "Net beta-adjusted exposure is ${:.2} {} BTC-equivalent (limit: ${:.2})",
+                    beta_exposure, direction, max_beta_exposure
+                ),
+                suggested_action: format!(
+                    "Reduce {} exposure to high-beta coins or hedge with an opposite BTC position.",
+                    direction
+                ),
+                related_position: None,
+            });
+        }
+        
+        // Check the probability that the cross account is liquidated
+        if let Some(forecast) = &metrics.cross_liquidation_forecast {
+            if let Some((severity, hours, probability)) = self.liquidation_probability_breach(forecast) {
+                warnings.push(RiskWarning {
+                    warning_type: RiskWarningType::LiquidationProbability,
+                    severity,
+                    message: format!(
+                        "Cross margin account has a {:.1}% chance of liquidation within {}h (limit: {:.1}%)",
+                        probability * 100.0, hours, self.config.risk_limits.max_liquidation_probability * 100.0
+                    ),
+                    suggested_action: "Add collateral or reduce correlated cross positions to widen the liquidation buffer.".to_string(),
+                    related_position: None,
+                });
+            }
+        }
+        
         // Check average leverage
         let max_leverage = self.config.risk_limits.max_leverage;
         if metrics.average_leverage > max_leverage * 0.8 {
@@ -210,6 +254,28 @@ impl RiskLimiter {
                 });
             }
             
+            // Check the forecast probability of reaching the liquidation price
+            if let Some(forecast) = &metrics.liquidation_forecast {
+                if let Some((severity, hours, probability)) = self.liquidation_probability_breach(forecast) {
+                    let margin_type = if position.is_cross { "cross" } else { "isolated" };
+                    
+                    warnings.push(RiskWarning {
+                        warning_type: RiskWarningType::LiquidationProbability,
+                        severity,
+                        message: format!(
+                            "{}: {} margin position has a {:.1}% chance of liquidation within {}h (limit: {:.1}%)",
+                            position.coin, margin_type, probability * 100.0, hours,
+                            self.config.risk_limits.max_liquidation_probability * 100.0
+                        ),
+                        suggested_action: format!(
+                            "Reduce the {} position or add margin to move the liquidation price further from the mark.",
+                            position.coin
+                        ),
+                        related_position: Some(position.coin.clone()),
+                    });
+                }
+            }
+            
             // Check position size
             let max_position_pct = self.config.risk_limits.max_position_pct;
             // For isolated margin, we apply stricter position size limits
@@ -264,4 +330,20 @@ impl RiskLimiter {
             }
         }
     }
-} 
\ No newline at end of file
+    
+    /// Returns the severity, horizon (hours) and probability of the shortest horizon whose liquidation
+    /// probability reaches the limit: 1h is Critical, 8h is High and 24h is Medium
+    fn liquidation_probability_breach(&self, forecast: &LiquidationForecast) -> Option<(RiskSeverity, usize, f64)> {
+        let limit = self.config.risk_limits.max_liquidation_probability;
+        if limit <= 0.0 {
+            return None;
+        }
+        
+        let severities = [RiskSeverity::Critical, RiskSeverity::High, RiskSeverity::Medium];
+        forecast.horizons()
+            .into_iter()
+            .zip(severities)
+            .find(|((_, probability), _)| *probability >= limit)
+            .map(|((hours, probability), severity)| (severity, hours, probability))
+    }
+}
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::Error;
+
+/// A weight that differs between cross and isolated margin positions
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct MarginTypeWeights {
+    pub cross: f64,
+    pub isolated: f64,
+}
+
+impl MarginTypeWeights {
+    /// Returns the weight for the given margin type
+    pub fn for_position(&self, is_cross: bool) -> f64 {
+        if is_cross {
+            self.cross
+        } else {
+            self.isolated
+        }
+    }
+}
+
+/// Maximum points each component contributes to a position's 0-100 risk score
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PositionScoreWeights {
+    pub leverage: MarginTypeWeights,
+    pub liquidation: MarginTypeWeights,
+    pub size: MarginTypeWeights,
+}
+
+/// Maximum points each component contributes to the 0-100 portfolio heat
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HeatWeights {
+    pub leverage: MarginTypeWeights,
+    pub margin_utilization: f64,
+    pub concentration: f64,
+    pub liquidation: MarginTypeWeights,
+}
+
+/// Component weights used by the risk calculator for position scores and portfolio heat
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RiskScoringModel {
+    pub name: String,
+    pub position: PositionScoreWeights,
+    pub heat: HeatWeights,
+}
+
+impl Default for RiskScoringModel {
+    fn default() -> Self {
+        Self {
+            name: "default".to_string(),
+            position: PositionScoreWeights {
+                leverage: MarginTypeWeights { cross: 35.0, isolated: 40.0 },
+                liquidation: MarginTypeWeights { cross: 35.0, isolated: 40.0 },
+                size: MarginTypeWeights { cross: 20.0, isolated: 25.0 },
+            },
+            heat: HeatWeights {
+                leverage: MarginTypeWeights { cross: 25.0, isolated: 30.0 },
+                margin_utilization: 40.0,
+                concentration: 20.0,
+                liquidation: MarginTypeWeights { cross: 8.0, isolated: 10.0 },
+            },
+        }
+    }
+}
+
+impl RiskScoringModel {
+    /// Names of the built-in presets
+    pub const PRESETS: [&'static str; 3] = ["default", "conservative", "aggressive"];
+
+    /// Returns a built-in preset by name
+    pub fn preset(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "default" => Ok(Self::default()),
+            // Penalizes liquidation proximity and isolated margin more heavily
+            "conservative" => Ok(Self {
+                name: "conservative".to_string(),
+                position: PositionScoreWeights {
+                    leverage: MarginTypeWeights { cross: 35.0, isolated: 45.0 },
+                    liquidation: MarginTypeWeights { cross: 45.0, isolated: 55.0 },
+                    size: MarginTypeWeights { cross: 25.0, isolated: 30.0 },
+                },
+                heat: HeatWeights {
+                    leverage: MarginTypeWeights { cross: 30.0, isolated: 35.0 },
+                    margin_utilization: 45.0,
+                    concentration: 25.0,
+                    liquidation: MarginTypeWeights { cross: 12.0, isolated: 15.0 },
+                },
+            }),
+            // Tolerates leverage and concentration, focuses on margin headroom
+            "aggressive" => Ok(Self {
+                name: "aggressive".to_string(),
+                position: PositionScoreWeights {
+                    leverage: MarginTypeWeights { cross: 25.0, isolated: 30.0 },
+                    liquidation: MarginTypeWeights { cross: 35.0, isolated: 40.0 },
+                    size: MarginTypeWeights { cross: 10.0, isolated: 15.0 },
+                },
+                heat: HeatWeights {
+                    leverage: MarginTypeWeights { cross: 15.0, isolated: 20.0 },
+                    margin_utilization: 40.0,
+                    concentration: 10.0,
+                    liquidation: MarginTypeWeights { cross: 8.0, isolated: 10.0 },
+                },
+            }),
+            _ => Err(Error::Custom(format!(
+                "Unknown risk scoring preset: {}. Valid presets are: {}",
+                name,
+                Self::PRESETS.join(", ")
+            ))),
+        }
+    }
+}
+
+/// How a scoring model is given in the settings file: a preset name or explicit weights
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ScoringModelSetting {
+    Preset(String),
+    Custom(RiskScoringModel),
+}
+
+impl ScoringModelSetting {
+    /// Resolves the setting into a concrete model
+    pub fn resolve(&self) -> Result<RiskScoringModel> {
+        match self {
+            ScoringModelSetting::Preset(name) => RiskScoringModel::preset(name),
+            ScoringModelSetting::Custom(model) => Ok(model.clone()),
+        }
+    }
+}
+
+/// Points contributed by each component of a position's risk score
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RiskScoreBreakdown {
+    pub leverage: f64,
+    pub liquidation: f64,
+    pub size: f64,
+    pub total: f64,
+}
+
+/// Points contributed by each component of the portfolio heat
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HeatBreakdown {
+    pub leverage: f64,
+    pub margin_utilization: f64,
+    pub concentration: f64,
+    pub liquidation: f64,
+    pub total: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_accept_preset_names_or_weights() {
+        let preset: ScoringModelSetting = serde_json::from_str("\"conservative\"").unwrap();
+        assert_eq!(preset.resolve().unwrap(), RiskScoringModel::preset("conservative").unwrap());
+
+        let custom_json = serde_json::to_string(&RiskScoringModel::default()).unwrap();
+        let custom: ScoringModelSetting = serde_json::from_str(&custom_json).unwrap();
+        assert_eq!(custom.resolve().unwrap(), RiskScoringModel::default());
+
+        let unknown: ScoringModelSetting = serde_json::from_str("\"reckless\"").unwrap();
+        assert!(unknown.resolve().is_err());
+    }
+}
@@ -0,0 +1,246 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::error;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::time::{self, MissedTickBehavior};
+
+use crate::prelude::*;
+use crate::Error;
+use super::{
+    attribute_with_equity, DataLogger, FeeAnalytics, FeeAnalyzer, PnlAttribution, PnlAttributor, RiskAnalysisResult,
+    RiskConfig, RiskManagementSystem, RiskSummary, UserSettings,
+};
+
+// Requests queued for the owner task before callers wait for a free slot
+const COMMAND_CAPACITY: usize = 32;
+
+/// Immutable view of the risk system after an analysis tick
+#[derive(Debug, Clone)]
+pub struct RiskSnapshot {
+    /// Latest successful analysis, kept through failed ticks so readers always have something to show
+    pub analysis: Option<RiskAnalysisResult>,
+    pub summary: Option<RiskSummary>,
+    pub settings: UserSettings,
+    /// When `analysis` was computed (unix ms)
+    pub updated_at: Option<u64>,
+    /// Why the latest tick failed, if it did
+    pub last_error: Option<String>,
+    /// Number of ticks so far, successful or not
+    pub sequence: u64,
+}
+
+impl RiskSnapshot {
+    /// Milliseconds since `analysis` was computed
+    pub fn age_ms(&self) -> Option<u64> {
+        self.updated_at
+            .map(|updated_at| (chrono::Utc::now().timestamp_millis() as u64).saturating_sub(updated_at))
+    }
+}
+
+enum RiskCommand {
+    UpdateSettings(Box<UserSettings>, oneshot::Sender<Result<()>>),
+}
+
+/// Handle to a background task that owns a `RiskManagementSystem`, analyzes it on a fixed interval
+/// and publishes every result as an immutable snapshot.
+///
+/// Readers take the latest snapshot without locking or calling the API. Settings changes are queued
+/// to the owner task and run between ticks. PnL attribution and fee analytics page through fill
+/// history, so each runs on a task of its own with its own API client instead of holding up ticks.
+/// Clones share the same task, which stops once every handle is dropped.
+#[derive(Clone)]
+pub struct RiskService {
+    commands: mpsc::Sender<RiskCommand>,
+    snapshots: watch::Receiver<Arc<RiskSnapshot>>,
+    /// Configuration as of the latest settings change
+    config: watch::Receiver<RiskConfig>,
+}
+
+impl RiskService {
+    /// Spawns the owner task on the current runtime; the first analysis runs immediately
+    pub fn spawn(system: RiskManagementSystem, interval: Duration) -> Self {
+        let (commands, command_receiver) = mpsc::channel(COMMAND_CAPACITY);
+        let (snapshot_sender, snapshots) = watch::channel(Arc::new(RiskSnapshot {
+            analysis: None,
+            summary: None,
+            settings: system.get_config().get_user_settings(),
+            updated_at: None,
+            last_error: None,
+            sequence: 0,
+        }));
+        let (config_sender, config) = watch::channel(system.get_config());
+        tokio::spawn(run(system, interval, command_receiver, snapshot_sender, config_sender));
+        Self {
+            commands,
+            snapshots,
+            config,
+        }
+    }
+
+    /// The latest snapshot
+    pub fn snapshot(&self) -> Arc<RiskSnapshot> {
+        self.snapshots.borrow().clone()
+    }
+
+    /// Receiver that is notified after every tick
+    pub fn subscribe(&self) -> watch::Receiver<Arc<RiskSnapshot>> {
+        self.snapshots.clone()
+    }
+
+    /// Applies new settings and analyzes with them before returning
+    pub async fn update_settings(&self, settings: UserSettings) -> Result<()> {
+        self.request(|reply| RiskCommand::UpdateSettings(Box::new(settings), reply)).await?
+    }
+
+    /// Attributes PnL over an interval (unix ms)
+    pub async fn attribute_pnl(&self, start_time: u64, end_time: u64) -> Result<PnlAttribution> {
+        let config = self.config.borrow().clone();
+        spawn_report(async move {
+            let pnl_attributor = PnlAttributor::new(config.clone()).await?;
+            attribute_with_equity(&pnl_attributor, &DataLogger::new(config), start_time, end_time).await
+        })
+        .await
+    }
+
+    /// Fee tier and trading cost analytics over the last `lookback_days` of fills
+    pub async fn get_fee_analytics(&self, lookback_days: u64) -> Result<FeeAnalytics> {
+        let config = self.config.borrow().clone();
+        spawn_report(async move { FeeAnalyzer::new(config).await?.analyze(lookback_days).await }).await
+    }
+
+    async fn request<T>(&self, command: impl FnOnce(oneshot::Sender<T>) -> RiskCommand) -> Result<T> {
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(command(reply))
+            .await
+            .map_err(|_| Error::Custom("Risk service has stopped".to_string()))?;
+        response
+            .await
+            .map_err(|_| Error::Custom("Risk service has stopped".to_string()))
+    }
+}
+
+async fn run(
+    mut system: RiskManagementSystem,
+    interval: Duration,
+    mut commands: mpsc::Receiver<RiskCommand>,
+    snapshots: watch::Sender<Arc<RiskSnapshot>>,
+    config: watch::Sender<RiskConfig>,
+) {
+    let mut ticker = time::interval(interval);
+    // A slow analysis pushes the next tick back instead of causing a burst of catch-up ticks
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => analyze(&mut system, &snapshots).await,
+            command = commands.recv() => match command {
+                Some(RiskCommand::UpdateSettings(settings, reply)) => {
+                    let result = system.update_settings(*settings);
+                    if result.is_ok() {
+                        config.send_replace(system.get_config());
+                        analyze(&mut system, &snapshots).await;
+                        ticker.reset();
+                    }
+                    let _ = reply.send(result);
+                }
+                None => break,
+            },
+        }
+    }
+}
+
+/// Runs a report on a task of its own, away from the owner task
+async fn spawn_report<T: Send + 'static>(report: impl Future<Output = Result<T>> + Send + 'static) -> Result<T> {
+    tokio::spawn(report)
+        .await
+        .map_err(|e| Error::Custom(format!("Risk report task failed: {e}")))?
+}
+
+async fn analyze(system: &mut RiskManagementSystem, snapshots: &watch::Sender<Arc<RiskSnapshot>>) {
+    let mut snapshot = RiskSnapshot::clone(&snapshots.borrow());
+    match system.analyze_risk_profile().await {
+        Ok(analysis) => {
+            snapshot.summary = Some(RiskSummary::from_analysis(&analysis));
+            snapshot.analysis = Some(analysis);
+            snapshot.updated_at = Some(chrono::Utc::now().timestamp_millis() as u64);
+            snapshot.last_error = None;
+        }
+        Err(e) => {
+            error!("Failed to update risk analysis: {}", e);
+            snapshot.last_error = Some(e.to_string());
+        }
+    }
+    snapshot.settings = system.get_config().get_user_settings();
+    snapshot.sequence += 1;
+    snapshots.send_replace(Arc::new(snapshot));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::risk_management::position_source::tests::{offline_config, user_state};
+    use crate::risk_management::ScriptedPositionSource;
+    use crate::mock_server::{MockEndpoint, MockFault, MockFixtures, MockServer};
+
+    #[tokio::test]
+    async fn snapshots_follow_ticks() -> Result<()> {
+        let script = ScriptedPositionSource::new();
+        script.push_state(user_state("1.0", "100000"));
+        script.push_error(Error::Custom("outage".to_string()));
+        script.push_state(user_state("2.0", "100000"));
+
+        let system = RiskManagementSystem::with_position_source(offline_config(), Box::new(script)).await?;
+        let service = RiskService::spawn(system, Duration::from_millis(200));
+        let mut snapshots = service.subscribe();
+
+        // The first tick runs right away
+        snapshots.changed().await.unwrap();
+        let first = snapshots.borrow_and_update().clone();
+        assert_eq!(first.sequence, 1);
+        assert_eq!(first.analysis.as_ref().unwrap().positions[0].size, 1.0);
+        assert!(first.age_ms().is_some() && first.last_error.is_none());
+
+        // A failed tick keeps the last good analysis
+        snapshots.changed().await.unwrap();
+        let failed = snapshots.borrow_and_update().clone();
+        assert_eq!(failed.sequence, 2);
+        assert!(failed.last_error.as_deref().unwrap().contains("outage"));
+        assert_eq!(failed.updated_at, first.updated_at);
+        assert_eq!(failed.analysis.as_ref().unwrap().positions[0].size, 1.0);
+
+        snapshots.changed().await.unwrap();
+        let second = snapshots.borrow_and_update().clone();
+        assert_eq!(second.analysis.as_ref().unwrap().positions[0].size, 2.0);
+        assert!(second.last_error.is_none());
+        assert_eq!(service.snapshot().updated_at, second.updated_at);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_do_not_hold_up_ticks() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        server.inject_fault(MockEndpoint::Info, MockFault::Latency(Duration::from_secs(1)), None);
+        let config = RiskConfig { base_url: server.base_url(), ..offline_config() };
+        let script = ScriptedPositionSource::new();
+        script.push_state(user_state("1.0", "100000"));
+
+        let system = RiskManagementSystem::with_position_source(config, Box::new(script)).await?;
+        let service = RiskService::spawn(system, Duration::from_millis(50));
+        let fees = tokio::spawn({
+            let service = service.clone();
+            async move { service.get_fee_analytics(1).await }
+        });
+
+        // Ticks keep coming while the fee analytics wait on the slow API
+        let sequence = service.snapshot().sequence;
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(service.snapshot().sequence >= sequence + 2);
+        assert!(!fees.is_finished());
+        // The mock has no userFees fixture
+        assert!(fees.await.unwrap().is_err());
+        Ok(())
+    }
+}
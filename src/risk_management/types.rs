@@ -1,4 +1,7 @@
 use crate::info::AssetPosition;
+use crate::prelude::*;
+use crate::risk_management::{HeatBreakdown, LiquidationForecast, RiskScoreBreakdown};
+use crate::{decimal_to_f64, parse_decimal, parse_optional_decimal, Decimal};
 use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -16,6 +19,69 @@ pub struct Position {
     pub return_on_equity: f64,
     pub is_cross: bool,
     pub max_leverage: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exact: Option<ExactPosition>,     // exact API values, present when decimal mode is enabled
+}
+
+/// Exact decimal values of a position as reported by the API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExactPosition {
+    pub size: Decimal,
+    pub entry_price: Option<Decimal>,
+    pub liquidation_price: Option<Decimal>,
+    pub unrealized_pnl: Decimal,
+    pub margin_used: Decimal,
+    pub position_value: Decimal,
+    pub return_on_equity: Decimal,
+}
+
+impl TryFrom<&AssetPosition> for ExactPosition {
+    type Error = crate::Error;
+
+    fn try_from(asset_position: &AssetPosition) -> Result<Self> {
+        let position = &asset_position.position;
+        Ok(ExactPosition {
+            size: parse_decimal("szi", &position.szi)?,
+            entry_price: parse_optional_decimal("entryPx", position.entry_px.as_ref())?,
+            liquidation_price: parse_optional_decimal("liquidationPx", position.liquidation_px.as_ref())?,
+            unrealized_pnl: parse_decimal("unrealizedPnl", &position.unrealized_pnl)?,
+            margin_used: parse_decimal("marginUsed", &position.margin_used)?,
+            position_value: parse_decimal("positionValue", &position.position_value)?,
+            return_on_equity: parse_decimal("returnOnEquity", &position.return_on_equity)?,
+        })
+    }
+}
+
+impl Position {
+    /// Current mark price implied by position value and size
+    pub fn mark_price(&self) -> Option<f64> {
+        if self.size.abs() < f64::EPSILON {
+            None
+        } else {
+            Some(self.position_value.abs() / self.size.abs())
+        }
+    }
+    
+    /// Converts an API position, returning an error if any numeric field fails to parse
+    /// instead of treating it as zero. The exact values are kept alongside the `f64` ones.
+    pub fn try_from_asset_position(asset_position: &AssetPosition) -> Result<Self> {
+        let exact = ExactPosition::try_from(asset_position)?;
+        let position = &asset_position.position;
+        Ok(Position {
+            coin: position.coin.clone(),
+            size: decimal_to_f64(exact.size),
+            entry_price: exact.entry_price.map(decimal_to_f64),
+            leverage: position.leverage.value as f64,
+            liquidation_price: exact.liquidation_price.map(decimal_to_f64),
+            unrealized_pnl: decimal_to_f64(exact.unrealized_pnl),
+            margin_used: decimal_to_f64(exact.margin_used),
+            position_value: decimal_to_f64(exact.position_value),
+            return_on_equity: decimal_to_f64(exact.return_on_equity),
+            is_cross: position.leverage.type_string == "cross",
+            max_leverage: position.max_leverage,
+            exact: Some(exact),
+        })
+    }
 }
 
 impl From<AssetPosition> for Position {
@@ -33,10 +99,20 @@ impl From<AssetPosition> for Position {
             return_on_equity: position.return_on_equity.parse::<f64>().unwrap_or(0.0),
             is_cross: position.leverage.type_string == "cross",
             max_leverage: position.max_leverage,
+            exact: None,
         }
     }
 }
 
+/// Exact decimal sums across a portfolio
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExactPortfolioTotals {
+    pub total_unrealized_pnl: Decimal,
+    pub total_position_value: Decimal,
+    pub total_margin_used: Decimal,
+    pub account_value: Option<Decimal>,
+}
+
 /// Portfolio-level risk metrics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PortfolioMetrics {
@@ -48,6 +124,18 @@ pub struct PortfolioMetrics {
     pub account_value: f64,               // total account value
     pub total_position_value: f64,        // sum of all position values
     pub average_leverage: f64,            // weighted average leverage across positions
+    #[serde(default)]
+    pub net_dollar_delta: f64,            // portfolio PnL for a 1% move in every held coin
+    #[serde(default)]
+    pub gross_dollar_delta: f64,          // sum of absolute per-position dollar deltas
+    #[serde(default)]
+    pub btc_equivalent_exposure: f64,     // net beta-adjusted notional expressed as BTC exposure (USD)
+    #[serde(default)]
+    pub heat_breakdown: HeatBreakdown,    // points contributed by each heat component
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exact_totals: Option<ExactPortfolioTotals>, // exact sums, present when every position has exact values
+    #[serde(default)]
+    pub cross_liquidation_forecast: Option<LiquidationForecast>, // probability the cross account is liquidated within 1h/8h/24h
 }
 
 /// Position-level risk metrics
@@ -58,6 +146,22 @@ pub struct PositionMetrics {
     pub position_size_ratio: f64,         // position size relative to account value
     pub risk_score: f64,                  // 0-100 composite risk rating per position
     pub contribution_to_portfolio: f64,   // how much this position contributes to overall portfolio risk
+    #[serde(default)]
+    pub daily_volatility: Option<f64>,    // realized daily sigma of the coin, in percent
+    #[serde(default)]
+    pub liquidation_distance_sigma: Option<f64>, // gap from mark to liquidation in daily-sigma units
+    #[serde(default)]
+    pub liquidation_probability_24h: Option<f64>, // estimated probability (0-1) of touching liquidation within 24h
+    #[serde(default)]
+    pub dollar_delta: f64,                // PnL in USD for a 1% move in the coin
+    #[serde(default)]
+    pub beta_to_btc: Option<f64>,         // regression beta of the coin's returns against BTC
+    #[serde(default)]
+    pub btc_equivalent_exposure: f64,     // signed notional scaled by beta (USD of BTC exposure)
+    #[serde(default)]
+    pub risk_score_breakdown: RiskScoreBreakdown, // points contributed by each risk score component
+    #[serde(default)]
+    pub liquidation_forecast: Option<LiquidationForecast>, // probability of liquidation within 1h/8h/24h
 }
 
 /// Risk warning with details about the violated threshold
@@ -71,7 +175,7 @@ pub struct RiskWarning {
 }
 
 /// Types of risk warnings
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum RiskWarningType {
     HighLeverage,
     LiquidationRisk,
@@ -80,6 +184,8 @@ pub enum RiskWarningType {
     MarginUtilizationHigh,
     MaxDrawdownExceeded,
     OverallPortfolioRisk,
+    BetaExposureExceeded,
+    LiquidationProbability,
 }
 
 /// Risk warning severity levels
@@ -140,4 +246,75 @@ pub struct RiskSummary {
     pub warning_count: usize,
     pub margin_utilization: f64,
     pub account_value: f64,
-} 
\ No newline at end of file
+    pub heat_breakdown: HeatBreakdown,
+}
+
+impl RiskSummary {
+    /// Summarizes an analysis that has already been run
+    pub fn from_analysis(analysis: &RiskAnalysisResult) -> Self {
+        Self {
+            portfolio_heat: analysis.portfolio_metrics.portfolio_heat,
+            highest_risk_position: analysis.position_metrics.iter()
+                .max_by(|a, b| a.risk_score.partial_cmp(&b.risk_score).unwrap_or(std::cmp::Ordering::Equal))
+                .map(|p| (p.position.clone(), p.risk_score)),
+            warning_count: analysis.warnings.len(),
+            margin_utilization: analysis.portfolio_metrics.margin_utilization,
+            account_value: analysis.portfolio_metrics.account_value,
+            heat_breakdown: analysis.portfolio_metrics.heat_breakdown.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    fn asset_position(overrides: serde_json::Value) -> AssetPosition {
+        let mut position = serde_json::json!({
+            "coin": "ETH", "entryPx": "3000.5", "liquidationPx": null,
+            "leverage": {"type": "isolated", "value": 10},
+            "marginUsed": "300.05", "positionValue": "3000.5", "returnOnEquity": "0.01",
+            "szi": "-1.0", "unrealizedPnl": "3.1", "maxLeverage": 25,
+            "cumFunding": {"allTime": "0", "sinceOpen": "0", "sinceChange": "0"}
+        });
+        for (key, value) in overrides.as_object().unwrap() {
+            position[key] = value.clone();
+        }
+        serde_json::from_value(serde_json::json!({ "type": "oneWay", "position": position })).unwrap()
+    }
+
+    #[test]
+    fn try_from_asset_position_keeps_exact_values() -> Result<()> {
+        let position = Position::try_from_asset_position(&asset_position(serde_json::json!({})))?;
+        assert_eq!(position.size, -1.0);
+        assert_eq!(position.liquidation_price, None);
+        assert!(!position.is_cross);
+        let exact = position.exact.unwrap();
+        assert_eq!(exact.entry_price, Some(parse_decimal("entryPx", "3000.5")?));
+        assert_eq!(exact.margin_used.to_string(), "300.05");
+        Ok(())
+    }
+
+    #[test]
+    fn try_from_asset_position_names_the_unparseable_field() {
+        let cases = [
+            ("szi", serde_json::json!({ "szi": "" })),
+            ("entryPx", serde_json::json!({ "entryPx": "n/a" })),
+            ("liquidationPx", serde_json::json!({ "liquidationPx": "NaN" })),
+            ("unrealizedPnl", serde_json::json!({ "unrealizedPnl": "1,000" })),
+            ("returnOnEquity", serde_json::json!({ "returnOnEquity": "abc" })),
+        ];
+        for (expected, overrides) in cases {
+            let asset_position = asset_position(overrides);
+            let err = Position::try_from_asset_position(&asset_position).unwrap_err();
+            assert!(
+                matches!(&err, Error::DecimalParse { field, .. } if field == expected),
+                "{expected}: {err:?}"
+            );
+            // The lenient conversion still falls back to zero or None
+            let lenient = Position::from(asset_position);
+            assert!(lenient.exact.is_none());
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::info::info_client::InfoClient;
+use crate::prelude::*;
+use crate::risk_management::{regression_beta, return_correlation, RiskConfig};
+use crate::{CandlesSnapshotResponse, Error};
+
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+/// Realized volatility estimator used to turn candle data into a daily sigma
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VolatilityEstimator {
+    /// Sample standard deviation of close-to-close log returns
+    CloseToClose,
+    /// High/low range estimator (Parkinson, 1980)
+    Parkinson,
+    /// Exponentially weighted moving average of squared log returns (RiskMetrics style)
+    Ewma,
+}
+
+impl std::str::FromStr for VolatilityEstimator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "close_to_close" | "close-to-close" | "cc" => Ok(Self::CloseToClose),
+            "parkinson" => Ok(Self::Parkinson),
+            "ewma" => Ok(Self::Ewma),
+            _ => Err(Error::Custom(format!("Unknown volatility estimator: {}", s))),
+        }
+    }
+}
+
+/// Settings for the per-coin volatility cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolatilityConfig {
+    pub enabled: bool,
+    pub estimator: VolatilityEstimator,
+    pub candle_interval: String,
+    pub lookback_hours: u64,
+    pub ewma_lambda: f64,
+    pub refresh_interval_seconds: u64,
+    /// Score liquidation distance in daily-sigma units instead of raw percentages
+    pub use_for_scoring: bool,
+}
+
+impl Default for VolatilityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            estimator: VolatilityEstimator::Ewma,
+            candle_interval: "1h".to_string(),
+            lookback_hours: 168,
+            ewma_lambda: 0.94,
+            refresh_interval_seconds: 900,
+            use_for_scoring: false,
+        }
+    }
+}
+
+/// Realized volatility estimates for a single coin, expressed as daily sigma in percent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoinVolatility {
+    pub coin: String,
+    pub close_to_close: Option<f64>,
+    pub parkinson: Option<f64>,
+    pub ewma: Option<f64>,
+    pub sample_count: usize,
+    pub updated_at: u64,
+    /// Close-to-close log returns keyed by candle open time (ms)
+    #[serde(skip)]
+    pub returns: Vec<(u64, f64)>,
+}
+
+impl CoinVolatility {
+    /// Builds the estimates from a candle series sampled at `interval_ms`
+    pub fn from_candles(
+        coin: &str,
+        candles: &[CandlesSnapshotResponse],
+        interval_ms: u64,
+        ewma_lambda: f64,
+    ) -> Self {
+        let mut bars: Vec<(u64, f64, f64, f64)> = candles
+            .iter()
+            .filter_map(|c| {
+                let close = c.close.parse::<f64>().ok()?;
+                let high = c.high.parse::<f64>().ok()?;
+                let low = c.low.parse::<f64>().ok()?;
+                if close > 0.0 && high > 0.0 && low > 0.0 {
+                    Some((c.time_open, close, high, low))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        bars.sort_by_key(|bar| bar.0);
+
+        let returns: Vec<(u64, f64)> = bars
+            .windows(2)
+            .map(|w| (w[1].0, (w[1].1 / w[0].1).ln()))
+            .collect();
+        let log_ranges: Vec<f64> = bars.iter().map(|bar| (bar.2 / bar.3).ln()).collect();
+
+        let periods_per_day = MS_PER_DAY / interval_ms.max(1) as f64;
+        let raw_returns: Vec<f64> = returns.iter().map(|r| r.1).collect();
+        let to_daily_pct = |sigma: f64| sigma * periods_per_day.sqrt() * 100.0;
+
+        Self {
+            coin: coin.to_string(),
+            close_to_close: close_to_close_sigma(&raw_returns).map(to_daily_pct),
+            parkinson: parkinson_sigma(&log_ranges).map(to_daily_pct),
+            ewma: ewma_sigma(&raw_returns, ewma_lambda).map(to_daily_pct),
+            sample_count: raw_returns.len(),
+            updated_at: now_secs(),
+            returns,
+        }
+    }
+
+    /// Returns the daily sigma (in percent) for the requested estimator
+    pub fn daily_sigma(&self, estimator: VolatilityEstimator) -> Option<f64> {
+        match estimator {
+            VolatilityEstimator::CloseToClose => self.close_to_close,
+            VolatilityEstimator::Parkinson => self.parkinson,
+            VolatilityEstimator::Ewma => self.ewma,
+        }
+    }
+}
+
+/// Keeps cached volatility estimates per coin and refreshes them from `candles_snapshot`
+pub struct VolatilityTracker {
+    info_client: InfoClient,
+    config: RiskConfig,
+    estimates: HashMap<String, CoinVolatility>,
+}
+
+impl VolatilityTracker {
+    /// Creates a new volatility tracker with the provided configuration
+    pub async fn new(config: RiskConfig) -> Result<Self> {
+        let info_client = InfoClient::new(None, Some(config.base_url)).await?;
+
+        Ok(Self {
+            info_client,
+            config,
+            estimates: HashMap::new(),
+        })
+    }
+
+    /// Updates the configuration
+    pub fn update_config(&mut self, config: RiskConfig) {
+        self.config = config;
+    }
+
+    /// Refreshes the estimates for any of the given coins that are missing or older than the refresh interval.
+    /// A failure for one coin is logged and its previous estimate is kept.
+    pub async fn refresh(&mut self, coins: &[String]) -> Result<()> {
+        let settings = &self.config.volatility;
+        let interval_ms = interval_to_ms(&settings.candle_interval)?;
+        let now = now_secs();
+
+        for coin in coins {
+            let is_fresh = self
+                .estimates
+                .get(coin)
+                .map(|v| now.saturating_sub(v.updated_at) < settings.refresh_interval_seconds)
+                .unwrap_or(false);
+            if is_fresh {
+                continue;
+            }
+
+            let end_time = now * 1000;
+            let start_time = end_time.saturating_sub(settings.lookback_hours * 3_600_000);
+            match self
+                .info_client
+                .candles_snapshot(coin.clone(), settings.candle_interval.clone(), start_time, end_time)
+                .await
+            {
+                Ok(candles) => {
+                    let estimate = CoinVolatility::from_candles(coin, &candles, interval_ms, settings.ewma_lambda);
+                    self.estimates.insert(coin.clone(), estimate);
+                }
+                Err(e) => warn!("Failed to refresh volatility for {}: {}", coin, e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Seeds or replaces the estimate for a coin from already fetched candles
+    pub fn insert_candles(&mut self, coin: &str, candles: &[CandlesSnapshotResponse]) -> Result<()> {
+        let interval_ms = interval_to_ms(&self.config.volatility.candle_interval)?;
+        let estimate = CoinVolatility::from_candles(coin, candles, interval_ms, self.config.volatility.ewma_lambda);
+        self.estimates.insert(coin.to_string(), estimate);
+        Ok(())
+    }
+
+    /// Returns the cached estimate for a coin, if any
+    pub fn get(&self, coin: &str) -> Option<&CoinVolatility> {
+        self.estimates.get(coin)
+    }
+
+    /// Returns all cached estimates
+    pub fn estimates(&self) -> &HashMap<String, CoinVolatility> {
+        &self.estimates
+    }
+
+    /// Returns each coin's regression beta against the benchmark coin's returns
+    pub fn betas(&self, benchmark: &str) -> HashMap<String, f64> {
+        let Some(benchmark_returns) = self.estimates.get(benchmark).map(|v| &v.returns) else {
+            return HashMap::new();
+        };
+        self.estimates
+            .iter()
+            .filter_map(|(coin, v)| {
+                regression_beta(&v.returns, benchmark_returns).map(|beta| (coin.clone(), beta))
+            })
+            .collect()
+    }
+
+    /// Returns the return correlation for every pair of cached coins with enough overlapping samples
+    pub fn correlations(&self) -> HashMap<(String, String), f64> {
+        let mut correlations = HashMap::new();
+        for (coin, v) in &self.estimates {
+            for (other, w) in &self.estimates {
+                if coin < other {
+                    if let Some(rho) = return_correlation(&v.returns, &w.returns) {
+                        correlations.insert((coin.clone(), other.clone()), rho);
+                    }
+                }
+            }
+        }
+        correlations
+    }
+
+    /// Returns the daily sigma (in percent) per coin for the configured estimator
+    pub fn daily_sigmas(&self) -> HashMap<String, f64> {
+        let estimator = self.config.volatility.estimator;
+        self.estimates
+            .iter()
+            .filter_map(|(coin, v)| v.daily_sigma(estimator).map(|sigma| (coin.clone(), sigma)))
+            .collect()
+    }
+}
+
+/// Converts a Hyperliquid candle interval ("1m", "15m", "1h", "1d", ...) to milliseconds
+pub fn interval_to_ms(interval: &str) -> Result<u64> {
+    let (value, unit) = interval.split_at(interval.len().saturating_sub(1));
+    let value = value
+        .parse::<u64>()
+        .map_err(|_| Error::Custom(format!("Invalid candle interval: {}", interval)))?;
+    let unit_ms = match unit {
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        "w" => 604_800_000,
+        _ => return Err(Error::Custom(format!("Invalid candle interval: {}", interval))),
+    };
+    Ok(value * unit_ms)
+}
+
+/// Probability that a driftless log-price touches a barrier `sigma_distance` daily sigmas away
+/// within `horizon_days`, using the reflection principle: P = 2 * N(-d / sqrt(T))
+pub fn barrier_hit_probability(sigma_distance: f64, horizon_days: f64) -> f64 {
+    if sigma_distance <= 0.0 {
+        return 1.0;
+    }
+    if horizon_days <= 0.0 {
+        return 0.0;
+    }
+    (2.0 * normal_cdf(-sigma_distance / horizon_days.sqrt())).clamp(0.0, 1.0)
+}
+
+/// Standard normal cumulative distribution function
+pub(crate) fn normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+// Abramowitz and Stegun 7.1.26, max absolute error 1.5e-7
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    sign * (1.0 - poly * (-x * x).exp())
+}
+
+fn close_to_close_sigma(returns: &[f64]) -> Option<f64> {
+    if returns.len() < 2 {
+        return None;
+    }
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some(variance.sqrt())
+}
+
+fn parkinson_sigma(log_ranges: &[f64]) -> Option<f64> {
+    if log_ranges.is_empty() {
+        return None;
+    }
+    let n = log_ranges.len() as f64;
+    let sum_sq = log_ranges.iter().map(|r| r * r).sum::<f64>();
+    Some((sum_sq / (4.0 * n * std::f64::consts::LN_2)).sqrt())
+}
+
+fn ewma_sigma(returns: &[f64], lambda: f64) -> Option<f64> {
+    let (first, rest) = returns.split_first()?;
+    let variance = rest
+        .iter()
+        .fold(first * first, |var, r| lambda * var + (1.0 - lambda) * r * r);
+    Some(variance.sqrt())
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(time_open: u64, open: f64, close: f64, high: f64, low: f64) -> CandlesSnapshotResponse {
+        CandlesSnapshotResponse {
+            time_open,
+            time_close: time_open + 3_599_999,
+            coin: "BTC".to_string(),
+            candle_interval: "1h".to_string(),
+            open: open.to_string(),
+            close: close.to_string(),
+            high: high.to_string(),
+            low: low.to_string(),
+            vlm: "0".to_string(),
+            num_trades: 0,
+        }
+    }
+
+    #[test]
+    fn interval_parsing() -> Result<()> {
+        assert_eq!(interval_to_ms("1m")?, 60_000);
+        assert_eq!(interval_to_ms("15m")?, 900_000);
+        assert_eq!(interval_to_ms("4h")?, 14_400_000);
+        assert_eq!(interval_to_ms("1d")?, 86_400_000);
+        assert!(interval_to_ms("1x").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn estimators_on_alternating_series() {
+        // Closes alternate +1%/-1% in log terms, each bar spans a 2% log range
+        let step = 0.01f64;
+        let candles: Vec<_> = (0..25u64)
+            .map(|i| {
+                let close = 100.0 * if i % 2 == 0 { 1.0 } else { step.exp() };
+                candle(i * 3_600_000, close, close, close * step.exp(), close / step.exp())
+            })
+            .collect();
+        let vol = CoinVolatility::from_candles("BTC", &candles, 3_600_000, 0.94);
+
+        assert_eq!(vol.sample_count, 24);
+        let hourly_to_daily = 24f64.sqrt() * 100.0;
+        // Returns are +-step with mean 0, so the sample sigma is step * sqrt(n / (n - 1))
+        let expected_cc = step * (24.0f64 / 23.0).sqrt() * hourly_to_daily;
+        assert!((vol.close_to_close.unwrap() - expected_cc).abs() < 1e-9);
+        // Every squared return equals step^2, so the EWMA converges immediately
+        assert!((vol.ewma.unwrap() - step * hourly_to_daily).abs() < 1e-9);
+        let expected_parkinson = (0.02f64.powi(2) / (4.0 * std::f64::consts::LN_2)).sqrt() * hourly_to_daily;
+        assert!((vol.parkinson.unwrap() - expected_parkinson).abs() < 1e-9);
+    }
+
+    #[test]
+    fn barrier_probability() {
+        assert!((normal_cdf(0.0) - 0.5).abs() < 1e-7);
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-4);
+        assert_eq!(barrier_hit_probability(0.0, 1.0), 1.0);
+        // A barrier one daily sigma away is touched within a day about 31.7% of the time
+        assert!((barrier_hit_probability(1.0, 1.0) - 0.3173).abs() < 1e-3);
+        assert!(barrier_hit_probability(3.0, 1.0) < barrier_hit_probability(3.0, 2.0));
+    }
+}
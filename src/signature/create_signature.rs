@@ -1,35 +1,69 @@
 use ethers::{
     core::k256::{elliptic_curve::FieldBytes, Secp256k1},
     signers::LocalWallet,
-    types::{transaction::eip712::Eip712, Signature, H256, U256},
+    types::{transaction::eip712::Eip712, Signature, H160, H256, U256},
 };
 
-use crate::{prelude::*, proxy_digest::Sha256Proxy, signature::agent::l1, Error};
+use crate::{
+    prelude::*, proxy_digest::Sha256Proxy, signature::agent::l1, Actions, Error, SigningKind,
+};
+
+/// Digest of the phantom agent that L1 actions are signed as
+pub(crate) fn l1_action_digest(connection_id: H256, is_mainnet: bool) -> Result<H256> {
+    let source = if is_mainnet { "a" } else { "b" }.to_string();
+    typed_data_digest(&l1::Agent {
+        source,
+        connection_id,
+    })
+}
+
+pub(crate) fn typed_data_digest<T: Eip712>(payload: &T) -> Result<H256> {
+    let encoded = payload
+        .encode_eip712()
+        .map_err(|e| Error::Eip712(e.to_string()))?;
+    Ok(H256::from(encoded))
+}
 
+/// How an action is signed and the digest its signature covers. Transfers, withdrawals and agent
+/// approvals are user-signed over their own EIP-712 types; everything else is an L1 action.
+pub(crate) fn action_digest(
+    action: &Actions,
+    nonce: u64,
+    vault_address: Option<H160>,
+    is_mainnet: bool,
+) -> Result<(SigningKind, H256)> {
+    let digest = match action {
+        Actions::UsdSend(usd_send) => typed_data_digest(usd_send)?,
+        Actions::Withdraw3(withdraw) => typed_data_digest(withdraw)?,
+        Actions::SpotSend(spot_send) => typed_data_digest(spot_send)?,
+        Actions::ApproveAgent(approve_agent) => typed_data_digest(approve_agent)?,
+        _ => {
+            let connection_id = action.hash(nonce, vault_address)?;
+            return Ok((
+                SigningKind::L1Action,
+                l1_action_digest(connection_id, is_mainnet)?,
+            ));
+        }
+    };
+    Ok((SigningKind::UserAction, digest))
+}
+
+#[cfg(test)]
 pub(crate) fn sign_l1_action(
     wallet: &LocalWallet,
     connection_id: H256,
     is_mainnet: bool,
 ) -> Result<Signature> {
-    let source = if is_mainnet { "a" } else { "b" }.to_string();
-    sign_typed_data(
-        &l1::Agent {
-            source,
-            connection_id,
-        },
-        wallet,
-    )
+    sign_hash(l1_action_digest(connection_id, is_mainnet)?, wallet)
 }
 
+#[cfg(test)]
 pub(crate) fn sign_typed_data<T: Eip712>(payload: &T, wallet: &LocalWallet) -> Result<Signature> {
-    let encoded = payload
-        .encode_eip712()
-        .map_err(|e| Error::Eip712(e.to_string()))?;
-
-    sign_hash(H256::from(encoded), wallet)
+    sign_hash(typed_data_digest(payload)?, wallet)
 }
 
-fn sign_hash(hash: H256, wallet: &LocalWallet) -> Result<Signature> {
+#[allow(deprecated)]
+pub(crate) fn sign_hash(hash: H256, wallet: &LocalWallet) -> Result<Signature> {
     let (sig, rec_id) = wallet
         .signer()
         .sign_digest_recoverable(Sha256Proxy::from(hash))
@@ -1,4 +1,8 @@
 pub(crate) mod agent;
 mod create_signature;
+mod signer;
 
-pub(crate) use create_signature::{sign_l1_action, sign_typed_data};
+pub(crate) use create_signature::{action_digest, l1_action_digest, typed_data_digest};
+#[cfg(test)]
+pub(crate) use create_signature::sign_l1_action;
+pub use signer::{HyperliquidSigner, KeystoreSigner, RemoteSigner, SigningKind, SigningRequest};
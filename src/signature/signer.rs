@@ -0,0 +1,425 @@
+use std::fmt::Debug;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{RecoveryMessage, Signature, H160, H256},
+};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    prelude::*,
+    signature::{action_digest, create_signature::sign_hash},
+    Actions, Error,
+};
+
+const REMOTE_SIGNER_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SigningKind {
+    /// Trading and account actions, signed as a phantom agent over the action's hash
+    L1Action,
+    /// Transfers, withdrawals and approvals, signed as EIP-712 typed data
+    UserAction,
+}
+
+/// Everything a signer needs to produce a signature for one exchange request, and enough context
+/// for signers that enforce a policy to decide whether they should.
+///
+/// `digest` is only as trustworthy as whoever sent the request. A signer that checks `action`
+/// against a policy must sign `expected_digest()` instead, or a harmless action could be paired
+/// with the digest of one the policy forbids.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningRequest {
+    /// EIP-712 digest to sign
+    pub digest: H256,
+    pub kind: SigningKind,
+    /// The action as it will be posted, e.g. `{"type": "order", "orders": [...], ...}`
+    pub action: Value,
+    pub nonce: u64,
+    pub vault_address: Option<H160>,
+    pub is_mainnet: bool,
+}
+
+impl SigningRequest {
+    /// Recomputes the digest from `action`, `nonce`, `vault_address` and `is_mainnet`, failing if
+    /// `kind` is not how that action is signed
+    pub fn expected_digest(&self) -> Result<H256> {
+        let action: Actions = serde_json::from_value(self.action.clone())
+            .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let (kind, digest) =
+            action_digest(&action, self.nonce, self.vault_address, self.is_mainnet)?;
+        if kind != self.kind {
+            return Err(Error::SignatureFailure(format!(
+                "Action is signed as {kind:?}, not {:?}",
+                self.kind
+            )));
+        }
+        Ok(digest)
+    }
+}
+
+/// Signs exchange requests on behalf of one address
+#[async_trait]
+pub trait HyperliquidSigner: Debug + Send + Sync {
+    fn address(&self) -> H160;
+
+    /// Returns a recoverable secp256k1 signature over `request.digest`
+    async fn sign(&self, request: &SigningRequest) -> Result<Signature>;
+}
+
+#[async_trait]
+impl HyperliquidSigner for LocalWallet {
+    fn address(&self) -> H160 {
+        Signer::address(self)
+    }
+
+    async fn sign(&self, request: &SigningRequest) -> Result<Signature> {
+        sign_hash(request.digest, self)
+    }
+}
+
+#[async_trait]
+impl<T: HyperliquidSigner + ?Sized> HyperliquidSigner for Arc<T> {
+    fn address(&self) -> H160 {
+        (**self).address()
+    }
+
+    async fn sign(&self, request: &SigningRequest) -> Result<Signature> {
+        (**self).sign(request).await
+    }
+}
+
+/// Key loaded from an encrypted JSON (Web3 secret storage) keystore, so it never sits on disk in
+/// plain text
+#[derive(Debug, Clone)]
+pub struct KeystoreSigner {
+    wallet: LocalWallet,
+}
+
+impl KeystoreSigner {
+    pub fn from_file(path: impl AsRef<Path>, password: impl AsRef<[u8]>) -> Result<Self> {
+        let wallet = LocalWallet::decrypt_keystore(path, password)
+            .map_err(|e| Error::Wallet(e.to_string()))?;
+        Ok(Self { wallet })
+    }
+}
+
+#[async_trait]
+impl HyperliquidSigner for KeystoreSigner {
+    fn address(&self) -> H160 {
+        Signer::address(&self.wallet)
+    }
+
+    async fn sign(&self, request: &SigningRequest) -> Result<Signature> {
+        sign_hash(request.digest, &self.wallet)
+    }
+}
+
+#[derive(Deserialize)]
+struct RemoteAddress {
+    address: H160,
+}
+
+#[derive(Serialize)]
+struct RemoteSignRequest<'a> {
+    address: H160,
+    #[serde(flatten)]
+    request: &'a SigningRequest,
+}
+
+#[derive(Deserialize)]
+struct RemoteSignature {
+    signature: String,
+}
+
+/// Delegates signing to an HTTP signing daemon that holds the key and applies its own policy.
+///
+/// The daemon serves `GET /address`, answering `{"address": "0x..."}`, and `POST /sign`, which
+/// takes a `SigningRequest` plus the signing `address` and answers `{"signature": "0x..."}` with
+/// the 65-byte signature, or a non-2xx status if it refuses. The daemon must sign the digest it
+/// recomputes from the request (see `SigningRequest::expected_digest`), not the `digest` field.
+/// Returned signatures are checked to recover to the signer's address.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    client: Client,
+    endpoint: String,
+    address: H160,
+}
+
+impl RemoteSigner {
+    pub fn new(endpoint: impl Into<String>, address: H160) -> Self {
+        Self {
+            client: Client::new(),
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            address,
+        }
+    }
+
+    /// Asks the daemon at `endpoint` which address it signs for
+    pub async fn connect(endpoint: impl Into<String>) -> Result<Self> {
+        let mut signer = Self::new(endpoint, H160::zero());
+        let response = signer
+            .client
+            .get(format!("{}/address", signer.endpoint))
+            .timeout(REMOTE_SIGNER_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| Error::GenericRequest(e.to_string()))?;
+        let text = Self::ok_text(response).await?;
+        signer.address = serde_json::from_str::<RemoteAddress>(&text)
+            .map_err(|e| Error::JsonParse(e.to_string()))?
+            .address;
+        Ok(signer)
+    }
+
+    async fn ok_text(response: reqwest::Response) -> Result<String> {
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| Error::GenericRequest(e.to_string()))?;
+        if !status.is_success() {
+            return Err(Error::SignatureFailure(format!(
+                "Remote signer returned {status}: {text}"
+            )));
+        }
+        Ok(text)
+    }
+}
+
+#[async_trait]
+impl HyperliquidSigner for RemoteSigner {
+    fn address(&self) -> H160 {
+        self.address
+    }
+
+    async fn sign(&self, request: &SigningRequest) -> Result<Signature> {
+        let body = RemoteSignRequest {
+            address: self.address,
+            request,
+        };
+        let response = self
+            .client
+            .post(format!("{}/sign", self.endpoint))
+            .timeout(REMOTE_SIGNER_TIMEOUT)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| Error::GenericRequest(e.to_string()))?;
+        let text = Self::ok_text(response).await?;
+        let signature = serde_json::from_str::<RemoteSignature>(&text)
+            .map_err(|e| Error::JsonParse(e.to_string()))?
+            .signature;
+        let signature =
+            Signature::from_str(&signature).map_err(|e| Error::SignatureFailure(e.to_string()))?;
+
+        let recovered = signature
+            .recover(RecoveryMessage::Hash(request.digest))
+            .map_err(|e| Error::SignatureFailure(e.to_string()))?;
+        if recovered != self.address {
+            return Err(Error::SignatureFailure(format!(
+                "Remote signer signed as {recovered:?} instead of {:?}",
+                self.address
+            )));
+        }
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::test_utils::*;
+    use crate::mock_server::{MockEndpoint, MockFixtures, MockServer};
+    use crate::{ExchangeClient, ExchangeDataStatus};
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn keystore_signer_signs_like_the_wallet_it_encrypts() -> Result<()> {
+        let key = "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e";
+        let dir = std::env::temp_dir().join(format!("hl-keystore-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)?;
+        let (wallet, name) = LocalWallet::encrypt_keystore(
+            &dir,
+            &mut rand::thread_rng(),
+            hex::decode(key).unwrap(),
+            "hunter2",
+            None,
+        )
+        .map_err(|e| Error::Wallet(e.to_string()))?;
+
+        let signer = KeystoreSigner::from_file(dir.join(&name), "hunter2")?;
+        let request = SigningRequest {
+            digest: H256::repeat_byte(7),
+            kind: SigningKind::L1Action,
+            action: json!({ "type": "scheduleCancel" }),
+            nonce: 1,
+            vault_address: None,
+            is_mainnet: false,
+        };
+        assert_eq!(
+            HyperliquidSigner::address(&signer),
+            Signer::address(&wallet)
+        );
+        assert_eq!(
+            signer.sign(&request).await?,
+            HyperliquidSigner::sign(&wallet, &request).await?
+        );
+        let wrong_password = KeystoreSigner::from_file(dir.join(&name), "hunter3");
+        assert!(matches!(wrong_password, Err(Error::Wallet(_))));
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    // A signing daemon holding the key that refuses to sign withdrawals or digests that don't
+    // match their action, and remembers the kind of every request it was asked to sign
+    async fn start_signing_daemon(
+        wallet: LocalWallet,
+    ) -> Result<(String, Arc<Mutex<Vec<String>>>)> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let state = web::Data::new((wallet, Arc::clone(&seen)));
+
+        async fn address(state: web::Data<(LocalWallet, Arc<Mutex<Vec<String>>>)>) -> HttpResponse {
+            HttpResponse::Ok().json(json!({ "address": HyperliquidSigner::address(&state.0) }))
+        }
+        async fn sign(
+            state: web::Data<(LocalWallet, Arc<Mutex<Vec<String>>>)>,
+            body: web::Json<Value>,
+        ) -> HttpResponse {
+            let (wallet, seen) = state.get_ref();
+            let refuse =
+                |error: String| HttpResponse::UnprocessableEntity().json(json!({ "error": error }));
+            let request = match serde_json::from_value::<SigningRequest>(body.into_inner()) {
+                Ok(request) => request,
+                Err(e) => return refuse(e.to_string()),
+            };
+            seen.lock().unwrap().push(format!(
+                "{}:{}",
+                serde_json::to_value(request.kind)
+                    .unwrap()
+                    .as_str()
+                    .unwrap(),
+                request.action["type"].as_str().unwrap_or_default()
+            ));
+            let digest = match request.expected_digest() {
+                Ok(digest) if digest == request.digest => digest,
+                Ok(_) => return refuse("digest does not match the action".to_string()),
+                Err(e) => return refuse(e.to_string()),
+            };
+            if request.action["type"] == "withdraw3" {
+                return refuse("withdrawals are not allowed".to_string());
+            }
+            let signature = sign_hash(digest, wallet).unwrap();
+            HttpResponse::Ok().json(json!({ "signature": format!("0x{signature}") }))
+        }
+
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(state.clone())
+                .route("/address", web::get().to(address))
+                .route("/sign", web::post().to(sign))
+        })
+        .workers(1)
+        .disable_signals()
+        .listen(listener)?
+        .run();
+        tokio::spawn(server);
+        Ok((url, seen))
+    }
+
+    #[tokio::test]
+    async fn remote_signer_signs_through_the_daemon_policy() -> Result<()> {
+        let server = MockServer::start("127.0.0.1:0", MockFixtures::default()).await?;
+        let wallet = MAKER_KEY.parse::<LocalWallet>().unwrap();
+        let (url, seen) = start_signing_daemon(wallet.clone()).await?;
+        let signer = RemoteSigner::connect(url).await?;
+        assert_eq!(
+            HyperliquidSigner::address(&signer),
+            HyperliquidSigner::address(&wallet)
+        );
+        server.register_user(HyperliquidSigner::address(&wallet));
+
+        let client = ExchangeClient::new(None, signer, Some(server.base_url()), None, None).await?;
+        assert!(matches!(
+            status(
+                client
+                    .order(limit(true, 49_000.0, 0.1, "Gtc"), None)
+                    .await?
+            ),
+            ExchangeDataStatus::Resting(_)
+        ));
+
+        // The daemon's refusal surfaces as a signing failure and nothing reaches the exchange
+        let sent = server
+            .requests()
+            .iter()
+            .filter(|request| request.endpoint == MockEndpoint::Exchange)
+            .count();
+        let refused = client
+            .withdraw_from_bridge("5", "0x0D1d9635D0640821d15e323ac8AdADfA9c111414", None)
+            .await;
+        assert!(
+            matches!(refused, Err(Error::SignatureFailure(message)) if message.contains("withdrawals are not allowed"))
+        );
+        assert_eq!(
+            server
+                .requests()
+                .iter()
+                .filter(|request| request.endpoint == MockEndpoint::Exchange)
+                .count(),
+            sent
+        );
+        assert_eq!(
+            *seen.lock().unwrap(),
+            ["l1Action:order", "userAction:withdraw3"]
+        );
+
+        // A per-call signer overrides the client's
+        let local: &dyn HyperliquidSigner = &wallet;
+        assert!(matches!(
+            status(
+                client
+                    .order(limit(true, 48_000.0, 0.1, "Gtc"), Some(local))
+                    .await?
+            ),
+            ExchangeDataStatus::Resting(_)
+        ));
+        assert_eq!(seen.lock().unwrap().len(), 2);
+
+        // A harmless action can't smuggle in the digest of another
+        let forged = SigningRequest {
+            digest: H256::repeat_byte(7),
+            kind: SigningKind::L1Action,
+            action: json!({ "type": "scheduleCancel" }),
+            nonce: 1,
+            vault_address: None,
+            is_mainnet: false,
+        };
+        let refused = HyperliquidSigner::sign(client.wallet.as_ref(), &forged).await;
+        assert!(
+            matches!(refused, Err(Error::SignatureFailure(message)) if message.contains("does not match"))
+        );
+        let honest = SigningRequest {
+            digest: forged.expected_digest()?,
+            ..forged
+        };
+        assert!(HyperliquidSigner::sign(client.wallet.as_ref(), &honest)
+            .await
+            .is_ok());
+        Ok(())
+    }
+}
@@ -60,3 +60,13 @@ pub struct WebData2 {
 pub struct ActiveAssetCtx {
     pub data: ActiveAssetCtxData,
 }
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct UserTwapSliceFills {
+    pub data: UserTwapSliceFillsData,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct UserTwapHistory {
+    pub data: UserTwapHistoryData,
+}